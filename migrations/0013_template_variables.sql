@@ -0,0 +1,11 @@
+-- migrations/0013_template_variables.sql
+-- Parameterized templates.
+--
+-- variables_json is a JSON array of typed variable declarations
+-- (template_vars::TemplateVariable: name, type, default, allowed, min/max,
+-- min_length/max_length, pattern). The template body may reference them as
+-- ${name} placeholders in names, match_json and params_json; they are resolved
+-- and substituted when the template (or its project) is applied. Existing
+-- templates declare no variables and apply verbatim, as before.
+
+ALTER TABLE templates ADD COLUMN variables_json TEXT NOT NULL DEFAULT '[]';
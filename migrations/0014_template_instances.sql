@@ -0,0 +1,33 @@
+-- migrations/0014_template_instances.sql
+-- Linked template instances.
+--
+-- Applying a template now records what it created, so a fix to the template can
+-- be re-synced into every copy instead of edited by hand:
+--   templates.revision        bumped whenever the body or declared variables change.
+--   template_instances        one row per apply: the created channel (and, for a
+--                             rule template, the created rule), the template
+--                             revision it was rendered from, the resolved
+--                             variables, and snapshot_json = the rendered body as
+--                             applied/last synced.
+-- The snapshot is the common base of a three-way merge on re-sync: fields the
+-- instance changed locally (local != snapshot) are preserved; fields only the
+-- template changed are updated.
+
+ALTER TABLE templates ADD COLUMN revision INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE IF NOT EXISTS template_instances (
+  id                INTEGER PRIMARY KEY AUTOINCREMENT,
+  template_id       INTEGER NOT NULL REFERENCES templates(id) ON DELETE CASCADE,
+  template_revision INTEGER NOT NULL,
+  kind              TEXT NOT NULL,                 -- 'rule' | 'channel'
+  channel_id        INTEGER NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+  rule_id           INTEGER REFERENCES rules(id) ON DELETE CASCADE, -- kind='rule' only
+  variables_json    TEXT NOT NULL DEFAULT '{}',
+  snapshot_json     TEXT NOT NULL,
+  owner_user_id     INTEGER REFERENCES users(id),
+  created_at        TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  synced_at         TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_template_instances_template ON template_instances(template_id);
+CREATE INDEX IF NOT EXISTS idx_template_instances_channel  ON template_instances(channel_id);
@@ -0,0 +1,11 @@
+-- migrations/0015_config_managed.sql
+-- Config-as-code ownership.
+--
+-- Channels (and their rules) converged from a declarative config document carry
+-- the document's source label in managed_by (e.g. 'file:/etc/pois/pois.yaml' or
+-- 'api'). A managed channel is read-only through the regular API: the document
+-- is the source of truth, and the next apply would revert any manual edit.
+-- NULL = managed by hand, as before.
+
+ALTER TABLE channels ADD COLUMN managed_by TEXT;
+ALTER TABLE rules    ADD COLUMN managed_by TEXT;
@@ -0,0 +1,19 @@
+-- migrations/0016_event_retention.sql
+-- Per-channel event retention overrides.
+--
+-- The global policy comes from the environment (POIS_EVENT_PAYLOAD_DAYS,
+-- POIS_EVENT_RETENTION_DAYS, POIS_EVENT_RETENTION_MODE). A row here overrides
+-- it for the events of one channel (keyed by name, like esam_events, so the
+-- policy also covers events of deleted channels):
+--   payload_days  drop raw_esam_request/raw_esam_response after N days
+--   row_days      delete (or archive, then delete) events after M days
+--   mode          'delete' | 'archive'
+-- NULL = inherit the global value; 0 days = never.
+
+CREATE TABLE IF NOT EXISTS event_retention (
+  channel_name  TEXT PRIMARY KEY,
+  payload_days  INTEGER,
+  row_days      INTEGER,
+  mode          TEXT CHECK (mode IN ('delete', 'archive')),
+  updated_at    TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
@@ -0,0 +1,47 @@
+-- Watchdog policies and alerts.
+--
+-- A policy row configures the checks for one channel (keyed by name, like
+-- esam_events); channel_name '*' is the default for channels without a row.
+-- A NULL threshold disables that check:
+--   silence_minutes     no ESAM request for N minutes while a daypart is active
+--   dayparts            JSON array like ["Mon-Fri 06:00-23:00"]; NULL = always
+--   open_break_minutes  an out cue without its in cue (and no declared
+--                       duration) for N minutes
+--   error_rate_pct      share of responses >= 400 over error_window_minutes,
+--                       once at least error_min_requests were seen
+--   sesame_rejections   N or more SESAME rejections in sesame_window_minutes
+--   notify              comma-separated notifier names; NULL = all configured
+--
+-- At most one alert per (channel, kind) is firing at a time; it is updated
+-- while the condition holds and resolved when it clears.
+
+CREATE TABLE IF NOT EXISTS watchdog_policies (
+  channel_name           TEXT PRIMARY KEY,
+  enabled                INTEGER NOT NULL DEFAULT 1,
+  silence_minutes        INTEGER,
+  dayparts               TEXT,
+  open_break_minutes     INTEGER,
+  error_rate_pct         REAL,
+  error_window_minutes   INTEGER NOT NULL DEFAULT 10,
+  error_min_requests     INTEGER NOT NULL DEFAULT 20,
+  sesame_rejections      INTEGER,
+  sesame_window_minutes  INTEGER NOT NULL DEFAULT 5,
+  notify                 TEXT,
+  updated_at             TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE TABLE IF NOT EXISTS watchdog_alerts (
+  id            INTEGER PRIMARY KEY AUTOINCREMENT,
+  channel_name  TEXT NOT NULL,
+  kind          TEXT NOT NULL CHECK (kind IN ('silence', 'open_break', 'error_rate', 'sesame_rejections')),
+  state         TEXT NOT NULL CHECK (state IN ('firing', 'resolved')),
+  message       TEXT NOT NULL,
+  details       TEXT,
+  fired_at      TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  last_seen_at  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  resolved_at   TEXT
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_watchdog_alerts_firing
+  ON watchdog_alerts(channel_name, kind) WHERE state = 'firing';
+CREATE INDEX IF NOT EXISTS idx_watchdog_alerts_fired_at ON watchdog_alerts(fired_at);
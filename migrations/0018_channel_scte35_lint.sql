@@ -0,0 +1,7 @@
+-- Optional SCTE-35 conformance lint on ESAM ingest.
+--
+-- When set, every inbound cue on the channel is run through splice_lint and the
+-- findings are exposed to rules as `scte35.lint` / `scte35.lint_errors` facts.
+-- Off by default: linting is advisory and never rejects a request.
+
+ALTER TABLE channels ADD COLUMN scte35_lint INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,10 @@
+-- migrations/0019_decision_events.sql
+-- The decisions POIS returned, without the MPEG-TS monitor's rows.
+--
+-- The monitor logs what it saw on air (`on_air`, `not_honored`) to esam_events
+-- so the Event Monitor shows it next to the decisions. Everything that reads
+-- decisions back (as-run, break audit, replay, the watchdog and the monitor
+-- itself) selects from this view instead of repeating the filter.
+
+CREATE VIEW decision_events AS
+SELECT * FROM esam_events WHERE action NOT IN ('on_air', 'not_honored');
@@ -0,0 +1,817 @@
+// src/as_run.rs
+//! As-run logs: the ad breaks that actually ran on a channel, per broadcast day.
+//!
+//! Built from `esam_events`, not from the rules: every logged SCTE-35 cue is
+//! decoded and out/in cues are paired by event id — `splice_insert`
+//! out_of_network 1/0 by `splice_event_id`, segmentation start/end types
+//! (0x22/0x23, 0x30/0x31 … 0x46/0x47) by `segmentation_event_id`. Each break
+//! gets its start (out cue UTC point), end and duration, and the action POIS
+//! applied to the out and in cues. Breaks without an in cue end after their
+//! declared duration (`duration`) or stay `open`; cancelled events are
+//! `cancelled`; an in cue with no out is reported as `missing_out`. A repeated
+//! out cue for an open break and a repeated in cue for a closed one are
+//! ignored, but an out cue after the break closed starts a new break: encoders
+//! reuse event ids.
+//!
+//! A broadcast day is midnight to midnight in the channel's timezone, and
+//! times in the report carry that offset.
+//!
+//! `GET /api/asrun/{channel}?date=YYYY-MM-DD&format=json|csv` builds a report
+//! on demand (date defaults to today). With `POIS_ASRUN_DIR` set, a background
+//! task writes `<dir>/<channel>/asrun-<channel>-<date>.{csv,json}` for each
+//! completed day every `POIS_ASRUN_INTERVAL_MINUTES` (default 60), catching up
+//! on the last `POIS_ASRUN_BACKFILL_DAYS` (default 7) days that have no file.
+
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, NaiveDate, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{Pool, Sqlite};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info};
+
+use crate::event_export::{csv_row, num, text, Cell};
+use crate::jwt_auth::Claims;
+use crate::rbac;
+use crate::tools_api::decode_scte35_internal;
+use crate::AppState;
+
+pub(crate) const TS_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+/// Events this far either side of the day are read so breaks crossing
+/// midnight still find their out/in cue.
+const LOOKAROUND_HOURS: i64 = 12;
+/// A day is written to disk once it has been over for this long, so its last
+/// in cues have arrived.
+const GRACE: chrono::Duration = chrono::Duration::hours(1);
+pub(crate) const PTS_MASK: u64 = (1 << 33) - 1;
+
+const CSV_COLUMNS: &[&str] = &[
+    "channel",
+    "date",
+    "break_ref",
+    "cue",
+    "event_id",
+    "start",
+    "end",
+    "duration_s",
+    "planned_duration_s",
+    "status",
+    "action",
+    "end_action",
+    "upid",
+    "acquisition_signal_id",
+    "out_event_id",
+    "in_event_id",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakStatus {
+    /// Closed by its in cue.
+    Complete,
+    /// No in cue; ended after the duration declared on the out cue.
+    Duration,
+    /// No in cue and no (elapsed) declared duration.
+    Open,
+    Cancelled,
+    /// An in cue whose out cue was never logged.
+    MissingOut,
+}
+
+impl BreakStatus {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            BreakStatus::Complete => "complete",
+            BreakStatus::Duration => "duration",
+            BreakStatus::Open => "open",
+            BreakStatus::Cancelled => "cancelled",
+            BreakStatus::MissingOut => "missing_out",
+        }
+    }
+}
+
+/// One as-run line.
+#[derive(Debug, Clone, Serialize)]
+pub struct Break {
+    /// `splice:<splice_event_id>` or `seg:<start type>:<segmentation_event_id>`.
+    pub break_ref: String,
+    /// `splice_insert` or the segmentation type name of the out cue.
+    pub cue: String,
+    pub event_id: u64,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub duration_s: Option<f64>,
+    pub planned_duration_s: Option<f64>,
+    pub status: BreakStatus,
+    /// Action POIS applied to the out cue (noop, delete, replace, …).
+    pub action: Option<String>,
+    /// Action applied to the in cue.
+    pub end_action: Option<String>,
+    pub upid: Option<String>,
+    pub acquisition_signal_id: Option<String>,
+    pub out_event_id: Option<i64>,
+    pub in_event_id: Option<i64>,
+    #[serde(skip)]
+    start_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    end_at: Option<DateTime<Utc>>,
+}
+
+impl Break {
+    /// Out cue time (None for `missing_out`).
+    pub(crate) fn started_at(&self) -> Option<DateTime<Utc>> {
+        self.start_at
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub channel: String,
+    pub date: NaiveDate,
+    pub timezone: String,
+    pub generated_at: String,
+    pub total_breaks: usize,
+    /// Sum of the durations that are known.
+    pub total_duration_s: f64,
+    pub breaks: Vec<Break>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Edge {
+    Out,
+    In,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Key {
+    Splice(u64),
+    /// (start segmentation type, segmentation_event_id)
+    Seg(u8, u64),
+}
+
+impl Key {
+    pub(crate) fn break_ref(self) -> String {
+        match self {
+            Key::Splice(id) => format!("splice:{id}"),
+            Key::Seg(t, id) => format!("seg:0x{t:02X}:{id}"),
+        }
+    }
+
+    pub(crate) fn event_id(self) -> u64 {
+        match self {
+            Key::Splice(id) | Key::Seg(_, id) => id,
+        }
+    }
+}
+
+/// One out/in/cancel signal taken from a logged event.
+#[derive(Debug, Clone)]
+pub(crate) struct Cue {
+    pub(crate) row_id: i64,
+    pub(crate) at: DateTime<Utc>,
+    pub(crate) action: String,
+    pub(crate) signal_id: String,
+    pub(crate) key: Key,
+    pub(crate) edge: Edge,
+    pub(crate) label: String,
+    pub(crate) duration: Option<f64>,
+    pub(crate) upid: Option<String>,
+    /// Segmentation type id as signalled; `None` for splice_insert and cancels.
+    pub(crate) type_id: Option<u8>,
+    /// Splice time in 90 kHz ticks, `pts_adjustment` applied.
+    pub(crate) pts: Option<u64>,
+    pub(crate) segment_num: Option<u8>,
+    pub(crate) segments_expected: Option<u8>,
+}
+
+/// Segmentation types that open/close an ad break: Break, the Advertisement,
+/// Placement Opportunity, Overlay, Promo, Unscheduled Event, Alternate
+/// Content and Ad Block pairs. Starts are even, ends are start + 1.
+pub(crate) fn is_break_type(t: u8) -> bool {
+    matches!(t, 0x22 | 0x23 | 0x30..=0x47)
+}
+
+/// The `esam_events` columns [`cues_from`] reads.
+#[derive(sqlx::FromRow)]
+pub(crate) struct EventRow {
+    pub(crate) id: i64,
+    pub(crate) timestamp: String,
+    pub(crate) utc_point: String,
+    pub(crate) action: String,
+    pub(crate) acquisition_signal_id: String,
+    pub(crate) scte35_b64: Option<String>,
+    pub(crate) scte35_upid: Option<String>,
+}
+
+pub(crate) fn parse_ts(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc))
+}
+
+/// The break cues carried by one event. Segmentation descriptors win over the
+/// splice_insert itself when both are present (they describe the same break).
+pub(crate) fn cues_from(row: &EventRow) -> Vec<Cue> {
+    let Some(b64) = row.scte35_b64.as_deref().filter(|b| !b.is_empty()) else {
+        return Vec::new();
+    };
+    let Ok(d) = decode_scte35_internal(b64) else {
+        return Vec::new();
+    };
+    let Some(at) = parse_ts(&row.utc_point).or_else(|| parse_ts(&row.timestamp)) else {
+        return Vec::new();
+    };
+    let pts = d
+        .command_info
+        .get("pts_time")
+        .and_then(Value::as_u64)
+        .map(|p| (p + d.pts_adjustment) & PTS_MASK);
+    let cue = |key: Key, edge: Edge, label: String, duration: Option<f64>, upid: Option<String>| Cue {
+        row_id: row.id,
+        at,
+        action: row.action.clone(),
+        signal_id: row.acquisition_signal_id.clone(),
+        key,
+        edge,
+        label,
+        duration,
+        upid,
+        type_id: None,
+        pts,
+        segment_num: None,
+        segments_expected: None,
+    };
+
+    let mut cues = Vec::new();
+    for data in d.descriptors.iter().map(|x| &x.data) {
+        let Some(id) = data.get("segmentation_event_id").and_then(Value::as_u64) else {
+            continue;
+        };
+        if data.get("cancelled").and_then(Value::as_bool) == Some(true) {
+            // The cancel does not say which type it cancels.
+            cues.push(cue(Key::Seg(0, id), Edge::Cancel, String::new(), None, None));
+            continue;
+        }
+        let Some(t) = data
+            .get("segmentation_type_id")
+            .and_then(Value::as_str)
+            .and_then(|s| u8::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+            .filter(|t| is_break_type(*t))
+        else {
+            continue;
+        };
+        let edge = if t % 2 == 0 { Edge::Out } else { Edge::In };
+        let label = data.get("segmentation_type_name").and_then(Value::as_str).unwrap_or_default().to_string();
+        let duration = data.get("segmentation_duration_seconds").and_then(Value::as_f64);
+        let upid = data
+            .get("upid_value")
+            .and_then(Value::as_str)
+            .filter(|u| !u.is_empty())
+            .map(str::to_string);
+        let num = |k: &str| data.get(k).and_then(Value::as_u64).and_then(|n| u8::try_from(n).ok());
+        cues.push(Cue {
+            type_id: Some(t),
+            segment_num: num("segment_num"),
+            segments_expected: num("segments_expected"),
+            ..cue(Key::Seg(t & 0xFE, id), edge, label, duration, upid)
+        });
+    }
+    if !cues.is_empty() {
+        return cues;
+    }
+
+    let info = &d.command_info;
+    if info.get("command").and_then(Value::as_str) == Some("splice_insert") {
+        if let Some(id) = info.get("splice_event_id").and_then(Value::as_u64) {
+            let edge = if info.get("splice_event_cancel_indicator").and_then(Value::as_bool) == Some(true) {
+                Edge::Cancel
+            } else if info.get("out_of_network_indicator").and_then(Value::as_bool) == Some(true) {
+                Edge::Out
+            } else {
+                Edge::In
+            };
+            let duration = info.pointer("/break_duration/duration_seconds").and_then(Value::as_f64);
+            cues.push(cue(Key::Splice(id), edge, "splice_insert".into(), duration, row.scte35_upid.clone()));
+        }
+    }
+    cues
+}
+
+fn seconds(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_milliseconds() as f64 / 1000.0
+}
+
+fn opened(out: &Cue) -> Break {
+    Break {
+        break_ref: out.key.break_ref(),
+        cue: out.label.clone(),
+        event_id: out.key.event_id(),
+        start: None,
+        end: None,
+        duration_s: None,
+        planned_duration_s: out.duration,
+        status: BreakStatus::Open,
+        action: Some(out.action.clone()),
+        end_action: None,
+        upid: out.upid.clone(),
+        acquisition_signal_id: Some(out.signal_id.clone()),
+        out_event_id: Some(out.row_id),
+        in_event_id: None,
+        start_at: Some(out.at),
+        end_at: None,
+    }
+}
+
+fn close(mut b: Break, end: &Cue, status: BreakStatus) -> Break {
+    b.end_at = Some(end.at);
+    b.end_action = Some(end.action.clone());
+    b.in_event_id = Some(end.row_id);
+    b.status = status;
+    b
+}
+
+/// Pair cues (sorted by time) into breaks. `now` decides whether a break with
+/// no in cue has already run its declared duration.
+fn pair(cues: &[Cue], now: DateTime<Utc>) -> Vec<Break> {
+    let mut open: HashMap<Key, Break> = HashMap::new();
+    let mut closed: HashSet<Key> = HashSet::new();
+    let mut breaks = Vec::new();
+    for c in cues {
+        match c.edge {
+            Edge::Out => {
+                if let Entry::Vacant(slot) = open.entry(c.key) {
+                    closed.remove(&c.key);
+                    slot.insert(opened(c));
+                }
+            }
+            Edge::In => {
+                if let Some(b) = open.remove(&c.key) {
+                    breaks.push(close(b, c, BreakStatus::Complete));
+                    closed.insert(c.key);
+                } else if !closed.contains(&c.key) {
+                    let mut b = opened(c);
+                    b.start_at = None;
+                    b.action = None;
+                    b.out_event_id = None;
+                    b.planned_duration_s = None;
+                    breaks.push(close(b, c, BreakStatus::MissingOut));
+                    closed.insert(c.key);
+                }
+            }
+            Edge::Cancel => {
+                let hit: Vec<Key> = open
+                    .keys()
+                    .copied()
+                    .filter(|k| match (k, c.key) {
+                        (Key::Seg(_, a), Key::Seg(_, b)) => *a == b,
+                        (a, b) => *a == b,
+                    })
+                    .collect();
+                for k in hit {
+                    if let Some(b) = open.remove(&k) {
+                        breaks.push(close(b, c, BreakStatus::Cancelled));
+                        closed.insert(k);
+                    }
+                }
+            }
+        }
+    }
+    for (_, mut b) in open {
+        if let (Some(start), Some(d)) = (b.start_at, b.planned_duration_s) {
+            let end = start + chrono::Duration::milliseconds((d * 1000.0).round() as i64);
+            if end <= now {
+                b.end_at = Some(end);
+                b.status = BreakStatus::Duration;
+            }
+        }
+        breaks.push(b);
+    }
+    for b in &mut breaks {
+        if let (Some(s), Some(e)) = (b.start_at, b.end_at) {
+            b.duration_s = Some(seconds(s, e));
+        }
+    }
+    breaks.sort_by_key(|b| (b.start_at.or(b.end_at), b.out_event_id.or(b.in_event_id)));
+    breaks
+}
+
+/// UTC bounds of a broadcast day in `tz`.
+pub(crate) fn day_bounds(tz: Tz, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start_of = |d: NaiveDate| {
+        let midnight = d.and_hms_opt(0, 0, 0).expect("valid midnight");
+        tz.from_local_datetime(&midnight)
+            .earliest()
+            // Midnight skipped by a DST change: the day starts an hour later.
+            .or_else(|| tz.from_local_datetime(&(midnight + chrono::Duration::hours(1))).earliest())
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+    };
+    (start_of(date), start_of(date.succ_opt().unwrap_or(date)))
+}
+
+pub(crate) fn parse_tz(name: &str) -> Tz {
+    name.parse().unwrap_or(Tz::UTC)
+}
+
+/// Logged break cues of `channel` with a timestamp in `[from, to)`, in time order.
+async fn load_cues(db: &Pool<Sqlite>, channel: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Cue>, String> {
+    let rows: Vec<EventRow> = sqlx::query_as(
+        "SELECT id, timestamp, utc_point, action, acquisition_signal_id, scte35_b64, scte35_upid \
+         FROM decision_events WHERE channel_name = ? AND timestamp >= ? AND timestamp < ? \
+         AND scte35_b64 IS NOT NULL AND response_status < 400 ORDER BY timestamp, id",
+    )
+    .bind(channel)
+    .bind(from.format(TS_FORMAT).to_string())
+    .bind(to.format(TS_FORMAT).to_string())
+    .fetch_all(db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut cues: Vec<Cue> = rows.iter().flat_map(cues_from).collect();
+    cues.sort_by_key(|c| (c.at, c.row_id));
+    Ok(cues)
+}
+
+/// Breaks paired from the cues logged since `since`, times in UTC (used by the watchdog).
+pub(crate) async fn recent_breaks(db: &Pool<Sqlite>, channel: &str, since: DateTime<Utc>) -> Result<Vec<Break>, String> {
+    let now = Utc::now();
+    let utc = |t: DateTime<Utc>| t.format(TS_FORMAT).to_string();
+    Ok(pair(&load_cues(db, channel, since, now + chrono::Duration::minutes(1)).await?, now)
+        .into_iter()
+        .map(|mut b| {
+            b.start = b.start_at.map(utc);
+            b.end = b.end_at.map(utc);
+            b
+        })
+        .collect())
+}
+
+/// Build the as-run report for `channel` on `date` (in `tz_name`).
+pub async fn build_report(db: &Pool<Sqlite>, channel: &str, tz_name: &str, date: NaiveDate) -> Result<Report, String> {
+    let tz = parse_tz(tz_name);
+    let (day_start, day_end) = day_bounds(tz, date);
+    let around = chrono::Duration::hours(LOOKAROUND_HOURS);
+    let cues = load_cues(db, channel, day_start - around, day_end + around).await?;
+    let local = |t: DateTime<Utc>| t.with_timezone(&tz).to_rfc3339_opts(SecondsFormat::Millis, true);
+    let breaks: Vec<Break> = pair(&cues, Utc::now())
+        .into_iter()
+        .filter(|b| b.start_at.or(b.end_at).is_some_and(|t| t >= day_start && t < day_end))
+        .map(|mut b| {
+            b.start = b.start_at.map(local);
+            b.end = b.end_at.map(local);
+            b
+        })
+        .collect();
+
+    Ok(Report {
+        channel: channel.to_string(),
+        date,
+        timezone: tz.name().to_string(),
+        generated_at: Utc::now().format(TS_FORMAT).to_string(),
+        total_breaks: breaks.len(),
+        total_duration_s: breaks.iter().filter_map(|b| b.duration_s).fold(0.0, |a, d| a + d),
+        breaks,
+    })
+}
+
+pub fn to_csv(report: &Report) -> String {
+    let mut out = CSV_COLUMNS.join(",");
+    out.push_str("\r\n");
+    let date = report.date.to_string();
+    for b in &report.breaks {
+        csv_row(
+            &mut out,
+            &[
+                Cell::Text(Some(report.channel.clone())),
+                Cell::Text(Some(date.clone())),
+                Cell::Text(Some(b.break_ref.clone())),
+                Cell::Text(Some(b.cue.clone())),
+                num(Some(b.event_id)),
+                text(&b.start),
+                text(&b.end),
+                num(b.duration_s),
+                num(b.planned_duration_s),
+                Cell::Text(Some(b.status.as_str().to_string())),
+                text(&b.action),
+                text(&b.end_action),
+                text(&b.upid),
+                text(&b.acquisition_signal_id),
+                num(b.out_event_id),
+                num(b.in_event_id),
+            ],
+        );
+    }
+    out
+}
+
+/// Channel names as used in file names.
+fn file_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+pub struct AsRunSchedule {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    pub backfill_days: i64,
+    /// Serializes scheduled and on-demand runs.
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl AsRunSchedule {
+    /// `None` when `POIS_ASRUN_DIR` is unset (no reports written to disk).
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("POIS_ASRUN_DIR").ok().filter(|s| !s.trim().is_empty())?;
+        let num = |k: &str| std::env::var(k).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let minutes = num("POIS_ASRUN_INTERVAL_MINUTES").unwrap_or(60).max(1);
+        let backfill_days = num("POIS_ASRUN_BACKFILL_DAYS").unwrap_or(7).max(1) as i64;
+        Some(Self {
+            dir: PathBuf::from(dir),
+            interval: Duration::from_secs(minutes * 60),
+            backfill_days,
+            lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn spawn(self: Arc<Self>, db: Pool<Sqlite>) {
+        tokio::spawn(async move {
+            loop {
+                match self.run_once(&db).await {
+                    Ok(written) if !written.is_empty() => info!("as-run: wrote {} report(s)", written.len()),
+                    Ok(_) => {}
+                    Err(e) => error!("as-run reports failed: {}", e),
+                }
+                tokio::time::sleep(self.interval).await;
+            }
+        });
+    }
+
+    /// Write reports for every completed day in the backfill window that has
+    /// none yet. Returns the JSON files written.
+    pub async fn run_once(&self, db: &Pool<Sqlite>) -> Result<Vec<String>, String> {
+        let _guard = self.lock.lock().await;
+        let channels: Vec<(String, String)> =
+            sqlx::query_as("SELECT name, timezone FROM channels WHERE deleted_at IS NULL ORDER BY name")
+                .fetch_all(db)
+                .await
+                .map_err(|e| e.to_string())?;
+        let now = Utc::now();
+        let mut written = Vec::new();
+        for (channel, tz_name) in channels {
+            let tz = parse_tz(&tz_name);
+            let today = now.with_timezone(&tz).date_naive();
+            let dir = self.dir.join(file_safe(&channel));
+            for back in (1..=self.backfill_days).rev() {
+                let date = today - chrono::Duration::days(back);
+                if day_bounds(tz, date).1 + GRACE > now {
+                    continue;
+                }
+                let stem = format!("asrun-{}-{date}", file_safe(&channel));
+                let json_path = dir.join(format!("{stem}.json"));
+                if tokio::fs::try_exists(&json_path).await.unwrap_or(false) {
+                    continue;
+                }
+                let report = build_report(db, &channel, &tz_name, date).await?;
+                tokio::fs::create_dir_all(&dir).await.map_err(|e| format!("{}: {e}", dir.display()))?;
+                write_file(&dir.join(format!("{stem}.csv")), to_csv(&report).as_bytes()).await?;
+                let body = serde_json::to_vec_pretty(&report).map_err(|e| e.to_string())?;
+                // JSON last: its presence marks the day as done.
+                write_file(&json_path, &body).await?;
+                written.push(json_path.display().to_string());
+            }
+        }
+        Ok(written)
+    }
+}
+
+async fn write_file(path: &std::path::Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    let res = async {
+        let mut f = tokio::fs::File::create(&tmp).await?;
+        f.write_all(bytes).await?;
+        f.sync_all().await?;
+        tokio::fs::rename(&tmp, path).await
+    }
+    .await;
+    res.map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Timezone of channel `name` if it exists and the caller may read it
+/// (404 otherwise, so hidden channels look like missing ones).
+pub(crate) async fn readable_channel(st: &AppState, claims: &Claims, name: &str) -> Result<String, Response> {
+    let found: Option<(i64, String)> =
+        sqlx::query_as("SELECT id, timezone FROM channels WHERE name = ? AND deleted_at IS NULL")
+            .bind(name)
+            .fetch_optional(&st.db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())?;
+    let eff = rbac::effective(&st.db, claims).await;
+    let Some((id, tz_name)) = found else {
+        return Err((StatusCode::NOT_FOUND, "Channel not found").into_response());
+    };
+    if !rbac::can_read(&st.db, &eff, "channels", "channel_groups", "channel_id", id).await {
+        return Err((StatusCode::NOT_FOUND, "Channel not found").into_response());
+    }
+    Ok(tz_name)
+}
+
+/// `date=YYYY-MM-DD`, defaulting to today in the channel's timezone.
+pub(crate) fn report_date(date: Option<&str>, tz_name: &str) -> Result<NaiveDate, String> {
+    match date {
+        Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| format!("invalid date: {d} (YYYY-MM-DD)")),
+        None => Ok(Utc::now().with_timezone(&parse_tz(tz_name)).date_naive()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    pub date: Option<String>,
+    pub format: Option<String>,
+}
+
+/// GET /api/asrun/{channel} — as-run report for one broadcast day.
+pub async fn get_report(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(channel): Path<String>,
+    Query(q): Query<ReportQuery>,
+) -> Response {
+    let csv = match q.format.as_deref().unwrap_or("json") {
+        "json" => false,
+        "csv" => true,
+        other => return (StatusCode::BAD_REQUEST, format!("unsupported format: {other} (json or csv)")).into_response(),
+    };
+    let tz_name = match readable_channel(&st, &claims, &channel).await {
+        Ok(tz) => tz,
+        Err(rej) => return rej,
+    };
+    let date = match report_date(q.date.as_deref(), &tz_name) {
+        Ok(d) => d,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let report = match build_report(&st.db, &channel, &tz_name, date).await {
+        Ok(r) => r,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+    if csv {
+        let name = format!("asrun-{}-{date}.csv", file_safe(&channel));
+        (
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{name}\"")),
+            ],
+            to_csv(&report),
+        )
+            .into_response()
+    } else {
+        Json(report).into_response()
+    }
+}
+
+/// POST /api/asrun/run — write any missing daily reports now (super-admin).
+pub async fn run_reports(State(st): State<Arc<AppState>>, Extension(claims): Extension<Claims>) -> Response {
+    if !rbac::effective(&st.db, &claims).await.super_admin {
+        return (StatusCode::FORBIDDEN, "Super-admin only").into_response();
+    }
+    let Some(s) = st.asrun.as_ref() else {
+        return (StatusCode::CONFLICT, "As-run reports are not configured (set POIS_ASRUN_DIR)").into_response();
+    };
+    match s.run_once(&st.db).await {
+        Ok(written) => Json(json!({ "written": written })).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn cue(row_id: i64, secs: i64, key: Key, edge: Edge) -> Cue {
+        Cue {
+            row_id,
+            at: at(secs),
+            action: "noop".into(),
+            signal_id: format!("sig{row_id}"),
+            key,
+            edge,
+            label: "x".into(),
+            duration: None,
+            upid: None,
+            type_id: None,
+            pts: None,
+            segment_num: None,
+            segments_expected: None,
+        }
+    }
+
+    #[test]
+    fn pairs_out_and_in_by_event_id() {
+        let cues = [
+            cue(1, 0, Key::Splice(7), Edge::Out),
+            cue(2, 5, Key::Seg(0x34, 9), Edge::Out),
+            cue(3, 6, Key::Splice(7), Edge::Out), // repeat: ignored
+            cue(4, 30, Key::Splice(7), Edge::In),
+            cue(5, 31, Key::Splice(7), Edge::In), // repeat: ignored
+            cue(6, 65, Key::Seg(0x34, 9), Edge::In),
+        ];
+        let b = pair(&cues, at(1000));
+        assert_eq!(b.len(), 2);
+        assert_eq!((b[0].break_ref.as_str(), b[0].duration_s, b[0].status), ("splice:7", Some(30.0), BreakStatus::Complete));
+        assert_eq!((b[0].out_event_id, b[0].in_event_id), (Some(1), Some(4)));
+        assert_eq!((b[1].break_ref.as_str(), b[1].duration_s), ("seg:0x34:9", Some(60.0)));
+    }
+
+    #[test]
+    fn out_after_close_starts_a_new_break() {
+        let cues = [
+            cue(1, 0, Key::Splice(7), Edge::Out),
+            cue(2, 30, Key::Splice(7), Edge::In),
+            cue(3, 31, Key::Splice(7), Edge::In), // repeat: ignored
+            cue(4, 100, Key::Splice(7), Edge::Out),
+            cue(5, 160, Key::Splice(7), Edge::In),
+        ];
+        let b = pair(&cues, at(1000));
+        let ids: Vec<_> = b.iter().map(|b| (b.out_event_id, b.in_event_id, b.status)).collect();
+        assert_eq!(
+            ids,
+            [(Some(1), Some(2), BreakStatus::Complete), (Some(4), Some(5), BreakStatus::Complete)]
+        );
+    }
+
+    #[test]
+    fn unterminated_cancelled_and_orphans() {
+        let mut with_dur = cue(1, 0, Key::Splice(1), Edge::Out);
+        with_dur.duration = Some(30.0);
+        let mut future = cue(2, 900, Key::Splice(2), Edge::Out);
+        future.duration = Some(300.0);
+        let cues = [
+            with_dur,
+            cue(3, 10, Key::Seg(0x30, 5), Edge::Out),
+            cue(4, 20, Key::Seg(0, 5), Edge::Cancel),
+            cue(5, 40, Key::Seg(0x22, 8), Edge::In),
+            future,
+        ];
+        let b = pair(&cues, at(1000));
+        let status: Vec<_> = b.iter().map(|b| (b.break_ref.as_str(), b.status)).collect();
+        assert_eq!(
+            status,
+            [
+                ("splice:1", BreakStatus::Duration),
+                ("seg:0x30:5", BreakStatus::Cancelled),
+                ("seg:0x22:8", BreakStatus::MissingOut),
+                ("splice:2", BreakStatus::Open),
+            ]
+        );
+        assert_eq!(b[0].duration_s, Some(30.0));
+        assert!(b[2].start_at.is_none() && b[2].end_at == Some(at(40)));
+        assert!(b[3].end_at.is_none());
+    }
+
+    #[test]
+    fn break_types_and_day_bounds() {
+        assert!(is_break_type(0x34) && is_break_type(0x35) && is_break_type(0x22));
+        assert!(!is_break_type(0x10) && !is_break_type(0x50));
+
+        let d = NaiveDate::from_ymd_opt(2026, 7, 1).unwrap();
+        let (s, e) = day_bounds("America/New_York".parse().unwrap(), d);
+        assert_eq!(s.to_rfc3339(), "2026-07-01T04:00:00+00:00");
+        assert_eq!(e - s, chrono::Duration::hours(24));
+        // DST start: a 23-hour broadcast day.
+        let (s, e) = day_bounds("America/New_York".parse().unwrap(), NaiveDate::from_ymd_opt(2026, 3, 8).unwrap());
+        assert_eq!(e - s, chrono::Duration::hours(23));
+        assert_eq!(parse_tz("not/a zone"), Tz::UTC);
+    }
+
+    #[test]
+    fn decodes_splice_insert_cue() {
+        let row = EventRow {
+            id: 1,
+            timestamp: "2026-10-18T00:00:01.000Z".into(),
+            utc_point: "2026-10-18T00:00:00Z".into(),
+            action: "noop".into(),
+            acquisition_signal_id: "a".into(),
+            scte35_b64: Some("/DAlAAAAAAAAAP/wFAUAAAABf+/+AAAAAH4AKTLgAAEAAAAAhUfjqg==".into()),
+            scte35_upid: None,
+        };
+        let cues = cues_from(&row);
+        assert_eq!(cues.len(), 1);
+        assert_eq!((cues[0].key, cues[0].edge, cues[0].duration), (Key::Splice(1), Edge::Out, Some(30.0)));
+        assert_eq!(cues[0].at, parse_ts("2026-10-18T00:00:00Z").unwrap());
+    }
+}
@@ -6,21 +6,47 @@
 //! - match_json/action/params_json fields (not condition/action)
 //! - No description fields
 //! - Arc<AppState> for handlers
-#![allow(dead_code)]
+//!
+//! Access follows the channel RBAC in `rbac.rs`: exports need read access to
+//! the channel (a full export covers only the caller's readable channels), and
+//! restores create rows owned by the caller in their own group(s). Updating an
+//! existing channel/rule needs write access to it. Soft-deleted rows are never
+//! exported.
+//!
+//! `RestoreOptions` when a channel/rule name already exists:
+//!   - `update_existing` -> overwrite it in place (takes precedence),
+//!   - else `skip_existing` (default) -> leave it, counted as skipped,
+//!   - else -> reported as an error.
+//!
+//! A soft-deleted channel still owns its name (UNIQUE), so restoring onto it
+//! revives it: that needs write access to the deleted channel, keeps its owner,
+//! and replaces its old rules and group links with the backup's. Channels
+//! managed by a config document (`config_as_code`) are never touched by a
+//! restore. Backups never carry ids; restored rows always get fresh ones.
+//!
+//! Each import runs in one transaction: any error, including a single channel
+//! or rule that could not be restored, rolls the whole restore back.
 
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use sqlx::{Pool, QueryBuilder, Sqlite, SqliteConnection};
 use std::sync::Arc;
+use pois_esam_server::timecode::FrameRate;
 
+use crate::jwt_auth::Claims;
 use crate::models::{Channel, Rule};
+use crate::rbac;
 use crate::AppState;
 
+/// Format version written by exports (and accepted by restores).
+pub const BACKUP_VERSION: &str = "1.0";
+
 // ===== Backup/Restore Models =====
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -44,7 +70,7 @@ pub struct ChannelBackup {
     pub timezone: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChannelFullBackup {
     pub channel: ChannelBackup,
     #[serde(default)]
@@ -53,7 +79,7 @@ pub struct ChannelFullBackup {
     pub backup_metadata: BackupMetadata,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct BackupMetadata {
     pub version: String,
     pub created_at: String,
@@ -83,8 +109,11 @@ pub struct BackupFile {
 pub struct RestoreOptions {
     #[serde(default = "default_true")]
     pub skip_existing: bool,
+    /// Overwrite same-named rows; wins over `skip_existing`.
     #[serde(default)]
     pub update_existing: bool,
+    /// Backups carry no ids, so restored rows always get new ones; `false` only
+    /// produces a warning.
     #[serde(default = "default_true")]
     pub new_ids: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -187,91 +216,245 @@ fn default_timezone() -> String {
     "UTC".to_string()
 }
 
+type ApiError = (StatusCode, String);
+
+fn internal(e: sqlx::Error) -> ApiError {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+impl RestoreResult {
+    /// Fold a sub-result (one channel / rule) into this one.
+    fn absorb(&mut self, other: RestoreResult) {
+        self.success &= other.success;
+        self.channels_created += other.channels_created;
+        self.channels_updated += other.channels_updated;
+        self.channels_skipped += other.channels_skipped;
+        self.rules_created += other.rules_created;
+        self.rules_updated += other.rules_updated;
+        self.rules_skipped += other.rules_skipped;
+        self.errors.extend(other.errors);
+        self.warnings.extend(other.warnings);
+    }
+
+    fn fail(&mut self, msg: String) {
+        self.success = false;
+        self.errors.push(msg);
+    }
+}
+
+/// Commit `tx` if every item restored, else roll it back and zero the
+/// created/updated counts: nothing was written.
+async fn finish(tx: sqlx::Transaction<'_, Sqlite>, result: &mut RestoreResult) -> Result<(), ApiError> {
+    if result.success {
+        return tx.commit().await.map_err(internal);
+    }
+    tx.rollback().await.map_err(internal)?;
+    result.channels_created = 0;
+    result.channels_updated = 0;
+    result.rules_created = 0;
+    result.rules_updated = 0;
+    result.warnings.push("Restore rolled back because of the errors above; nothing was written".to_string());
+    Ok(())
+}
+
+fn prefixed(name: &str, opts: &RestoreOptions) -> String {
+    match &opts.prefix_names {
+        Some(prefix) => format!("{}{}", prefix, name),
+        None => name.to_string(),
+    }
+}
+
+fn rule_to_backup(r: Rule) -> Option<RuleBackup> {
+    let match_json: JsonValue = serde_json::from_str(&r.match_json).ok()?;
+    let params_json: JsonValue = serde_json::from_str(&r.params_json).ok()?;
+    Some(RuleBackup {
+        name: r.name,
+        match_json,
+        action: r.action,
+        params_json,
+        priority: r.priority,
+        enabled: r.enabled != 0,
+    })
+}
+
+fn channel_to_backup(c: &Channel) -> ChannelBackup {
+    ChannelBackup {
+        name: c.name.clone(),
+        enabled: c.enabled != 0,
+        timezone: c.timezone.clone(),
+    }
+}
+
+/// Live channel the caller may read (404 if missing/deleted, 403 if hidden).
+async fn readable_channel(
+    db: &Pool<Sqlite>,
+    eff: &rbac::Eff,
+    channel_id: i64,
+) -> Result<Channel, ApiError> {
+    let channel = sqlx::query_as::<_, Channel>(
+        "SELECT * FROM channels WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(channel_id)
+    .fetch_optional(db)
+    .await
+    .map_err(internal)?
+    .ok_or((StatusCode::NOT_FOUND, "Channel not found".to_string()))?;
+    if !rbac::can_read(db, eff, "channels", "channel_groups", "channel_id", channel_id).await {
+        return Err((StatusCode::FORBIDDEN, "Not allowed to export this channel".to_string()));
+    }
+    Ok(channel)
+}
+
+async fn channel_rules(db: &Pool<Sqlite>, channel_id: i64) -> Result<Vec<RuleBackup>, sqlx::Error> {
+    let rules = sqlx::query_as::<_, Rule>(
+        "SELECT * FROM rules WHERE channel_id = ? AND deleted_at IS NULL \
+         ORDER BY priority DESC, created_at ASC",
+    )
+    .bind(channel_id)
+    .fetch_all(db)
+    .await?;
+    Ok(rules.into_iter().filter_map(rule_to_backup).collect())
+}
+
+/// Full backup of every live channel visible to `eff` (`None` = all channels,
+/// used by the on-disk scheduler).
+pub async fn build_backup_file(
+    db: &Pool<Sqlite>,
+    eff: Option<&rbac::Eff>,
+) -> Result<BackupFile, sqlx::Error> {
+    let mut qb: QueryBuilder<Sqlite> =
+        QueryBuilder::new("SELECT * FROM channels WHERE deleted_at IS NULL");
+    if let Some(eff) = eff {
+        rbac::push_read_predicate(&mut qb, eff, "channels", "channel_groups", "channel_id");
+    }
+    qb.push(" ORDER BY created_at, id");
+    let channels = qb.build_query_as::<Channel>().fetch_all(db).await?;
+
+    let mut full_backups = Vec::new();
+    let mut total_rules = 0;
+    for channel in channels {
+        let rules = channel_rules(db, channel.id).await?;
+        total_rules += rules.len();
+        full_backups.push(ChannelFullBackup {
+            channel: channel_to_backup(&channel),
+            backup_metadata: BackupMetadata {
+                channel_id: Some(channel.id),
+                rule_count: Some(rules.len()),
+                ..Default::default()
+            },
+            rules,
+        });
+    }
+
+    let mut metadata = serde_json::Map::new();
+    metadata.insert(
+        "channel_count".to_string(),
+        JsonValue::Number(full_backups.len().into()),
+    );
+    metadata.insert(
+        "total_rules".to_string(),
+        JsonValue::Number(total_rules.into()),
+    );
+
+    Ok(BackupFile {
+        version: BACKUP_VERSION.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        backup_type: "full".to_string(),
+        full_channels: full_backups,
+        channels: Vec::new(),
+        rules: Vec::new(),
+        metadata,
+    })
+}
+
+/// Consistency problems in a backup file: counts that disagree with its
+/// metadata, rules with unparseable bodies. Empty = consistent.
+pub fn check_backup_file(b: &BackupFile) -> Vec<String> {
+    let mut problems = Vec::new();
+    if b.version != BACKUP_VERSION {
+        problems.push(format!("unsupported backup version '{}'", b.version));
+    }
+    let count = |k: &str| b.metadata.get(k).and_then(JsonValue::as_u64);
+    if let Some(n) = count("channel_count") {
+        if n as usize != b.full_channels.len() {
+            problems.push(format!(
+                "metadata says {} channels, file has {}",
+                n,
+                b.full_channels.len()
+            ));
+        }
+    }
+    let rules: usize = b.full_channels.iter().map(|c| c.rules.len()).sum();
+    if let Some(n) = count("total_rules") {
+        if n as usize != rules {
+            problems.push(format!("metadata says {} rules, file has {}", n, rules));
+        }
+    }
+    for fc in &b.full_channels {
+        if let Some(n) = fc.backup_metadata.rule_count {
+            if n != fc.rules.len() {
+                problems.push(format!(
+                    "channel '{}': metadata says {} rules, file has {}",
+                    fc.channel.name,
+                    n,
+                    fc.rules.len()
+                ));
+            }
+        }
+    }
+    problems
+}
+
 // ===== Export Handlers =====
 
 /// Export channel metadata only (no rules)
 pub async fn export_channel_only(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
     Path(channel_id): Path<i64>,
-) -> Result<Json<ChannelBackup>, (StatusCode, String)> {
-    let channel = sqlx::query_as::<_, Channel>("SELECT * FROM channels WHERE id = ?")
-        .bind(channel_id)
-        .fetch_optional(&state.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or((StatusCode::NOT_FOUND, "Channel not found".to_string()))?;
-
-    Ok(Json(ChannelBackup {
-        name: channel.name,
-        enabled: channel.enabled != 0,
-        timezone: channel.timezone,
-    }))
+) -> Result<Json<ChannelBackup>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
+    let channel = readable_channel(&state.db, &eff, channel_id).await?;
+    Ok(Json(channel_to_backup(&channel)))
 }
 
 /// Export full channel with all rules
 pub async fn export_channel_full(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
     Path(channel_id): Path<i64>,
-) -> Result<Json<ChannelFullBackup>, (StatusCode, String)> {
-    let channel = sqlx::query_as::<_, Channel>("SELECT * FROM channels WHERE id = ?")
-        .bind(channel_id)
-        .fetch_optional(&state.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or((StatusCode::NOT_FOUND, "Channel not found".to_string()))?;
-
-    let rules = sqlx::query_as::<_, Rule>(
-        "SELECT * FROM rules WHERE channel_id = ? ORDER BY priority DESC, created_at ASC",
-    )
-    .bind(channel_id)
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let rule_backups: Vec<RuleBackup> = rules
-        .into_iter()
-        .filter_map(|r| {
-            let match_json: JsonValue = serde_json::from_str(&r.match_json).ok()?;
-            let params_json: JsonValue = serde_json::from_str(&r.params_json).ok()?;
-            Some(RuleBackup {
-                name: r.name,
-                match_json,
-                action: r.action,
-                params_json,
-                priority: r.priority,
-                enabled: r.enabled != 0,
-            })
-        })
-        .collect();
+) -> Result<Json<ChannelFullBackup>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
+    let channel = readable_channel(&state.db, &eff, channel_id).await?;
+    let rule_backups = channel_rules(&state.db, channel_id).await.map_err(internal)?;
 
     Ok(Json(ChannelFullBackup {
-        channel: ChannelBackup {
-            name: channel.name,
-            enabled: channel.enabled != 0,
-            timezone: channel.timezone,
-        },
-        rules: rule_backups.clone(),
+        channel: channel_to_backup(&channel),
         backup_metadata: BackupMetadata {
-            version: "1.0".to_string(),
+            version: BACKUP_VERSION.to_string(),
             created_at: Utc::now().to_rfc3339(),
             backup_type: "full".to_string(),
             channel_id: Some(channel_id),
             rule_count: Some(rule_backups.len()),
         },
+        rules: rule_backups,
     }))
 }
 
 /// Export a single rule
 pub async fn export_rule(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
     Path(rule_id): Path<i64>,
-) -> Result<Json<RuleBackup>, (StatusCode, String)> {
-    let rule = sqlx::query_as::<_, Rule>("SELECT * FROM rules WHERE id = ?")
+) -> Result<Json<RuleBackup>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
+    let rule = sqlx::query_as::<_, Rule>("SELECT * FROM rules WHERE id = ? AND deleted_at IS NULL")
         .bind(rule_id)
         .fetch_optional(&state.db)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(internal)?
         .ok_or((StatusCode::NOT_FOUND, "Rule not found".to_string()))?;
+    readable_channel(&state.db, &eff, rule.channel_id).await?;
 
     let match_json: JsonValue = serde_json::from_str(&rule.match_json)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Invalid match_json: {}", e)))?;
@@ -291,36 +474,28 @@ pub async fn export_rule(
 /// Export multiple rules
 pub async fn export_rules(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
     Json(rule_ids): Json<Vec<i64>>,
-) -> Result<Json<Vec<RuleBackup>>, (StatusCode, String)> {
+) -> Result<Json<Vec<RuleBackup>>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
     let mut rule_backups = Vec::new();
     let mut not_found = Vec::new();
 
     for rule_id in rule_ids {
-        match sqlx::query_as::<_, Rule>("SELECT * FROM rules WHERE id = ?")
+        let rule = sqlx::query_as::<_, Rule>("SELECT * FROM rules WHERE id = ? AND deleted_at IS NULL")
             .bind(rule_id)
             .fetch_optional(&state.db)
             .await
-        {
-            Ok(Some(rule)) => {
-                if let (Ok(match_json), Ok(params_json)) = (
-                    serde_json::from_str(&rule.match_json),
-                    serde_json::from_str(&rule.params_json),
-                ) {
-                    rule_backups.push(RuleBackup {
-                        name: rule.name,
-                        match_json,
-                        action: rule.action,
-                        params_json,
-                        priority: rule.priority,
-                        enabled: rule.enabled != 0,
-                    });
-                }
-            }
-            Ok(None) => not_found.push(rule_id),
-            Err(e) => {
-                return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+            .map_err(internal)?;
+        match rule {
+            // Hidden rules are reported as not found (no existence oracle).
+            Some(rule)
+                if rbac::can_read(&state.db, &eff, "channels", "channel_groups", "channel_id", rule.channel_id)
+                    .await =>
+            {
+                rule_backups.extend(rule_to_backup(rule));
             }
+            _ => not_found.push(rule_id),
         }
     }
 
@@ -334,396 +509,470 @@ pub async fn export_rules(
     Ok(Json(rule_backups))
 }
 
-/// Export all channels with all rules (full system backup)
+/// Export all channels with all rules (full system backup). Non-admins get the
+/// channels they can read.
 pub async fn export_all(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<BackupFile>, (StatusCode, String)> {
-    let channels = sqlx::query_as::<_, Channel>("SELECT * FROM channels ORDER BY created_at")
-        .fetch_all(&state.db)
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<BackupFile>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
+    build_backup_file(&state.db, Some(&eff))
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let mut full_backups = Vec::new();
-    let mut total_rules = 0;
+        .map(Json)
+        .map_err(internal)
+}
 
-    for channel in channels {
-        let rules = sqlx::query_as::<_, Rule>(
-            "SELECT * FROM rules WHERE channel_id = ? ORDER BY priority DESC, created_at ASC",
-        )
-        .bind(channel.id)
-        .fetch_all(&state.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-        let rule_backups: Vec<RuleBackup> = rules
-            .into_iter()
-            .filter_map(|r| {
-                let match_json: JsonValue = serde_json::from_str(&r.match_json).ok()?;
-                let params_json: JsonValue = serde_json::from_str(&r.params_json).ok()?;
-                Some(RuleBackup {
-                    name: r.name,
-                    match_json,
-                    action: r.action,
-                    params_json,
-                    priority: r.priority,
-                    enabled: r.enabled != 0,
-                })
-            })
-            .collect();
-
-        total_rules += rule_backups.len();
+// ===== Channel/rule writes =====
+//
+// The one place channel and rule rows are written from user input: the rules
+// API, restores and config-as-code apply all go through these.
 
-        full_backups.push(ChannelFullBackup {
-            channel: ChannelBackup {
-                name: channel.name,
-                enabled: channel.enabled != 0,
-                timezone: channel.timezone,
-            },
-            rules: rule_backups,
-            backup_metadata: BackupMetadata::default(),
-        });
+/// What is wrong with a rule before it is stored (empty when it is fine).
+pub(crate) fn rule_problems(rule: &RuleBackup) -> Vec<String> {
+    let mut problems = Vec::new();
+    if rule.name.trim().is_empty() {
+        problems.push("name is empty".to_string());
     }
+    if rule.action.trim().is_empty() {
+        problems.push("has no action".to_string());
+    }
+    if !(rule.match_json.is_object() || rule.match_json.is_null()) {
+        problems.push("match must be an object".to_string());
+    }
+    if !(rule.params_json.is_object() || rule.params_json.is_null()) {
+        problems.push("params must be an object".to_string());
+    }
+    if let Some(rate) = rule.params_json.get("frame_rate") {
+        match rate.as_str().map(FrameRate::parse) {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => problems.push(format!("params.frame_rate: {e}")),
+            None => problems.push("params.frame_rate must be a string".to_string()),
+        }
+    }
+    problems
+}
 
-    let mut metadata = serde_json::Map::new();
-    metadata.insert(
-        "channel_count".to_string(),
-        JsonValue::Number(full_backups.len().into()),
-    );
-    metadata.insert(
-        "total_rules".to_string(),
-        JsonValue::Number(total_rules.into()),
-    );
-
-    Ok(Json(BackupFile {
-        version: "1.0".to_string(),
-        created_at: Utc::now().to_rfc3339(),
-        backup_type: "full".to_string(),
-        full_channels: full_backups,
-        channels: Vec::new(),
-        rules: Vec::new(),
-        metadata,
-    }))
+pub(crate) async fn insert_rule(
+    conn: &mut SqliteConnection,
+    channel_id: i64,
+    rule: &RuleBackup,
+    owner_id: Option<i64>,
+    managed_by: Option<&str>,
+) -> Result<Rule, sqlx::Error> {
+    sqlx::query_as::<_, Rule>(
+        "INSERT INTO rules (channel_id, name, priority, enabled, match_json, action, params_json, owner_user_id, managed_by) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(channel_id)
+    .bind(&rule.name)
+    .bind(rule.priority)
+    .bind(rule.enabled as i64)
+    .bind(rule.match_json.to_string())
+    .bind(&rule.action)
+    .bind(rule.params_json.to_string())
+    .bind(owner_id)
+    .bind(managed_by)
+    .fetch_one(&mut *conn)
+    .await
 }
 
-// ===== Import Handlers =====
+/// Overwrite a live rule (name included) in place.
+pub(crate) async fn update_rule(
+    conn: &mut SqliteConnection,
+    rule_id: i64,
+    rule: &RuleBackup,
+    managed_by: Option<&str>,
+) -> Result<Rule, sqlx::Error> {
+    sqlx::query_as::<_, Rule>(
+        "UPDATE rules SET name = ?, priority = ?, enabled = ?, match_json = ?, action = ?, params_json = ?, \
+           managed_by = ?, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') \
+         WHERE id = ? AND deleted_at IS NULL RETURNING *",
+    )
+    .bind(&rule.name)
+    .bind(rule.priority)
+    .bind(rule.enabled as i64)
+    .bind(rule.match_json.to_string())
+    .bind(&rule.action)
+    .bind(rule.params_json.to_string())
+    .bind(managed_by)
+    .bind(rule_id)
+    .fetch_one(&mut *conn)
+    .await
+}
 
-/// Import a channel (metadata only)
-pub async fn import_channel(
-    State(state): State<Arc<AppState>>,
-    Json(req): Json<ImportChannelRequest>,
-) -> Result<Json<RestoreResult>, (StatusCode, String)> {
-    let mut result = RestoreResult::default();
-    let mut channel_name = req.channel.name.clone();
+pub(crate) async fn insert_channel(
+    conn: &mut SqliteConnection,
+    channel: &ChannelBackup,
+    owner_id: Option<i64>,
+) -> Result<i64, sqlx::Error> {
+    let (id,): (i64,) = sqlx::query_as(
+        "INSERT INTO channels (name, enabled, timezone, owner_user_id) VALUES (?, ?, ?, ?) RETURNING id",
+    )
+    .bind(&channel.name)
+    .bind(channel.enabled as i64)
+    .bind(&channel.timezone)
+    .bind(owner_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(id)
+}
 
-    // Apply prefix if specified
-    if let Some(ref prefix) = req.options.prefix_names {
-        channel_name = format!("{}{}", prefix, channel_name);
-    }
+/// Overwrite a channel's settings in place; a soft-deleted channel is revived.
+pub(crate) async fn update_channel(
+    conn: &mut SqliteConnection,
+    channel_id: i64,
+    channel: &ChannelBackup,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE channels SET enabled = ?, timezone = ?, deleted_at = NULL, \
+           updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id = ?",
+    )
+    .bind(channel.enabled as i64)
+    .bind(&channel.timezone)
+    .bind(channel_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
 
-    // Check if channel exists
+// ===== Restore core =====
+
+/// Create (or update/skip, per `opts`) one channel. Returns the channel id
+/// rules should be restored into, or `None` when the channel was skipped or
+/// failed.
+async fn restore_channel(
+    conn: &mut SqliteConnection,
+    eff: &rbac::Eff,
+    backup: &ChannelBackup,
+    opts: &RestoreOptions,
+    result: &mut RestoreResult,
+) -> Result<Option<i64>, ApiError> {
+    let channel_name = prefixed(&backup.name, opts);
+
+    // Names are UNIQUE across live and soft-deleted channels.
     let existing = sqlx::query_as::<_, Channel>("SELECT * FROM channels WHERE name = ?")
         .bind(&channel_name)
-        .fetch_optional(&state.db)
+        .fetch_optional(&mut *conn)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(internal)?;
 
-    if let Some(existing_channel) = existing {
-        if req.options.skip_existing {
-            result.channels_skipped = 1;
-            result
-                .warnings
-                .push(format!("Channel '{}' already exists, skipped", channel_name));
-            return Ok(Json(result));
-        } else if req.options.update_existing {
+    // New channels land in the restorer's own group(s); super: unlinked.
+    let groups: Vec<i64> = if eff.super_admin { Vec::new() } else { eff.member_of.clone() };
+
+    match existing {
+        Some(Channel { managed_by: Some(src), .. }) => {
+            result.fail(format!("Channel '{}' is managed by config ({}); skipped", channel_name, src));
+            Ok(None)
+        }
+        Some(ch) if ch.deleted_at.is_some() => {
+            if !rbac::can_write_owned(&mut *conn, eff, ch.owner_user_id, "channel_groups", "channel_id", ch.id).await {
+                result.fail(format!("Channel '{}' was deleted and you may not restore it", channel_name));
+                return Ok(None);
+            }
+            update_channel(conn, ch.id, backup).await.map_err(internal)?;
+            // The revived channel is the backup's, not what it was when deleted.
             sqlx::query(
-                "UPDATE channels SET enabled = ?, timezone = ?, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id = ?",
+                "UPDATE rules SET deleted_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') \
+                 WHERE channel_id = ? AND deleted_at IS NULL",
             )
-            .bind(req.channel.enabled as i64)
-            .bind(&req.channel.timezone)
-            .bind(existing_channel.id)
-            .execute(&state.db)
+            .bind(ch.id)
+            .execute(&mut *conn)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-            result.channels_updated = 1;
-            return Ok(Json(result));
-        } else {
-            result.success = false;
+            .map_err(internal)?;
+            sqlx::query("DELETE FROM channel_groups WHERE channel_id = ?")
+                .bind(ch.id)
+                .execute(&mut *conn)
+                .await
+                .map_err(internal)?;
+            rbac::link_groups_in(&mut *conn, "channel_groups", "channel_id", ch.id, &groups).await;
+            result.channels_created += 1;
+            result
+                .warnings
+                .push(format!("Channel '{}' was deleted; restored in place", channel_name));
+            Ok(Some(ch.id))
+        }
+        Some(ch) if opts.update_existing => {
+            if !rbac::can_write_owned(&mut *conn, eff, ch.owner_user_id, "channel_groups", "channel_id", ch.id).await {
+                result.fail(format!("Channel '{}' exists and you may not modify it", channel_name));
+                return Ok(None);
+            }
+            update_channel(conn, ch.id, backup).await.map_err(internal)?;
+            result.channels_updated += 1;
+            Ok(Some(ch.id))
+        }
+        Some(_) if opts.skip_existing => {
+            result.channels_skipped += 1;
             result
-                .errors
-                .push(format!("Channel '{}' already exists", channel_name));
-            return Ok(Json(result));
+                .warnings
+                .push(format!("Channel '{}' already exists, skipped", channel_name));
+            Ok(None)
+        }
+        Some(_) => {
+            result.fail(format!("Channel '{}' already exists", channel_name));
+            Ok(None)
+        }
+        None => {
+            let named = ChannelBackup { name: channel_name, ..backup.clone() };
+            let id = insert_channel(conn, &named, Some(eff.uid)).await.map_err(internal)?;
+            rbac::link_groups_in(&mut *conn, "channel_groups", "channel_id", id, &groups).await;
+            result.channels_created += 1;
+            Ok(Some(id))
         }
     }
+}
 
-    // Create new channel
-    sqlx::query(
-        "INSERT INTO channels (name, enabled, timezone) VALUES (?, ?, ?)",
+/// Create (or update/skip, per `opts`) one rule in a channel the caller has
+/// already been cleared to write.
+async fn restore_rule(
+    conn: &mut SqliteConnection,
+    eff: &rbac::Eff,
+    channel_id: i64,
+    rule: &RuleBackup,
+    opts: &RestoreOptions,
+    result: &mut RestoreResult,
+) -> Result<(), ApiError> {
+    let rule_name = prefixed(&rule.name, opts);
+    let problems = rule_problems(rule);
+    if !problems.is_empty() {
+        result.fail(format!("Rule '{}': {}", rule_name, problems.join("; ")));
+        return Ok(());
+    }
+    let rule = &RuleBackup { name: rule_name.clone(), ..rule.clone() };
+
+    let existing = sqlx::query_as::<_, Rule>(
+        "SELECT * FROM rules WHERE channel_id = ? AND name = ? AND deleted_at IS NULL",
     )
-    .bind(&channel_name)
-    .bind(req.channel.enabled as i64)
-    .bind(&req.channel.timezone)
-    .execute(&state.db)
+    .bind(channel_id)
+    .bind(&rule_name)
+    .fetch_optional(&mut *conn)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    .map_err(internal)?;
 
-    result.channels_created = 1;
-    Ok(Json(result))
+    match existing {
+        Some(existing_rule) if opts.update_existing => {
+            update_rule(conn, existing_rule.id, rule, None).await.map_err(internal)?;
+            result.rules_updated += 1;
+        }
+        Some(_) if opts.skip_existing => {
+            result.rules_skipped += 1;
+            result.warnings.push(format!(
+                "Rule '{}' already exists in channel, skipped",
+                rule_name
+            ));
+        }
+        Some(_) => result.fail(format!("Rule '{}' already exists in channel", rule_name)),
+        None => {
+            insert_rule(conn, channel_id, rule, Some(eff.uid), None).await.map_err(internal)?;
+            result.rules_created += 1;
+        }
+    }
+    Ok(())
 }
 
-/// Import full channel with rules
-pub async fn import_channel_full(
-    State(state): State<Arc<AppState>>,
-    Json(req): Json<ImportChannelFullRequest>,
-) -> Result<Json<RestoreResult>, (StatusCode, String)> {
-    let mut result = RestoreResult::default();
-
-    // First import the channel
-    let channel_req = ImportChannelRequest {
-        channel: req.backup.channel.clone(),
-        options: req.options.clone(),
+/// Channel + its rules. Rules of a skipped channel are counted as skipped.
+async fn restore_channel_full(
+    conn: &mut SqliteConnection,
+    eff: &rbac::Eff,
+    backup: &ChannelFullBackup,
+    opts: &RestoreOptions,
+    result: &mut RestoreResult,
+) -> Result<(), ApiError> {
+    let Some(channel_id) = restore_channel(conn, eff, &backup.channel, opts, result).await? else {
+        result.rules_skipped += backup.rules.len() as u32;
+        return Ok(());
     };
+    for rule in &backup.rules {
+        restore_rule(conn, eff, channel_id, rule, opts, result).await?;
+    }
+    Ok(())
+}
 
-    let channel_result = import_channel(State(state.clone()), Json(channel_req))
-        .await?
-        .0;
-
-    result.channels_created = channel_result.channels_created;
-    result.channels_updated = channel_result.channels_updated;
-    result.channels_skipped = channel_result.channels_skipped;
-    result.errors.extend(channel_result.errors);
-    result.warnings.extend(channel_result.warnings);
-
-    if !channel_result.success {
-        result.success = false;
-        return Ok(Json(result));
+fn new_result(opts: &RestoreOptions) -> RestoreResult {
+    let mut result = RestoreResult::default();
+    if !opts.new_ids {
+        result
+            .warnings
+            .push("new_ids=false ignored: backups carry no ids, restored rows get new ones".to_string());
     }
+    result
+}
 
-    // Get channel name with prefix
-    let mut channel_name = req.backup.channel.name.clone();
-    if let Some(ref prefix) = req.options.prefix_names {
-        channel_name = format!("{}{}", prefix, channel_name);
+/// Target channel for a rules import: must be live and writable.
+async fn writable_channel(db: &Pool<Sqlite>, eff: &rbac::Eff, channel_id: i64) -> Result<(), ApiError> {
+    let exists: Option<(i64,)> =
+        sqlx::query_as("SELECT id FROM channels WHERE id = ? AND deleted_at IS NULL")
+            .bind(channel_id)
+            .fetch_optional(db)
+            .await
+            .map_err(internal)?;
+    if exists.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Channel not found".to_string()));
+    }
+    if !rbac::can_write(db, eff, "channels", "channel_groups", "channel_id", channel_id).await {
+        return Err((StatusCode::FORBIDDEN, "Not allowed to modify this channel".to_string()));
     }
+    if let Some(src) = crate::config_as_code::channel_manager(db, channel_id).await {
+        return Err((StatusCode::CONFLICT, format!("Channel is managed by config ({src})")));
+    }
+    Ok(())
+}
 
-    // Get the channel
-    let channel = sqlx::query_as::<_, Channel>("SELECT * FROM channels WHERE name = ?")
-        .bind(&channel_name)
-        .fetch_optional(&state.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to retrieve created channel".to_string(),
-        ))?;
-
-    // Import rules
-    for rule_backup in req.backup.rules {
-        let mut rule_name = rule_backup.name.clone();
-        if let Some(ref prefix) = req.options.prefix_names {
-            rule_name = format!("{}{}", prefix, rule_name);
-        }
+// ===== Import Handlers =====
 
-        match sqlx::query(
-            "INSERT INTO rules (channel_id, name, match_json, action, params_json, priority, enabled) VALUES (?, ?, ?, ?, ?, ?, ?)",
-        )
-        .bind(channel.id)
-        .bind(&rule_name)
-        .bind(rule_backup.match_json.to_string())
-        .bind(&rule_backup.action)
-        .bind(rule_backup.params_json.to_string())
-        .bind(rule_backup.priority)
-        .bind(rule_backup.enabled as i64)
-        .execute(&state.db)
-        .await
-        {
-            Ok(_) => result.rules_created += 1,
-            Err(e) => result
-                .warnings
-                .push(format!("Failed to import rule '{}': {}", rule_name, e)),
-        }
-    }
+/// Import a channel (metadata only)
+pub async fn import_channel(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<ImportChannelRequest>,
+) -> Result<Json<RestoreResult>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
+    let mut result = new_result(&req.options);
+    let mut tx = state.db.begin().await.map_err(internal)?;
+    restore_channel(&mut tx, &eff, &req.channel, &req.options, &mut result).await?;
+    finish(tx, &mut result).await?;
+    Ok(Json(result))
+}
 
+/// Import full channel with rules
+pub async fn import_channel_full(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<ImportChannelFullRequest>,
+) -> Result<Json<RestoreResult>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
+    let mut result = new_result(&req.options);
+    let mut tx = state.db.begin().await.map_err(internal)?;
+    restore_channel_full(&mut tx, &eff, &req.backup, &req.options, &mut result).await?;
+    finish(tx, &mut result).await?;
     Ok(Json(result))
 }
 
 /// Import a single rule to a specific channel
 pub async fn import_rule_to_channel(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
     Path(channel_id): Path<i64>,
     Json(req): Json<ImportRuleRequest>,
-) -> Result<Json<RestoreResult>, (StatusCode, String)> {
-    let mut result = RestoreResult::default();
-
-    // Verify channel exists
-    let _channel = sqlx::query_as::<_, Channel>("SELECT * FROM channels WHERE id = ?")
-        .bind(channel_id)
-        .fetch_optional(&state.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or((StatusCode::NOT_FOUND, "Channel not found".to_string()))?;
-
-    // Apply prefix if specified
-    let mut rule_name = req.rule.name.clone();
-    if let Some(ref prefix) = req.options.prefix_names {
-        rule_name = format!("{}{}", prefix, rule_name);
-    }
-
-    // Check if rule exists in this channel
-    let existing = sqlx::query_as::<_, Rule>(
-        "SELECT * FROM rules WHERE channel_id = ? AND name = ?",
-    )
-    .bind(channel_id)
-    .bind(&rule_name)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if let Some(existing_rule) = existing {
-        if req.options.skip_existing {
-            result.rules_skipped = 1;
-            result.warnings.push(format!(
-                "Rule '{}' already exists in channel, skipped",
-                rule_name
-            ));
-            return Ok(Json(result));
-        } else if req.options.update_existing {
-            sqlx::query(
-                "UPDATE rules SET match_json = ?, action = ?, params_json = ?, priority = ?, enabled = ?, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id = ?",
-            )
-            .bind(req.rule.match_json.to_string())
-            .bind(&req.rule.action)
-            .bind(req.rule.params_json.to_string())
-            .bind(req.rule.priority)
-            .bind(req.rule.enabled as i64)
-            .bind(existing_rule.id)
-            .execute(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-            result.rules_updated = 1;
-            return Ok(Json(result));
-        } else {
-            result.success = false;
-            result
-                .errors
-                .push(format!("Rule '{}' already exists in channel", rule_name));
-            return Ok(Json(result));
-        }
-    }
-
-    // Create new rule
-    sqlx::query(
-        "INSERT INTO rules (channel_id, name, match_json, action, params_json, priority, enabled) VALUES (?, ?, ?, ?, ?, ?, ?)",
-    )
-    .bind(channel_id)
-    .bind(&rule_name)
-    .bind(req.rule.match_json.to_string())
-    .bind(&req.rule.action)
-    .bind(req.rule.params_json.to_string())
-    .bind(req.rule.priority)
-    .bind(req.rule.enabled as i64)
-    .execute(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    result.rules_created = 1;
+) -> Result<Json<RestoreResult>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
+    writable_channel(&state.db, &eff, channel_id).await?;
+    let mut result = new_result(&req.options);
+    let mut tx = state.db.begin().await.map_err(internal)?;
+    restore_rule(&mut tx, &eff, channel_id, &req.rule, &req.options, &mut result).await?;
+    finish(tx, &mut result).await?;
     Ok(Json(result))
 }
 
 /// Import multiple rules to a channel
 pub async fn import_rules_to_channel(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
     Path(channel_id): Path<i64>,
     Json(req): Json<ImportRulesRequest>,
-) -> Result<Json<RestoreResult>, (StatusCode, String)> {
-    let mut result = RestoreResult::default();
-
-    for rule_backup in req.rules {
-        let rule_req = ImportRuleRequest {
-            rule: rule_backup,
-            options: req.options.clone(),
-        };
+) -> Result<Json<RestoreResult>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
+    writable_channel(&state.db, &eff, channel_id).await?;
+    let mut result = new_result(&req.options);
+    let mut tx = state.db.begin().await.map_err(internal)?;
+    for rule in &req.rules {
+        restore_rule(&mut tx, &eff, channel_id, rule, &req.options, &mut result).await?;
+    }
+    finish(tx, &mut result).await?;
+    Ok(Json(result))
+}
 
-        match import_rule_to_channel(
-            State(state.clone()),
-            Path(channel_id),
-            Json(rule_req),
-        )
-        .await
-        {
-            Ok(Json(rule_result)) => {
-                result.rules_created += rule_result.rules_created;
-                result.rules_updated += rule_result.rules_updated;
-                result.rules_skipped += rule_result.rules_skipped;
-                result.warnings.extend(rule_result.warnings);
-                if !rule_result.success {
-                    result.errors.extend(rule_result.errors);
-                }
-            }
-            Err((_, err)) => {
-                result.warnings.push(format!("Failed to import rule: {}", err));
-            }
-        }
+/// Restore a whole `BackupFile` (also used for on-disk snapshots), all or
+/// nothing.
+pub async fn restore_backup_file(
+    db: &Pool<Sqlite>,
+    eff: &rbac::Eff,
+    backup: &BackupFile,
+    opts: &RestoreOptions,
+) -> Result<RestoreResult, ApiError> {
+    let mut result = new_result(opts);
+    // Integrity problems are surfaced but don't block the restore.
+    result.warnings.extend(check_backup_file(backup));
+    if !backup.rules.is_empty() {
+        result.warnings.push(format!(
+            "{} standalone rule(s) ignored: they have no channel; import them with /api/backup/import/channel/{{id}}/rules",
+            backup.rules.len()
+        ));
     }
 
-    Ok(Json(result))
+    let mut tx = db.begin().await.map_err(internal)?;
+    for full_backup in &backup.full_channels {
+        let mut sub = RestoreResult::default();
+        restore_channel_full(&mut tx, eff, full_backup, opts, &mut sub).await?;
+        result.absorb(sub);
+    }
+    for channel_backup in &backup.channels {
+        let mut sub = RestoreResult::default();
+        restore_channel(&mut tx, eff, channel_backup, opts, &mut sub).await?;
+        result.absorb(sub);
+    }
+    finish(tx, &mut result).await?;
+    Ok(result)
 }
 
 /// Import a complete backup file
 pub async fn import_backup_file(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
     Json(req): Json<ImportFileRequest>,
-) -> Result<Json<RestoreResult>, (StatusCode, String)> {
-    let mut result = RestoreResult::default();
+) -> Result<Json<RestoreResult>, ApiError> {
+    let eff = rbac::effective(&state.db, &claims).await;
+    restore_backup_file(&state.db, &eff, &req.backup, &req.options)
+        .await
+        .map(Json)
+}
 
-    // Import full channel backups
-    for full_backup in req.backup.full_channels {
-        let full_req = ImportChannelFullRequest {
-            backup: full_backup,
-            options: req.options.clone(),
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        match import_channel_full(State(state.clone()), Json(full_req)).await {
-            Ok(Json(fb_result)) => {
-                result.channels_created += fb_result.channels_created;
-                result.channels_updated += fb_result.channels_updated;
-                result.channels_skipped += fb_result.channels_skipped;
-                result.rules_created += fb_result.rules_created;
-                result.rules_updated += fb_result.rules_updated;
-                result.rules_skipped += fb_result.rules_skipped;
-                result.errors.extend(fb_result.errors);
-                result.warnings.extend(fb_result.warnings);
-            }
-            Err((_, err)) => {
-                result
-                    .warnings
-                    .push(format!("Failed to import channel: {}", err));
-            }
-        }
+    fn file(json: JsonValue) -> BackupFile {
+        serde_json::from_value(json).unwrap()
     }
 
-    // Import standalone channels
-    for channel_backup in req.backup.channels {
-        let channel_req = ImportChannelRequest {
-            channel: channel_backup,
-            options: req.options.clone(),
-        };
+    #[test]
+    fn consistent_backup_has_no_problems() {
+        let b = file(serde_json::json!({
+            "version": "1.0", "created_at": "", "backup_type": "full",
+            "full_channels": [{
+                "channel": {"name": "a"},
+                "rules": [{"name": "r", "match_json": {}, "action": "noop", "params_json": {}}],
+                "backup_metadata": {"version": "", "created_at": "", "backup_type": "", "rule_count": 1}
+            }],
+            "metadata": {"channel_count": 1, "total_rules": 1}
+        }));
+        assert!(check_backup_file(&b).is_empty());
+    }
 
-        match import_channel(State(state.clone()), Json(channel_req)).await {
-            Ok(Json(ch_result)) => {
-                result.channels_created += ch_result.channels_created;
-                result.channels_updated += ch_result.channels_updated;
-                result.channels_skipped += ch_result.channels_skipped;
-                result.errors.extend(ch_result.errors);
-                result.warnings.extend(ch_result.warnings);
-            }
-            Err((_, err)) => {
-                result
-                    .warnings
-                    .push(format!("Failed to import channel: {}", err));
-            }
-        }
+    #[test]
+    fn truncated_backup_is_flagged() {
+        let b = file(serde_json::json!({
+            "version": "9.9", "created_at": "", "backup_type": "full",
+            "full_channels": [{"channel": {"name": "a"}, "rules": []}],
+            "metadata": {"channel_count": 2, "total_rules": 3}
+        }));
+        let p = check_backup_file(&b);
+        assert_eq!(p.len(), 3, "{p:?}");
     }
 
-    Ok(Json(result))
+    #[test]
+    fn rule_frame_rate_is_checked() {
+        let rule = |params: JsonValue| -> RuleBackup {
+            serde_json::from_value(serde_json::json!({"name": "r", "match_json": {}, "action": "extend", "params_json": params})).unwrap()
+        };
+        assert!(rule_problems(&rule(serde_json::json!({"frame_rate": "29.97df"}))).is_empty());
+        assert_eq!(rule_problems(&rule(serde_json::json!({"frame_rate": "1/2"}))).len(), 1);
+        assert_eq!(rule_problems(&rule(serde_json::json!({"frame_rate": 25}))), ["params.frame_rate must be a string"]);
+    }
+
+    #[test]
+    fn update_existing_options_parse() {
+        let o: RestoreOptions = serde_json::from_value(serde_json::json!({"update_existing": true})).unwrap();
+        assert!(o.update_existing && o.skip_existing && o.new_ids);
+        assert_eq!(prefixed("x", &RestoreOptions { prefix_names: Some("p-".into()), ..o }), "p-x");
+    }
 }
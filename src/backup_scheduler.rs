@@ -0,0 +1,412 @@
+// src/backup_scheduler.rs
+//! Scheduled on-disk backups.
+//!
+//! Enabled by `POIS_BACKUP_DIR`. Every `POIS_BACKUP_INTERVAL_MINUTES` (default
+//! 1440) a full `backup::BackupFile` of all live channels is written to
+//! `pois-backup-<UTC timestamp>.json` with a `sha256sum`-compatible `.sha256`
+//! sidecar. Each file is written to a temp name, fsynced, renamed, then read
+//! back and verified (checksum, parse, internal counts) before older files are
+//! rotated out, so a failed run never costs a good backup. Retention keeps the
+//! newest `POIS_BACKUP_KEEP` files (default 14) and, if
+//! `POIS_BACKUP_MAX_AGE_DAYS` is set, drops older ones — the newest verified
+//! backup is always kept.
+//!
+//! Snapshots are system-wide, so the API here is super-admin only.
+
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Sqlite};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+
+use crate::backup::{self, BackupFile, RestoreOptions};
+use crate::jwt_auth::Claims;
+use crate::rbac;
+use crate::AppState;
+
+const PREFIX: &str = "pois-backup-";
+const SUFFIX: &str = ".json";
+const STAMP: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+pub struct BackupSchedule {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    pub keep: usize,
+    pub max_age: Option<chrono::Duration>,
+    /// Serializes scheduled and on-demand runs.
+    lock: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotInfo {
+    pub file: String,
+    pub created_at: String,
+    pub size: u64,
+    pub sha256: Option<String>,
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+impl BackupSchedule {
+    /// `None` when `POIS_BACKUP_DIR` is unset (scheduled backups off).
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("POIS_BACKUP_DIR").ok().filter(|s| !s.trim().is_empty())?;
+        let num = |k: &str| std::env::var(k).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let minutes = num("POIS_BACKUP_INTERVAL_MINUTES").unwrap_or(1440).max(1);
+        let keep = num("POIS_BACKUP_KEEP").unwrap_or(14).max(1) as usize;
+        let max_age = num("POIS_BACKUP_MAX_AGE_DAYS").map(|d| chrono::Duration::days(d as i64));
+        Some(Self {
+            dir: PathBuf::from(dir),
+            interval: Duration::from_secs(minutes * 60),
+            keep,
+            max_age,
+            lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Run forever: first backup once the newest on-disk one is an interval old
+    /// (immediately if there is none), then every interval.
+    pub fn spawn(self: Arc<Self>, db: Pool<Sqlite>) {
+        tokio::spawn(async move {
+            if let Err(e) = tokio::fs::create_dir_all(&self.dir).await {
+                error!("backup dir {}: {}", self.dir.display(), e);
+            }
+            let age = self
+                .snapshot_files()
+                .first()
+                .map(|(_, ts)| (Utc::now() - *ts).to_std().unwrap_or_default())
+                .unwrap_or(self.interval);
+            tokio::time::sleep(self.interval.saturating_sub(age)).await;
+            loop {
+                match self.run_once(&db).await {
+                    Ok(s) => info!("backup written: {} ({} bytes)", s.file, s.size),
+                    Err(e) => error!("scheduled backup failed: {}", e),
+                }
+                tokio::time::sleep(self.interval).await;
+            }
+        });
+    }
+
+    /// Write, verify and rotate one backup.
+    pub async fn run_once(&self, db: &Pool<Sqlite>) -> Result<SnapshotInfo, String> {
+        let _guard = self.lock.lock().await;
+        tokio::fs::create_dir_all(&self.dir).await.map_err(|e| e.to_string())?;
+
+        let file = backup::build_backup_file(db, None).await.map_err(|e| e.to_string())?;
+        let bytes = serde_json::to_vec_pretty(&file).map_err(|e| e.to_string())?;
+        let sha = sha256_hex(&bytes);
+
+        let now = Utc::now();
+        let name = format!("{PREFIX}{}{SUFFIX}", now.format(STAMP));
+        let path = self.dir.join(&name);
+        let tmp = self.dir.join(format!(".{name}.tmp"));
+        write_synced(&tmp, &bytes).await.map_err(|e| format!("{}: {e}", tmp.display()))?;
+        tokio::fs::rename(&tmp, &path).await.map_err(|e| e.to_string())?;
+        write_synced(&sidecar(&path), format!("{sha}  {name}\n").as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let info = self.inspect(&name, now).await;
+        if !info.verified {
+            // Keep the evidence but never let it rotate out a good backup.
+            let bad = self.dir.join(format!("{name}.corrupt"));
+            let _ = tokio::fs::rename(&path, &bad).await;
+            let _ = tokio::fs::remove_file(sidecar(&path)).await;
+            return Err(format!("verification failed for {name}: {}", info.problems.join("; ")));
+        }
+
+        for old in expired(&self.snapshot_files(), self.keep, self.max_age, now) {
+            let p = self.dir.join(&old);
+            if let Err(e) = tokio::fs::remove_file(&p).await {
+                warn!("backup rotation: {}: {}", p.display(), e);
+            }
+            let _ = tokio::fs::remove_file(sidecar(&p)).await;
+        }
+        Ok(info)
+    }
+
+    /// Backup files on disk, newest first.
+    fn snapshot_files(&self) -> Vec<(String, DateTime<Utc>)> {
+        let Ok(rd) = std::fs::read_dir(&self.dir) else { return Vec::new() };
+        let mut v: Vec<(String, DateTime<Utc>)> = rd
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().into_string().ok()?;
+                let ts = parse_name(&name)?;
+                Some((name, ts))
+            })
+            .collect();
+        v.sort_by_key(|f| std::cmp::Reverse(f.1));
+        v
+    }
+
+    /// Re-read a snapshot and verify it against its sidecar checksum.
+    async fn inspect(&self, name: &str, ts: DateTime<Utc>) -> SnapshotInfo {
+        let path = self.dir.join(name);
+        let mut info = SnapshotInfo {
+            file: name.to_string(),
+            created_at: ts.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            size: 0,
+            sha256: None,
+            verified: false,
+            problems: Vec::new(),
+        };
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(b) => b,
+            Err(e) => {
+                info.problems.push(e.to_string());
+                return info;
+            }
+        };
+        info.size = bytes.len() as u64;
+        let expected = tokio::fs::read_to_string(sidecar(&path))
+            .await
+            .ok()
+            .and_then(|s| s.split_whitespace().next().map(str::to_string));
+        info.problems = verify_bytes(&bytes, expected.as_deref());
+        info.sha256 = expected;
+        info.verified = info.problems.is_empty();
+        info
+    }
+
+    async fn list(&self) -> Vec<SnapshotInfo> {
+        let mut out = Vec::new();
+        for (name, ts) in self.snapshot_files() {
+            out.push(self.inspect(&name, ts).await);
+        }
+        out
+    }
+}
+
+fn sidecar(path: &FsPath) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(".sha256");
+    PathBuf::from(s)
+}
+
+async fn write_synced(path: &FsPath, bytes: &[u8]) -> std::io::Result<()> {
+    let mut f = tokio::fs::File::create(path).await?;
+    f.write_all(bytes).await?;
+    f.sync_all().await
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Timestamp of a `pois-backup-<stamp>.json` name (`None` for anything else).
+fn parse_name(name: &str) -> Option<DateTime<Utc>> {
+    let stamp = name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+    NaiveDateTime::parse_from_str(stamp, STAMP).ok().map(|t| t.and_utc())
+}
+
+/// Integrity problems in a snapshot's bytes. Empty = good.
+pub fn verify_bytes(bytes: &[u8], expected_sha: Option<&str>) -> Vec<String> {
+    let mut problems = Vec::new();
+    match expected_sha {
+        Some(exp) if !exp.eq_ignore_ascii_case(&sha256_hex(bytes)) => {
+            problems.push("checksum mismatch".to_string())
+        }
+        Some(_) => {}
+        None => problems.push("missing .sha256 sidecar".to_string()),
+    }
+    match serde_json::from_slice::<BackupFile>(bytes) {
+        Ok(file) => problems.extend(backup::check_backup_file(&file)),
+        Err(e) => problems.push(format!("not a backup file: {e}")),
+    }
+    problems
+}
+
+/// Files to delete: beyond the newest `keep`, or older than `max_age`. The
+/// newest file is never expired. `files` must be newest first.
+pub fn expired(
+    files: &[(String, DateTime<Utc>)],
+    keep: usize,
+    max_age: Option<chrono::Duration>,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    files
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(i, (_, ts))| *i >= keep || max_age.is_some_and(|age| now - *ts > age))
+        .map(|(_, (name, _))| name.clone())
+        .collect()
+}
+
+// -------------------------------- handlers --------------------------------
+
+async fn require_super(st: &AppState, claims: &Claims) -> Result<Arc<BackupSchedule>, Response> {
+    let eff = rbac::effective(&st.db, claims).await;
+    if !eff.super_admin {
+        return Err((StatusCode::FORBIDDEN, "Admin only").into_response());
+    }
+    st.backups.clone().ok_or_else(|| {
+        (StatusCode::CONFLICT, "Scheduled backups are not configured (set POIS_BACKUP_DIR)").into_response()
+    })
+}
+
+/// Snapshot name from the URL, limited to names this module writes.
+fn snapshot_name(name: &str) -> Option<(String, DateTime<Utc>)> {
+    match parse_name(name) {
+        Some(ts) if !name.contains('/') && !name.contains('\\') => Some((name.to_string(), ts)),
+        _ => None,
+    }
+}
+
+/// GET /api/backup/snapshots — configuration plus every on-disk snapshot with
+/// its verification status.
+pub async fn list_snapshots(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    let eff = rbac::effective(&st.db, &claims).await;
+    if !eff.super_admin {
+        return (StatusCode::FORBIDDEN, "Admin only").into_response();
+    }
+    let Some(s) = st.backups.clone() else {
+        return Json(json!({ "enabled": false, "snapshots": [] })).into_response();
+    };
+    Json(json!({
+        "enabled": true,
+        "dir": s.dir.display().to_string(),
+        "interval_minutes": s.interval.as_secs() / 60,
+        "keep": s.keep,
+        "max_age_days": s.max_age.map(|d| d.num_days()),
+        "snapshots": s.list().await,
+    }))
+    .into_response()
+}
+
+/// POST /api/backup/snapshots — take a backup now (same write/verify/rotate path).
+pub async fn create_snapshot(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    let s = match require_super(&st, &claims).await {
+        Ok(s) => s,
+        Err(rej) => return rej,
+    };
+    match s.run_once(&st.db).await {
+        Ok(info) => Json(info).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// GET /api/backup/snapshots/{file} — download a snapshot.
+pub async fn download_snapshot(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(file): Path<String>,
+) -> impl IntoResponse {
+    let s = match require_super(&st, &claims).await {
+        Ok(s) => s,
+        Err(rej) => return rej,
+    };
+    let Some((name, _)) = snapshot_name(&file) else {
+        return (StatusCode::NOT_FOUND, "Snapshot not found").into_response();
+    };
+    match tokio::fs::read(s.dir.join(&name)).await {
+        Ok(bytes) => (
+            [
+                (header::CONTENT_TYPE, "application/json".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{name}\"")),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Err(_) => (StatusCode::NOT_FOUND, "Snapshot not found").into_response(),
+    }
+}
+
+/// POST /api/backup/snapshots/{file}/restore — restore a verified snapshot with
+/// the usual `RestoreOptions` (body optional).
+pub async fn restore_snapshot(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(file): Path<String>,
+    body: Option<Json<RestoreOptions>>,
+) -> impl IntoResponse {
+    let s = match require_super(&st, &claims).await {
+        Ok(s) => s,
+        Err(rej) => return rej,
+    };
+    let Some((name, ts)) = snapshot_name(&file) else {
+        return (StatusCode::NOT_FOUND, "Snapshot not found").into_response();
+    };
+    let info = s.inspect(&name, ts).await;
+    if !info.verified {
+        return (StatusCode::CONFLICT, Json(info)).into_response();
+    }
+    let bytes = match tokio::fs::read(s.dir.join(&name)).await {
+        Ok(b) => b,
+        Err(e) => return (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    };
+    let file: BackupFile = match serde_json::from_slice(&bytes) {
+        Ok(f) => f,
+        Err(e) => return (StatusCode::CONFLICT, e.to_string()).into_response(),
+    };
+    let opts = body.map(|Json(o)| o).unwrap_or_default();
+    let eff = rbac::effective(&st.db, &claims).await;
+    match backup::restore_backup_file(&st.db, &eff, &file, &opts).await {
+        Ok(r) => Json(r).into_response(),
+        Err(rej) => rej.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn names_round_trip() {
+        let ts = at("2026-10-18T13:43:56.123Z");
+        let name = format!("{PREFIX}{}{SUFFIX}", ts.format(STAMP));
+        assert_eq!(name, "pois-backup-20261018T134356.123Z.json");
+        assert_eq!(parse_name(&name), Some(ts));
+        assert_eq!(parse_name("pois-backup-x.json"), None);
+        assert_eq!(parse_name(&format!("{name}.sha256")), None);
+        assert_eq!(parse_name(&format!(".{name}.tmp")), None);
+    }
+
+    #[test]
+    fn retention_keeps_newest_and_honours_count_and_age() {
+        let files: Vec<_> = ["2026-10-18T00:00:00Z", "2026-10-17T00:00:00Z", "2026-10-10T00:00:00Z", "2026-10-01T00:00:00Z"]
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (format!("f{i}"), at(s)))
+            .collect();
+        let now = at("2026-10-18T01:00:00Z");
+        assert_eq!(expired(&files, 3, None, now), vec!["f3"]);
+        assert_eq!(expired(&files, 10, Some(chrono::Duration::days(5)), now), vec!["f2", "f3"]);
+        // Even when everything is too old, the newest survives.
+        assert_eq!(expired(&files, 1, Some(chrono::Duration::hours(0)), at("2027-01-01T00:00:00Z")).len(), 3);
+    }
+
+    #[test]
+    fn verification_catches_tampering() {
+        let body = br#"{"version":"1.0","created_at":"","backup_type":"full","full_channels":[],"metadata":{"channel_count":0,"total_rules":0}}"#;
+        let sha = sha256_hex(body);
+        assert!(verify_bytes(body, Some(&sha)).is_empty());
+        assert_eq!(verify_bytes(body, Some(&sha256_hex(b"other"))), vec!["checksum mismatch"]);
+        assert_eq!(verify_bytes(body, None), vec!["missing .sha256 sidecar"]);
+        assert!(!verify_bytes(&body[..40], Some(&sha256_hex(&body[..40]))).is_empty());
+    }
+}
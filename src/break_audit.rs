@@ -0,0 +1,618 @@
+// src/break_audit.rs
+//! Break pairing and cue integrity analytics.
+//!
+//! Where the as-run log shows what ran, this audits how the encoder signalled
+//! it. Every logged SCTE-35 cue of a channel's broadcast day is decoded and
+//! the break timeline rebuilt from `splice_event_id` / `segmentation_event_id`,
+//! segmentation type ids, declared durations and splice PTS (with
+//! `pts_adjustment`). Findings:
+//!   - `orphan_start`: an out cue that is never closed by an in cue or cancel
+//!     (only a note when the declared duration ended the break).
+//!   - `orphan_end`: an in cue without an out cue.
+//!   - `overlap`: a break starting while another break of the same kind
+//!     (splice_insert, or the same segmentation type) is still open. Nesting
+//!     different segmentation types is legitimate and not flagged.
+//!   - `duration_mismatch`: the measured duration (PTS when both cues carry a
+//!     splice time, else the UTC points) differs from `break_duration` /
+//!     `segmentation_duration` by more than `tolerance_s`.
+//!   - `duplicate_cue`: the same out (or in) cue repeated for a break that is
+//!     already open (or closed).
+//!   - `event_id_reused`: a new break that reuses the event id of a break
+//!     closed earlier.
+//!   - `segment_num_order`: segmentation descriptors of one UPID and type
+//!     whose `segment_num` does not run 1, 2, … `segments_expected`.
+//!
+//! `GET /api/analytics/breaks/{channel}?date=YYYY-MM-DD&tolerance_s=1`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+
+use crate::as_run::{self, BreakStatus, Cue, Edge, EventRow, Key, PTS_MASK, TS_FORMAT};
+use crate::jwt_auth::Claims;
+use crate::AppState;
+
+/// Cues this far either side of the day are read so breaks crossing midnight
+/// still pair.
+const LOOKAROUND_HOURS: i64 = 12;
+const DEFAULT_TOLERANCE_S: f64 = 1.0;
+
+/// Breaks of the same class may not overlap.
+fn class(key: Key) -> u16 {
+    match key {
+        Key::Splice(_) => 0x100,
+        Key::Seg(t, _) => t.into(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub kind: &'static str,
+    pub severity: Severity,
+    pub break_ref: String,
+    /// `esam_events` ids of the cues involved.
+    pub event_ids: Vec<i64>,
+    pub at: String,
+    pub message: String,
+    #[serde(skip)]
+    at_utc: DateTime<Utc>,
+}
+
+impl Issue {
+    fn new(kind: &'static str, severity: Severity, key: Key, at: DateTime<Utc>, event_ids: Vec<i64>, message: String) -> Self {
+        Self {
+            kind,
+            severity,
+            break_ref: key.break_ref(),
+            event_ids,
+            at: at.format(TS_FORMAT).to_string(),
+            message,
+            at_utc: at,
+        }
+    }
+}
+
+/// One break on the rebuilt timeline.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditBreak {
+    pub break_ref: String,
+    pub segmentation_type_id: Option<String>,
+    pub event_id: u64,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub status: BreakStatus,
+    /// Between the UTC points of the out and in cue.
+    pub duration_s: Option<f64>,
+    /// Between the splice times, when both cues carry one.
+    pub pts_duration_s: Option<f64>,
+    pub planned_duration_s: Option<f64>,
+    pub segment_num: Option<u8>,
+    pub segments_expected: Option<u8>,
+    pub upid: Option<String>,
+    pub out_event_id: Option<i64>,
+    pub in_event_id: Option<i64>,
+    /// Kinds of the issues raised on this break.
+    pub issues: Vec<&'static str>,
+    #[serde(skip)]
+    start_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    end_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    start_pts: Option<u64>,
+    #[serde(skip)]
+    key: Key,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub cues: usize,
+    pub breaks: usize,
+    pub complete: usize,
+    pub issues: usize,
+    pub by_kind: BTreeMap<&'static str, usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Audit {
+    pub channel: String,
+    pub date: NaiveDate,
+    pub timezone: String,
+    pub generated_at: String,
+    pub tolerance_s: f64,
+    pub summary: Summary,
+    pub breaks: Vec<AuditBreak>,
+    pub issues: Vec<Issue>,
+}
+
+fn seconds(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_milliseconds() as f64 / 1000.0
+}
+
+fn pts_seconds(from: u64, to: u64) -> f64 {
+    (to.wrapping_sub(from) & PTS_MASK) as f64 / 90_000.0
+}
+
+/// Rebuilds the timeline from time-ordered signals and collects the issues.
+#[derive(Default)]
+struct Auditor {
+    breaks: Vec<AuditBreak>,
+    issues: Vec<Issue>,
+    open: HashMap<Key, usize>,
+    closed: HashMap<Key, usize>,
+    /// Last segment_num per (segmentation type, UPID).
+    sequences: HashMap<(u8, String), u8>,
+}
+
+impl Auditor {
+    /// Record `issue`, tagging break `brk` with its kind.
+    fn raise(&mut self, brk: Option<usize>, issue: Issue) {
+        if let Some(i) = brk {
+            self.tag(i, issue.kind);
+        }
+        self.issues.push(issue);
+    }
+
+    fn tag(&mut self, brk: usize, kind: &'static str) {
+        if !self.breaks[brk].issues.contains(&kind) {
+            self.breaks[brk].issues.push(kind);
+        }
+    }
+
+    fn signal(&mut self, s: &Cue, tolerance: f64) {
+        match s.edge {
+            Edge::Out => self.out_cue(s),
+            Edge::In => self.in_cue(s, tolerance),
+            Edge::Cancel => {
+                let hit: Vec<Key> = self
+                    .open
+                    .keys()
+                    .copied()
+                    .filter(|k| match (k, s.key) {
+                        (Key::Seg(_, a), Key::Seg(_, b)) => *a == b,
+                        (a, b) => *a == b,
+                    })
+                    .collect();
+                for k in hit {
+                    if let Some(i) = self.open.remove(&k) {
+                        let b = &mut self.breaks[i];
+                        b.status = BreakStatus::Cancelled;
+                        b.end_at = Some(s.at);
+                        b.in_event_id = Some(s.row_id);
+                        self.closed.insert(k, i);
+                    }
+                }
+            }
+        }
+    }
+
+    fn out_cue(&mut self, s: &Cue) {
+        if let Some(&i) = self.open.get(&s.key) {
+            let first = self.breaks[i].out_event_id.unwrap_or_default();
+            let msg = format!("out cue for {} repeated while the break is open", s.key.break_ref());
+            self.raise(Some(i), Issue::new("duplicate_cue", Severity::Info, s.key, s.at, vec![first, s.row_id], msg));
+            return;
+        }
+        let i = self.breaks.len();
+        self.breaks.push(AuditBreak {
+            break_ref: s.key.break_ref(),
+            segmentation_type_id: s.type_id.map(|t| format!("0x{t:02X}")),
+            event_id: s.key.event_id(),
+            start: None,
+            end: None,
+            status: BreakStatus::Open,
+            duration_s: None,
+            pts_duration_s: None,
+            planned_duration_s: s.duration,
+            segment_num: s.segment_num,
+            segments_expected: s.segments_expected,
+            upid: s.upid.clone(),
+            out_event_id: Some(s.row_id),
+            in_event_id: None,
+            issues: Vec::new(),
+            start_at: Some(s.at),
+            end_at: None,
+            start_pts: s.pts,
+            key: s.key,
+        });
+
+        if let Some(prev) = self.closed.remove(&s.key) {
+            let prev_out = self.breaks[prev].out_event_id.or(self.breaks[prev].in_event_id).unwrap_or_default();
+            let msg = format!(
+                "event id {} reused for a new break (previous break closed at {})",
+                s.key.event_id(),
+                self.breaks[prev].end_at.map(|t| t.format(TS_FORMAT).to_string()).unwrap_or_default()
+            );
+            self.raise(Some(i), Issue::new("event_id_reused", Severity::Warning, s.key, s.at, vec![prev_out, s.row_id], msg));
+        }
+        let overlapping: Vec<usize> = self
+            .open
+            .iter()
+            .filter(|(k, _)| class(**k) == class(s.key))
+            .map(|(_, &j)| j)
+            .collect();
+        for j in overlapping {
+            self.tag(j, "overlap");
+            let msg = format!("{} starts while {} is still open", s.key.break_ref(), self.breaks[j].break_ref);
+            let ids = vec![self.breaks[j].out_event_id.unwrap_or_default(), s.row_id];
+            self.raise(Some(i), Issue::new("overlap", Severity::Warning, s.key, s.at, ids, msg));
+        }
+        self.open.insert(s.key, i);
+        self.check_segment_num(i, s);
+    }
+
+    fn check_segment_num(&mut self, i: usize, s: &Cue) {
+        let (Some(t), Some(num), Some(expected)) = (s.type_id, s.segment_num, s.segments_expected) else {
+            return;
+        };
+        if expected == 0 {
+            return;
+        }
+        let chain = (t & 0xFE, s.upid.clone().unwrap_or_default());
+        // An out-of-range number is reported but not remembered, so the next
+        // cue is still checked against the last valid one.
+        let problem = if num == 0 || num > expected {
+            Some(format!("segment_num {num} outside 1..={expected}"))
+        } else {
+            match self.sequences.insert(chain, num) {
+                Some(last) if num != 1 && u16::from(num) != u16::from(last) + 1 => {
+                    Some(format!("segment_num {num} follows {last} (expected {})", u16::from(last) + 1))
+                }
+                None if num != 1 => Some(format!("sequence starts at segment_num {num} of {expected}")),
+                _ => None,
+            }
+        };
+        if let Some(msg) = problem {
+            self.raise(Some(i), Issue::new("segment_num_order", Severity::Warning, s.key, s.at, vec![s.row_id], msg));
+        }
+    }
+
+    fn in_cue(&mut self, s: &Cue, tolerance: f64) {
+        if let Some(i) = self.open.remove(&s.key) {
+            let b = &mut self.breaks[i];
+            b.status = BreakStatus::Complete;
+            b.end_at = Some(s.at);
+            b.in_event_id = Some(s.row_id);
+            b.duration_s = b.start_at.map(|t| seconds(t, s.at));
+            b.pts_duration_s = b.start_pts.zip(s.pts).map(|(a, z)| pts_seconds(a, z));
+            self.closed.insert(s.key, i);
+            let b = &self.breaks[i];
+            if let (Some(planned), Some(measured)) = (b.planned_duration_s, b.pts_duration_s.or(b.duration_s)) {
+                if (measured - planned).abs() > tolerance {
+                    let basis = if b.pts_duration_s.is_some() { "PTS" } else { "UTC points" };
+                    let ids = vec![b.out_event_id.unwrap_or_default(), s.row_id];
+                    let msg = format!("break ran {measured:.3}s ({basis}) but declared {planned:.3}s");
+                    self.raise(Some(i), Issue::new("duration_mismatch", Severity::Warning, s.key, s.at, ids, msg));
+                }
+            }
+        } else if let Some(&i) = self.closed.get(&s.key) {
+            let first = self.breaks[i].in_event_id.unwrap_or_default();
+            let msg = format!("in cue for {} repeated after the break closed", s.key.break_ref());
+            self.raise(Some(i), Issue::new("duplicate_cue", Severity::Info, s.key, s.at, vec![first, s.row_id], msg));
+        } else {
+            let i = self.breaks.len();
+            self.breaks.push(AuditBreak {
+                break_ref: s.key.break_ref(),
+                segmentation_type_id: s.type_id.map(|t| format!("0x{:02X}", t & 0xFE)),
+                event_id: s.key.event_id(),
+                start: None,
+                end: None,
+                status: BreakStatus::MissingOut,
+                duration_s: None,
+                pts_duration_s: None,
+                planned_duration_s: None,
+                segment_num: s.segment_num,
+                segments_expected: s.segments_expected,
+                upid: s.upid.clone(),
+                out_event_id: None,
+                in_event_id: Some(s.row_id),
+                issues: Vec::new(),
+                start_at: None,
+                end_at: Some(s.at),
+                start_pts: None,
+                key: s.key,
+            });
+            self.closed.insert(s.key, i);
+            let msg = format!("in cue for {} without an out cue", s.key.break_ref());
+            self.raise(Some(i), Issue::new("orphan_end", Severity::Error, s.key, s.at, vec![s.row_id], msg));
+        }
+    }
+
+    /// Breaks still open once every signal is in.
+    fn finish(&mut self, now: DateTime<Utc>) {
+        let mut still_open: Vec<usize> = self.open.drain().map(|(_, i)| i).collect();
+        still_open.sort_unstable();
+        for i in still_open {
+            let b = &mut self.breaks[i];
+            let Some(start) = b.start_at else { continue };
+            let (severity, message) = match b.planned_duration_s {
+                Some(d) => {
+                    let end = start + chrono::Duration::milliseconds((d * 1000.0).round() as i64);
+                    if end > now {
+                        continue; // still running
+                    }
+                    b.status = BreakStatus::Duration;
+                    b.end_at = Some(end);
+                    (Severity::Info, format!("{} has no in cue; ended by its declared {d:.3}s", b.break_ref))
+                }
+                None => (Severity::Warning, format!("{} has no in cue", b.break_ref)),
+            };
+            let (key, ids) = (b.key, vec![b.out_event_id.unwrap_or_default()]);
+            self.raise(Some(i), Issue::new("orphan_start", severity, key, start, ids, message));
+        }
+    }
+}
+
+/// Rebuild breaks and issues from signals sorted by time.
+fn audit(signals: &[Cue], tolerance: f64, now: DateTime<Utc>) -> (Vec<AuditBreak>, Vec<Issue>) {
+    let mut a = Auditor::default();
+    for s in signals {
+        a.signal(s, tolerance);
+    }
+    a.finish(now);
+    (a.breaks, a.issues)
+}
+
+/// Audit `channel` on `date` (in its timezone `tz_name`).
+pub async fn build_audit(
+    db: &Pool<Sqlite>,
+    channel: &str,
+    tz_name: &str,
+    date: NaiveDate,
+    tolerance: f64,
+) -> Result<Audit, String> {
+    let tz = as_run::parse_tz(tz_name);
+    let (day_start, day_end) = as_run::day_bounds(tz, date);
+    let around = chrono::Duration::hours(LOOKAROUND_HOURS);
+    let rows: Vec<EventRow> = sqlx::query_as(
+        "SELECT id, timestamp, utc_point, action, acquisition_signal_id, scte35_b64, scte35_upid FROM decision_events \
+         WHERE channel_name = ? AND timestamp >= ? AND timestamp < ? AND scte35_b64 IS NOT NULL ORDER BY timestamp, id",
+    )
+    .bind(channel)
+    .bind((day_start - around).format(TS_FORMAT).to_string())
+    .bind((day_end + around).format(TS_FORMAT).to_string())
+    .fetch_all(db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut cues: Vec<Cue> = rows.iter().flat_map(as_run::cues_from).collect();
+    cues.sort_by_key(|c| (c.at, c.row_id));
+    let in_day = |t: DateTime<Utc>| t >= day_start && t < day_end;
+    let in_day_cues = cues.iter().filter(|c| in_day(c.at)).count();
+
+    let (breaks, issues) = audit(&cues, tolerance, Utc::now());
+    let local = |t: DateTime<Utc>| t.with_timezone(&tz).to_rfc3339_opts(SecondsFormat::Millis, true);
+    let breaks: Vec<AuditBreak> = breaks
+        .into_iter()
+        .filter(|b| b.start_at.or(b.end_at).is_some_and(in_day))
+        .map(|mut b| {
+            b.start = b.start_at.map(local);
+            b.end = b.end_at.map(local);
+            b
+        })
+        .collect();
+    let mut issues: Vec<Issue> = issues
+        .into_iter()
+        .filter(|i| in_day(i.at_utc))
+        .map(|mut i| {
+            i.at = local(i.at_utc);
+            i
+        })
+        .collect();
+    issues.sort_by_key(|i| i.at_utc);
+
+    let mut by_kind = BTreeMap::new();
+    for i in &issues {
+        *by_kind.entry(i.kind).or_insert(0) += 1;
+    }
+    Ok(Audit {
+        channel: channel.to_string(),
+        date,
+        timezone: tz.name().to_string(),
+        generated_at: Utc::now().format(TS_FORMAT).to_string(),
+        tolerance_s: tolerance,
+        summary: Summary {
+            cues: in_day_cues,
+            breaks: breaks.len(),
+            complete: breaks.iter().filter(|b| b.status == BreakStatus::Complete).count(),
+            issues: issues.len(),
+            by_kind,
+        },
+        breaks,
+        issues,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    pub date: Option<String>,
+    pub tolerance_s: Option<f64>,
+}
+
+/// GET /api/analytics/breaks/{channel} — cue integrity audit for one day.
+pub async fn get_audit(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(channel): Path<String>,
+    Query(q): Query<AuditQuery>,
+) -> Response {
+    let tolerance = q.tolerance_s.unwrap_or(DEFAULT_TOLERANCE_S);
+    if !(tolerance.is_finite() && tolerance >= 0.0) {
+        return (StatusCode::BAD_REQUEST, "tolerance_s must be >= 0").into_response();
+    }
+    let tz_name = match as_run::readable_channel(&st, &claims, &channel).await {
+        Ok(tz) => tz,
+        Err(rej) => return rej,
+    };
+    let date = match as_run::report_date(q.date.as_deref(), &tz_name) {
+        Ok(d) => d,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    match build_audit(&st.db, &channel, &tz_name, date, tolerance).await {
+        Ok(a) => Json(a).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_790_000_000 + secs, 0).unwrap()
+    }
+
+    fn splice(row_id: i64, secs: i64, id: u64, edge: Edge) -> Cue {
+        Cue {
+            row_id,
+            at: at(secs),
+            action: "noop".into(),
+            signal_id: String::new(),
+            key: Key::Splice(id),
+            edge,
+            label: "splice_insert".into(),
+            type_id: None,
+            duration: None,
+            pts: None,
+            segment_num: None,
+            segments_expected: None,
+            upid: None,
+        }
+    }
+
+    fn seg(row_id: i64, secs: i64, id: u64, t: u8, num: u8, expected: u8) -> Cue {
+        Cue {
+            key: Key::Seg(t & 0xFE, id),
+            edge: if t.is_multiple_of(2) { Edge::Out } else { Edge::In },
+            type_id: Some(t),
+            segment_num: Some(num),
+            segments_expected: Some(expected),
+            upid: Some("ABCD".into()),
+            ..splice(row_id, secs, id, Edge::Out)
+        }
+    }
+
+    fn kinds(issues: &[Issue]) -> Vec<&'static str> {
+        issues.iter().map(|i| i.kind).collect()
+    }
+
+    #[test]
+    fn orphans_duplicates_and_reuse() {
+        let signals = [
+            splice(1, 0, 7, Edge::Out),
+            splice(2, 1, 7, Edge::Out),
+            splice(3, 30, 7, Edge::In),
+            splice(4, 31, 7, Edge::In),
+            splice(5, 40, 9, Edge::In),
+            splice(6, 100, 7, Edge::Out),
+        ];
+        let (breaks, issues) = audit(&signals, 1.0, at(1000));
+        assert_eq!(
+            kinds(&issues),
+            ["duplicate_cue", "duplicate_cue", "orphan_end", "event_id_reused", "orphan_start"]
+        );
+        assert_eq!(issues[0].event_ids, [1, 2]);
+        assert_eq!(breaks.len(), 3);
+        assert_eq!(breaks[0].status, BreakStatus::Complete);
+        assert_eq!(breaks[1].status, BreakStatus::MissingOut);
+        assert_eq!(breaks[2].issues, ["event_id_reused", "orphan_start"]);
+    }
+
+    #[test]
+    fn overlap_only_within_one_kind() {
+        let signals = [
+            seg(1, 0, 1, 0x22, 0, 0),
+            seg(2, 5, 2, 0x30, 0, 0), // nested ad inside the break: fine
+            seg(3, 10, 3, 0x22, 0, 0),
+            seg(4, 20, 2, 0x31, 0, 0),
+            seg(5, 30, 1, 0x23, 0, 0),
+            seg(6, 40, 3, 0x23, 0, 0),
+        ];
+        let (breaks, issues) = audit(&signals, 1.0, at(1000));
+        assert_eq!(kinds(&issues), ["overlap"]);
+        assert_eq!(issues[0].break_ref, "seg:0x22:3");
+        assert!(issues[0].message.contains("seg:0x22:1"));
+        assert_eq!(breaks[0].issues, ["overlap"]);
+        assert!(breaks[1].issues.is_empty());
+    }
+
+    #[test]
+    fn duration_checked_against_pts() {
+        let mut out = splice(1, 0, 7, Edge::Out);
+        out.duration = Some(30.0);
+        out.pts = Some(PTS_MASK - 90_000); // wraps before the in cue
+        let mut back = splice(2, 31, 7, Edge::In);
+        back.pts = Some(29 * 90_000 - 1);
+        let (breaks, issues) = audit(&[out.clone(), back.clone()], 1.0, at(100));
+        assert!(issues.is_empty(), "{issues:?}");
+        assert!((breaks[0].pts_duration_s.unwrap() - 30.0).abs() < 0.001);
+
+        back.pts = Some(27 * 90_000);
+        let (_, issues) = audit(&[out.clone(), back], 1.0, at(100));
+        assert_eq!(kinds(&issues), ["duration_mismatch"]);
+        assert!(issues[0].message.contains("(PTS)"));
+
+        // Declared duration elapsed without an in cue: only a note.
+        let (breaks, issues) = audit(&[out], 1.0, at(100));
+        assert_eq!(breaks[0].status, BreakStatus::Duration);
+        assert_eq!((issues[0].kind, issues[0].severity), ("orphan_start", Severity::Info));
+    }
+
+    #[test]
+    fn segment_num_must_run_in_order() {
+        let signals = [
+            seg(1, 0, 1, 0x30, 1, 3),
+            seg(2, 10, 1, 0x31, 1, 3),
+            seg(3, 10, 2, 0x30, 3, 3),
+            seg(4, 20, 2, 0x31, 3, 3),
+            seg(5, 20, 3, 0x30, 4, 3),
+            seg(6, 30, 3, 0x31, 4, 3),
+        ];
+        let (_, issues) = audit(&signals, 1.0, at(100));
+        assert_eq!(kinds(&issues), ["segment_num_order", "segment_num_order"]);
+        assert_eq!(issues[0].message, "segment_num 3 follows 1 (expected 2)");
+        assert_eq!(issues[1].message, "segment_num 4 outside 1..=3");
+    }
+
+    #[test]
+    fn out_of_range_segment_num_is_not_remembered() {
+        let signals = [
+            seg(1, 0, 1, 0x30, 1, 3),
+            seg(2, 10, 1, 0x31, 1, 3),
+            seg(3, 10, 2, 0x30, 255, 3),
+            seg(4, 20, 2, 0x31, 255, 3),
+            seg(5, 20, 3, 0x30, 2, 3),
+            seg(6, 30, 3, 0x31, 2, 3),
+            seg(7, 30, 4, 0x30, 255, 255),
+            seg(8, 40, 4, 0x31, 255, 255),
+            seg(9, 40, 5, 0x30, 2, 255),
+            seg(10, 50, 5, 0x31, 2, 255),
+        ];
+        let (_, issues) = audit(&signals, 1.0, at(100));
+        let messages: Vec<&str> = issues.iter().map(|i| i.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "segment_num 255 outside 1..=3",
+                "segment_num 255 follows 2 (expected 3)",
+                "segment_num 2 follows 255 (expected 256)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,925 @@
+// src/config_as_code.rs
+//! Declarative configuration: channels and rules from a YAML/TOML/JSON document.
+//!
+//! ```yaml
+//! version: 1
+//! channels:
+//!   - name: east-feed
+//!     timezone: America/New_York
+//!     sesame_min_tier: 2          # SESAME policy (0-3)
+//!     is_global: false
+//!     groups: [ops]               # publish to these groups (by name)
+//!     rules:                      # evaluated in document order
+//!       - name: drop-ads
+//!         match: { anyOf: [ { scte35.command: time_signal } ] }
+//!         action: delete
+//! ```
+//!
+//! `plan` diffs the document against the DB; `apply` converges the DB to it in
+//! one transaction. Channels are matched by name (a soft-deleted channel of the
+//! same name is revived, an unmanaged one is adopted) and rules by name within
+//! their channel. A managed channel's rules are exactly the document's: others
+//! are soft-deleted. Rule priority defaults to 10 × position.
+//!
+//! Every apply has a *source* label (`file:<path>` for `POIS_CONFIG_FILE` at
+//! startup, `api` or `?source=` over HTTP) recorded in `managed_by`. Channels
+//! managed by that source but absent from the document are soft-deleted;
+//! channels of another source are refused. Managed channels and their rules are
+//! read-only in the regular API (409) — change the document instead.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use sqlx::{Pool, Sqlite, SqliteConnection};
+
+use crate::backup::{self, ChannelBackup, RuleBackup};
+use crate::jwt_auth::Claims;
+use crate::rbac;
+use crate::AppState;
+
+pub const CONFIG_VERSION: u32 = 1;
+
+// -------------------------------- document --------------------------------
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigDocument {
+    #[serde(default = "default_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub channels: Vec<ChannelSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelSpec {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    #[serde(default)]
+    pub sesame_min_tier: i64,
+    #[serde(default)]
+    pub is_global: bool,
+    /// Group names the channel is published to (exactly these).
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<RuleSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RuleSpec {
+    pub name: String,
+    /// Defaults to 10 × the rule's position in the document.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i64>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(rename = "match", alias = "match_json", default = "empty_object")]
+    pub match_json: JsonValue,
+    pub action: String,
+    #[serde(alias = "params_json", default = "empty_object")]
+    pub params: JsonValue,
+}
+
+fn default_version() -> u32 {
+    CONFIG_VERSION
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn empty_object() -> JsonValue {
+    json!({})
+}
+
+impl RuleSpec {
+    /// `position` is the rule's index in its channel.
+    fn priority(&self, position: usize) -> i64 {
+        self.priority.unwrap_or(position as i64 * 10)
+    }
+
+    /// The rule as written to the DB.
+    fn to_backup(&self, position: usize) -> RuleBackup {
+        RuleBackup {
+            name: self.name.clone(),
+            match_json: self.match_json.clone(),
+            action: self.action.clone(),
+            params_json: self.params.clone(),
+            priority: self.priority(position),
+            enabled: self.enabled,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Format {
+    /// By file extension; YAML unless `.toml` / `.json`.
+    pub fn from_path(path: &str) -> Self {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".toml") {
+            Format::Toml
+        } else if lower.ends_with(".json") {
+            Format::Json
+        } else {
+            Format::Yaml
+        }
+    }
+
+    fn from_content_type(ct: &str) -> Self {
+        if ct.contains("toml") {
+            Format::Toml
+        } else if ct.contains("json") {
+            Format::Json
+        } else {
+            Format::Yaml
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Yaml => "application/yaml",
+            Format::Toml => "application/toml",
+            Format::Json => "application/json",
+        }
+    }
+}
+
+pub fn parse_document(text: &str, format: Format) -> Result<ConfigDocument, String> {
+    let doc: ConfigDocument = match format {
+        Format::Yaml => serde_yaml::from_str(text).map_err(|e| format!("YAML: {e}"))?,
+        Format::Toml => toml::from_str(text).map_err(|e| format!("TOML: {e}"))?,
+        Format::Json => serde_json::from_str(text).map_err(|e| format!("JSON: {e}"))?,
+    };
+    Ok(doc)
+}
+
+pub fn render_document(doc: &ConfigDocument, format: Format) -> Result<String, String> {
+    match format {
+        Format::Yaml => serde_yaml::to_string(doc).map_err(|e| e.to_string()),
+        Format::Toml => toml::to_string_pretty(doc).map_err(|e| e.to_string()),
+        Format::Json => serde_json::to_string_pretty(doc).map_err(|e| e.to_string()),
+    }
+}
+
+/// Structural checks that need no DB.
+pub fn validate(doc: &ConfigDocument) -> Vec<String> {
+    let mut errors = Vec::new();
+    if doc.version != CONFIG_VERSION {
+        errors.push(format!("unsupported version {} (expected {CONFIG_VERSION})", doc.version));
+    }
+    let mut names = HashSet::new();
+    for ch in &doc.channels {
+        if ch.name.trim().is_empty() {
+            errors.push("channel with an empty name".into());
+        } else if !names.insert(ch.name.as_str()) {
+            errors.push(format!("channel '{}' is declared twice", ch.name));
+        }
+        if !(0..=3).contains(&ch.sesame_min_tier) {
+            errors.push(format!("channel '{}': sesame_min_tier must be 0-3", ch.name));
+        }
+        let mut rule_names = HashSet::new();
+        for (ri, r) in ch.rules.iter().enumerate() {
+            if !r.name.trim().is_empty() && !rule_names.insert(r.name.as_str()) {
+                errors.push(format!("channel '{}': rule '{}' is declared twice", ch.name, r.name));
+            }
+            for problem in backup::rule_problems(&r.to_backup(ri)) {
+                errors.push(format!("channel '{}': rule '{}': {problem}", ch.name, r.name));
+            }
+        }
+    }
+    errors
+}
+
+// ---------------------------------- plan ----------------------------------
+
+/// A channel as it stands in the DB (live or soft-deleted).
+#[derive(Debug, Clone)]
+struct CurrentChannel {
+    id: i64,
+    name: String,
+    enabled: bool,
+    timezone: String,
+    sesame_min_tier: i64,
+    is_global: bool,
+    managed_by: Option<String>,
+    deleted: bool,
+    groups: Vec<String>,
+    rules: Vec<CurrentRule>,
+}
+
+#[derive(Debug, Clone)]
+struct CurrentRule {
+    id: i64,
+    name: String,
+    priority: i64,
+    enabled: bool,
+    match_json: JsonValue,
+    action: String,
+    params: JsonValue,
+    managed_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub from: JsonValue,
+    pub to: JsonValue,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Change {
+    /// "create" | "update" | "delete"
+    pub op: &'static str,
+    /// "channel" | "rule"
+    pub kind: &'static str,
+    pub channel: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Debug)]
+enum RuleOp {
+    Create(usize),
+    Update(i64, usize),
+    Delete(i64),
+}
+
+#[derive(Debug)]
+struct ChannelOp {
+    spec: usize,
+    id: Option<i64>,
+    write_channel: bool,
+    groups: Option<Vec<i64>>,
+    rules: Vec<RuleOp>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct Plan {
+    pub source: String,
+    pub changes: Vec<Change>,
+    /// Channels and rules already matching the document.
+    pub unchanged: u32,
+    /// Set by `apply`.
+    pub applied: bool,
+    #[serde(skip)]
+    ops: Vec<ChannelOp>,
+    #[serde(skip)]
+    deletes: Vec<i64>,
+}
+
+fn field_changes(pairs: Vec<(&'static str, JsonValue, JsonValue)>) -> Vec<FieldChange> {
+    pairs
+        .into_iter()
+        .filter(|(_, from, to)| from != to)
+        .map(|(field, from, to)| FieldChange { field, from, to })
+        .collect()
+}
+
+/// Diff the document against the current state. Pure; `groups` maps live group
+/// names to ids.
+fn diff(
+    doc: &ConfigDocument,
+    source: &str,
+    current: &[CurrentChannel],
+    groups: &HashMap<String, i64>,
+) -> Result<Plan, Vec<String>> {
+    let mut errors = validate(doc);
+    let mut plan = Plan { source: source.to_string(), ..Default::default() };
+    let by_name: HashMap<&str, &CurrentChannel> = current.iter().map(|c| (c.name.as_str(), c)).collect();
+
+    for (si, spec) in doc.channels.iter().enumerate() {
+        let cur = by_name.get(spec.name.as_str()).copied();
+        if let Some(other) = cur.and_then(|c| c.managed_by.as_deref()).filter(|m| *m != source) {
+            errors.push(format!("channel '{}' is managed by {other}", spec.name));
+            continue;
+        }
+
+        let mut group_ids = Vec::new();
+        for g in &spec.groups {
+            match groups.get(g) {
+                Some(id) => group_ids.push(*id),
+                None => errors.push(format!("channel '{}': unknown group '{g}'", spec.name)),
+            }
+        }
+        let mut want_groups = spec.groups.clone();
+        want_groups.sort();
+        want_groups.dedup();
+
+        let mut op = ChannelOp { spec: si, id: cur.map(|c| c.id), write_channel: false, groups: None, rules: Vec::new() };
+        match cur {
+            None => {
+                op.write_channel = true;
+                op.groups = Some(group_ids);
+                plan.changes.push(Change {
+                    op: "create",
+                    kind: "channel",
+                    channel: spec.name.clone(),
+                    rule: None,
+                    fields: Vec::new(),
+                });
+            }
+            Some(c) => {
+                let mut have_groups = c.groups.clone();
+                have_groups.sort();
+                let fields = field_changes(vec![
+                    ("deleted", json!(c.deleted), json!(false)),
+                    ("enabled", json!(c.enabled), json!(spec.enabled)),
+                    ("timezone", json!(c.timezone), json!(spec.timezone)),
+                    ("sesame_min_tier", json!(c.sesame_min_tier), json!(spec.sesame_min_tier)),
+                    ("is_global", json!(c.is_global), json!(spec.is_global)),
+                    ("groups", json!(have_groups), json!(want_groups)),
+                    ("managed_by", json!(c.managed_by), json!(source)),
+                ]);
+                op.write_channel = fields.iter().any(|f| f.field != "groups");
+                if fields.iter().any(|f| f.field == "groups") {
+                    op.groups = Some(group_ids);
+                }
+                if fields.is_empty() {
+                    plan.unchanged += 1;
+                } else {
+                    plan.changes.push(Change {
+                        op: "update",
+                        kind: "channel",
+                        channel: spec.name.clone(),
+                        rule: None,
+                        fields,
+                    });
+                }
+            }
+        }
+
+        // Rules: match by name; the first same-named live rule wins.
+        let have: &[CurrentRule] = cur.map(|c| c.rules.as_slice()).unwrap_or_default();
+        let mut used = HashSet::new();
+        for (ri, r) in spec.rules.iter().enumerate() {
+            let priority = r.priority(ri);
+            let rule_change = |op, fields| Change {
+                op,
+                kind: "rule",
+                channel: spec.name.clone(),
+                rule: Some(r.name.clone()),
+                fields,
+            };
+            match have.iter().find(|h| h.name == r.name && !used.contains(&h.id)) {
+                None => {
+                    op.rules.push(RuleOp::Create(ri));
+                    plan.changes.push(rule_change("create", Vec::new()));
+                }
+                Some(h) => {
+                    used.insert(h.id);
+                    let fields = field_changes(vec![
+                        ("priority", json!(h.priority), json!(priority)),
+                        ("enabled", json!(h.enabled), json!(r.enabled)),
+                        ("match", h.match_json.clone(), r.match_json.clone()),
+                        ("action", json!(h.action), json!(r.action)),
+                        ("params", h.params.clone(), r.params.clone()),
+                        ("managed_by", json!(h.managed_by), json!(source)),
+                    ]);
+                    if fields.is_empty() {
+                        plan.unchanged += 1;
+                    } else {
+                        op.rules.push(RuleOp::Update(h.id, ri));
+                        plan.changes.push(rule_change("update", fields));
+                    }
+                }
+            }
+        }
+        for h in have.iter().filter(|h| !used.contains(&h.id)) {
+            op.rules.push(RuleOp::Delete(h.id));
+            plan.changes.push(Change {
+                op: "delete",
+                kind: "rule",
+                channel: spec.name.clone(),
+                rule: Some(h.name.clone()),
+                fields: Vec::new(),
+            });
+        }
+        plan.ops.push(op);
+    }
+
+    // Channels this source manages that the document no longer lists.
+    let declared: HashSet<&str> = doc.channels.iter().map(|c| c.name.as_str()).collect();
+    for c in current {
+        if !c.deleted && c.managed_by.as_deref() == Some(source) && !declared.contains(c.name.as_str()) {
+            plan.deletes.push(c.id);
+            plan.changes.push(Change {
+                op: "delete",
+                kind: "channel",
+                channel: c.name.clone(),
+                rule: None,
+                fields: Vec::new(),
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(plan)
+    } else {
+        Err(errors)
+    }
+}
+
+// ------------------------------- DB access --------------------------------
+
+type ChannelRow = (i64, String, i64, String, i64, i64, Option<String>, Option<String>);
+type RuleRow = (i64, i64, String, i64, i64, String, String, String, Option<String>);
+
+fn parse_json(s: &str) -> JsonValue {
+    serde_json::from_str(s).unwrap_or(JsonValue::Null)
+}
+
+async fn load_state(conn: &mut SqliteConnection) -> Result<(Vec<CurrentChannel>, HashMap<String, i64>), sqlx::Error> {
+    let rows: Vec<ChannelRow> = sqlx::query_as(
+        "SELECT id, name, enabled, timezone, sesame_min_tier, is_global, managed_by, deleted_at FROM channels",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let links: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT cg.channel_id, g.name FROM channel_groups cg JOIN groups g ON g.id = cg.group_id \
+         WHERE g.deleted_at IS NULL",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let rules: Vec<RuleRow> = sqlx::query_as(
+        "SELECT id, channel_id, name, priority, enabled, match_json, action, params_json, managed_by \
+         FROM rules WHERE deleted_at IS NULL ORDER BY priority, id",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let groups: Vec<(String, i64)> = sqlx::query_as("SELECT name, id FROM groups WHERE deleted_at IS NULL")
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let channels = rows
+        .into_iter()
+        .map(|(id, name, enabled, timezone, tier, is_global, managed_by, deleted_at)| CurrentChannel {
+            id,
+            name,
+            enabled: enabled != 0,
+            timezone,
+            sesame_min_tier: tier,
+            is_global: is_global != 0,
+            managed_by,
+            deleted: deleted_at.is_some(),
+            groups: links.iter().filter(|(c, _)| *c == id).map(|(_, g)| g.clone()).collect(),
+            rules: rules
+                .iter()
+                .filter(|r| r.1 == id)
+                .map(|r| CurrentRule {
+                    id: r.0,
+                    name: r.2.clone(),
+                    priority: r.3,
+                    enabled: r.4 != 0,
+                    match_json: parse_json(&r.5),
+                    action: r.6.clone(),
+                    params: parse_json(&r.7),
+                    managed_by: r.8.clone(),
+                })
+                .collect(),
+        })
+        .collect();
+    Ok((channels, groups.into_iter().collect()))
+}
+
+async fn execute(conn: &mut SqliteConnection, doc: &ConfigDocument, plan: &Plan) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    let source = Some(plan.source.as_str());
+    for op in &plan.ops {
+        let spec = &doc.channels[op.spec];
+        let channel = ChannelBackup { name: spec.name.clone(), enabled: spec.enabled, timezone: spec.timezone.clone() };
+        let channel_id = match op.id {
+            Some(id) => {
+                if op.write_channel {
+                    backup::update_channel(conn, id, &channel).await?;
+                    set_channel_policy(conn, id, spec, &plan.source).await?;
+                }
+                id
+            }
+            None => {
+                let id = backup::insert_channel(conn, &channel, None).await?;
+                set_channel_policy(conn, id, spec, &plan.source).await?;
+                id
+            }
+        };
+
+        if let Some(gids) = &op.groups {
+            sqlx::query("DELETE FROM channel_groups WHERE channel_id = ?")
+                .bind(channel_id)
+                .execute(&mut *conn)
+                .await?;
+            for g in gids {
+                sqlx::query("INSERT OR IGNORE INTO channel_groups (channel_id, group_id) VALUES (?, ?)")
+                    .bind(channel_id)
+                    .bind(g)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+        }
+
+        for rop in &op.rules {
+            match *rop {
+                RuleOp::Create(ri) => {
+                    backup::insert_rule(conn, channel_id, &spec.rules[ri].to_backup(ri), None, source).await?;
+                }
+                RuleOp::Update(id, ri) => {
+                    backup::update_rule(conn, id, &spec.rules[ri].to_backup(ri), source).await?;
+                }
+                RuleOp::Delete(id) => {
+                    sqlx::query("UPDATE rules SET deleted_at = ?, enabled = 0, managed_by = NULL WHERE id = ?")
+                        .bind(&now)
+                        .bind(id)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+            }
+        }
+    }
+
+    for id in &plan.deletes {
+        sqlx::query("UPDATE channels SET deleted_at = ?, enabled = 0, managed_by = NULL WHERE id = ?")
+            .bind(&now)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("UPDATE rules SET managed_by = NULL WHERE channel_id = ?")
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        crate::watchdog::disable_policy(&mut *conn, *id).await?;
+    }
+    Ok(())
+}
+
+/// The channel columns only a config document sets (beyond `ChannelBackup`).
+async fn set_channel_policy(
+    conn: &mut SqliteConnection,
+    channel_id: i64,
+    spec: &ChannelSpec,
+    source: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE channels SET sesame_min_tier = ?, is_global = ?, managed_by = ? WHERE id = ?")
+        .bind(spec.sesame_min_tier)
+        .bind(spec.is_global as i64)
+        .bind(source)
+        .bind(channel_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Why a plan/apply failed: the document is invalid against this DB, or the DB
+/// itself errored.
+#[derive(Debug)]
+pub enum ConfigError {
+    Invalid(Vec<String>),
+    Db(sqlx::Error),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Invalid(errs) => write!(f, "invalid config: {}", errs.join("; ")),
+            ConfigError::Db(e) => write!(f, "database error: {e}"),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ConfigError {
+    fn from(e: sqlx::Error) -> Self {
+        ConfigError::Db(e)
+    }
+}
+
+impl IntoResponse for ConfigError {
+    fn into_response(self) -> Response {
+        match self {
+            ConfigError::Invalid(errors) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "errors": errors }))).into_response()
+            }
+            ConfigError::Db(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+}
+
+/// Diff `doc` against the DB without changing anything.
+pub async fn plan(db: &Pool<Sqlite>, doc: &ConfigDocument, source: &str) -> Result<Plan, ConfigError> {
+    let mut conn = db.acquire().await?;
+    let (current, groups) = load_state(&mut conn).await?;
+    diff(doc, source, &current, &groups).map_err(ConfigError::Invalid)
+}
+
+/// Converge the DB to `doc` in one transaction; returns what was changed.
+pub async fn apply(db: &Pool<Sqlite>, doc: &ConfigDocument, source: &str) -> Result<Plan, ConfigError> {
+    let mut tx = db.begin().await?;
+    let (current, groups) = load_state(&mut tx).await?;
+    let mut plan = diff(doc, source, &current, &groups).map_err(ConfigError::Invalid)?;
+    if !plan.changes.is_empty() {
+        execute(&mut tx, doc, &plan).await?;
+        tx.commit().await?;
+    }
+    plan.applied = true;
+    Ok(plan)
+}
+
+/// Startup: apply `POIS_CONFIG_FILE` (source `file:<path>`).
+pub async fn apply_file(db: &Pool<Sqlite>, path: &str) -> anyhow::Result<Plan> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+    let doc = parse_document(&text, Format::from_path(path)).map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+    apply(db, &doc, &format!("file:{path}")).await.map_err(|e| anyhow::anyhow!("{path}: {e}"))
+}
+
+/// The config source managing a channel, if any.
+pub async fn channel_manager(db: &Pool<Sqlite>, channel_id: i64) -> Option<String> {
+    sqlx::query_as::<_, (Option<String>,)>("SELECT managed_by FROM channels WHERE id = ?")
+        .bind(channel_id)
+        .fetch_optional(db)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|(m,)| m)
+}
+
+/// 409 for an API write to a config-managed channel or its rules.
+pub fn read_only(source: &str) -> Response {
+    (
+        StatusCode::CONFLICT,
+        format!("Channel is managed by config ({source}); change the config document instead"),
+    )
+        .into_response()
+}
+
+// -------------------------------- handlers --------------------------------
+
+#[derive(Deserialize, Default)]
+pub struct ConfigQuery {
+    /// Overrides the Content-Type (`yaml` | `toml` | `json`).
+    #[serde(default)]
+    pub format: Option<Format>,
+    /// Source label recorded in `managed_by` (default `api`).
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+async fn super_only(st: &AppState, claims: &Claims) -> Option<Response> {
+    let eff = rbac::effective(&st.db, claims).await;
+    (!eff.super_admin).then(|| (StatusCode::FORBIDDEN, "Admin only").into_response())
+}
+
+fn document_from_request(q: &ConfigQuery, headers: &HeaderMap, body: &str) -> Result<ConfigDocument, String> {
+    let format = q.format.unwrap_or_else(|| {
+        let ct = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or("");
+        Format::from_content_type(ct)
+    });
+    parse_document(body, format)
+}
+
+fn source_label(q: &ConfigQuery) -> String {
+    q.source.clone().filter(|s| !s.trim().is_empty()).unwrap_or_else(|| "api".into())
+}
+
+/// POST /api/config/plan — diff a document (request body) against the DB.
+pub async fn plan_handler(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(q): Query<ConfigQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    if let Some(rej) = super_only(&st, &claims).await {
+        return rej;
+    }
+    let doc = match document_from_request(&q, &headers, &body) {
+        Ok(d) => d,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    match plan(&st.db, &doc, &source_label(&q)).await {
+        Ok(p) => Json(p).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// POST /api/config/apply — converge the DB to a document (request body).
+pub async fn apply_handler(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(q): Query<ConfigQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    if let Some(rej) = super_only(&st, &claims).await {
+        return rej;
+    }
+    let doc = match document_from_request(&q, &headers, &body) {
+        Ok(d) => d,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let source = source_label(&q);
+    match apply(&st.db, &doc, &source).await {
+        Ok(p) => {
+            tracing::info!(source = %source, changes = p.changes.len(), "config applied");
+            Json(p).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// GET /api/config/export — the live channels as a document (to start a
+/// config repo from an existing instance).
+pub async fn export_handler(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(q): Query<ConfigQuery>,
+) -> Response {
+    if let Some(rej) = super_only(&st, &claims).await {
+        return rej;
+    }
+    let mut conn = match st.db.acquire().await {
+        Ok(c) => c,
+        Err(e) => return ConfigError::Db(e).into_response(),
+    };
+    let (current, _) = match load_state(&mut conn).await {
+        Ok(s) => s,
+        Err(e) => return ConfigError::Db(e).into_response(),
+    };
+    let mut channels: Vec<&CurrentChannel> = current.iter().filter(|c| !c.deleted).collect();
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+    let doc = ConfigDocument {
+        version: CONFIG_VERSION,
+        channels: channels
+            .into_iter()
+            .map(|c| ChannelSpec {
+                name: c.name.clone(),
+                enabled: c.enabled,
+                timezone: c.timezone.clone(),
+                sesame_min_tier: c.sesame_min_tier,
+                is_global: c.is_global,
+                groups: c.groups.clone(),
+                rules: c
+                    .rules
+                    .iter()
+                    .map(|r| RuleSpec {
+                        name: r.name.clone(),
+                        priority: Some(r.priority),
+                        enabled: r.enabled,
+                        match_json: r.match_json.clone(),
+                        action: r.action.clone(),
+                        params: r.params.clone(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+    let format = q.format.unwrap_or(Format::Yaml);
+    match render_document(&doc, format) {
+        Ok(text) => ([(header::CONTENT_TYPE, format.content_type())], text).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"
+channels:
+  - name: east
+    sesame_min_tier: 2
+    groups: [ops]
+    rules:
+      - name: drop
+        match: { anyOf: [ { "scte35.command": time_signal } ] }
+        action: delete
+      - name: pass
+        action: noop
+"#;
+
+    fn groups() -> HashMap<String, i64> {
+        HashMap::from([("ops".to_string(), 7)])
+    }
+
+    fn current_east(managed_by: Option<&str>) -> CurrentChannel {
+        CurrentChannel {
+            id: 3,
+            name: "east".into(),
+            enabled: true,
+            timezone: "UTC".into(),
+            sesame_min_tier: 2,
+            is_global: false,
+            managed_by: managed_by.map(str::to_string),
+            deleted: false,
+            groups: vec!["ops".into()],
+            rules: vec![
+                CurrentRule {
+                    id: 10,
+                    name: "drop".into(),
+                    priority: 0,
+                    enabled: true,
+                    match_json: json!({"anyOf": [{"scte35.command": "time_signal"}]}),
+                    action: "delete".into(),
+                    params: json!({}),
+                    managed_by: managed_by.map(str::to_string),
+                },
+                CurrentRule {
+                    id: 11,
+                    name: "manual".into(),
+                    priority: 5,
+                    enabled: true,
+                    match_json: json!({}),
+                    action: "noop".into(),
+                    params: json!({}),
+                    managed_by: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn yaml_and_toml_parse_to_the_same_document() {
+        let yaml = parse_document(DOC, Format::Yaml).unwrap();
+        let toml_text = render_document(&yaml, Format::Toml).unwrap();
+        let back = parse_document(&toml_text, Format::Toml).unwrap();
+        assert_eq!(serde_json::to_value(&yaml).unwrap(), serde_json::to_value(&back).unwrap());
+        assert!(validate(&yaml).is_empty());
+        assert!(parse_document("channels: [{name: a, bogus: 1}]", Format::Yaml).is_err());
+    }
+
+    #[test]
+    fn fresh_db_creates_everything() {
+        let doc = parse_document(DOC, Format::Yaml).unwrap();
+        let plan = diff(&doc, "api", &[], &groups()).unwrap();
+        let ops: Vec<_> = plan.changes.iter().map(|c| (c.op, c.kind)).collect();
+        assert_eq!(ops, vec![("create", "channel"), ("create", "rule"), ("create", "rule")]);
+        assert_eq!(plan.ops[0].groups, Some(vec![7]));
+    }
+
+    #[test]
+    fn converged_channel_only_diffs_rules() {
+        let doc = parse_document(DOC, Format::Yaml).unwrap();
+        let plan = diff(&doc, "api", &[current_east(Some("api"))], &groups()).unwrap();
+        // channel + "drop" unchanged; "pass" created; hand-made "manual" removed.
+        assert_eq!(plan.unchanged, 2);
+        let ops: Vec<_> = plan.changes.iter().map(|c| (c.op, c.rule.as_deref())).collect();
+        assert_eq!(ops, vec![("create", Some("pass")), ("delete", Some("manual"))]);
+        assert!(!plan.ops[0].write_channel);
+    }
+
+    #[test]
+    fn adoption_other_sources_and_pruning() {
+        let doc = parse_document(DOC, Format::Yaml).unwrap();
+        let plan = diff(&doc, "api", &[current_east(None)], &groups()).unwrap();
+        let ch = &plan.changes[0];
+        assert_eq!((ch.op, ch.kind), ("update", "channel"));
+        assert_eq!(ch.fields, vec![FieldChange { field: "managed_by", from: JsonValue::Null, to: json!("api") }]);
+
+        let errs = diff(&doc, "api", &[current_east(Some("file:/x.yaml"))], &groups()).unwrap_err();
+        assert_eq!(errs, vec!["channel 'east' is managed by file:/x.yaml"]);
+
+        let empty = ConfigDocument { version: 1, channels: vec![] };
+        let plan = diff(&empty, "api", &[current_east(Some("api"))], &groups()).unwrap();
+        assert_eq!(plan.deletes, vec![3]);
+        let untouched = diff(&empty, "api", &[current_east(None)], &groups()).unwrap();
+        assert!(untouched.changes.is_empty());
+    }
+
+    #[test]
+    fn validation_catches_bad_documents() {
+        let doc = parse_document(
+            "version: 2\nchannels:\n  - {name: a, sesame_min_tier: 5, groups: [nope], rules: [{name: r, action: noop}, {name: r, action: ''}]}\n  - {name: a}\n",
+            Format::Yaml,
+        )
+        .unwrap();
+        let errs = diff(&doc, "api", &[], &groups()).unwrap_err();
+        assert!(errs.iter().any(|e| e.contains("unsupported version")));
+        assert!(errs.iter().any(|e| e.contains("sesame_min_tier")));
+        assert!(errs.iter().any(|e| e.contains("rule 'r' is declared twice")));
+        assert!(errs.iter().any(|e| e.contains("has no action")));
+        assert!(errs.iter().any(|e| e.contains("'a' is declared twice")));
+        assert!(errs.iter().any(|e| e.contains("unknown group 'nope'")));
+    }
+}
@@ -1,8 +1,17 @@
 // src/esam.rs
-// Version: 2.3.0
-// Updated: 2026-03-12
+// Version: 2.5.0
+// Updated: 2026-10-18
 //
 // Changelog:
+// v2.5.0 (2026-10-18):
+//   - decode_scte35_details keeps every segmentation descriptor in the section
+//     (Scte35Info::segmentations); extract_facts publishes them as
+//     `scte35.segmentations` alongside the existing last-descriptor facts
+//   - UPID type names/decoders follow SCTE 35 Table 21 (MID is 0x0D, URI 0x0F,
+//     UUID 0x10); MID bodies are split into child UPIDs via splice_info::mid_children
+// v2.4.0 (2026-10-18):
+//   - decode_scte35_details parses through splice_info::SpliceInfoSection; the
+//     private BitReader / splice_time / splice_insert walkers are gone
 // v2.3.0 (2026-03-12):
 //   - extract_facts now extracts acquisitionPointIdentity from AcquiredSignal
 //   - build_notification: added acq_point param; echoes inbound acquisitionPointIdentity
@@ -26,6 +35,7 @@
 
 use base64::engine::general_purpose::STANDARD as B64;
 use base64::Engine;
+use pois_esam_server::splice_info::{self, SpliceCommand, SpliceInfoSection};
 use quick_xml::{events::Event, Reader};
 use serde_json::json;
 use tracing::{debug, warn, error, info};
@@ -118,6 +128,7 @@ pub fn extract_facts(esam_xml: &str) -> Result<serde_json::Value, String> {
     let mut seg_upid_repr = None;
     let mut upid_type_name = None;
     let mut pts_time = None;
+    let mut segmentations = Vec::new();
     
     if let Some(ref b64) = scte35_b64 {
         debug!("extract_facts: Found SCTE-35 base64 (length={}), calling decode_scte35_details", b64.len());
@@ -143,6 +154,19 @@ pub fn extract_facts(esam_xml: &str) -> Result<serde_json::Value, String> {
                            upid_type_name.as_ref().unwrap(), seg_upid_repr.as_ref().unwrap());
                 }
                 
+                segmentations = info
+                    .segmentations
+                    .iter()
+                    .filter(|seg| !seg.cancelled)
+                    .map(|seg| json!({
+                        "segmentation_event_id": seg.event_id,
+                        "segmentation_type_id": format!("0x{:02X}", seg.type_id),
+                        "segmentation_type_name": decode_segmentation_type_name(seg.type_id),
+                        "segmentation_upid": decode_upid_data(seg.upid_type, &seg.upid),
+                        "upid_type_name": decode_upid_type_name(seg.upid_type),
+                    }))
+                    .collect();
+
                 pts_time = info.pts_time;
                 if let Some(pts) = pts_time {
                     debug!("extract_facts: Extracted PTS time={}", pts);
@@ -177,6 +201,7 @@ pub fn extract_facts(esam_xml: &str) -> Result<serde_json::Value, String> {
     if let Some(u) = seg_upid_repr { out["scte35.segmentation_upid"] = json!(u); }
     if let Some(upid_name) = upid_type_name { out["scte35.upid_type_name"] = json!(upid_name); }
     if let Some(pts) = pts_time { out["scte35.pts_time"] = json!(pts); }
+    if !segmentations.is_empty() { out["scte35.segmentations"] = json!(segmentations); }
     
     Ok(out)
 }
@@ -316,7 +341,7 @@ fn decode_segmentation_type_name(type_id: u8) -> String {
     }
 }
 
-/// Decode UPID type to human-readable name
+/// Decode UPID type to human-readable name (SCTE 35 Table 21)
 fn decode_upid_type_name(upid_type: u8) -> String {
     match upid_type {
         0x00 => "Not Used".to_string(),
@@ -324,18 +349,19 @@ fn decode_upid_type_name(upid_type: u8) -> String {
         0x02 => "ISCI (Deprecated)".to_string(), 
         0x03 => "Ad-ID".to_string(),
         0x04 => "UMID".to_string(),
-        0x05 => "ISAN".to_string(),
-        0x06 => "V-ISAN".to_string(),
-        0x07 => "TI".to_string(),
-        0x08 => "ADI".to_string(),
-        0x09 => "EIDR".to_string(),
-        0x0A => "ATSC Content Identifier".to_string(),
-        0x0B => "MPU".to_string(),
-        0x0C => "MID".to_string(),
-        0x0D => "ADS Information".to_string(),
-        0x0E => "URI".to_string(),
-        0x0F => "UUID".to_string(),
-        0x10 => "SCR".to_string(),
+        0x05 => "ISAN (Deprecated)".to_string(),
+        0x06 => "ISAN".to_string(),
+        0x07 => "TID".to_string(),
+        0x08 => "TI".to_string(),
+        0x09 => "ADI".to_string(),
+        0x0A => "EIDR".to_string(),
+        0x0B => "ATSC Content Identifier".to_string(),
+        0x0C => "MPU".to_string(),
+        0x0D => "MID".to_string(),
+        0x0E => "ADS Information".to_string(),
+        0x0F => "URI".to_string(),
+        0x10 => "UUID".to_string(),
+        0x11 => "SCR".to_string(),
         _ => format!("Reserved/Unknown (0x{:02X})", upid_type),
     }
 }
@@ -345,13 +371,13 @@ fn decode_upid_data(upid_type: u8, data: &[u8]) -> String {
     match upid_type {
         0x00 => "Not Used".to_string(),
         0x03 => decode_ad_id(data),        // Ad-ID
-        0x05 => decode_isan(data),         // ISAN
-        0x07 => decode_ti(data),           // TI
-        0x08 => decode_adi(data),          // ADI
-        0x09 => decode_eidr(data),         // EIDR
-        0x0C => decode_mid(data),          // MID
-        0x0E => decode_uri(data),          // URI
-        0x0F => decode_uuid(data),         // UUID
+        0x05 | 0x06 => decode_isan(data),  // ISAN
+        0x08 => decode_ti(data),           // TI
+        0x09 => decode_adi(data),          // ADI
+        0x0A => decode_eidr(data),         // EIDR
+        0x0D => decode_mid(data),          // MID
+        0x0F => decode_uri(data),          // URI
+        0x10 => decode_uuid(data),         // UUID
         _ => {
             // For unknown types, show both ASCII (if printable) and hex
             if is_ascii_printable(data) {
@@ -408,26 +434,16 @@ fn decode_mid(data: &[u8]) -> String {
     if data.is_empty() {
         return "MID: (empty)".to_string();
     }
-    
-    let mut result = String::from("MID: ");
-    let mut pos = 0;
-    
-    while pos < data.len() {
-        if pos + 1 >= data.len() { break; }
-        
-        let sub_type = data[pos];
-        let sub_len = data[pos + 1] as usize;
-        pos += 2;
-        
-        if pos + sub_len > data.len() { break; }
-        
-        let sub_data = &data[pos..pos + sub_len];
-        result.push_str(&format!("[Type 0x{:02X}: {}] ", sub_type, 
-            decode_upid_data(sub_type, sub_data)));
-        pos += sub_len;
+    match splice_info::mid_children(data) {
+        Some(children) => {
+            let parts: Vec<String> = children
+                .iter()
+                .map(|(sub_type, sub_data)| format!("[Type 0x{:02X}: {}]", sub_type, decode_upid_data(*sub_type, sub_data)))
+                .collect();
+            format!("MID: {}", parts.join(" "))
+        }
+        None => format!("MID (invalid): hex:{}", hex_encode(data)),
     }
-    
-    result
 }
 
 fn decode_uri(data: &[u8]) -> String {
@@ -450,7 +466,7 @@ fn decode_uuid(data: &[u8]) -> String {
     }
 }
 
-fn xml_escape(s: &str) -> String {
+pub(crate) fn xml_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
@@ -478,45 +494,20 @@ pub struct Scte35Info {
     pub segmentation_type_id: Option<u8>,
     pub segmentation_upid_with_type: Option<(u8, Vec<u8>)>, // (type, data)
     pub pts_time: Option<u64>,
+    /// Every segmentation descriptor in section order, cancelled ones included.
+    pub segmentations: Vec<SegmentationInfo>,
 }
 
-/// Minimal bit reader for SCTE-35 parsing.
-struct BitReader<'a> {
-    data: &'a [u8],
-    bitpos: usize,
-}
-
-impl<'a> BitReader<'a> {
-    fn new(data: &'a [u8]) -> Self { Self { data, bitpos: 0 } }
-
-    fn read_u8(&mut self, nbits: u32) -> Result<u8, String>  { Ok(self.read_bits(nbits)? as u8) }
-    fn read_u16(&mut self, nbits: u32) -> Result<u16, String> { Ok(self.read_bits(nbits)? as u16) }
-    fn read_u32(&mut self, nbits: u32) -> Result<u32, String> { Ok(self.read_bits(nbits)? as u32) }
-
-    #[allow(dead_code)]
-    fn read_u64(&mut self, nbits: u32) -> Result<u64, String> { self.read_bits(nbits) }
-
-    fn read_bits(&mut self, nbits: u32) -> Result<u64, String> {
-        let mut v = 0u64;
-        for _ in 0..nbits {
-            let idx = self.bitpos / 8;
-            if idx >= self.data.len() { return Err("out of bounds".into()); }
-            let byte = self.data[idx];
-            let bit = 7 - (self.bitpos % 8);
-            v = (v << 1) | (((byte >> bit) & 1) as u64);
-            self.bitpos += 1;
-        }
-        Ok(v)
-    }
-
-    fn skip_bits(&mut self, nbits: u32) -> Result<(), String> {
-        self.bitpos = self.bitpos.checked_add(nbits as usize).ok_or_else(|| "overflow".to_string())?;
-        if self.bitpos / 8 > self.data.len() { return Err("out of bounds".into()); }
-        Ok(())
-    }
+pub struct SegmentationInfo {
+    pub event_id: u32,
+    pub cancelled: bool,
+    pub type_id: u8,
+    pub upid_type: u8,
+    pub upid: Vec<u8>,
 }
 
 /// Return command + optional segmentation details + PTS from SCTE-35
+/// v2.4.0: Parsed through the shared typed `splice_info::SpliceInfoSection`
 /// v2.2.1: Fixed parse_splice_insert_pts to include missing final fields
 /// v2.2.0: Fixed descriptor_loop_length parsing with proper bit masking
 pub fn decode_scte35_details(b64: &str) -> Result<Scte35Info, String> {
@@ -536,262 +527,59 @@ pub fn decode_scte35_details(b64: &str) -> Result<Scte35Info, String> {
         return Ok(Scte35Info::default());
     }
     
-    let mut br = BitReader::new(&bytes);
-    
-    let table_id = br.read_u8(8).map_err(|e| {
-        let err_msg = format!("Failed to read table_id: {e}");
+    let section = SpliceInfoSection::decode(&bytes).map_err(|e| {
+        let err_msg = e.to_string();
         error!("SCTE-35 DECODE FAILED: {}", err_msg);
         err_msg
     })?;
-    
-    debug!("decode_scte35_details: table_id=0x{:02x}", table_id);
-    
-    if table_id != 0xFC { 
-        warn!("SCTE-35: table_id mismatch (expected 0xFC, got 0x{:02x}) - returning default", table_id);
-        return Ok(Scte35Info::default()); 
+
+    if section.encrypted_packet {
+        warn!("SCTE-35: encrypted splice command - command left as 'unknown'");
+        return Ok(Scte35Info::default());
     }
 
-    debug!("decode_scte35_details: Skipping section header (16 bits)...");
-    // section_syntax_indicator, private_indicator, reserved(2), section_length(12)
-    br.skip_bits(1 + 1 + 2 + 12).map_err(|e| {
-        let err_msg = format!("Failed to skip section header: {e} at bitpos {}", br.bitpos);
-        error!("SCTE-35 DECODE FAILED: {}", err_msg);
-        err_msg
-    })?;
-    
-    debug!("decode_scte35_details: After section header, bitpos={}", br.bitpos);
-    
-    debug!("decode_scte35_details: Skipping SCTE-35 header fields (68 bits)...");
-    // protocol_version(8), encrypted(1), encryption_algorithm(6), pts_adjustment(33), cw_index(8), tier(12)
-    br.skip_bits(8 + 1 + 6 + 33 + 8 + 12).map_err(|e| {
-        let err_msg = format!("Failed to skip SCTE-35 header: {e} at bitpos {} (byte {})", 
-                             br.bitpos, br.bitpos / 8);
-        error!("SCTE-35 DECODE FAILED: {}", err_msg);
-        err_msg
-    })?;
-    
-    debug!("decode_scte35_details: After header fields, bitpos={} (byte {})", 
-           br.bitpos, br.bitpos / 8);
+    let command = &section.splice_command;
+    if let SpliceCommand::Unknown { command_type, .. } = command {
+        warn!("SCTE-35: Unrecognized splice_command_type 0x{:02x}, treating as 'unknown'", command_type);
+    }
 
-    debug!("decode_scte35_details: Reading splice_command_length (12 bits)...");
-    let splice_command_length = br.read_u16(12).map_err(|e| {
-        let err_msg = format!("Failed to read splice_command_length: {e} at bitpos {} (byte {})", 
-                             br.bitpos, br.bitpos / 8);
-        error!("SCTE-35 DECODE FAILED: {}", err_msg);
-        err_msg
-    })? as usize;
-    
-    debug!("decode_scte35_details: Reading splice_command_type (8 bits)...");
-    let splice_command_type = br.read_u8(8).map_err(|e| {
-        let err_msg = format!("Failed to read splice_command_type: {e} at bitpos {} (byte {})", 
-                             br.bitpos, br.bitpos / 8);
-        error!("SCTE-35 DECODE FAILED: {}", err_msg);
-        err_msg
-    })?;
-    
-    debug!("decode_scte35_details: splice_command_length={}, splice_command_type=0x{:02x}", 
-           splice_command_length, splice_command_type);
-    
-    let command_name = match splice_command_type {
-        0x00 => "splice_null",
-        0x04 => "splice_schedule", 
-        0x05 => "splice_insert",
-        0x06 => "time_signal",
-        0x07 => "bandwidth_reservation",
-        0xFF => "private_command",
-        _ => {
-            warn!("SCTE-35: Unrecognized splice_command_type 0x{:02x}, treating as 'unknown'", 
-                  splice_command_type);
-            "unknown"
-        }
-    };
-    
-    debug!("decode_scte35_details: ✅ Successfully decoded command: {}", command_name);
-    
     let mut info = Scte35Info {
-        command: Some(command_name.into()),
+        command: Some(command.name().into()),
         segmentation_type_id: None,
         segmentation_upid_with_type: None,
-        pts_time: None,
+        pts_time: command.pts_time(),
+        segmentations: Vec::new(),
     };
-
-    // Parse command-specific data to extract PTS times
-    match splice_command_type {
-        0x05 => {
-            debug!("decode_scte35_details: Parsing splice_insert for PTS...");
-            // splice_insert() command - parse for PTS time
-            if let Ok(pts) = parse_splice_insert_pts(&mut br) {
-                info.pts_time = pts;
-                debug!("decode_scte35_details: Extracted PTS: {:?}", pts);
-            }
-        },
-        0x06 => {
-            debug!("decode_scte35_details: Parsing time_signal for PTS...");
-            // time_signal() command - parse splice_time()
-            if let Ok(pts) = parse_splice_time(&mut br) {
-                info.pts_time = pts;
-                debug!("decode_scte35_details: Extracted PTS: {:?}", pts);
-            }
-        },
-        _ => {
-            debug!("decode_scte35_details: Command type 0x{:02x} - no PTS extraction needed", splice_command_type);
-        }
-    }
-
-    // v2.2.1 FIX: parse_splice_insert_pts now includes all final fields (break_duration, unique_program_id, avail_num, avails_expected)
-    // v2.2.0 FIX: REMOVED the v2.1.0 skip logic - it was causing wrong bitpos!
-    // The command parsers already consume the correct number of bytes.
-    // descriptor_loop_length immediately follows the command data.
-
-    info!("decode_scte35_details: After command, bitpos={} (byte {})", br.bitpos, br.bitpos / 8);
-
-    // v2.2.0 FIX: descriptor_loop_length is 10 bits with 6 reserved bits
-    debug!("decode_scte35_details: Reading descriptor_loop_length (16 bits with masking)...");
-    let descriptor_word = br.read_u16(16).map_err(|e| {
-        let err_msg = format!("Failed to read descriptor_loop_length: {e} at bitpos {} (byte {})", 
-                             br.bitpos, br.bitpos / 8);
-        warn!("SCTE-35 WARNING: {}", err_msg);
-        err_msg
-    })?;
-    let descriptor_loop_length = (descriptor_word & 0x03FF) as usize;  // Mask to get lower 10 bits
-    
-    info!("decode_scte35_details: descriptor_loop_word=0x{:04X}, masked_length={} bytes", 
-          descriptor_word, descriptor_loop_length);
-    
-    let loop_end = br.bitpos + descriptor_loop_length * 8;
-    while br.bitpos + 16 <= loop_end {
-        let tag = br.read_u8(8)?;
-        let len = br.read_u8(8)? as usize;
-        let desc_end = br.bitpos + len * 8;
-
-        if tag == 0x02 {
-            debug!("decode_scte35_details: Found segmentation_descriptor (tag=0x02, length={})", len);
-            // segmentation_descriptor
-            let _cuei = br.read_u32(32)?;
-            let _event_id = br.read_u32(32)?;
-            let cancel = br.read_u8(1)? == 1;
-            br.skip_bits(7)?;
-            if !cancel {
-                let program_flag = br.read_u8(1)? == 1;
-                let duration_flag = br.read_u8(1)? == 1;
-                let delivery_not_restricted = br.read_u8(1)? == 1;
-                
-                // v2.1.0 FIX: Always skip 5 bits (either reserved or restriction flags)
-                br.skip_bits(5)?;
-                
-                if !program_flag {
-                    let count = br.read_u8(8)? as usize;
-                    for _ in 0..count {
-                        br.skip_bits(8 + 7 + 33)?; // component_tag + reserved + pts_offset
-                    }
-                }
-                if duration_flag { br.skip_bits(40)?; } // segmentation_duration
-                
-                // Extract UPID type and data
-                let upid_type = br.read_u8(8)?;
-                let upid_len  = br.read_u8(8)? as usize;
-                if upid_len > 0 {
-                    let mut upid = Vec::with_capacity(upid_len);
-                    for _ in 0..upid_len { upid.push(br.read_u8(8)?); }
-                    info.segmentation_upid_with_type = Some((upid_type, upid));
-                    debug!("decode_scte35_details: Extracted UPID (type=0x{:02x}, {} bytes)", 
-                           upid_type, upid_len);
-                }
-                
-                if br.bitpos + 24 <= desc_end {
-                    let seg_type_id = br.read_u8(8)?;
-                    info.segmentation_type_id = Some(seg_type_id);
-                    info!("decode_scte35_details: Extracted segmentation_type_id=0x{:02x}", seg_type_id);
-                    br.skip_bits(16)?; // segment_num + segments_expected
-                }
-                
-                // Log delivery_not_restricted for debugging
-                debug!("decode_scte35_details: delivery_not_restricted={}", delivery_not_restricted);
-            }
-        } else {
-            debug!("decode_scte35_details: Skipping descriptor tag=0x{:02x}, length={}", tag, len);
+    debug!("decode_scte35_details: command={}, pts={:?}", command.name(), info.pts_time);
+
+    // Every descriptor is kept; the scalar fields still report the last
+    // non-cancelled one, as before.
+    for seg in section.segmentation_descriptors() {
+        info.segmentations.push(SegmentationInfo {
+            event_id: seg.segmentation_event_id,
+            cancelled: seg.segmentation_event_cancel_indicator,
+            type_id: seg.segmentation_type_id,
+            upid_type: seg.segmentation_upid_type,
+            upid: seg.segmentation_upid.clone(),
+        });
+        if seg.segmentation_event_cancel_indicator {
+            continue;
         }
-
-        if br.bitpos < desc_end {
-            br.skip_bits((desc_end - br.bitpos) as u32)?;
+        if !seg.segmentation_upid.is_empty() {
+            info.segmentation_upid_with_type = Some((seg.segmentation_upid_type, seg.segmentation_upid.clone()));
+            debug!("decode_scte35_details: Extracted UPID (type=0x{:02x}, {} bytes)", 
+                   seg.segmentation_upid_type, seg.segmentation_upid.len());
         }
+        info.segmentation_type_id = Some(seg.segmentation_type_id);
+        debug!("decode_scte35_details: delivery_not_restricted={}", seg.delivery_not_restricted_flag);
     }
 
-    info!("decode_scte35_details: ✅ DECODE COMPLETE - command={}, type_id={:?}, has_upid={}", 
-           command_name, info.segmentation_type_id, info.segmentation_upid_with_type.is_some());
+    info!("decode_scte35_details: ✅ DECODE COMPLETE - command={}, type_id={:?}, has_upid={}, segmentations={}", 
+           command.name(), info.segmentation_type_id, info.segmentation_upid_with_type.is_some(), info.segmentations.len());
     
     Ok(info)
 }
 
-/// Parse splice_time() structure from time_signal command
-fn parse_splice_time(br: &mut BitReader) -> Result<Option<u64>, String> {
-    let time_specified_flag = br.read_u8(1)? == 1;
-    if time_specified_flag {
-        // reserved 6 bits, then 33-bit pts_time
-        br.skip_bits(6)?;
-        let pts_time = br.read_bits(33)?;
-        Ok(Some(pts_time))
-    } else {
-        // reserved 7 bits, no pts_time
-        br.skip_bits(7)?;
-        Ok(None)
-    }
-}
-
-/// Parse splice_insert() command for PTS time
-fn parse_splice_insert_pts(br: &mut BitReader) -> Result<Option<u64>, String> {
-    let _splice_event_id = br.read_u32(32)?;
-    let splice_event_cancel_indicator = br.read_u8(1)? == 1;
-    br.skip_bits(7)?; // reserved
-    
-    if !splice_event_cancel_indicator {
-        let _out_of_network_indicator = br.read_u8(1)?;
-        let program_splice_flag = br.read_u8(1)? == 1;
-        let duration_flag = br.read_u8(1)? == 1;
-        let splice_immediate_flag = br.read_u8(1)? == 1;
-        br.skip_bits(4)?; // reserved
-        
-        let mut pts_result = None;
-        
-        if program_splice_flag && !splice_immediate_flag {
-            // Parse splice_time() for program splice
-            pts_result = parse_splice_time(br)?;
-        } else if !program_splice_flag {
-            let component_count = br.read_u8(8)? as usize;
-            for _ in 0..component_count {
-                let _component_tag = br.read_u8(8)?;
-                if !splice_immediate_flag {
-                    // Each component has a splice_time()
-                    if let Ok(Some(pts)) = parse_splice_time(br) {
-                        if pts_result.is_none() {
-                            pts_result = Some(pts);
-                        }
-                    }
-                }
-            }
-        }
-        
-        // v2.2.1 CRITICAL FIX: Parse remaining splice_insert fields
-        // These fields must be consumed to advance bitpos correctly for descriptor_loop_length
-        // Without this, bitpos stops at byte 20 instead of byte 29 (72 bits short)
-        
-        // Parse break_duration if duration_flag is set (40 bits)
-        if duration_flag {
-            let _auto_return = br.read_u8(1)?;
-            br.skip_bits(6)?; // reserved
-            let _duration_ticks = br.read_u64(33)?;
-        }
-        
-        // Always present at end of splice_insert: unique_program_id (16), avail_num (8), avails_expected (8)
-        let _unique_program_id = br.read_u16(16)?;
-        let _avail_num = br.read_u8(8)?;
-        let _avails_expected = br.read_u8(8)?;
-        
-        return Ok(pts_result);
-    }
-    
-    Ok(None)
-}
 #[cfg(test)]
 mod extract_facts_tests {
     use super::*;
@@ -811,4 +599,33 @@ mod extract_facts_tests {
         assert_eq!(f["acquisitionPointIdentity"], "SportsFeed-East");
         assert_eq!(f["acquisitionSignalID"], "sig-1");
     }
+
+    #[test]
+    fn every_segmentation_descriptor_is_reported() {
+        use pois_esam_server::splice_info::{SegmentationDescriptor, SpliceDescriptor, SpliceTime};
+        let seg = |event_id, type_id, upid: &[u8]| {
+            SpliceDescriptor::Segmentation(SegmentationDescriptor {
+                segmentation_event_id: event_id,
+                segmentation_type_id: type_id,
+                segmentation_upid_type: 0x0D,
+                segmentation_upid: upid.to_vec(),
+                ..Default::default()
+            })
+        };
+        let mut section = SpliceInfoSection::new(SpliceCommand::TimeSignal(SpliceTime::immediate()));
+        section.descriptors = vec![seg(1, 0x34, b"\x0F\x03a:b"), seg(2, 0x30, b"\x03\x02AD")];
+        let b64 = B64.encode(section.encode().unwrap());
+
+        let info = decode_scte35_details(&b64).unwrap();
+        assert_eq!(info.segmentations.len(), 2);
+        assert_eq!(info.segmentation_type_id, Some(0x30), "scalar fields keep the last descriptor");
+
+        let xml = format!(
+            r#"<SignalProcessingEvent><AcquiredSignal acquisitionSignalID="s"><BinaryData>{b64}</BinaryData></AcquiredSignal></SignalProcessingEvent>"#
+        );
+        let f = extract_facts(&xml).expect("parse");
+        assert_eq!(f["scte35.segmentations"][0]["segmentation_type_id"], "0x34");
+        assert_eq!(f["scte35.segmentations"][0]["segmentation_upid"], "MID: [Type 0x0F: URI: a:b]");
+        assert_eq!(f["scte35.segmentations"][1]["upid_type_name"], "MID");
+    }
 }
@@ -0,0 +1,388 @@
+// src/event_export.rs
+//! Streaming event export: `GET /api/events/export`.
+//!
+//! Takes every `/api/events` filter (`channel`, `action`, `since`, `until`,
+//! `rule_id`, `status`, `sesame_tier`, `upid`, `search`) and writes the
+//! matching rows as CSV (default) or NDJSON (`format=ndjson`), oldest first
+//! unless `order=desc`, optionally capped by `limit`. With `decode=true` each
+//! row also carries its SCTE-35 decoded by the tools decoder: flat
+//! `scte35_*` columns in CSV, the full `scte35_decoded` object in NDJSON.
+//!
+//! Rows are read with a cursor and written to the response in ~32 KiB chunks
+//! as they arrive, so an export of the whole table never sits in memory. The
+//! caller only sees channels they may read (same RBAC scope as `/api/events`).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use futures::TryStreamExt;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::event_logging::{EsamEventView, EventFilters};
+use crate::jwt_auth::Claims;
+use crate::tools_api::{decode_scte35_internal, DecodedScte35};
+use crate::{rbac, AppState};
+
+/// Flush the row buffer to the response once it reaches this size.
+const CHUNK: usize = 32 * 1024;
+
+const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "timestamp",
+    "channel_name",
+    "acquisition_signal_id",
+    "utc_point",
+    "source_ip",
+    "scte35_command",
+    "scte35_type_id",
+    "scte35_upid",
+    "scte35_b64",
+    "matched_rule_id",
+    "matched_rule_name",
+    "action",
+    "processing_time_ms",
+    "response_status",
+    "error_message",
+    "sesame_tier",
+];
+
+const CSV_DECODED_COLUMNS: &[&str] = &[
+    "scte35_command_type",
+    "scte35_pts_adjustment",
+    "scte35_pts_time",
+    "scte35_break_duration_s",
+    "scte35_segmentation_event_id",
+    "scte35_segmentation_type_id",
+    "scte35_segmentation_type_name",
+    "scte35_segmentation_duration_s",
+    "scte35_upid_type",
+    "scte35_upid_value",
+    "scte35_decode_error",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    Ndjson,
+}
+
+impl Format {
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Quote a CSV field when needed (RFC 4180). Text starting with a formula
+/// trigger (`= + - @`, tab, CR) gets a leading `'` so spreadsheets show it
+/// verbatim: acquisition IDs and error messages come from encoders.
+pub(crate) fn csv_text(out: &mut String, value: &str) {
+    let guarded;
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        guarded = format!("'{value}");
+        guarded.as_str()
+    } else {
+        value
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        out.push('"');
+        out.push_str(&value.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(value);
+    }
+}
+
+pub(crate) fn csv_row(out: &mut String, fields: &[Cell]) {
+    for (i, f) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        match f {
+            Cell::Text(Some(s)) => csv_text(out, s),
+            Cell::Num(Some(n)) => out.push_str(n),
+            Cell::Text(None) | Cell::Num(None) => {}
+        }
+    }
+    out.push_str("\r\n");
+}
+
+/// A CSV cell: text is escaped and formula-guarded, numbers are written as-is.
+pub(crate) enum Cell {
+    Text(Option<String>),
+    Num(Option<String>),
+}
+
+pub(crate) fn text(v: &Option<String>) -> Cell {
+    Cell::Text(v.clone())
+}
+
+pub(crate) fn num<T: ToString>(v: Option<T>) -> Cell {
+    Cell::Num(v.map(|n| n.to_string()))
+}
+
+/// Decode the event's SCTE-35, if any: `None` when the event has no payload.
+fn decode(e: &EsamEventView) -> Option<Result<DecodedScte35, String>> {
+    e.scte35_b64.as_deref().filter(|b| !b.is_empty()).map(decode_scte35_internal)
+}
+
+/// The flat decoded columns (see `CSV_DECODED_COLUMNS`). Segmentation fields
+/// come from the first segmentation_descriptor.
+fn decoded_cells(decoded: Option<Result<DecodedScte35, String>>) -> Vec<Cell> {
+    let d = match decoded {
+        None => return CSV_DECODED_COLUMNS.iter().map(|_| Cell::Text(None)).collect(),
+        Some(Err(e)) => {
+            let mut cells: Vec<Cell> = CSV_DECODED_COLUMNS.iter().map(|_| Cell::Text(None)).collect();
+            cells[CSV_DECODED_COLUMNS.len() - 1] = Cell::Text(Some(e));
+            return cells;
+        }
+        Some(Ok(d)) => d,
+    };
+    let seg = d
+        .descriptors
+        .iter()
+        .map(|x| &x.data)
+        .find(|data| data.get("segmentation_type_id").is_some());
+    let seg_str = |k: &str| seg.and_then(|s| s.get(k)).and_then(Value::as_str).map(str::to_string);
+    let seg_num = |k: &str| seg.and_then(|s| s.get(k)).filter(|v| v.is_number()).map(Value::to_string);
+    vec![
+        Cell::Text(Some(d.command_type.clone())),
+        num(Some(d.pts_adjustment)),
+        Cell::Num(d.command_info.get("pts_time").filter(|v| v.is_number()).map(Value::to_string)),
+        Cell::Num(
+            d.command_info
+                .pointer("/break_duration/duration_seconds")
+                .filter(|v| v.is_number())
+                .map(Value::to_string),
+        ),
+        Cell::Num(seg_num("segmentation_event_id")),
+        Cell::Text(seg_str("segmentation_type_id")),
+        Cell::Text(seg_str("segmentation_type_name")),
+        Cell::Num(seg_num("segmentation_duration_seconds")),
+        Cell::Text(seg_str("upid_type")),
+        Cell::Text(seg_str("upid_value")),
+        Cell::Text(None),
+    ]
+}
+
+fn write_csv(out: &mut String, e: &EsamEventView, with_decode: bool) {
+    let mut cells = vec![
+        num(Some(e.id)),
+        Cell::Text(Some(e.timestamp.clone())),
+        Cell::Text(Some(e.channel_name.clone())),
+        Cell::Text(Some(e.acquisition_signal_id.clone())),
+        Cell::Text(Some(e.utc_point.clone())),
+        text(&e.source_ip),
+        text(&e.scte35_command),
+        text(&e.scte35_type_id),
+        text(&e.scte35_upid),
+        text(&e.scte35_b64),
+        num(e.matched_rule_id),
+        text(&e.matched_rule_name),
+        Cell::Text(Some(e.action.clone())),
+        num(e.processing_time_ms),
+        num(Some(e.response_status)),
+        text(&e.error_message),
+        num(e.sesame_tier),
+    ];
+    if with_decode {
+        cells.extend(decoded_cells(decode(e)));
+    }
+    csv_row(out, &cells);
+}
+
+fn write_ndjson(out: &mut String, e: &EsamEventView, with_decode: bool) {
+    let mut obj = serde_json::to_value(e).unwrap_or(Value::Null);
+    if with_decode {
+        if let Value::Object(map) = &mut obj {
+            match decode(e) {
+                Some(Ok(d)) => {
+                    map.insert("scte35_decoded".into(), serde_json::to_value(d).unwrap_or(Value::Null));
+                }
+                Some(Err(err)) => {
+                    map.insert("scte35_decoded".into(), Value::Null);
+                    map.insert("scte35_decode_error".into(), Value::String(err));
+                }
+                None => {
+                    map.insert("scte35_decoded".into(), Value::Null);
+                }
+            }
+        }
+    }
+    out.push_str(&obj.to_string());
+    out.push('\n');
+}
+
+fn csv_header(with_decode: bool) -> String {
+    let mut cols: Vec<&str> = CSV_COLUMNS.to_vec();
+    if with_decode {
+        cols.extend_from_slice(CSV_DECODED_COLUMNS);
+    }
+    let mut out = cols.join(",");
+    out.push_str("\r\n");
+    out
+}
+
+/// GET /api/events/export — stream filtered events as CSV or NDJSON.
+pub async fn export_events(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let format = match params.get("format").map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some("csv") => Format::Csv,
+        Some("ndjson") | Some("jsonl") => Format::Ndjson,
+        Some(other) => {
+            return (StatusCode::BAD_REQUEST, format!("unsupported format: {other} (csv or ndjson)")).into_response()
+        }
+    };
+    let with_decode = matches!(params.get("decode").map(String::as_str), Some("true") | Some("1"));
+    let descending = match params.get("order").map(String::as_str) {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => return (StatusCode::BAD_REQUEST, format!("invalid order: {other} (asc or desc)")).into_response(),
+    };
+    let limit = match params.get("limit") {
+        None => None,
+        Some(v) => match v.parse::<i64>() {
+            Ok(n) if n > 0 => Some(n),
+            _ => return (StatusCode::BAD_REQUEST, format!("invalid limit: {v}")).into_response(),
+        },
+    };
+
+    // RBAC: same channel scope as /api/events (None = super-admin).
+    let eff = rbac::effective(&st.db, &claims).await;
+    let filters = match EventFilters::from_query(&params, rbac::event_scope(&eff)) {
+        Ok(f) => f,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let (tx, mut rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(4);
+    let db = st.db.clone();
+    tokio::spawn(async move {
+        let mut qb: sqlx::QueryBuilder<sqlx::Sqlite> =
+            sqlx::QueryBuilder::new("SELECT * FROM esam_events_view WHERE 1=1");
+        filters.push_where(&mut qb);
+        qb.push(if descending { " ORDER BY timestamp DESC, id DESC" } else { " ORDER BY timestamp, id" });
+        if let Some(n) = limit {
+            qb.push(" LIMIT ").push_bind(n);
+        }
+        let mut rows = qb.build_query_as::<EsamEventView>().fetch(&db);
+
+        let mut buf = match format {
+            Format::Csv => csv_header(with_decode),
+            Format::Ndjson => String::new(),
+        };
+        loop {
+            match rows.try_next().await {
+                Ok(Some(e)) => {
+                    match format {
+                        Format::Csv => write_csv(&mut buf, &e, with_decode),
+                        Format::Ndjson => write_ndjson(&mut buf, &e, with_decode),
+                    }
+                    if buf.len() >= CHUNK && tx.send(Ok(Bytes::from(std::mem::take(&mut buf)))).await.is_err() {
+                        return; // client went away
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    // Before the first chunk this becomes a 500; later it aborts
+                    // the transfer so the client sees a truncated download.
+                    warn!("event export failed: {e}");
+                    if !buf.is_empty() {
+                        let _ = tx.send(Ok(Bytes::from(std::mem::take(&mut buf)))).await;
+                    }
+                    let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
+                    return;
+                }
+            }
+        }
+        if !buf.is_empty() {
+            let _ = tx.send(Ok(Bytes::from(buf))).await;
+        }
+    });
+
+    // Wait for the first chunk so a failing query still gets a proper status.
+    let first = match rx.recv().await {
+        Some(Ok(b)) => Some(b),
+        Some(Err(e)) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        None => None,
+    };
+    let stream = futures::stream::unfold((first, rx), |(first, mut rx)| async move {
+        match first {
+            Some(b) => Some((Ok(b), (None, rx))),
+            None => rx.recv().await.map(|item| (item, (None, rx))),
+        }
+    });
+
+    let name = format!("esam-events-{}.{}", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"), format.extension());
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{name}\"")),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(v: &str) -> String {
+        let mut out = String::new();
+        csv_text(&mut out, v);
+        out
+    }
+
+    #[test]
+    fn csv_escaping() {
+        assert_eq!(cell("plain"), "plain");
+        assert_eq!(cell("a,b"), "\"a,b\"");
+        assert_eq!(cell("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(cell("two\nlines"), "\"two\nlines\"");
+        assert_eq!(cell("=HYPERLINK(1)"), "'=HYPERLINK(1)");
+        assert_eq!(cell("-1,2"), "\"'-1,2\"");
+    }
+
+    #[test]
+    fn csv_row_leaves_missing_values_empty() {
+        let mut out = String::new();
+        csv_row(&mut out, &[num(Some(-5)), Cell::Text(None), text(&Some("x".into())), num::<i32>(None)]);
+        assert_eq!(out, "-5,,x,\r\n");
+    }
+
+    #[test]
+    fn decoded_columns_line_up_with_header() {
+        assert_eq!(decoded_cells(None).len(), CSV_DECODED_COLUMNS.len());
+        let err = decoded_cells(Some(Err("bad".into())));
+        assert_eq!(err.len(), CSV_DECODED_COLUMNS.len());
+        assert!(matches!(err.last(), Some(Cell::Text(Some(e))) if e == "bad"));
+
+        let b64 = "/DAlAAAAAAAAAP/wFAUAAAABf+/+AAAAAH4AKTLgAAEAAAAAhUfjqg==";
+        let ok = decoded_cells(Some(decode_scte35_internal(b64)));
+        assert_eq!(ok.len(), CSV_DECODED_COLUMNS.len());
+        assert!(matches!(&ok[0], Cell::Text(Some(c)) if c.contains("splice_insert")));
+        assert!(matches!(&ok[3], Cell::Num(Some(d)) if d == "30.0"));
+    }
+}
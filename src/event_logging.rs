@@ -1,11 +1,12 @@
 // src/event_logging.rs
-use crate::esam::decode_scte35_details;
+use crate::event_queue::{EventQueue, EventRow, Receipt};
+use crate::monitor_ws::MonitorHub;
 use axum::http::HeaderMap;
 use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Sqlite};
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use tracing::{debug, info, instrument};
+use tracing::instrument;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -70,6 +71,10 @@ pub struct EsamEventView {
 pub struct EventLogger {
     pub db: Pool<Sqlite>,
     pub store_raw_payloads: bool,
+    /// Live fan-out to `/ws/monitor` subscribers.
+    pub monitor: MonitorHub,
+    /// Bounded queue in front of the batching writer task.
+    pub queue: EventQueue,
 }
 
 impl EventLogger {
@@ -78,14 +83,19 @@ impl EventLogger {
             .map(|v| v == "true")
             .unwrap_or(false);
             
+        let monitor = MonitorHub::from_env();
         Self {
+            queue: EventQueue::start(db.clone(), monitor.clone()),
             db,
             store_raw_payloads,
+            monitor,
         }
     }
 
+    /// Queue the event for the background writer (see `event_queue`); does not
+    /// wait for the database. Await the receipt to learn the event id.
     #[allow(clippy::too_many_arguments)] // cohesive event record; a params struct would not read better
-    #[instrument(skip(self, facts, request_body, response_body))]
+    #[instrument(name = "esam.log_event", skip(self, facts, request_body, response_body))]
     pub async fn log_esam_event(
         &self,
         channel_name: &str,
@@ -95,90 +105,32 @@ impl EventLogger {
         metrics: ProcessingMetrics,
         request_body: Option<&str>,
         response_body: Option<&str>,
-    ) -> Result<i64, sqlx::Error> {
+    ) -> Receipt {
         let raw_request = if self.store_raw_payloads { request_body } else { None };
         let raw_response = if self.store_raw_payloads { response_body } else { None };
-        
-        let (matched_rule_id, matched_rule_name) = match matched_rule {
-            Some((rule, _)) => (Some(rule.id), Some(rule.name.as_str())),
-            None => (None, None),
-        };
-        
-        let action = matched_rule.map(|(_, action)| action).unwrap_or("noop");
-        // Decode SCTE-35 if present in BinaryData
-        let (scte35_command, scte35_type_id, scte35_upid) = if let Some(binary_data) = facts
-            .get("scte35_b64")
-            
-            .and_then(|v| v.as_str()) 
-        {
-            match decode_scte35_details(binary_data) {
-                Ok(info) => {
-                    debug!("Successfully decoded SCTE-35: command={:?}", info.command);
-                    let upid_hex = info.segmentation_upid_with_type.as_ref().map(|(upid_type, data)| {
-                        format!("0x{:02X}:{}", upid_type, data.iter().map(|b| format!("{:02X}", b)).collect::<String>())
-                    });
-                    (
-                        info.command,
-                        info.segmentation_type_id.map(|id| id.to_string()),
-                        upid_hex
-                    )
-                },
-                Err(e) => {
-                    debug!("Failed to decode SCTE-35 in event logging: {}", e);
-                    (None, None, None)
-                }
-            }
-        } else {
-            (None, None, None)
-        };
-        
-        
-        let event_id = sqlx::query_scalar::<_, i64>(
-            r#"
-            INSERT INTO esam_events (
-                channel_name, acquisition_signal_id, utc_point, source_ip, user_agent,
-                scte35_command, scte35_type_id, scte35_upid, scte35_b64,
-                matched_rule_id, matched_rule_name, action,
-                request_size, processing_time_ms, response_status, error_message,
-                raw_esam_request, raw_esam_response, sesame_tier
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            RETURNING id
-            "#
-        )
-        .bind(channel_name)
-        .bind(facts.get("acquisitionSignalID").and_then(|v| v.as_str()).unwrap_or(""))
-        .bind(facts.get("utcPoint").and_then(|v| v.as_str()).unwrap_or(""))
-        .bind(client_info.source_ip)
-        .bind(client_info.user_agent)
-        .bind(scte35_command.as_deref())
-        .bind(scte35_type_id.as_deref())
-        .bind(scte35_upid.as_deref())
-        .bind(facts.get("scte35_b64").and_then(|v| v.as_str()))
-        .bind(matched_rule_id)
-        .bind(matched_rule_name)
-        .bind(action)
-        .bind(metrics.request_size)
-        .bind(metrics.processing_time_ms)
-        .bind(metrics.response_status)
-        .bind(metrics.error_message.as_deref())
-        .bind(raw_request)
-        .bind(raw_response)
-        .bind(client_info.sesame_tier)
-        .fetch_one(&self.db)
-        .await?;
-
-        info!(
-            event_id = event_id,
-            channel = channel_name,
-            action = action,
-            rule_id = matched_rule_id,
-            processing_ms = metrics.processing_time_ms,
-            "ESAM event logged"
-        );
-
-        // ← NEW: Broadcast to WebSocket clients if registry provided
+        let text = |k: &str| facts.get(k).and_then(|v| v.as_str());
 
-        Ok(event_id)
+        self.queue
+            .push(EventRow {
+                timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                channel_name: channel_name.to_string(),
+                acquisition_signal_id: text("acquisitionSignalID").unwrap_or("").to_string(),
+                utc_point: text("utcPoint").unwrap_or("").to_string(),
+                source_ip: client_info.source_ip,
+                user_agent: client_info.user_agent,
+                scte35_b64: text("scte35_b64").map(str::to_string),
+                matched_rule_id: matched_rule.map(|(rule, _)| rule.id),
+                matched_rule_name: matched_rule.map(|(rule, _)| rule.name.clone()),
+                action: matched_rule.map(|(_, action)| action).unwrap_or("noop").to_string(),
+                request_size: metrics.request_size,
+                processing_time_ms: metrics.processing_time_ms,
+                response_status: metrics.response_status,
+                error_message: metrics.error_message,
+                raw_esam_request: raw_request.map(str::to_string),
+                raw_esam_response: raw_response.map(str::to_string),
+                sesame_tier: client_info.sesame_tier,
+            })
+            .await
     }
 
     pub async fn get_recent_events(
@@ -190,60 +142,8 @@ impl EventLogger {
         // Build the query dynamically; every user value is bound (no injection).
         let mut qb: sqlx::QueryBuilder<Sqlite> =
             sqlx::QueryBuilder::new("SELECT * FROM esam_events_view WHERE 1=1");
-
         if let Some(f) = filters {
-            if let Some(channel) = f.channel_name {
-                qb.push(" AND channel_name = ").push_bind(channel);
-            }
-            if let Some(action) = f.action {
-                qb.push(" AND action = ").push_bind(action);
-            }
-            if let Some(since) = f.since {
-                qb.push(" AND timestamp >= ").push_bind(since);
-            }
-            if let Some(search) = f.search {
-                let term = search.trim();
-                if !term.is_empty() {
-                    let like = format!("%{}%", term);
-                    // The UPID column stores hex ("0xTT:HHHH…"). Also match the
-                    // ASCII→hex of the term so typing an ASCII UPID (e.g. ABCD1234)
-                    // matches an ASCII-type UPID stored as its hex bytes.
-                    let hex: String = term.bytes().map(|b| format!("{:02X}", b)).collect();
-                    let hex_like = format!("%{}%", hex);
-                    qb.push(" AND (acquisition_signal_id LIKE ")
-                        .push_bind(like.clone())
-                        .push(" OR source_ip LIKE ")
-                        .push_bind(like.clone())
-                        .push(" OR scte35_command LIKE ")
-                        .push_bind(like.clone())
-                        .push(" OR scte35_upid LIKE ")
-                        .push_bind(like)
-                        .push(" OR scte35_upid LIKE ")
-                        .push_bind(hex_like)
-                        .push(")");
-                }
-            }
-            // RBAC: restrict to events whose channel the caller may read. Resolves
-            // channel_name -> channels (names are globally unique). None = super.
-            if let Some((uid, member_of)) = f.event_scope {
-                qb.push(
-                    " AND channel_name IN (SELECT name FROM channels WHERE deleted_at IS NULL \
-                     AND (owner_user_id = ",
-                )
-                .push_bind(uid)
-                .push(" OR is_global = 1");
-                if !member_of.is_empty() {
-                    qb.push(
-                        " OR EXISTS(SELECT 1 FROM channel_groups cg WHERE cg.channel_id = channels.id AND cg.group_id IN (",
-                    );
-                    let mut sep = qb.separated(", ");
-                    for g in &member_of {
-                        sep.push_bind(*g);
-                    }
-                    qb.push("))");
-                }
-                qb.push("))");
-            }
+            f.push_where(&mut qb);
         }
 
         qb.push(" ORDER BY timestamp DESC LIMIT ")
@@ -357,11 +257,21 @@ pub struct ProcessingMetrics {
     pub error_message: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct EventFilters {
     pub channel_name: Option<String>,
     pub action: Option<String>,
     pub since: Option<String>,
+    /// Exclusive upper bound on `timestamp` (same RFC 3339 form as `since`).
+    pub until: Option<String>,
+    pub rule_id: Option<i64>,
+    /// Inclusive `response_status` range: one code (`204`) or a class (`4xx`).
+    pub status: Option<(i32, i32)>,
+    /// SESAME tier; `Some(0)` selects unauthenticated events (NULL tier).
+    pub sesame_tier: Option<i32>,
+    /// Exact UPID match: `0xTT:HEX` as stored, or just the value as hex or
+    /// ASCII (any UPID type).
+    pub upid: Option<String>,
     /// Free-text search across acquisition signal ID, source IP, SCTE-35 command,
     /// and UPID (hex form, plus an ASCII→hex match so an ASCII UPID also matches).
     pub search: Option<String>,
@@ -370,10 +280,196 @@ pub struct EventFilters {
     pub event_scope: Option<(i64, Vec<i64>)>,
 }
 
+impl EventFilters {
+    /// Filters from the `/api/events` query string (`channel`, `action`,
+    /// `since`, `until`, `rule_id`, `status`, `sesame_tier`, `upid`, `search`).
+    /// Malformed numeric values are an error rather than silently ignored.
+    pub fn from_query(
+        params: &HashMap<String, String>,
+        event_scope: Option<(i64, Vec<i64>)>,
+    ) -> Result<Self, String> {
+        let text = |k: &str| {
+            params
+                .get(k)
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        let rule_id = match text("rule_id") {
+            Some(v) => Some(v.parse::<i64>().map_err(|_| format!("invalid rule_id: {v}"))?),
+            None => None,
+        };
+        let status = match text("status") {
+            Some(v) => Some(parse_status(&v).ok_or_else(|| format!("invalid status: {v} (use e.g. 200 or 4xx)"))?),
+            None => None,
+        };
+        let sesame_tier = match text("sesame_tier") {
+            Some(v) => match v.parse::<i32>() {
+                Ok(t @ 0..=3) => Some(t),
+                _ => return Err(format!("invalid sesame_tier: {v} (0-3)")),
+            },
+            None => None,
+        };
+        Ok(Self {
+            channel_name: text("channel"),
+            action: text("action"),
+            since: text("since"),
+            until: text("until"),
+            rule_id,
+            status,
+            sesame_tier,
+            upid: text("upid"),
+            search: text("search"),
+            event_scope,
+        })
+    }
+
+    /// Append `AND …` clauses for every set filter to a query over
+    /// `esam_events_view` (or `esam_events`). All values are bound.
+    pub fn push_where<'a>(self, qb: &mut sqlx::QueryBuilder<'a, Sqlite>) {
+        if let Some(channel) = self.channel_name {
+            qb.push(" AND channel_name = ").push_bind(channel);
+        }
+        if let Some(action) = self.action {
+            qb.push(" AND action = ").push_bind(action);
+        }
+        if let Some(since) = self.since {
+            qb.push(" AND timestamp >= ").push_bind(since);
+        }
+        if let Some(until) = self.until {
+            qb.push(" AND timestamp < ").push_bind(until);
+        }
+        if let Some(rule_id) = self.rule_id {
+            qb.push(" AND matched_rule_id = ").push_bind(rule_id);
+        }
+        if let Some((lo, hi)) = self.status {
+            qb.push(" AND response_status BETWEEN ")
+                .push_bind(lo)
+                .push(" AND ")
+                .push_bind(hi);
+        }
+        match self.sesame_tier {
+            Some(0) => {
+                qb.push(" AND sesame_tier IS NULL");
+            }
+            Some(tier) => {
+                qb.push(" AND sesame_tier = ").push_bind(tier);
+            }
+            None => {}
+        }
+        if let Some(upid) = self.upid {
+            let (exact, values) = upid_match(&upid);
+            qb.push(" AND (");
+            match exact {
+                Some(stored) => {
+                    qb.push("scte35_upid = ").push_bind(stored);
+                }
+                None => {
+                    qb.push("substr(scte35_upid, instr(scte35_upid, ':') + 1) IN (");
+                    let mut sep = qb.separated(", ");
+                    for v in values {
+                        sep.push_bind(v);
+                    }
+                    qb.push(")");
+                }
+            }
+            qb.push(")");
+        }
+        if let Some(search) = self.search {
+            let term = search.trim();
+            if !term.is_empty() {
+                let like = format!("%{}%", term);
+                // The UPID column stores hex ("0xTT:HHHH…"). Also match the
+                // ASCII→hex of the term so typing an ASCII UPID (e.g. ABCD1234)
+                // matches an ASCII-type UPID stored as its hex bytes.
+                let hex: String = term.bytes().map(|b| format!("{:02X}", b)).collect();
+                let hex_like = format!("%{}%", hex);
+                qb.push(" AND (acquisition_signal_id LIKE ")
+                    .push_bind(like.clone())
+                    .push(" OR source_ip LIKE ")
+                    .push_bind(like.clone())
+                    .push(" OR scte35_command LIKE ")
+                    .push_bind(like.clone())
+                    .push(" OR scte35_upid LIKE ")
+                    .push_bind(like)
+                    .push(" OR scte35_upid LIKE ")
+                    .push_bind(hex_like)
+                    .push(")");
+            }
+        }
+        // RBAC: restrict to events whose channel the caller may read. Resolves
+        // channel_name -> channels (names are globally unique). None = super.
+        push_event_scope(qb, &self.event_scope);
+    }
+}
+
+/// `"204"` -> (204, 204); `"4xx"` -> (400, 499).
+fn parse_status(s: &str) -> Option<(i32, i32)> {
+    let lower = s.to_ascii_lowercase();
+    if let Some(class) = lower.strip_suffix("xx") {
+        let c: i32 = class.parse().ok().filter(|c| (1..=5).contains(c))?;
+        return Some((c * 100, c * 100 + 99));
+    }
+    let code: i32 = lower.parse().ok().filter(|c| (100..=599).contains(c))?;
+    Some((code, code))
+}
+
+/// How a `upid` filter matches the stored `0xTT:HEX` form: either the exact
+/// stored string, or a set of candidate value encodings (the term as hex when
+/// it is valid hex, and its ASCII bytes as hex).
+fn upid_match(term: &str) -> (Option<String>, Vec<String>) {
+    let term = term.trim();
+    if let Some((ty, value)) = term.split_once(':') {
+        let ty = ty.trim_start_matches("0x").trim_start_matches("0X");
+        if ty.len() == 2 && ty.bytes().all(|b| b.is_ascii_hexdigit()) && value.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return (Some(format!("0x{}:{}", ty.to_ascii_uppercase(), value.to_ascii_uppercase())), Vec::new());
+        }
+    }
+    let mut values = Vec::new();
+    let hex = term.strip_prefix("0x").or_else(|| term.strip_prefix("0X")).unwrap_or(term);
+    if !hex.is_empty() && hex.len().is_multiple_of(2) && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        values.push(hex.to_ascii_uppercase());
+    }
+    values.push(term.bytes().map(|b| format!("{:02X}", b)).collect());
+    (None, values)
+}
+
 #[derive(Debug, Serialize)]
 pub struct EventStats {
     pub total_events: i64,
     pub last_24h_events: i64,
     pub action_counts: HashMap<String, i64>,
     pub avg_processing_time_ms: Option<f64>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_accepts_codes_and_classes() {
+        assert_eq!(parse_status("204"), Some((204, 204)));
+        assert_eq!(parse_status("4xx"), Some((400, 499)));
+        assert_eq!(parse_status("5XX"), Some((500, 599)));
+        assert_eq!(parse_status("9xx"), None);
+        assert_eq!(parse_status("abc"), None);
+    }
+
+    #[test]
+    fn upid_filter_forms() {
+        assert_eq!(upid_match("0x0c:abcd").0.as_deref(), Some("0x0C:ABCD"));
+        let (exact, values) = upid_match("ABCD");
+        assert!(exact.is_none());
+        assert_eq!(values, vec!["ABCD".to_string(), "41424344".to_string()]);
+        // Not valid hex: only the ASCII encoding is tried.
+        assert_eq!(upid_match("EP01").1, vec!["45503031".to_string()]);
+    }
+
+    #[test]
+    fn query_rejects_malformed_numbers() {
+        let q = |k: &str, v: &str| HashMap::from([(k.to_string(), v.to_string())]);
+        assert!(EventFilters::from_query(&q("rule_id", "x"), None).is_err());
+        assert!(EventFilters::from_query(&q("sesame_tier", "7"), None).is_err());
+        let f = EventFilters::from_query(&q("status", "2xx"), None).unwrap();
+        assert_eq!(f.status, Some((200, 299)));
+        assert!(EventFilters::from_query(&q("channel", "  "), None).unwrap().channel_name.is_none());
+    }
+}
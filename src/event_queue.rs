@@ -0,0 +1,531 @@
+// src/event_queue.rs
+//! Asynchronous, batched event logging.
+//!
+//! `EventLogger::log_esam_event` only builds an `EventRow` and queues it; the
+//! ESAM response never waits on SQLite. A single writer task drains the bounded
+//! queue (`POIS_EVENT_QUEUE`, default 10000) and inserts up to
+//! `POIS_EVENT_BATCH` rows (default 500) per transaction, then decodes the
+//! SCTE-35 summary columns, publishes to `/ws/monitor` and updates metrics.
+//!
+//! When the queue is full, `POIS_EVENT_OVERFLOW` decides:
+//!   - `block` (default): the request waits for room — no loss, bounded memory;
+//!   - `drop`: the event is discarded and counted (`pois_events_dropped_total`);
+//!   - `spill`: the event is appended to `pois-events.spill.ndjson` in
+//!     `POIS_EVENT_SPILL_DIR` and inserted by the writer once it catches up
+//!     (also at the next startup). A batch that cannot be written after retries
+//!     is spilled the same way instead of being lost.
+//!
+//! `EventQueue::flush` (called on graceful shutdown) returns once everything
+//! queued before it has been written.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info, warn};
+
+use crate::esam::decode_scte35_details;
+use crate::metrics::METRICS;
+use crate::monitor_ws::{MonitorEvent, MonitorHub};
+
+const SPILL_FILE: &str = "pois-events.spill.ndjson";
+const REPLAY_SUFFIX: &str = ".replay";
+const WRITE_ATTEMPTS: u32 = 3;
+
+/// One `esam_events` row as captured on the request path. `timestamp` is the
+/// capture time, so batching and spilling do not skew it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRow {
+    pub timestamp: String,
+    pub channel_name: String,
+    pub acquisition_signal_id: String,
+    pub utc_point: String,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub scte35_b64: Option<String>,
+    pub matched_rule_id: Option<i64>,
+    pub matched_rule_name: Option<String>,
+    pub action: String,
+    pub request_size: Option<i32>,
+    pub processing_time_ms: Option<i32>,
+    pub response_status: i32,
+    pub error_message: Option<String>,
+    pub raw_esam_request: Option<String>,
+    pub raw_esam_response: Option<String>,
+    pub sesame_tier: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Block,
+    Drop,
+    Spill,
+}
+
+/// Resolves to the event id once the row is written. Callers on the ESAM path
+/// drop it; tools that report the id await it.
+pub struct Receipt(Option<oneshot::Receiver<Result<i64, String>>>);
+
+impl Receipt {
+    pub async fn event_id(self) -> Result<i64, String> {
+        match self.0 {
+            Some(rx) => rx.await.unwrap_or_else(|_| Err("event deferred to the spill file".into())),
+            None => Err("event log queue full; event dropped".into()),
+        }
+    }
+}
+
+enum Msg {
+    Event(Box<EventRow>, Ack),
+    Flush(oneshot::Sender<()>),
+}
+
+#[derive(Clone)]
+pub struct EventQueue {
+    tx: mpsc::Sender<Msg>,
+    capacity: usize,
+    overflow: Overflow,
+    spill: Option<Arc<Spill>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl EventQueue {
+    /// Read the `POIS_EVENT_*` settings and start the writer task.
+    pub fn start(db: Pool<Sqlite>, monitor: MonitorHub) -> Self {
+        let num = |k: &str, d: usize| {
+            std::env::var(k).ok().and_then(|v| v.trim().parse::<usize>().ok()).unwrap_or(d).max(1)
+        };
+        let capacity = num("POIS_EVENT_QUEUE", 10_000);
+        let batch = num("POIS_EVENT_BATCH", 500);
+        let spill = std::env::var("POIS_EVENT_SPILL_DIR")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(|d| Arc::new(Spill::new(PathBuf::from(d))));
+        let mut overflow = match std::env::var("POIS_EVENT_OVERFLOW").unwrap_or_default().trim() {
+            "drop" => Overflow::Drop,
+            "spill" => Overflow::Spill,
+            _ => Overflow::Block,
+        };
+        if overflow == Overflow::Spill && spill.is_none() {
+            warn!("POIS_EVENT_OVERFLOW=spill needs POIS_EVENT_SPILL_DIR; blocking instead");
+            overflow = Overflow::Block;
+        }
+        info!("Event log queue: capacity {capacity}, batch {batch}, overflow {overflow:?}");
+        Self::with(db, monitor, capacity, batch, overflow, spill)
+    }
+
+    fn with(
+        db: Pool<Sqlite>,
+        monitor: MonitorHub,
+        capacity: usize,
+        batch: usize,
+        overflow: Overflow,
+        spill: Option<Arc<Spill>>,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel(capacity);
+        let writer = Writer { db, monitor, batch, spill: spill.clone() };
+        tokio::spawn(writer.run(rx));
+        Self { tx, capacity, overflow, spill, dropped: Arc::new(AtomicU64::new(0)) }
+    }
+
+    /// Events waiting for the writer.
+    pub fn depth(&self) -> usize {
+        self.capacity - self.tx.capacity()
+    }
+
+    pub async fn push(&self, row: EventRow) -> Receipt {
+        let (ack, rx) = oneshot::channel();
+        let msg = Msg::Event(Box::new(row), Some(ack));
+        match self.overflow {
+            Overflow::Block => {
+                if self.tx.send(msg).await.is_err() {
+                    error!("event writer stopped; event lost");
+                    return Receipt(None);
+                }
+            }
+            Overflow::Drop | Overflow::Spill => match self.tx.try_send(msg) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(Msg::Event(row, _))) => {
+                    return self.overflowed(*row);
+                }
+                Err(_) => {
+                    error!("event writer stopped; event lost");
+                    return Receipt(None);
+                }
+            },
+        }
+        Receipt(Some(rx))
+    }
+
+    fn overflowed(&self, row: EventRow) -> Receipt {
+        if let (Overflow::Spill, Some(spill)) = (self.overflow, &self.spill) {
+            match spill.append(std::slice::from_ref(&row)) {
+                Ok(()) => {
+                    METRICS.events_spilled.inc();
+                    let (_, rx) = oneshot::channel();
+                    return Receipt(Some(rx));
+                }
+                Err(e) => error!("event spill to {}: {}", spill.dir.display(), e),
+            }
+        }
+        METRICS.events_dropped.inc();
+        let n = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        if n.is_power_of_two() {
+            warn!(dropped_total = n, "event log queue full; dropping events");
+        }
+        Receipt(None)
+    }
+
+    /// Wait (up to `timeout`) until everything queued so far is written.
+    pub async fn flush(&self, timeout: Duration) -> bool {
+        let (tx, rx) = oneshot::channel();
+        let flushed = async {
+            self.tx.send(Msg::Flush(tx)).await.is_ok() && rx.await.is_ok()
+        };
+        tokio::time::timeout(timeout, flushed).await.unwrap_or(false)
+    }
+}
+
+// --------------------------------- writer ---------------------------------
+
+struct Writer {
+    db: Pool<Sqlite>,
+    monitor: MonitorHub,
+    batch: usize,
+    spill: Option<Arc<Spill>>,
+}
+
+type Ack = Option<oneshot::Sender<Result<i64, String>>>;
+
+impl Writer {
+    async fn run(self, mut rx: mpsc::Receiver<Msg>) {
+        if let Some(spill) = &self.spill {
+            spill.replay(&self).await;
+        }
+        while let Some(first) = rx.recv().await {
+            let mut rows: Vec<(EventRow, Ack)> = Vec::new();
+            let mut flushes = Vec::new();
+            let mut next = Some(first);
+            while let Some(msg) = next.take() {
+                match msg {
+                    Msg::Event(row, ack) => rows.push((*row, ack)),
+                    Msg::Flush(done) => flushes.push(done),
+                }
+                if rows.len() < self.batch {
+                    next = rx.try_recv().ok();
+                }
+            }
+            if !rows.is_empty() {
+                self.write(rows).await;
+            }
+            if let Some(spill) = &self.spill {
+                if spill.pending.load(Ordering::Relaxed) && (rx.is_empty() || !flushes.is_empty()) {
+                    spill.replay(&self).await;
+                }
+            }
+            for done in flushes {
+                let _ = done.send(());
+            }
+        }
+    }
+
+    /// Insert with retries; on final failure spill (if configured) or count the loss.
+    async fn write(&self, rows: Vec<(EventRow, Ack)>) {
+        let (rows, acks): (Vec<EventRow>, Vec<Ack>) = rows.into_iter().unzip();
+        let mut attempt = 0;
+        let err = loop {
+            match self.insert(&rows).await {
+                Ok(ids) => {
+                    for (ack, id) in acks.into_iter().zip(ids) {
+                        if let Some(ack) = ack {
+                            let _ = ack.send(Ok(id));
+                        }
+                    }
+                    return;
+                }
+                Err(e) if attempt + 1 < WRITE_ATTEMPTS => {
+                    attempt += 1;
+                    debug!("event batch write failed (attempt {attempt}): {e}");
+                    tokio::time::sleep(Duration::from_millis(100 << attempt)).await;
+                }
+                Err(e) => break e,
+            }
+        };
+        error!("event batch of {} could not be written: {}", rows.len(), err);
+        if let Some(spill) = &self.spill {
+            match spill.append(&rows) {
+                Ok(()) => {
+                    METRICS.events_spilled.inc_by(rows.len() as u64);
+                    return fail(acks, "event deferred to the spill file");
+                }
+                Err(e) => error!("event spill to {}: {}", spill.dir.display(), e),
+            }
+        }
+        METRICS.event_log_failures.inc_by(rows.len() as u64);
+        fail(acks, &err.to_string());
+    }
+
+    /// One transaction for the whole batch; publishes and acks on commit.
+    async fn insert(&self, rows: &[EventRow]) -> Result<Vec<i64>, sqlx::Error> {
+        let decoded: Vec<Scte35Summary> = rows.iter().map(|r| Scte35Summary::of(r.scte35_b64.as_deref())).collect();
+        let mut tx = self.db.begin().await?;
+        let mut ids = Vec::with_capacity(rows.len());
+        for (row, s) in rows.iter().zip(&decoded) {
+            let id = sqlx::query_scalar::<_, i64>(
+                r#"
+                INSERT INTO esam_events (
+                    timestamp, channel_name, acquisition_signal_id, utc_point, source_ip, user_agent,
+                    scte35_command, scte35_type_id, scte35_upid, scte35_b64,
+                    matched_rule_id, matched_rule_name, action,
+                    request_size, processing_time_ms, response_status, error_message,
+                    raw_esam_request, raw_esam_response, sesame_tier
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                RETURNING id
+                "#,
+            )
+            .bind(&row.timestamp)
+            .bind(&row.channel_name)
+            .bind(&row.acquisition_signal_id)
+            .bind(&row.utc_point)
+            .bind(&row.source_ip)
+            .bind(&row.user_agent)
+            .bind(&s.command)
+            .bind(&s.type_id)
+            .bind(&s.upid)
+            .bind(&row.scte35_b64)
+            .bind(row.matched_rule_id)
+            .bind(&row.matched_rule_name)
+            .bind(&row.action)
+            .bind(row.request_size)
+            .bind(row.processing_time_ms)
+            .bind(row.response_status)
+            .bind(&row.error_message)
+            .bind(&row.raw_esam_request)
+            .bind(&row.raw_esam_response)
+            .bind(row.sesame_tier)
+            .fetch_one(&mut *tx)
+            .await?;
+            ids.push(id);
+        }
+        tx.commit().await?;
+
+        METRICS.events_logged.inc_by(rows.len() as u64);
+        for ((row, s), id) in rows.iter().zip(decoded).zip(&ids) {
+            info!(
+                event_id = id,
+                channel = row.channel_name,
+                action = row.action,
+                rule_id = row.matched_rule_id,
+                processing_ms = row.processing_time_ms,
+                "ESAM event logged"
+            );
+            self.monitor.publish(MonitorEvent {
+                kind: "event",
+                event_id: *id,
+                timestamp: row.timestamp.clone(),
+                channel: row.channel_name.clone(),
+                action: row.action.clone(),
+                command_type: s.command,
+                segmentation_type: s.type_id,
+                details: serde_json::json!({
+                    "acquisition_signal_id": row.acquisition_signal_id,
+                    "utc_point": row.utc_point,
+                    "upid": s.upid,
+                    "rule_id": row.matched_rule_id,
+                    "rule_name": row.matched_rule_name,
+                    "response_status": row.response_status,
+                    "processing_time_ms": row.processing_time_ms,
+                    "error_message": row.error_message,
+                    "sesame_tier": row.sesame_tier,
+                }),
+            });
+        }
+        Ok(ids)
+    }
+}
+
+fn fail(acks: Vec<Ack>, err: &str) {
+    for ack in acks.into_iter().flatten() {
+        let _ = ack.send(Err(err.to_string()));
+    }
+}
+
+/// Summary columns decoded from the event's SCTE-35 cue.
+struct Scte35Summary {
+    command: Option<String>,
+    type_id: Option<String>,
+    upid: Option<String>,
+}
+
+impl Scte35Summary {
+    fn of(b64: Option<&str>) -> Self {
+        let none = Self { command: None, type_id: None, upid: None };
+        let Some(b64) = b64 else { return none };
+        match decode_scte35_details(b64) {
+            Ok(info) => Self {
+                command: info.command,
+                type_id: info.segmentation_type_id.map(|id| id.to_string()),
+                upid: info.segmentation_upid_with_type.as_ref().map(|(upid_type, data)| {
+                    format!("0x{:02X}:{}", upid_type, data.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+                }),
+            },
+            Err(e) => {
+                debug!("Failed to decode SCTE-35 in event logging: {}", e);
+                none
+            }
+        }
+    }
+}
+
+// ---------------------------------- spill ---------------------------------
+
+struct Spill {
+    dir: PathBuf,
+    /// Serializes appends with the rename that starts a replay.
+    lock: std::sync::Mutex<()>,
+    /// Something may be waiting on disk.
+    pending: AtomicBool,
+}
+
+impl Spill {
+    fn new(dir: PathBuf) -> Self {
+        Self { dir, lock: std::sync::Mutex::new(()), pending: AtomicBool::new(true) }
+    }
+
+    fn append(&self, rows: &[EventRow]) -> std::io::Result<()> {
+        use std::io::Write as _;
+        let mut buf = Vec::new();
+        for row in rows {
+            serde_json::to_writer(&mut buf, row)?;
+            buf.push(b'\n');
+        }
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        std::fs::create_dir_all(&self.dir)?;
+        let mut f = std::fs::OpenOptions::new().create(true).append(true).open(self.dir.join(SPILL_FILE))?;
+        f.write_all(&buf)?;
+        self.pending.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Move the live spill file aside, then insert every pending replay file.
+    /// A file is deleted only after all of its rows are committed.
+    async fn replay(&self, writer: &Writer) {
+        {
+            let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+            self.pending.store(false, Ordering::Relaxed);
+            let live = self.dir.join(SPILL_FILE);
+            if live.exists() {
+                let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.6fZ");
+                if let Err(e) = std::fs::rename(&live, self.dir.join(format!("{SPILL_FILE}.{stamp}{REPLAY_SUFFIX}"))) {
+                    error!("event spill rotate: {e}");
+                    self.pending.store(true, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+        let Ok(rd) = std::fs::read_dir(&self.dir) else { return };
+        let mut files: Vec<PathBuf> = rd
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.ends_with(REPLAY_SUFFIX)))
+            .collect();
+        files.sort();
+        for path in files {
+            let text = match tokio::fs::read_to_string(&path).await {
+                Ok(t) => t,
+                Err(e) => {
+                    error!("event spill {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let rows: Vec<EventRow> = text
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .filter_map(|l| serde_json::from_str(l).map_err(|e| warn!("bad spilled event skipped: {e}")).ok())
+                .collect();
+            for (i, chunk) in rows.chunks(writer.batch).enumerate() {
+                if let Err(e) = writer.insert(chunk).await {
+                    // Rows already committed from this file would be written
+                    // twice on retry; keep them out of the next attempt.
+                    error!("event spill replay {}: {}", path.display(), e);
+                    let _ = rewrite(&path, &rows[i * writer.batch..]);
+                    self.pending.store(true, Ordering::Relaxed);
+                    return;
+                }
+            }
+            info!("replayed {} spilled events from {}", rows.len(), path.display());
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+    }
+}
+
+fn rewrite(path: &std::path::Path, rows: &[EventRow]) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    for row in rows {
+        serde_json::to_writer(&mut buf, row)?;
+        buf.push(b'\n');
+    }
+    std::fs::write(path, buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(i: usize) -> EventRow {
+        EventRow {
+            timestamp: format!("2026-10-18T00:00:{:02}.000Z", i % 60),
+            channel_name: "c".into(),
+            acquisition_signal_id: format!("sig-{i}"),
+            utc_point: String::new(),
+            source_ip: None,
+            user_agent: None,
+            scte35_b64: None,
+            matched_rule_id: None,
+            matched_rule_name: None,
+            action: "noop".into(),
+            request_size: None,
+            processing_time_ms: None,
+            response_status: 200,
+            error_message: None,
+            raw_esam_request: None,
+            raw_esam_response: None,
+            sesame_tier: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn spill_appends_rows_as_ndjson() {
+        let dir = std::env::temp_dir().join(format!("pois-spill-test-{}", std::process::id()));
+        let spill = Spill::new(dir.clone());
+        spill.append(&[row(1), row(2)]).unwrap();
+        spill.append(&[row(3)]).unwrap();
+        let text = std::fs::read_to_string(dir.join(SPILL_FILE)).unwrap();
+        let ids: Vec<String> = text
+            .lines()
+            .map(|l| serde_json::from_str::<EventRow>(l).unwrap().acquisition_signal_id)
+            .collect();
+        assert_eq!(ids, ["sig-1", "sig-2", "sig-3"]);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn overflow_drop_does_not_wait() {
+        // A writer that can never connect keeps the queue full.
+        let db = sqlx::sqlite::SqlitePoolOptions::new()
+            .acquire_timeout(Duration::from_millis(50))
+            .connect_lazy("sqlite:///nonexistent-dir/never.db")
+            .unwrap();
+        let q = EventQueue::with(db, MonitorHub::new(1), 1, 1, Overflow::Drop, None);
+        let mut dropped = 0;
+        for i in 0..5 {
+            if q.push(row(i)).await.0.is_none() {
+                dropped += 1;
+            }
+        }
+        assert!(dropped >= 3, "dropped {dropped}");
+    }
+}
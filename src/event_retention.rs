@@ -0,0 +1,606 @@
+// src/event_retention.rs
+//! Event retention: payload purge, row expiry and NDJSON archives.
+//!
+//! `esam_events` otherwise grows forever (and with `POIS_STORE_RAW_PAYLOADS`
+//! every row carries the full request/response XML). A background task runs
+//! every `POIS_RETENTION_INTERVAL_MINUTES` (default 60) and, per channel:
+//!   1. drops the raw payloads of events older than `payload_days`;
+//!   2. removes events older than `row_days` — in `archive` mode they are first
+//!      written to `esam-events-<channel>-<UTC stamp>.ndjson.gz` (one JSON
+//!      object per row, all columns) under `POIS_EVENT_ARCHIVE_DIR`, and only
+//!      deleted once that file is synced and renamed into place.
+//!
+//! The global policy comes from `POIS_EVENT_PAYLOAD_DAYS`,
+//! `POIS_EVENT_RETENTION_DAYS` and `POIS_EVENT_RETENTION_MODE` (default
+//! `archive` when an archive dir is set, else `delete`); unset or 0 = never.
+//! Per-channel overrides live in `event_retention` (NULL = inherit, 0 = never).
+//!
+//! Retention is system-wide, so the API here is super-admin only:
+//! `GET /api/retention` (policy, DB size, last run), `POST /api/retention/run`
+//! (`?dry_run=true`, `?vacuum=true`) and `PUT|DELETE /api/retention/channels/{name}`.
+
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Pool, Sqlite};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info};
+
+use crate::jwt_auth::Claims;
+use crate::rbac;
+use crate::AppState;
+
+const TS_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+const STAMP: &str = "%Y%m%dT%H%M%S%.3fZ";
+/// Rows per archive read / delete statement, to keep write locks short.
+const BATCH: i64 = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Delete,
+    Archive,
+}
+
+impl Mode {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "delete" => Some(Mode::Delete),
+            "archive" => Some(Mode::Archive),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::Delete => "delete",
+            Mode::Archive => "archive",
+        }
+    }
+}
+
+/// An effective policy. `None` days = never.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Policy {
+    pub payload_days: Option<i64>,
+    pub row_days: Option<i64>,
+    pub mode: Mode,
+}
+
+/// A per-channel override as stored (`None` = inherit, `Some(0)` = never).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Override {
+    #[serde(default, skip_deserializing)]
+    pub channel_name: String,
+    #[serde(default)]
+    pub payload_days: Option<i64>,
+    #[serde(default)]
+    pub row_days: Option<i64>,
+    #[serde(default)]
+    pub mode: Option<String>,
+}
+
+impl Policy {
+    /// Apply an override on top of this (global) policy.
+    pub fn with(self, o: &Override) -> Policy {
+        let days = |v: Option<i64>, global: Option<i64>| match v {
+            None => global,
+            Some(d) if d <= 0 => None,
+            Some(d) => Some(d),
+        };
+        Policy {
+            payload_days: days(o.payload_days, self.payload_days),
+            row_days: days(o.row_days, self.row_days),
+            mode: o.mode.as_deref().and_then(Mode::parse).unwrap_or(self.mode),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ChannelReport {
+    pub channel: String,
+    pub policy: Option<Policy>,
+    pub payloads_dropped: u64,
+    pub rows_archived: u64,
+    pub rows_deleted: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub started_at: String,
+    pub dry_run: bool,
+    pub duration_ms: u128,
+    pub payloads_dropped: u64,
+    pub rows_archived: u64,
+    pub rows_deleted: u64,
+    pub vacuumed: bool,
+    pub channels: Vec<ChannelReport>,
+}
+
+pub struct Retention {
+    pub global: Policy,
+    pub archive_dir: Option<PathBuf>,
+    pub interval: Duration,
+    /// Serializes scheduled and on-demand runs.
+    lock: tokio::sync::Mutex<()>,
+    last_run: std::sync::Mutex<Option<Arc<RunReport>>>,
+}
+
+impl Retention {
+    pub fn from_env() -> Self {
+        let days = |k: &str| {
+            std::env::var(k).ok().and_then(|v| v.trim().parse::<i64>().ok()).filter(|d| *d > 0)
+        };
+        let archive_dir = std::env::var("POIS_EVENT_ARCHIVE_DIR")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(PathBuf::from);
+        let mode = std::env::var("POIS_EVENT_RETENTION_MODE")
+            .ok()
+            .and_then(|m| Mode::parse(&m))
+            .unwrap_or(if archive_dir.is_some() { Mode::Archive } else { Mode::Delete });
+        let minutes = std::env::var("POIS_RETENTION_INTERVAL_MINUTES")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(60)
+            .max(1);
+        Self {
+            global: Policy {
+                payload_days: days("POIS_EVENT_PAYLOAD_DAYS"),
+                row_days: days("POIS_EVENT_RETENTION_DAYS"),
+                mode,
+            },
+            archive_dir,
+            interval: Duration::from_secs(minutes * 60),
+            lock: tokio::sync::Mutex::new(()),
+            last_run: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Run forever: a first pass shortly after startup, then every interval.
+    pub fn spawn(self: Arc<Self>, db: Pool<Sqlite>) {
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(60).min(self.interval)).await;
+            loop {
+                match self.run_once(&db, false, false).await {
+                    Ok(r) if r.payloads_dropped + r.rows_deleted > 0 => info!(
+                        "event retention: {} payloads dropped, {} rows archived, {} rows deleted",
+                        r.payloads_dropped, r.rows_archived, r.rows_deleted
+                    ),
+                    Ok(_) => {}
+                    Err(e) => error!("event retention failed: {}", e),
+                }
+                tokio::time::sleep(self.interval).await;
+            }
+        });
+    }
+
+    pub fn last_run(&self) -> Option<Arc<RunReport>> {
+        self.last_run.lock().ok().and_then(|g| g.clone())
+    }
+
+    /// Apply the policies once. `dry_run` only counts what would be affected.
+    pub async fn run_once(&self, db: &Pool<Sqlite>, dry_run: bool, vacuum: bool) -> Result<Arc<RunReport>, String> {
+        let _guard = self.lock.lock().await;
+        let started = Instant::now();
+        let now = Utc::now();
+
+        let overrides = load_overrides(db).await.map_err(|e| e.to_string())?;
+        let channels: Vec<String> = sqlx::query_scalar("SELECT DISTINCT channel_name FROM esam_events ORDER BY 1")
+            .fetch_all(db)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut report = RunReport {
+            started_at: now.format(TS_FORMAT).to_string(),
+            dry_run,
+            duration_ms: 0,
+            payloads_dropped: 0,
+            rows_archived: 0,
+            rows_deleted: 0,
+            vacuumed: false,
+            channels: Vec::new(),
+        };
+        for channel in channels {
+            let policy = overrides
+                .iter()
+                .find(|o| o.channel_name == channel)
+                .map(|o| self.global.with(o))
+                .unwrap_or(self.global);
+            let mut cr = ChannelReport { channel, policy: Some(policy), ..Default::default() };
+            if let Err(e) = self.apply(db, &policy, now, dry_run, &mut cr).await {
+                error!("event retention for channel '{}': {}", cr.channel, e);
+                cr.error = Some(e);
+            }
+            if cr.payloads_dropped + cr.rows_archived + cr.rows_deleted > 0 || cr.error.is_some() {
+                report.payloads_dropped += cr.payloads_dropped;
+                report.rows_archived += cr.rows_archived;
+                report.rows_deleted += cr.rows_deleted;
+                report.channels.push(cr);
+            }
+        }
+        if vacuum && !dry_run {
+            sqlx::query("VACUUM").execute(db).await.map_err(|e| format!("VACUUM: {e}"))?;
+            report.vacuumed = true;
+        }
+        report.duration_ms = started.elapsed().as_millis();
+
+        let report = Arc::new(report);
+        if !dry_run {
+            if let Ok(mut g) = self.last_run.lock() {
+                *g = Some(report.clone());
+            }
+        }
+        Ok(report)
+    }
+
+    async fn apply(
+        &self,
+        db: &Pool<Sqlite>,
+        policy: &Policy,
+        now: DateTime<Utc>,
+        dry_run: bool,
+        cr: &mut ChannelReport,
+    ) -> Result<(), String> {
+        // Rows first, so archives still carry the payloads they are about to
+        // lose; a failed expiry does not hold up the payload purge.
+        let expired = match policy.row_days {
+            Some(days) => self.expire_rows(db, policy.mode, cutoff(now, days), now, dry_run, cr).await,
+            None => Ok(()),
+        };
+        if let Some(days) = policy.payload_days {
+            let cutoff = cutoff(now, days);
+            let filter = "channel_name = ? AND timestamp < ? \
+                          AND (raw_esam_request IS NOT NULL OR raw_esam_response IS NOT NULL)";
+            cr.payloads_dropped = if dry_run {
+                count(db, filter, &cr.channel, &cutoff).await?
+            } else {
+                sqlx::query(&format!(
+                    "UPDATE esam_events SET raw_esam_request = NULL, raw_esam_response = NULL WHERE {filter}"
+                ))
+                .bind(&cr.channel)
+                .bind(&cutoff)
+                .execute(db)
+                .await
+                .map_err(|e| e.to_string())?
+                .rows_affected()
+            };
+        }
+        expired
+    }
+
+    async fn expire_rows(
+        &self,
+        db: &Pool<Sqlite>,
+        mode: Mode,
+        cutoff: String,
+        now: DateTime<Utc>,
+        dry_run: bool,
+        cr: &mut ChannelReport,
+    ) -> Result<(), String> {
+        let expired = count(db, "channel_name = ? AND timestamp < ?", &cr.channel, &cutoff).await?;
+        if expired == 0 {
+            return Ok(());
+        }
+        if dry_run {
+            match mode {
+                Mode::Archive => cr.rows_archived = expired,
+                Mode::Delete => {}
+            }
+            cr.rows_deleted = expired;
+            return Ok(());
+        }
+
+        let mut max_id = i64::MAX;
+        if mode == Mode::Archive {
+            let Some(dir) = &self.archive_dir else {
+                return Err("archive mode needs POIS_EVENT_ARCHIVE_DIR; expired rows kept".into());
+            };
+            let (name, rows, last_id) = write_archive(db, dir, &cr.channel, &cutoff, now).await?;
+            cr.archive = Some(name);
+            cr.rows_archived = rows;
+            max_id = last_id;
+        }
+        // Delete in batches, never beyond what was archived.
+        loop {
+            let n = sqlx::query(
+                "DELETE FROM esam_events WHERE id IN (SELECT id FROM esam_events \
+                 WHERE channel_name = ? AND timestamp < ? AND id <= ? LIMIT ?)",
+            )
+            .bind(&cr.channel)
+            .bind(&cutoff)
+            .bind(max_id)
+            .bind(BATCH)
+            .execute(db)
+            .await
+            .map_err(|e| e.to_string())?
+            .rows_affected();
+            cr.rows_deleted += n;
+            if n < BATCH as u64 {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn cutoff(now: DateTime<Utc>, days: i64) -> String {
+    (now - chrono::Duration::days(days)).format(TS_FORMAT).to_string()
+}
+
+async fn count(db: &Pool<Sqlite>, filter: &str, channel: &str, cutoff: &str) -> Result<u64, String> {
+    let n: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM esam_events WHERE {filter}"))
+        .bind(channel)
+        .bind(cutoff)
+        .fetch_one(db)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(n as u64)
+}
+
+async fn load_overrides(db: &Pool<Sqlite>) -> Result<Vec<Override>, sqlx::Error> {
+    sqlx::query_as("SELECT channel_name, payload_days, row_days, mode FROM event_retention ORDER BY channel_name")
+        .fetch_all(db)
+        .await
+}
+
+/// `esam-events-<channel>-<stamp>.ndjson.gz`, with the channel reduced to a
+/// filesystem-safe form.
+pub fn archive_name(channel: &str, now: DateTime<Utc>) -> String {
+    let safe: String = channel
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("esam-events-{safe}-{}.ndjson.gz", now.format(STAMP))
+}
+
+/// Stream the channel's expired rows into a gzip'd NDJSON file. Returns the
+/// file name, row count and highest archived id.
+async fn write_archive(
+    db: &Pool<Sqlite>,
+    dir: &std::path::Path,
+    channel: &str,
+    cutoff: &str,
+    now: DateTime<Utc>,
+) -> Result<(String, u64, i64), String> {
+    tokio::fs::create_dir_all(dir).await.map_err(|e| format!("{}: {e}", dir.display()))?;
+    let name = archive_name(channel, now);
+    let path = dir.join(&name);
+    let tmp = dir.join(format!(".{name}.tmp"));
+
+    let mut conn = db.acquire().await.map_err(|e| e.to_string())?;
+    let cols = crate::system_archive::table_columns(&mut conn, "esam_events").await.map_err(|e| e.to_string())?;
+    let pairs: Vec<String> = cols.iter().map(|c| format!("'{c}', \"{c}\"")).collect();
+    let sql = format!(
+        "SELECT id, json_object({}) FROM esam_events \
+         WHERE channel_name = ? AND timestamp < ? AND id > ? ORDER BY id LIMIT ?",
+        pairs.join(", ")
+    );
+
+    let mut file = tokio::fs::File::create(&tmp).await.map_err(|e| format!("{}: {e}", tmp.display()))?;
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    let (mut rows, mut last_id) = (0u64, 0i64);
+    loop {
+        let batch: Vec<(i64, String)> = sqlx::query_as(&sql)
+            .bind(channel)
+            .bind(cutoff)
+            .bind(last_id)
+            .bind(BATCH)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        for (id, line) in &batch {
+            gz.write_all(line.as_bytes()).and_then(|_| gz.write_all(b"\n")).map_err(|e| e.to_string())?;
+            last_id = *id;
+        }
+        rows += batch.len() as u64;
+        // Hand the compressed bytes so far to the file; the encoder keeps going.
+        let chunk = std::mem::take(gz.get_mut());
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        if (batch.len() as i64) < BATCH {
+            break;
+        }
+    }
+    let tail = gz.finish().map_err(|e| e.to_string())?;
+    file.write_all(&tail).await.map_err(|e| e.to_string())?;
+    file.sync_all().await.map_err(|e| e.to_string())?;
+    drop(file);
+    tokio::fs::rename(&tmp, &path).await.map_err(|e| e.to_string())?;
+    Ok((name, rows, last_id))
+}
+
+/// Database size and event volume, for the status endpoint.
+pub async fn db_stats(db: &Pool<Sqlite>) -> Result<serde_json::Value, sqlx::Error> {
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(db).await?;
+    let pages: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(db).await?;
+    let free: i64 = sqlx::query_scalar("PRAGMA freelist_count").fetch_one(db).await?;
+    let (events, with_payload, payload_bytes, oldest): (i64, i64, i64, Option<String>) = sqlx::query_as(
+        "SELECT COUNT(*), \
+                COUNT(CASE WHEN raw_esam_request IS NOT NULL OR raw_esam_response IS NOT NULL THEN 1 END), \
+                COALESCE(SUM(COALESCE(length(raw_esam_request), 0) + COALESCE(length(raw_esam_response), 0)), 0), \
+                MIN(timestamp) \
+         FROM esam_events",
+    )
+    .fetch_one(db)
+    .await?;
+    Ok(json!({
+        "size_bytes": page_size * pages,
+        "free_bytes": page_size * free,
+        "events": events,
+        "events_with_payload": with_payload,
+        "payload_bytes": payload_bytes,
+        "oldest_event": oldest,
+    }))
+}
+
+// -------------------------------- handlers --------------------------------
+
+async fn require_super(st: &AppState, claims: &Claims) -> Result<(), Response> {
+    let eff = rbac::effective(&st.db, claims).await;
+    if !eff.super_admin {
+        return Err((StatusCode::FORBIDDEN, "Admin only").into_response());
+    }
+    Ok(())
+}
+
+/// GET /api/retention — global policy, overrides, DB size and the last run.
+pub async fn get_retention(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    if let Err(rej) = require_super(&st, &claims).await {
+        return rej;
+    }
+    let r = &st.retention;
+    let overrides = match load_overrides(&st.db).await {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let db = match db_stats(&st.db).await {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    Json(json!({
+        "global": r.global,
+        "archive_dir": r.archive_dir.as_ref().map(|d| d.display().to_string()),
+        "interval_minutes": r.interval.as_secs() / 60,
+        "channels": overrides,
+        "db": db,
+        "last_run": r.last_run(),
+    }))
+    .into_response()
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct RunQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub vacuum: bool,
+}
+
+/// POST /api/retention/run — apply the policies now.
+pub async fn run_retention(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(q): Query<RunQuery>,
+) -> impl IntoResponse {
+    if let Err(rej) = require_super(&st, &claims).await {
+        return rej;
+    }
+    match st.retention.run_once(&st.db, q.dry_run, q.vacuum).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// PUT /api/retention/channels/{name} — set a channel's override.
+pub async fn set_channel_retention(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+    Json(o): Json<Override>,
+) -> impl IntoResponse {
+    if let Err(rej) = require_super(&st, &claims).await {
+        return rej;
+    }
+    if o.payload_days.is_some_and(|d| d < 0) || o.row_days.is_some_and(|d| d < 0) {
+        return (StatusCode::BAD_REQUEST, "days must be >= 0 (0 = never, null = inherit)").into_response();
+    }
+    let mode = match o.mode.as_deref().map(|m| Mode::parse(m).ok_or(m)) {
+        None => None,
+        Some(Ok(m)) => Some(m.as_str()),
+        Some(Err(m)) => {
+            return (StatusCode::BAD_REQUEST, format!("invalid mode '{m}' (delete|archive)")).into_response()
+        }
+    };
+    let res = sqlx::query(
+        "INSERT INTO event_retention (channel_name, payload_days, row_days, mode) VALUES (?, ?, ?, ?) \
+         ON CONFLICT(channel_name) DO UPDATE SET payload_days = excluded.payload_days, \
+         row_days = excluded.row_days, mode = excluded.mode, \
+         updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now')",
+    )
+    .bind(&name)
+    .bind(o.payload_days)
+    .bind(o.row_days)
+    .bind(mode)
+    .execute(&st.db)
+    .await;
+    match res {
+        Ok(_) => {
+            let o = Override { channel_name: name, mode: mode.map(str::to_string), ..o };
+            Json(json!({ "override": o, "effective": st.retention.global.with(&o) })).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// DELETE /api/retention/channels/{name} — back to the global policy.
+pub async fn delete_channel_retention(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    if let Err(rej) = require_super(&st, &claims).await {
+        return rej;
+    }
+    match sqlx::query("DELETE FROM event_retention WHERE channel_name = ?").bind(&name).execute(&st.db).await {
+        Ok(r) if r.rows_affected() == 0 => (StatusCode::NOT_FOUND, "No override for this channel").into_response(),
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_inherit_replace_or_disable() {
+        let global = Policy { payload_days: Some(7), row_days: Some(90), mode: Mode::Delete };
+        assert_eq!(global.with(&Override::default()), global);
+        let o = Override { payload_days: Some(0), row_days: Some(30), mode: Some("archive".into()), ..Default::default() };
+        assert_eq!(global.with(&o), Policy { payload_days: None, row_days: Some(30), mode: Mode::Archive });
+        let bad_mode = Override { mode: Some("shred".into()), ..Default::default() };
+        assert_eq!(global.with(&bad_mode).mode, Mode::Delete);
+    }
+
+    #[test]
+    fn archive_names_and_cutoffs() {
+        let now = DateTime::parse_from_rfc3339("2026-10-18T12:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(archive_name("east/1 hd", now), "esam-events-east_1_hd-20261018T120000.000Z.ndjson.gz");
+        // Same text format as esam_events.timestamp, so string comparison is chronological.
+        assert_eq!(cutoff(now, 30), "2026-09-18T12:00:00.000Z");
+    }
+
+    #[test]
+    fn archive_chunks_form_one_gzip_stream() {
+        use std::io::Read as _;
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        let mut out = Vec::new();
+        for line in ["{\"id\":1}\n", "{\"id\":2}\n"] {
+            gz.write_all(line.as_bytes()).unwrap();
+            out.extend(std::mem::take(gz.get_mut()));
+        }
+        out.extend(gz.finish().unwrap());
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(&out[..]).read_to_string(&mut text).unwrap();
+        assert_eq!(text, "{\"id\":1}\n{\"id\":2}\n");
+    }
+}
@@ -12,5 +12,18 @@
 // extracted byte-for-byte from this repo's former `src/sesame/`). We re-export it
 // here so every existing `pois_esam_server::sesame::…` path resolves to the crate
 // unchanged — the rest of rust-pois (axum adapter, benches) compiles as-is.
+//
+// `splice_info` is the typed SCTE-35 splice_info_section() codec (lossless
+// decode/encode). The binary's ESAM extractor, Tools API and cue builders are
+// built on it as `pois_esam_server::splice_info`. `splice_lint` is the
+// conformance linter on top of it (structured findings with severity).
+// `scte104` converts between it and SCTE 104 multiple_operation_message()
+// bytes, using the frame rates and SMPTE timecode in `timecode`. `mpegts`
+// pulls SCTE-35 sections out of MPEG transport streams.
 
 pub use ::sesame as sesame;
+pub mod splice_info;
+pub mod mpegts;
+pub mod scte104;
+pub mod splice_lint;
+pub mod timecode;
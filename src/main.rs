@@ -25,18 +25,36 @@ mod rules;
 mod esam;
 mod scte35; // SCTE-35 builder module
 mod event_logging; // Events Logging
+mod event_queue; // Async batched event writer (bounded queue, overflow policy)
+mod event_export; // Streaming CSV/NDJSON event export
+mod as_run; // Daily as-run logs of ad breaks (paired out/in cues)
+mod break_audit; // Break pairing / cue integrity analytics per channel and day
+mod replay; // Replay logged events against current/draft rules (decision diff)
+mod watchdog; // Missing-signal / anomaly watchdog with alert dedup + auto-resolve
+mod notifiers; // Watchdog alert notifiers (webhook, SMTP, syslog)
 mod backup; // Backup/restore module
+mod backup_scheduler; // Scheduled, rotated on-disk backups
 mod jwt_auth; // JWT authentication
 mod auth_handlers; // Auth API endpoints
 mod tools_api; // SCTE-35 Tools API
 mod sesame_axum; // SESAME (SCTE 130-9) Axum adapter
 mod template_library; // Template library + projects
+mod template_vars; // Typed template variables (${name} placeholders)
+mod template_sync; // Linked template instances: drift + re-sync
 mod rbac; // Groups + RBAC (identity resolution, group/membership management)
 mod password_change; // Self-service password change + forced first-login change
+mod system_archive; // Full-system archive: export / restore with id remapping
+mod config_as_code; // Declarative channels/rules from YAML/TOML (plan/apply)
+mod monitor_ws; // Live /ws/monitor event stream
+mod metrics; // Prometheus /metrics
+mod event_retention; // Event payload purge / expiry / NDJSON archives
+mod otel; // Tracing setup + OpenTelemetry spans per ESAM transaction
+mod scte104_ingest; // SCTE-104 automation TCP ingest through the channel rules
+mod ts_monitor; // UDP/multicast MPEG-TS monitor: cues on air vs. ESAM decisions
 
 use axum::{
     body::{Body, Bytes},
-    extract::{ConnectInfo, Extension, OriginalUri, Path, Query, State},
+    extract::{ConnectInfo, DefaultBodyLimit, Extension, OriginalUri, Path, Query, State},
     http::{HeaderMap, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
@@ -48,8 +66,7 @@ use base64::Engine;
 use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
 use std::{net::SocketAddr, sync::Arc, time::Instant};
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
-use tracing::info;
-use tracing_subscriber::EnvFilter;
+use tracing::{info, info_span, Instrument};
 
 // Import event logging types
 use crate::event_logging::{
@@ -62,6 +79,8 @@ use crate::models::{
     Channel, DryRunRequest, DryRunResult, ReorderRules, Rule, UpsertChannel, UpsertRule,
 };
 use crate::rules::rule_matches;
+use pois_esam_server::splice_lint;
+use pois_esam_server::timecode::FrameRate;
 
 #[derive(Clone)]
 struct AppState {
@@ -70,6 +89,16 @@ struct AppState {
     admin_token: String,
     event_logger: EventLogger,
     sesame: Arc<SesameRuntime>,
+    /// Scheduled on-disk backups (`None` unless POIS_BACKUP_DIR is set).
+    backups: Option<Arc<backup_scheduler::BackupSchedule>>,
+    /// Event retention policy + background purge.
+    retention: Arc<event_retention::Retention>,
+    /// Daily as-run reports on disk (`None` unless POIS_ASRUN_DIR is set).
+    asrun: Option<Arc<as_run::AsRunSchedule>>,
+    /// Per-channel watchdog policies, alerts and notifiers.
+    watchdog: Arc<watchdog::Watchdog>,
+    /// Ingest lint state (open segmentation starts) for channels with `scte35_lint`.
+    lint: Arc<std::sync::Mutex<std::collections::HashMap<i64, splice_lint::LintState>>>,
 }
 
 #[tokio::main]
@@ -80,12 +109,7 @@ async fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::from_default_env()
-                .add_directive("pois_esam_server=info".parse()?),
-        )
-        .init();
+    let telemetry = otel::init()?;
 
     // --- Config from env ---
     let db_url = std::env::var("POIS_DB").unwrap_or_else(|_| "sqlite://pois.db".to_string());
@@ -112,6 +136,12 @@ async fn main() -> anyhow::Result<()> {
         .await?;
     sqlx::migrate!().run(&db).await?;
 
+    // --export-archive FILE / --restore-archive FILE [--replace]: one-shot
+    // maintenance against the DB, then exit (passphrase: POIS_ARCHIVE_PASSPHRASE).
+    if let Some(code) = system_archive::run_cli(&db).await? {
+        std::process::exit(code);
+    }
+
     // Seed admin user on first install if env vars are set
     if let (Ok(seed_user), Ok(seed_pass)) = (
         std::env::var("POIS_SEED_ADMIN_USER"),
@@ -142,6 +172,13 @@ async fn main() -> anyhow::Result<()> {
     // Seed default channel + rule if DB is empty
     seed_default_channel_and_rule(&db).await?;
 
+    // Config-as-code: converge channels/rules to POIS_CONFIG_FILE. A bad document
+    // stops startup rather than serving a half-configured instance.
+    if let Ok(path) = std::env::var("POIS_CONFIG_FILE") {
+        let plan = config_as_code::apply_file(&db, &path).await?;
+        info!("config {} applied: {} change(s), {} unchanged", path, plan.changes.len(), plan.unchanged);
+    }
+
     // Initialize event logger
     let event_logger = EventLogger::new(db.clone());
 
@@ -168,13 +205,80 @@ async fn main() -> anyhow::Result<()> {
         info!("SESAME inactive (no POIS_SESAME_* config); ESAM path unauthenticated");
     }
 
+    // Scheduled on-disk backups (off unless POIS_BACKUP_DIR is set).
+    let backups = backup_scheduler::BackupSchedule::from_env().map(Arc::new);
+    if let Some(b) = &backups {
+        info!(
+            "Scheduled backups to {} every {} min (keep {})",
+            b.dir.display(),
+            b.interval.as_secs() / 60,
+            b.keep
+        );
+        b.clone().spawn(db.clone());
+    }
+
+    // Event retention (policies default to "keep forever").
+    let retention = Arc::new(event_retention::Retention::from_env());
+    let days = |d: Option<i64>| d.map_or("never".to_string(), |d| format!("after {d} days"));
+    info!(
+        "Event retention: payloads {}, rows {} ({}), checked every {} min",
+        days(retention.global.payload_days),
+        days(retention.global.row_days),
+        retention.global.mode.as_str(),
+        retention.interval.as_secs() / 60
+    );
+    retention.clone().spawn(db.clone());
+
+    // Daily as-run reports (off unless POIS_ASRUN_DIR is set).
+    let asrun = as_run::AsRunSchedule::from_env().map(Arc::new);
+    if let Some(a) = &asrun {
+        info!(
+            "As-run reports to {} (checked every {} min, backfill {} days)",
+            a.dir.display(),
+            a.interval.as_secs() / 60,
+            a.backfill_days
+        );
+        a.clone().spawn(db.clone());
+    }
+
+    // Watchdog (checks only channels with a policy; notifiers from env).
+    let watchdog = Arc::new(watchdog::Watchdog::from_env().map_err(anyhow::Error::msg)?);
+    let notifier_names = watchdog.notifier_names();
+    info!(
+        "Watchdog sweeping every {}s, notifiers: {}",
+        watchdog.interval.as_secs(),
+        if notifier_names.is_empty() { "none".to_string() } else { notifier_names.join(", ") }
+    );
+    watchdog.clone().spawn(db.clone(), &event_logger.monitor);
+
     let state = Arc::new(AppState {
         db,
         admin_token,
         event_logger,
         sesame,
+        backups,
+        retention,
+        asrun,
+        watchdog,
+        lint: Default::default(),
     });
 
+    // SCTE-104 automation ingest (off unless POIS_SCTE104_LISTEN is set).
+    if let Some(ingest) = scte104_ingest::Scte104Ingest::from_env().map_err(anyhow::Error::msg)? {
+        for l in &ingest.listeners {
+            info!("SCTE-104 ingest on {} for channel '{}' (frame rate {})", l.addr, l.channel, ingest.frame_rate);
+        }
+        Arc::new(ingest).spawn(state.clone());
+    }
+
+    // Encoder output monitor (off unless POIS_TS_MONITOR is set).
+    if let Some(monitor) = ts_monitor::TsMonitor::from_env().map_err(anyhow::Error::msg)? {
+        for i in &monitor.inputs {
+            info!("TS monitor on {} for channel '{}' (window {} s)", i.addr, i.channel, monitor.window.as_secs());
+        }
+        Arc::new(monitor).spawn(state.clone());
+    }
+
     // --- App / routes ---
     
     // Create auth router with AuthState (public endpoints)
@@ -207,10 +311,48 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/tools/scte35/decode", post(tools_api::decode_scte35))
         .route("/api/tools/scte35/validate", post(tools_api::validate_scte35))
         .route("/api/tools/scte35/test-send", post(tools_api::test_send))
+        .route(
+            "/api/tools/scte35/extract-ts",
+            post(tools_api::extract_ts).layer(DefaultBodyLimit::max(tools_api::TS_UPLOAD_LIMIT)),
+        )
+        .route("/api/tools/scte104/convert", post(tools_api::convert_scte104))
         .route("/api/events", get(list_events))
         .route("/api/events/stats", get(get_event_stats))
+        .route("/api/events/export", get(event_export::export_events))
+        .route("/api/events/replay", post(replay::replay_events))
         .route("/api/events/{id}", get(get_event_detail))
         .route("/api/backup/export/channel/{id}", post(backup::export_channel_only))
+        .route("/api/backup/export/channel/{id}/full", post(backup::export_channel_full))
+        .route("/api/backup/export/rule/{id}", post(backup::export_rule))
+        .route("/api/backup/export/rules", post(backup::export_rules))
+        .route("/api/backup/export/all", post(backup::export_all))
+        .route("/api/backup/import/channel", post(backup::import_channel))
+        .route("/api/backup/import/channel/full", post(backup::import_channel_full))
+        .route("/api/backup/import/channel/{id}/rule", post(backup::import_rule_to_channel))
+        .route("/api/backup/import/channel/{id}/rules", post(backup::import_rules_to_channel))
+        .route("/api/backup/import/file", post(backup::import_backup_file))
+        .route("/api/backup/snapshots", get(backup_scheduler::list_snapshots).post(backup_scheduler::create_snapshot))
+        .route("/api/backup/snapshots/{file}", get(backup_scheduler::download_snapshot))
+        .route("/api/backup/snapshots/{file}/restore", post(backup_scheduler::restore_snapshot))
+        .route("/api/system/archive", post(system_archive::export_archive_handler))
+        .route("/api/system/archive/restore", post(system_archive::restore_archive_handler))
+        .route("/api/config/plan", post(config_as_code::plan_handler))
+        .route("/api/config/apply", post(config_as_code::apply_handler))
+        .route("/api/config/export", get(config_as_code::export_handler))
+        .route("/api/retention", get(event_retention::get_retention))
+        .route("/api/retention/run", post(event_retention::run_retention))
+        .route("/api/asrun/run", post(as_run::run_reports))
+        .route("/api/asrun/{channel}", get(as_run::get_report))
+        .route("/api/analytics/breaks/{channel}", get(break_audit::get_audit))
+        .route("/api/watchdog", get(watchdog::get_status))
+        .route("/api/watchdog/test", post(watchdog::test_notifiers))
+        .route("/api/watchdog/alerts", get(watchdog::list_alerts))
+        .route("/api/watchdog/policies", get(watchdog::list_policies))
+        .route("/api/watchdog/policies/{channel}", put(watchdog::put_policy).delete(watchdog::delete_policy))
+        .route(
+            "/api/retention/channels/{name}",
+            put(event_retention::set_channel_retention).delete(event_retention::delete_channel_retention),
+        )
         // Template library + projects
         .route("/api/projects", get(template_library::list_projects).post(template_library::create_project))
         .route("/api/projects/{id}", get(template_library::get_project).put(template_library::update_project).delete(template_library::delete_project))
@@ -220,6 +362,8 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/templates/from-rule/{rule_id}", post(template_library::save_rule_template))
         .route("/api/templates/from-channel/{channel_id}", post(template_library::save_channel_template))
         .route("/api/templates/{id}/apply", post(template_library::apply_template))
+        .route("/api/template-instances", get(template_sync::list_instances))
+        .route("/api/template-instances/{id}/resync", get(template_sync::preview_resync).post(template_sync::resync))
         // Groups + RBAC (Phase 1)
         .route("/api/me/groups", get(rbac::my_groups))
         .route("/api/groups", get(rbac::list_groups).post(rbac::create_group))
@@ -236,11 +380,19 @@ async fn main() -> anyhow::Result<()> {
             require_jwt_auth,
         ));
 
+    // Live monitor WebSocket: authenticates itself (JWT in ?token=, since
+    // browsers cannot set headers on a WebSocket).
+    let monitor = Router::new()
+        .route("/ws/monitor", get(monitor_ws::monitor_ws))
+        .with_state(state.clone())
+        .layer(axum::Extension(auth_state.clone()));
+
     // Main app - merge all routers (no with_state at this level!)
     let app = Router::new()
         .route("/esam/channel/{channel}", post(handle_esam_with_path))
         .route("/esam/channel={channel}", post(handle_esam_with_path))
         .route("/healthz", get(|| async { "ok" }))
+        .route("/metrics", get(metrics::metrics_handler))
         .route("/esam", post(handle_esam))
         .with_state(state.clone())
         .nest_service("/static", ServeDir::new("static"))
@@ -272,6 +424,7 @@ async fn main() -> anyhow::Result<()> {
         .merge(auth_public)
         .merge(auth_protected)
         .merge(pois_api)
+        .merge(monitor)
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http());
 
@@ -284,7 +437,16 @@ async fn main() -> anyhow::Result<()> {
         use axum_server::tls_rustls::RustlsConfig;
         let config = RustlsConfig::from_pem_file(cert_path, key_path).await?;
         info!("POIS listening with TLS on https://{addr}  (UI: /login.html | Events: /events.html)");
+        let handle = axum_server::Handle::new();
+        tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                shutdown_signal().await;
+                handle.graceful_shutdown(Some(std::time::Duration::from_secs(10)));
+            }
+        });
         axum_server::bind_rustls(addr, config)
+            .handle(handle)
             .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
     } else {
@@ -293,12 +455,39 @@ async fn main() -> anyhow::Result<()> {
         axum::serve(
             listener,
             app.into_make_service_with_connect_info::<SocketAddr>()
-        ).await?;
+        )
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
     }
 
+    info!("POIS shutting down");
+    if !state.event_logger.queue.flush(std::time::Duration::from_secs(10)).await {
+        tracing::warn!("event log queue not fully flushed at shutdown");
+    }
+    // Flush buffered spans; the exporter blocks, so keep it off the runtime threads.
+    let _ = tokio::task::spawn_blocking(move || telemetry.shutdown()).await;
     Ok(())
 }
 
+/// Resolves on Ctrl-C or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let term = async {
+        if let Ok(mut s) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            s.recv().await;
+        }
+    };
+    #[cfg(not(unix))]
+    let term = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = term => {}
+    }
+}
+
 // ---------------- JWT authentication middleware ----------------
 
 async fn require_jwt_auth(
@@ -417,6 +606,27 @@ async fn handle_esam_impl(
     headers: HeaderMap,
     raw_body: Bytes,
     path_channel: Option<String>,
+) -> Response {
+    let span = otel::esam_span(&headers);
+    let mut obs = metrics::EsamObservation::start();
+    let resp = esam_decision(st, addr, uri, headers, raw_body, path_channel, &mut obs)
+        .instrument(span.clone())
+        .await;
+    span.record("http.response.status_code", resp.status().as_u16());
+    obs.finish(resp.status());
+    resp
+}
+
+/// The ESAM decision itself, run inside the transaction's `esam` span; fills
+/// `obs` (channel, action) for the metrics and the span attributes as it goes.
+async fn esam_decision(
+    st: Arc<AppState>,
+    addr: SocketAddr,
+    uri: axum::http::Uri,
+    headers: HeaderMap,
+    raw_body: Bytes,
+    path_channel: Option<String>,
+    obs: &mut metrics::EsamObservation,
 ) -> Response {
     let start = Instant::now();
 
@@ -484,7 +694,7 @@ async fn handle_esam_impl(
         .as_ref()
         .map(|c| c.achieved_tier.level() as i32);
 
-    let facts = match extract_facts(&body) {
+    let mut facts = match info_span!("esam.extract_facts").in_scope(|| metrics::timed("parse", || extract_facts(&body))) {
         Ok(v) => v,
         Err(e) => {
             let duration = start.elapsed();
@@ -509,7 +719,10 @@ async fn handle_esam_impl(
         }
     };
 
-    let obj = facts.as_object().cloned().unwrap_or_default();
+    let mut obj = facts.as_object().cloned().unwrap_or_default();
+    if let Some(acq) = obj.get("acquisitionSignalID").and_then(|v| v.as_str()) {
+        tracing::Span::current().record("esam.acquisition_signal_id", acq);
+    }
 
     // Determine channel name: URL path takes priority, then acquisitionPointIdentity from XML body
     let channel_name = path_channel
@@ -521,16 +734,17 @@ async fn handle_esam_impl(
         })
         .unwrap_or_else(|| "default".into());
 
-    let ch: Option<(i64, String, i64)> = sqlx::query_as(
-        "SELECT id, timezone, sesame_min_tier FROM channels WHERE name=? AND enabled=1 AND deleted_at IS NULL",
+    let ch: Option<(i64, String, i64, i64)> = sqlx::query_as(
+        "SELECT id, timezone, sesame_min_tier, scte35_lint FROM channels WHERE name=? AND enabled=1 AND deleted_at IS NULL",
     )
     .bind(&channel_name)
     .fetch_optional(&st.db)
+    .instrument(info_span!("esam.channel_lookup", pois.channel = %channel_name))
     .await
     .ok()
     .flatten();
 
-    let Some((channel_id, _tz, channel_min_tier)) = ch else {
+    let Some((channel_id, _tz, channel_min_tier, channel_lint)) = ch else {
         let duration = start.elapsed();
         let _ = st
             .event_logger
@@ -555,6 +769,8 @@ async fn handle_esam_impl(
         )
             .into_response();
     };
+    obs.channel = Some(channel_name.clone());
+    tracing::Span::current().record("pois.channel", channel_name.as_str());
 
     // ---- SESAME per-channel policy (§9.3), now that the channel is resolved ----
     // The global default tier was enforced during inbound verification; here we
@@ -566,32 +782,49 @@ async fn handle_esam_impl(
             .map(|c| c.achieved_tier)
             .unwrap_or(sesame_axum::Tier::Zero);
         let required = sesame_axum::Tier::from_u8(channel_min_tier.clamp(0, 3) as u8);
-        if required.level() > achieved.level() {
+        let rejection = if required.level() > achieved.level() {
             let key_id = sesame_ctx.as_ref().map(|c| c.key_id.clone());
-            return sesame_axum::reject_insufficient_tier(key_id, required, achieved).into_response();
-        }
-        if let Some(ctx) = sesame_ctx.as_ref() {
-            if let Some(scope_ch) = ctx.scope_channel.as_deref() {
-                if scope_ch != channel_name {
-                    return sesame_axum::reject_scope_mismatch(
-                        Some(ctx.key_id.clone()),
-                        scope_ch,
-                        &channel_name,
-                    )
-                    .into_response();
-                }
-            }
+            Some(sesame_axum::reject_insufficient_tier(key_id, required, achieved))
+        } else {
+            sesame_ctx.as_ref().and_then(|ctx| {
+                ctx.scope_channel
+                    .as_deref()
+                    .filter(|scope_ch| *scope_ch != channel_name)
+                    .map(|scope_ch| sesame_axum::reject_scope_mismatch(Some(ctx.key_id.clone()), scope_ch, &channel_name))
+            })
+        };
+        if let Some(rej) = rejection {
+            // Logged like the inbound rejections so the watchdog's
+            // sesame_rejections check counts them too.
+            let duration = start.elapsed();
+            let _ = st
+                .event_logger
+                .log_esam_event(
+                    &channel_name,
+                    &facts,
+                    None,
+                    client_info,
+                    ProcessingMetrics {
+                        request_size: Some(body.len() as i32),
+                        processing_time_ms: Some(duration.as_millis() as i32),
+                        response_status: 0,
+                        error_message: Some(format!("SESAME: {}", rej.error_code())),
+                    },
+                    None,
+                    None,
+                )
+                .await;
+            return rej.into_response();
         }
     }
 
-    let rules = match sqlx::query_as::<_, Rule>(
-        "SELECT * FROM rules WHERE channel_id=? AND enabled=1 AND deleted_at IS NULL ORDER BY priority",
-    )
-    .bind(channel_id)
-    .fetch_all(&st.db)
-    .await
-    {
-        Ok(v) => v,
+    // ---- Optional SCTE-35 conformance lint (advisory; findings become facts) ----
+    if channel_lint != 0 {
+        lint_facts(&st, channel_id, &channel_name, &mut facts, &mut obj);
+    }
+
+    let decided = match decide(&st, channel_id, &channel_name, &obj, "handle_esam").await {
+        Ok(d) => d,
         Err(e) => {
             let duration = start.elapsed();
             let _ = st
@@ -615,101 +848,179 @@ async fn handle_esam_impl(
         }
     };
 
-    let mut matched_rule: Option<Rule> = None;
-    for r in rules {
-        let m: serde_json::Value =
-            serde_json::from_str(&r.match_json).unwrap_or(serde_json::json!({}));
-        if rule_matches(&m, &obj) {
-            matched_rule = Some(r);
-            break;
-        }
+    obs.action = Some(decided.action.clone());
+    if let Some(r) = &decided.rule {
+        tracing::Span::current().record("pois.rule_id", r.id);
     }
+    tracing::Span::current().record("pois.action", decided.action.as_str());
+
+    let duration = start.elapsed();
+    let _ = st
+        .event_logger
+        .log_esam_event(
+            &channel_name,
+            &facts,
+            decided.rule.as_ref().map(|r| (r, r.action.as_str())),
+            client_info,
+            ProcessingMetrics {
+                request_size: Some(body.len() as i32),
+                processing_time_ms: Some(duration.as_millis() as i32),
+                response_status: 200,
+                error_message: None,
+            },
+            Some(&body),
+            Some(&decided.resp_xml),
+        )
+        .await;
 
-    if let Some(r) = matched_rule {
-        let rule_params: serde_json::Value = serde_json::from_str(&r.params_json).unwrap_or_default();
-        let orig_b64 = facts.get("scte35_b64").and_then(|v| v.as_str());
+    // Sign (and, if the request was Tier 3, encrypt) the outbound response —
+    // the primary SESAME protection against a forged POIS decision.
+    let acq_id = facts.get("acquisitionSignalID").and_then(|v| v.as_str()).unwrap_or("");
+    sesame_axum::build_esam_response(&st.sesame, sesame_ctx.as_ref(), acq_id, &decided.resp_xml)
+}
 
-        // Condition the outbound SCTE-35 for the friendly action (build / passthrough
-        // / in-place edit of the incoming cue). The standard ESAM verb is derived in
-        // build_notification; the authored params ride the <pois:Decision> element.
-        let final_params = apply_action(&r.action, rule_params.clone(), orig_b64);
+/// What a channel's rules made of one inbound cue.
+pub(crate) struct Decided {
+    /// The first matching rule; `None` is the noop fallback.
+    pub rule: Option<Rule>,
+    pub action: String,
+    /// Conditioned params; `scte35_b64` is the outbound cue, when there is one.
+    pub params: serde_json::Value,
+    pub resp_xml: String,
+}
 
-        if esam_verb(&r.action) == "replace" && final_params.get("scte35_b64").is_none() {
-            tracing::warn!(
-                "handle_esam: replace-class action '{}' on channel '{}' rule '{}' produced no scte35_b64 — BinaryData will be absent (no incoming cue to condition, or unparseable)",
-                r.action, channel_name, r.name
-            );
-        }
+/// Match the request facts `obj` against the channel's enabled rules, condition
+/// the cue for the first match's action (pass it through when none matches) and
+/// build the notification. The ESAM endpoint and the SCTE-104 ingest both
+/// decide here, so both get the stage timings, spans and warnings; `source`
+/// names the caller in those warnings. Only the rule query can fail.
+pub(crate) async fn decide(
+    st: &AppState,
+    channel_id: i64,
+    channel_name: &str,
+    obj: &serde_json::Map<String, serde_json::Value>,
+    source: &str,
+) -> Result<Decided, sqlx::Error> {
+    let rule_eval = info_span!("esam.rule_eval", rules = tracing::field::Empty, pois.rule_id = tracing::field::Empty);
+    let rules = sqlx::query_as::<_, Rule>(
+        "SELECT * FROM rules WHERE channel_id=? AND enabled=1 AND deleted_at IS NULL ORDER BY priority",
+    )
+    .bind(channel_id)
+    .fetch_all(&st.db)
+    .instrument(rule_eval.clone())
+    .await?;
 
-        // Decision metadata = authored params minus the (possibly large) raw payload.
-        let mut decision = rule_params.clone();
-        if let Some(obj) = decision.as_object_mut() {
-            obj.remove("scte35_b64");
+    let match_started = Instant::now();
+    let mut matched_rule: Option<Rule> = None;
+    rule_eval.in_scope(|| {
+        rule_eval.record("rules", rules.len());
+        for r in rules {
+            let m: serde_json::Value =
+                serde_json::from_str(&r.match_json).unwrap_or(serde_json::json!({}));
+            if rule_matches(&m, obj) {
+                rule_eval.record("pois.rule_id", r.id);
+                matched_rule = Some(r);
+                break;
+            }
         }
+    });
+    metrics::stage("rule_match", match_started.elapsed());
+
+    let conditioning_started = Instant::now();
+    let fact = |k: &str| obj.get(k).and_then(|v| v.as_str());
+    let orig_b64 = fact("scte35_b64");
+    let (action, params, decision) = match &matched_rule {
+        Some(r) => {
+            let rule_params: serde_json::Value = serde_json::from_str(&r.params_json).unwrap_or_default();
+
+            // Condition the outbound SCTE-35 for the friendly action (build / passthrough
+            // / in-place edit of the incoming cue). The standard ESAM verb is derived in
+            // build_notification; the authored params ride the <pois:Decision> element.
+            let params = info_span!("esam.apply_action", pois.action = %r.action)
+                .in_scope(|| apply_action(&r.action, rule_params.clone(), orig_b64));
+
+            if esam_verb(&r.action) == "replace" && params.get("scte35_b64").is_none() {
+                tracing::warn!(
+                    "{}: replace-class action '{}' on channel '{}' rule '{}' produced no scte35_b64 — BinaryData will be absent (no incoming cue to condition, or unparseable)",
+                    source, r.action, channel_name, r.name
+                );
+            }
 
-        let acq_id = facts.get("acquisitionSignalID").and_then(|v| v.as_str()).unwrap_or("");
-        let utc_point = facts.get("utcPoint").and_then(|v| v.as_str()).unwrap_or("");
-        let acq_point = facts.get("acquisitionPointIdentity").and_then(|v| v.as_str()).unwrap_or("");
-        let resp_xml = build_notification(acq_id, utc_point, acq_point, &r.action, &final_params, Some(&decision));
-
-        let duration = start.elapsed();
-        let _ = st
-            .event_logger
-            .log_esam_event(
-                &channel_name,
-                &facts,
-                Some((&r, &r.action)),
-                client_info,
-                ProcessingMetrics {
-                    request_size: Some(body.len() as i32),
-                    processing_time_ms: Some(duration.as_millis() as i32),
-                    response_status: 200,
-                    error_message: None,
-                },
-                Some(&body),
-                Some(&resp_xml),
-            )
-            .await;
-
-        // Sign (and, if the request was Tier 3, encrypt) the outbound response —
-        // the primary SESAME protection against a forged POIS decision.
-        sesame_axum::build_esam_response(&st.sesame, sesame_ctx.as_ref(), acq_id, &resp_xml)
-    } else {
-        let duration = start.elapsed();
-        
-        let acq_id = facts.get("acquisitionSignalID").and_then(|v| v.as_str()).unwrap_or("");
-        let utc_point = facts.get("utcPoint").and_then(|v| v.as_str()).unwrap_or("");
-        let acq_point = facts.get("acquisitionPointIdentity").and_then(|v| v.as_str()).unwrap_or("");
-
+            // Decision metadata = authored params minus the (possibly large) raw payload.
+            let mut decision = rule_params;
+            if let Some(d) = decision.as_object_mut() {
+                d.remove("scte35_b64");
+            }
+            (r.action.clone(), params, Some(decision))
+        }
         // Pass through original SCTE-35 payload on fallback noop
-        let noop_params = match facts.get("scte35_b64").and_then(|v| v.as_str()) {
-            Some(b64) => serde_json::json!({ "scte35_b64": b64 }),
-            None => serde_json::json!({}),
-        };
-        let resp_xml = build_notification(acq_id, utc_point, acq_point, "noop", &noop_params, None);
-        
-        let _ = st
-            .event_logger
-            .log_esam_event(
-                &channel_name,
-                &facts,
-                None,
-                client_info,
-                ProcessingMetrics {
-                    request_size: Some(body.len() as i32),
-                    processing_time_ms: Some(duration.as_millis() as i32),
-                    response_status: 200,
-                    error_message: None,
-                },
-                Some(&body),
-                Some(&resp_xml),
-            )
-            .await;
+        None => {
+            let params = match orig_b64 {
+                Some(b64) => serde_json::json!({ "scte35_b64": b64 }),
+                None => serde_json::json!({}),
+            };
+            ("noop".to_string(), params, None)
+        }
+    };
+
+    let resp_xml = info_span!("esam.build_notification").in_scope(|| {
+        build_notification(
+            fact("acquisitionSignalID").unwrap_or(""),
+            fact("utcPoint").unwrap_or(""),
+            fact("acquisitionPointIdentity").unwrap_or(""),
+            &action,
+            &params,
+            decision.as_ref(),
+        )
+    });
+    metrics::stage("conditioning", conditioning_started.elapsed());
+    Ok(Decided { rule: matched_rule, action, params, resp_xml })
+}
 
-        // Sign (and, if the request was Tier 3, encrypt) the outbound response —
-        // the primary SESAME protection against a forged POIS decision.
-        sesame_axum::build_esam_response(&st.sesame, sesame_ctx.as_ref(), acq_id, &resp_xml)
+/// Lint the inbound cue of a `scte35_lint` channel and add the findings as the
+/// `scte35.lint` / `scte35.lint_errors` facts (advisory; never rejects).
+pub(crate) fn lint_facts(
+    st: &AppState,
+    channel_id: i64,
+    channel_name: &str,
+    facts: &mut serde_json::Value,
+    obj: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    let findings = add_lint_facts(st.lint.lock().unwrap().entry(channel_id).or_default(), obj);
+    let errors = findings.iter().filter(|f| f.severity == splice_lint::Severity::Error).count();
+    if errors > 0 {
+        tracing::warn!(
+            "channel '{}' cue has {} conformance error(s): {}",
+            channel_name,
+            errors,
+            findings.iter().map(|f| f.code).collect::<Vec<_>>().join(", ")
+        );
     }
+    for k in ["scte35.lint", "scte35.lint_errors"] {
+        if let Some(v) = obj.get(k) {
+            facts[k] = v.clone();
+        }
+    }
+}
+
+/// Lint the request's cue (`scte35_b64`) through `state` and set the lint
+/// facts on `obj`; live requests and replay share this. No cue, no facts.
+pub(crate) fn add_lint_facts(
+    state: &mut splice_lint::LintState,
+    obj: &mut serde_json::Map<String, serde_json::Value>,
+) -> Vec<splice_lint::Finding> {
+    let Some(bytes) = obj
+        .get("scte35_b64")
+        .and_then(|v| v.as_str())
+        .and_then(|b| base64::engine::general_purpose::STANDARD.decode(b).ok())
+    else {
+        return Vec::new();
+    };
+    let findings = state.lint(&bytes);
+    let errors = findings.iter().filter(|f| f.severity == splice_lint::Severity::Error).count();
+    obj.insert("scte35.lint".into(), serde_json::json!(findings));
+    obj.insert("scte35.lint_errors".into(), serde_json::json!(errors));
+    findings
 }
 
 // -------------------- Channels with ownership --------------------
@@ -736,6 +1047,7 @@ async fn create_channel(
     let enabled = p.enabled.unwrap_or(true) as i64;
     let tz = p.timezone.unwrap_or_else(|| "UTC".into());
     let is_global = (eff.super_admin && p.is_global.unwrap_or(false)) as i64;
+    let lint = p.scte35_lint.unwrap_or(false) as i64;
 
     // Resolve the groups to publish the new channel to.
     let mut groups: Vec<i64> = p.group_ids.unwrap_or_default();
@@ -753,13 +1065,14 @@ async fn create_channel(
     }
 
     let r = sqlx::query_as::<_, Channel>(
-        "INSERT INTO channels(name,enabled,timezone,owner_user_id,is_global) VALUES(?,?,?,?,?) RETURNING *",
+        "INSERT INTO channels(name,enabled,timezone,owner_user_id,is_global,scte35_lint) VALUES(?,?,?,?,?,?) RETURNING *",
     )
     .bind(p.name)
     .bind(enabled)
     .bind(tz)
     .bind(eff.uid)
     .bind(is_global)
+    .bind(lint)
     .fetch_one(&st.db)
     .await;
     match r {
@@ -781,6 +1094,9 @@ async fn update_channel(
     if !rbac::can_write(&st.db, &eff, "channels", "channel_groups", "channel_id", id).await {
         return (StatusCode::FORBIDDEN, "Not allowed to modify this channel").into_response();
     }
+    if let Some(src) = config_as_code::channel_manager(&st.db, id).await {
+        return config_as_code::read_only(&src);
+    }
 
     let enabled = p.enabled.map(|b| b as i64);
     let tz = p.timezone.unwrap_or_else(|| "UTC".into());
@@ -789,7 +1105,7 @@ async fn update_channel(
     let r = sqlx::query_as::<_, Channel>(
         "UPDATE channels
          SET name=COALESCE(?,name), enabled=COALESCE(?,enabled), timezone=?,
-             is_global=COALESCE(?,is_global),
+             is_global=COALESCE(?,is_global), scte35_lint=COALESCE(?,scte35_lint),
              updated_at=strftime('%Y-%m-%dT%H:%M:%fZ','now')
          WHERE id=? AND deleted_at IS NULL
          RETURNING *",
@@ -798,6 +1114,7 @@ async fn update_channel(
     .bind(enabled)
     .bind(tz)
     .bind(is_global)
+    .bind(p.scte35_lint.map(|b| b as i64))
     .bind(id)
     .fetch_one(&st.db)
     .await;
@@ -822,15 +1139,23 @@ async fn delete_channel(
     if !rbac::can_write(&st.db, &eff, "channels", "channel_groups", "channel_id", id).await {
         return (StatusCode::FORBIDDEN, "Not allowed to delete this channel").into_response();
     }
+    if let Some(src) = config_as_code::channel_manager(&st.db, id).await {
+        return config_as_code::read_only(&src);
+    }
     let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-    let r = sqlx::query(
-        "UPDATE channels SET deleted_at=?, enabled=0 WHERE id=? AND deleted_at IS NULL"
-    )
-    .bind(&now)
-    .bind(id)
-    .execute(&st.db)
-    .await
-    .map(|_| ());
+    let r: Result<(), sqlx::Error> = async {
+        let mut tx = st.db.begin().await?;
+        sqlx::query(
+            "UPDATE channels SET deleted_at=?, enabled=0 WHERE id=? AND deleted_at IS NULL"
+        )
+        .bind(&now)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        watchdog::disable_policy(&mut *tx, id).await?;
+        tx.commit().await
+    }
+    .await;
     resp(r)
 }
 
@@ -869,6 +1194,9 @@ async fn create_rule(
     if !rbac::can_write(&st.db, &eff, "channels", "channel_groups", "channel_id", channel_id).await {
         return (StatusCode::FORBIDDEN, "Not allowed to modify this channel").into_response();
     }
+    if let Some(src) = config_as_code::channel_manager(&st.db, channel_id).await {
+        return config_as_code::read_only(&src);
+    }
     let owner_id: i64 = eff.uid;
 
     // space priorities by 10; append if negative
@@ -885,20 +1213,15 @@ async fn create_rule(
         p.priority = nextp;
     }
 
-    let r = sqlx::query_as::<_, Rule>(
-        "INSERT INTO rules(channel_id,name,priority,enabled,match_json,action,params_json,owner_user_id) 
-         VALUES(?,?,?,?,?,?,?,?) RETURNING *"
-    )
-    .bind(channel_id)
-    .bind(p.name)
-    .bind(p.priority)
-    .bind(p.enabled.unwrap_or(true) as i64)
-    .bind(p.match_json.to_string())
-    .bind(p.action)
-    .bind(p.params_json.to_string())
-    .bind(owner_id)
-    .fetch_one(&st.db)
-    .await;
+    let rule = p.into_backup();
+    let problems = backup::rule_problems(&rule);
+    if !problems.is_empty() {
+        return (StatusCode::BAD_REQUEST, problems.join("; ")).into_response();
+    }
+    let r = match st.db.acquire().await {
+        Ok(mut conn) => backup::insert_rule(&mut conn, channel_id, &rule, Some(owner_id), None).await,
+        Err(e) => Err(e),
+    };
     resp(r)
 }
 
@@ -915,25 +1238,21 @@ async fn update_rule(
             if !rbac::can_write(&st.db, &eff, "channels", "channel_groups", "channel_id", cid).await {
                 return (StatusCode::FORBIDDEN, "Not allowed to modify this rule").into_response();
             }
+            if let Some(src) = config_as_code::channel_manager(&st.db, cid).await {
+                return config_as_code::read_only(&src);
+            }
         }
     }
 
-    let r = sqlx::query_as::<_, Rule>(
-        "UPDATE rules 
-         SET name=?, priority=?, enabled=?, match_json=?, action=?, params_json=?, 
-             updated_at=strftime('%Y-%m-%dT%H:%M:%fZ','now') 
-         WHERE id=? AND deleted_at IS NULL 
-         RETURNING *",
-    )
-    .bind(p.name)
-    .bind(p.priority)
-    .bind(p.enabled.unwrap_or(true) as i64)
-    .bind(p.match_json.to_string())
-    .bind(p.action)
-    .bind(p.params_json.to_string())
-    .bind(id)
-    .fetch_one(&st.db)
-    .await;
+    let rule = p.into_backup();
+    let problems = backup::rule_problems(&rule);
+    if !problems.is_empty() {
+        return (StatusCode::BAD_REQUEST, problems.join("; ")).into_response();
+    }
+    let r = match st.db.acquire().await {
+        Ok(mut conn) => backup::update_rule(&mut conn, id, &rule, None).await,
+        Err(e) => Err(e),
+    };
     resp(r)
 }
 
@@ -949,6 +1268,9 @@ async fn delete_rule(
             if !rbac::can_write(&st.db, &eff, "channels", "channel_groups", "channel_id", cid).await {
                 return (StatusCode::FORBIDDEN, "Not allowed to delete this rule").into_response();
             }
+            if let Some(src) = config_as_code::channel_manager(&st.db, cid).await {
+                return config_as_code::read_only(&src);
+            }
         }
     }
 
@@ -990,6 +1312,9 @@ async fn reorder_rules(
                 if !rbac::can_write(&st.db, &eff, "channels", "channel_groups", "channel_id", cid).await {
                     return (StatusCode::FORBIDDEN, "Not allowed to reorder these rules").into_response();
                 }
+                if let Some(src) = config_as_code::channel_manager(&st.db, cid).await {
+                    return config_as_code::read_only(&src);
+                }
             }
         }
     }
@@ -1104,6 +1429,21 @@ fn set_payload(p: &mut serde_json::Value, edited: Option<String>, orig_b64: Opti
     }
 }
 
+/// With a `frame_rate` param ("29.97df", "25", ...), round the conditioned
+/// break_duration / segmentation_duration to whole frames. The rate is checked
+/// when the rule is saved (`backup::rule_problems`); rules saved before that
+/// check only get a warning here.
+fn snap_to_frames(p: &serde_json::Value, b64: String) -> String {
+    let Some(rate) = p.get("frame_rate").and_then(|v| v.as_str()) else { return b64 };
+    match FrameRate::parse(rate) {
+        Ok(fr) => tools_api::snap_break_durations_b64(&b64, fr).unwrap_or(b64),
+        Err(e) => {
+            tracing::warn!("duration not snapped to frames: {}", e);
+            b64
+        }
+    }
+}
+
 /// Resolve a matched rule's friendly action into conditioned params carrying the
 /// outbound `scte35_b64`. `build`/`replace` produce a fresh or rewritten cue;
 /// blackout/regionalize/shorten/extend/fill edit the *incoming* cue in place;
@@ -1155,6 +1495,7 @@ fn apply_action(
                 (Some(s), Some(o)) => tools_api::rewrite_break_duration_b64(o, (s * 90000.0) as u64),
                 _ => None,
             };
+            let edited = edited.map(|b| snap_to_frames(&p, b));
             set_payload(&mut p, edited, orig_b64);
         }
         "extend" => {
@@ -1169,6 +1510,7 @@ fn apply_action(
                 }
                 _ => None,
             };
+            let edited = edited.map(|b| snap_to_frames(&p, b));
             set_payload(&mut p, edited, orig_b64);
         }
         "delete" => {}
@@ -1199,15 +1541,26 @@ fn maybe_build_scte35(mut params: serde_json::Value) -> serde_json::Value {
             let upid_val = build.get("upid").and_then(|v| v.as_str());
             let advanced = seg_type.is_some() || upid_type.is_some() || upid_val.is_some();
 
-            let out = match cmd {
-                "time_signal_immediate" | "time_signal" => {
+            // splice_insert / splice_null / bandwidth_reservation /
+            // splice_schedule / private_command (and a time_signal carrying
+            // `segmentations`) take their fields straight from `build`.
+            let spec = serde_json::from_value::<tools_api::CommandSpec>(build.clone()).map_err(|e| e.to_string());
+            let built = spec.as_ref().ok().and_then(|spec| tools_api::build_other_command(cmd, spec));
+            let full = built.is_some();
+            let out = match (built, cmd) {
+                (Some(Ok(b64)), _) => b64,
+                (Some(Err(e)), _) => {
+                    tracing::warn!("rule build params for {cmd}: {e}");
+                    String::new()
+                }
+                (None, "time_signal_immediate" | "time_signal") => {
                     if advanced {
                         scte35::build_time_signal_advanced_b64(seg_type, upid_type, upid_val)
                     } else {
                         scte35::build_time_signal_immediate_b64()
                     }
                 }
-                "splice_insert_out" => {
+                (None, "splice_insert_out") => {
                     let dur = build
                         .get("duration_s")
                         .and_then(|v| v.as_u64())
@@ -1220,7 +1573,23 @@ fn maybe_build_scte35(mut params: serde_json::Value) -> serde_json::Value {
                         scte35::build_splice_insert_out_b64(dur)
                     }
                 }
-                _ => String::new(),
+                (None, _) => {
+                    if let Err(e) = &spec {
+                        tracing::warn!("rule build params for {cmd}: {e}");
+                    }
+                    String::new()
+                }
+            };
+            // An explicit splice_event_id / pts_time applies to the basic and
+            // advanced builders too.
+            let out = match &spec {
+                Ok(spec) if !full && !out.is_empty() => {
+                    tools_api::set_event_id_and_pts_b64(&out, spec.splice_event_id, spec.pts_time).unwrap_or_else(|e| {
+                        tracing::warn!("rule build params for {cmd}: {e}");
+                        String::new()
+                    })
+                }
+                _ => out,
             };
             if !out.is_empty() {
                 params["scte35_b64"] = serde_json::Value::String(out);
@@ -1242,15 +1611,9 @@ async fn list_events(
 
     // RBAC: scope events to channels the caller may read (None = super-admin).
     let eff = rbac::effective(&st.db, &claims).await;
-    let filters = EventFilters {
-        channel_name: params.get("channel").cloned(),
-        action: params.get("action").cloned(),
-        since: params.get("since").cloned(),
-        search: params
-            .get("search")
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty()),
-        event_scope: rbac::event_scope(&eff),
+    let filters = match EventFilters::from_query(&params, rbac::event_scope(&eff)) {
+        Ok(f) => f,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
     };
 
     match st.event_logger.get_recent_events(limit, offset, Some(filters)).await {
@@ -1431,6 +1794,79 @@ mod build_params_tests {
         );
     }
 
+    #[test]
+    fn schedule_and_private_commands_build_from_params() {
+        let sched = b64(json!({"build":{"command":"splice_schedule","events":[
+            {"splice_event_id":7,"utc_splice_time":"2026-01-01T00:00:00Z","duration_seconds":30},
+            {"splice_event_id":8,"components":[{"component_tag":1,"utc_splice_time":100}]}
+        ]}}));
+        let d = tools_api::decode_scte35_internal(&sched).expect("decodes");
+        assert_eq!(d.command_type, "splice_schedule");
+        assert_eq!(d.command_info["events"][0]["utc_splice_time_iso"], "2026-01-01T00:00:00Z");
+        assert_eq!(d.command_info["events"][0]["break_duration"]["duration_seconds"], 30.0);
+        assert_eq!(d.command_info["events"][1]["components"][0]["utc_splice_time"], 100);
+
+        let private = b64(json!({"build":{"command":"private_command","identifier":"ABCD","private_bytes":"01 02 ff"}}));
+        let d = tools_api::decode_scte35_internal(&private).expect("decodes");
+        assert_eq!(d.command_info["identifier_ascii"], "ABCD");
+        assert_eq!(d.command_info["private_bytes_hex"], "0102FF");
+
+        assert_eq!(b64(json!({"build":{"command":"bandwidth_reservation"}})), scte35::build_bandwidth_reservation_b64());
+        // Invalid params leave the payload unset rather than emitting a bad cue.
+        assert_eq!(b64(json!({"build":{"command":"splice_schedule","events":[{"splice_event_id":1}]}})), "");
+    }
+
+    #[test]
+    fn full_splice_insert_with_multiple_segmentations() {
+        let out = b64(json!({"build":{
+            "command":"splice_insert","splice_event_id":42,"pts_time":900000,
+            "break_duration_seconds":30,"auto_return":false,
+            "unique_program_id":7,"avail_num":1,"avails_expected":2,
+            "segmentations":[
+                {"segmentation_event_id":100,"segmentation_type_id":"0x34","duration_seconds":30,
+                 "mid":[{"upid_type":"0x03","upid":"ABCD01234567"},{"upid_type":"0x0F","upid":"urn:x"}],
+                 "delivery_restrictions":{"archive_allowed":true,"device_restrictions":2},
+                 "segment_num":1,"segments_expected":2,"sub_segment_num":1,"sub_segments_expected":3},
+                {"segmentation_event_id":101,"segmentation_type_id":"0x10","upid_type":"0x0F","upid":"urn:y",
+                 "components":[{"component_tag":1,"pts_offset":90}]}
+            ]
+        }}));
+        let d = tools_api::decode_scte35_internal(&out).expect("decodes");
+        let ci = &d.command_info;
+        assert_eq!(ci["splice_event_id"], 42);
+        assert_eq!(ci["pts_time"], 900000);
+        assert_eq!(ci["break_duration"]["auto_return"], false);
+        assert_eq!(ci["break_duration"]["duration_ticks"], 30 * 90000);
+        assert_eq!((ci["unique_program_id"].clone(), ci["avails_expected"].clone()), (json!(7), json!(2)));
+
+        let (a, b) = (&d.descriptors[0].data, &d.descriptors[1].data);
+        assert_eq!(a["segmentation_event_id"], 100);
+        assert_eq!(a["upid_type"], "0x0D");
+        assert_eq!(a["upid_children"][1]["upid_value"], "urn:x");
+        assert_eq!((a["delivery_not_restricted"].clone(), a["archive_allowed"].clone()), (json!(false), json!(true)));
+        assert_eq!(a["device_restrictions"], 2);
+        assert_eq!((a["sub_segment_num"].clone(), a["sub_segments_expected"].clone()), (json!(1), json!(3)));
+        assert_eq!(b["segmentation_event_id"], 101);
+        assert_eq!(b["components"][0]["pts_offset"], 90);
+
+        // Sub-segments on a type that does not carry them is rejected.
+        assert_eq!(b64(json!({"build":{"command":"splice_insert","segmentations":[
+            {"segmentation_type_id":"0x10","sub_segment_num":1}
+        ]}})), "");
+    }
+
+    #[test]
+    fn event_id_and_pts_apply_to_basic_builds() {
+        let out = b64(json!({"build":{"command":"splice_insert_out","duration_s":30,"splice_event_id":9,"pts_time":1234}}));
+        let d = tools_api::decode_scte35_internal(&out).expect("decodes");
+        assert_eq!(d.command_info["splice_event_id"], 9);
+        assert_eq!(d.command_info["pts_time"], 1234);
+        assert_eq!(d.descriptors[0].data["segmentation_event_id"], 9);
+
+        let ts = b64(json!({"build":{"command":"time_signal","pts_time":5000}}));
+        assert_eq!(tools_api::decode_scte35_internal(&ts).unwrap().command_info["pts_time"], 5000);
+    }
+
     // ---- richer action set: verb mapping, dispatch, decision metadata ----
 
     #[test]
@@ -1477,4 +1913,15 @@ mod build_params_tests {
         let out = apply_action("delete", json!({}), Some("ORIG"));
         assert!(out.get("scte35_b64").is_none());
     }
+
+    #[test]
+    fn apply_action_extend_snaps_to_frames() {
+        let orig = scte35::build_splice_insert_out_advanced_b64(30, None, None, None);
+        let out = apply_action("extend", json!({ "duration_s": 0.5, "frame_rate": "25" }), Some(&orig));
+        let d = tools_api::decode_scte35_internal(out["scte35_b64"].as_str().unwrap()).unwrap();
+        assert_eq!(d.command_info["break_duration"]["duration_ticks"], 763 * 3600, "30.5 s rounds to frame 763");
+        let out = apply_action("shorten", json!({ "duration_s": 10.01, "frame_rate": "25" }), Some(&orig));
+        let d = tools_api::decode_scte35_internal(out["scte35_b64"].as_str().unwrap()).unwrap();
+        assert_eq!(d.command_info["break_duration"]["duration_ticks"], 10 * 90000, "10.01 s snaps to frame 250");
+    }
 }
\ No newline at end of file
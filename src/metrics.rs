@@ -0,0 +1,230 @@
+// src/metrics.rs
+//! Prometheus metrics for the ESAM decision path (`GET /metrics`).
+//!
+//! - `pois_esam_requests_total{channel,action,verb,status}` — one per ESAM
+//!   request. `channel` is `_unknown` until the channel resolves (so arbitrary
+//!   names in 404s can't explode cardinality); `action`/`verb` are `none` for
+//!   requests rejected before a decision.
+//! - `pois_esam_request_seconds` — end-to-end decision latency.
+//! - `pois_esam_stage_seconds{stage}` — `sesame_verify`, `parse`, `rule_match`,
+//!   `conditioning`, `sesame_sign`.
+//! - `pois_sesame_rejections_total{code,key_id}` — every SESAME rejection
+//!   (verification and per-channel policy). `key_id` is empty without the
+//!   header and `_unknown` for a key id that is not configured.
+//! - `pois_db_pool_connections{state}` — `idle` / `in_use`, sampled at scrape.
+//! - `pois_events_logged_total`, `pois_event_log_failures_total`,
+//!   `pois_events_dropped_total`, `pois_events_spilled_total` and
+//!   `pois_event_queue_depth` (the async event writer, see `event_queue`).
+//! - `pois_monitor_clients` — `/ws/monitor` subscribers.
+//!
+//! The endpoint is open unless `POIS_METRICS_TOKEN` is set, in which case it
+//! needs `Authorization: Bearer <token>`.
+
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::AppState;
+
+/// Label used for a channel that did not resolve (or wasn't reached).
+pub const UNKNOWN_CHANNEL: &str = "_unknown";
+/// Label used for a SESAME key id that is not one of the configured keys.
+pub const UNKNOWN_KEY: &str = "_unknown";
+
+/// Sub-millisecond to multi-second: decisions are normally well under 10 ms.
+const BUCKETS: &[f64] = &[0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+pub struct Metrics {
+    registry: Registry,
+    pub requests: IntCounterVec,
+    pub request_seconds: HistogramVec,
+    pub stage_seconds: HistogramVec,
+    pub sesame_rejections: IntCounterVec,
+    pub db_pool: IntGaugeVec,
+    pub events_logged: IntCounter,
+    pub event_log_failures: IntCounter,
+    pub events_dropped: IntCounter,
+    pub events_spilled: IntCounter,
+    pub event_queue_depth: IntGauge,
+    pub monitor_clients: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("pois_esam_requests_total", "ESAM requests by channel, action, ESAM verb and HTTP status"),
+            &["channel", "action", "verb", "status"],
+        )
+        .expect("metric");
+        let request_seconds = HistogramVec::new(
+            HistogramOpts::new("pois_esam_request_seconds", "End-to-end ESAM decision latency").buckets(BUCKETS.to_vec()),
+            &[],
+        )
+        .expect("metric");
+        let stage_seconds = HistogramVec::new(
+            HistogramOpts::new("pois_esam_stage_seconds", "ESAM decision latency by stage").buckets(BUCKETS.to_vec()),
+            &["stage"],
+        )
+        .expect("metric");
+        let sesame_rejections = IntCounterVec::new(
+            Opts::new("pois_sesame_rejections_total", "SESAME rejections by error code and key id"),
+            &["code", "key_id"],
+        )
+        .expect("metric");
+        let db_pool = IntGaugeVec::new(
+            Opts::new("pois_db_pool_connections", "SQLite pool connections by state"),
+            &["state"],
+        )
+        .expect("metric");
+        let events_logged = IntCounter::new("pois_events_logged_total", "ESAM events written").expect("metric");
+        let event_log_failures =
+            IntCounter::new("pois_event_log_failures_total", "ESAM events that failed to be written").expect("metric");
+        let events_dropped =
+            IntCounter::new("pois_events_dropped_total", "ESAM events discarded on a full log queue").expect("metric");
+        let events_spilled =
+            IntCounter::new("pois_events_spilled_total", "ESAM events deferred to the spill file").expect("metric");
+        let event_queue_depth =
+            IntGauge::new("pois_event_queue_depth", "ESAM events waiting for the log writer").expect("metric");
+        let monitor_clients = IntGauge::new("pois_monitor_clients", "Connected /ws/monitor clients").expect("metric");
+
+        for c in [
+            Box::new(requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(request_seconds.clone()),
+            Box::new(stage_seconds.clone()),
+            Box::new(sesame_rejections.clone()),
+            Box::new(db_pool.clone()),
+            Box::new(events_logged.clone()),
+            Box::new(event_log_failures.clone()),
+            Box::new(events_dropped.clone()),
+            Box::new(events_spilled.clone()),
+            Box::new(event_queue_depth.clone()),
+            Box::new(monitor_clients.clone()),
+        ] {
+            registry.register(c).expect("unique metric");
+        }
+        Self {
+            registry,
+            requests,
+            request_seconds,
+            stage_seconds,
+            sesame_rejections,
+            db_pool,
+            events_logged,
+            event_log_failures,
+            events_dropped,
+            events_spilled,
+            event_queue_depth,
+            monitor_clients,
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut buf = Vec::new();
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buf);
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Record how long one decision stage took.
+pub fn stage(name: &str, elapsed: Duration) {
+    METRICS.stage_seconds.with_label_values(&[name]).observe(elapsed.as_secs_f64());
+}
+
+/// Time a synchronous stage.
+pub fn timed<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let t = Instant::now();
+    let out = f();
+    stage(name, t.elapsed());
+    out
+}
+
+/// `key_id` must already be a configured key, `""` or [`UNKNOWN_KEY`].
+pub fn sesame_rejected(code: &str, key_id: &str) {
+    METRICS.sesame_rejections.with_label_values(&[code, key_id]).inc();
+}
+
+/// Labels of one ESAM request, filled in as the decision progresses and
+/// recorded once the response status is known.
+pub struct EsamObservation {
+    started: Instant,
+    pub channel: Option<String>,
+    pub action: Option<String>,
+}
+
+impl EsamObservation {
+    pub fn start() -> Self {
+        Self { started: Instant::now(), channel: None, action: None }
+    }
+
+    pub fn finish(self, status: StatusCode) {
+        let action = self.action.as_deref().unwrap_or("none");
+        let verb = if self.action.is_some() { crate::esam::esam_verb(action) } else { "none" };
+        METRICS
+            .requests
+            .with_label_values(&[
+                self.channel.as_deref().unwrap_or(UNKNOWN_CHANNEL),
+                action,
+                verb,
+                status.as_str(),
+            ])
+            .inc();
+        METRICS.request_seconds.with_label_values(&[]).observe(self.started.elapsed().as_secs_f64());
+    }
+}
+
+/// GET /metrics — Prometheus text exposition.
+pub async fn metrics_handler(State(st): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    if let Ok(expected) = std::env::var("POIS_METRICS_TOKEN") {
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.strip_prefix("Bearer "));
+        if given != Some(expected.as_str()) {
+            return (StatusCode::UNAUTHORIZED, "metrics token required").into_response();
+        }
+    }
+    let size = st.db.size() as i64;
+    let idle = st.db.num_idle() as i64;
+    METRICS.db_pool.with_label_values(&["idle"]).set(idle);
+    METRICS.db_pool.with_label_values(&["in_use"]).set(size - idle);
+    METRICS.event_queue_depth.set(st.event_logger.queue.depth() as i64);
+    METRICS.monitor_clients.set(st.event_logger.monitor.subscribers() as i64);
+
+    ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], METRICS.render()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn observation_labels_and_exposition() {
+        let mut obs = EsamObservation::start();
+        obs.channel = Some("metrics-test".into());
+        obs.action = Some("blackout".into());
+        obs.finish(StatusCode::OK);
+        EsamObservation::start().finish(StatusCode::BAD_REQUEST);
+        sesame_rejected("sesame_bad_signature", "k1");
+        timed("parse", || ());
+
+        let text = METRICS.render();
+        assert!(text.contains(
+            r#"pois_esam_requests_total{action="blackout",channel="metrics-test",status="200",verb="replace"} 1"#
+        ));
+        assert!(text.contains(r#"pois_esam_requests_total{action="none",channel="_unknown",status="400",verb="none"}"#));
+        assert!(text.contains(r#"pois_sesame_rejections_total{code="sesame_bad_signature",key_id="k1"}"#));
+        assert!(text.contains(r#"pois_esam_stage_seconds_count{stage="parse"}"#));
+    }
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::template_vars::TemplateVariable;
+
 #[derive(Serialize, sqlx::FromRow, Clone)]
 pub struct Channel {
     pub id: i64,
@@ -11,6 +13,12 @@ pub struct Channel {
     pub deleted_at: Option<String>,   // NEW: soft delete
     #[serde(default)]
     pub is_global: i64,               // RBAC: visible to all groups
+    /// Config-as-code source that owns this channel (read-only in the API).
+    #[sqlx(default)]
+    pub managed_by: Option<String>,
+    /// Run the SCTE-35 conformance lint on ingested cues.
+    #[sqlx(default)]
+    pub scte35_lint: i64,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -27,6 +35,9 @@ pub struct UpsertChannel {
     /// RBAC: visible to all groups (super-admin only).
     #[serde(default)]
     pub is_global: Option<bool>,
+    /// Lint inbound SCTE-35 cues and expose the findings as facts.
+    #[serde(default)]
+    pub scte35_lint: Option<bool>,
 }
 
 #[derive(Debug, Serialize, sqlx::FromRow, Clone)]
@@ -41,6 +52,9 @@ pub struct Rule {
     pub params_json: String,
     pub owner_user_id: Option<i64>,  // NEW: ownership tracking
     pub deleted_at: Option<String>,   // NEW: soft delete
+    /// Config-as-code source that owns this rule (read-only in the API).
+    #[sqlx(default)]
+    pub managed_by: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -59,6 +73,20 @@ pub struct UpsertRule {
     pub params_json: serde_json::Value,
 }
 
+impl UpsertRule {
+    /// The stored form (`enabled` defaults to true).
+    pub fn into_backup(self) -> crate::backup::RuleBackup {
+        crate::backup::RuleBackup {
+            name: self.name,
+            match_json: self.match_json,
+            action: self.action,
+            params_json: self.params_json,
+            priority: self.priority,
+            enabled: self.enabled.unwrap_or(true),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct ReorderRules {
     pub ordered_ids: Vec<i64>, // first -> 0, then 10, 20, ...
@@ -92,6 +120,12 @@ pub struct Template {
     pub description: Option<String>,
     pub project_id: Option<i64>,
     pub body_json: String,
+    /// Declared `${name}` variables (JSON array of `template_vars::TemplateVariable`).
+    #[serde(default)]
+    pub variables_json: String,
+    /// Bumped whenever the body or declared variables change (instance drift).
+    #[serde(default)]
+    pub revision: i64,
     pub is_shared: i64,
     pub is_default: i64,
     #[serde(default)]
@@ -102,6 +136,24 @@ pub struct Template {
     pub updated_at: String,
 }
 
+/// A channel (or rule) created by applying a template, linked back to the
+/// template revision it was rendered from. `snapshot_json` is the rendered body
+/// as applied / last re-synced (the base of the re-sync three-way merge).
+#[derive(Serialize, sqlx::FromRow, Clone)]
+pub struct TemplateInstance {
+    pub id: i64,
+    pub template_id: i64,
+    pub template_revision: i64,
+    pub kind: String,
+    pub channel_id: i64,
+    pub rule_id: Option<i64>,
+    pub variables_json: String,
+    pub snapshot_json: String,
+    pub owner_user_id: Option<i64>,
+    pub created_at: String,
+    pub synced_at: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct UpsertProject {
     pub name: String,
@@ -148,6 +200,9 @@ pub struct SaveTemplate {
     /// filed in a project, else the saver's own groups).
     #[serde(default)]
     pub group_ids: Option<Vec<i64>>,
+    /// Typed variables the body's `${name}` placeholders resolve against on apply.
+    #[serde(default)]
+    pub variables: Option<Vec<TemplateVariable>>,
 }
 
 #[derive(Deserialize)]
@@ -165,6 +220,25 @@ pub struct UpdateTemplateMeta {
     pub group_ids: Option<Vec<i64>>,
     #[serde(default)]
     pub is_global: Option<bool>,
+    /// Replace the declared variables (an empty list removes them).
+    #[serde(default)]
+    pub variables: Option<Vec<TemplateVariable>>,
+    /// Replace the template body (a RuleBackup / ChannelFullBackup, placeholders
+    /// allowed). Bumps `revision` so linked instances show as drifted.
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+/// Body for re-syncing a template instance.
+#[derive(Deserialize, Default)]
+pub struct ResyncInstance {
+    /// Values for variables the template gained since the instance was applied
+    /// (merged over the instance's recorded values).
+    #[serde(default)]
+    pub variables: serde_json::Map<String, Value>,
+    /// Refuse (409) if the template moved past the revision that was previewed.
+    #[serde(default)]
+    pub expected_revision: Option<i64>,
 }
 
 #[derive(Deserialize, Default)]
@@ -173,6 +247,24 @@ pub struct ApplyTemplate {
     pub target_channel_id: Option<i64>,
     #[serde(default)]
     pub name: Option<String>,
+    /// Values for the template's declared variables (defaults fill the rest).
+    #[serde(default)]
+    pub variables: serde_json::Map<String, Value>,
+}
+
+/// Body for applying a project. Every channel template is stamped out once per
+/// variable set: one set from `variables`, or one per row of `matrix` /
+/// `matrix_csv` (each row merged over `variables`, which then acts as shared
+/// values). An empty body applies the project once with defaults.
+#[derive(Deserialize, Default)]
+pub struct ApplyProject {
+    #[serde(default)]
+    pub variables: serde_json::Map<String, Value>,
+    #[serde(default)]
+    pub matrix: Option<Vec<serde_json::Map<String, Value>>>,
+    /// CSV alternative to `matrix`: header row of variable names, one set per line.
+    #[serde(default)]
+    pub matrix_csv: Option<String>,
 }
 
 /// Deserialize helper distinguishing "field absent" (None) from "field is null"
@@ -0,0 +1,318 @@
+// src/monitor_ws.rs
+//! Live event stream for `static/monitor.html` (`GET /ws/monitor?token=…`).
+//!
+//! The event writer (`event_queue`) publishes every logged event to a `MonitorHub`
+//! (a tokio broadcast channel; nothing is serialized while nobody listens).
+//! Each WebSocket subscriber:
+//!   - authenticates with a JWT (session or API token) in `?token=` — browsers
+//!     cannot set headers on a WebSocket — or an `Authorization: Bearer` header;
+//!   - sees only events for channels it may read (`rbac::event_scope`; the set of
+//!     readable channel names is cached and re-resolved periodically);
+//!   - may filter server-side with `?channel=a,b&action=delete,replace`, or
+//!     change the filters later by sending `{"channels":[…],"actions":[…]}`.
+//!
+//! Backpressure: the hub keeps `POIS_MONITOR_BUFFER` events (default 256) per
+//! subscriber. A client that falls further behind, or whose socket accepts no
+//! frame within 5 s, is disconnected with close code 1013 ("try again later")
+//! instead of slowing the ESAM path or buffering without bound.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{
+        ws::{CloseFrame, Message, Utf8Bytes, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, info};
+
+use crate::auth_handlers::AuthState;
+use crate::rbac;
+use crate::AppState;
+
+/// How long a single frame may take to be accepted by a client socket.
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often a subscriber's readable-channel set is re-resolved.
+const SCOPE_REFRESH: Duration = Duration::from_secs(30);
+/// Close code for dropped slow clients (RFC 6455 "Try Again Later").
+const CLOSE_TRY_AGAIN: u16 = 1013;
+
+/// One logged event as pushed to monitor clients. Field names follow what
+/// `monitor.html` renders (`channel`, `command_type`, `event_id`, `details`).
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorEvent {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub event_id: i64,
+    pub timestamp: String,
+    pub channel: String,
+    pub action: String,
+    pub command_type: Option<String>,
+    pub segmentation_type: Option<String>,
+    pub details: serde_json::Value,
+}
+
+/// A published event: routing keys plus the JSON frame, serialized once.
+#[derive(Debug)]
+pub struct Frame {
+    pub channel: String,
+    pub action: String,
+    pub json: Utf8Bytes,
+}
+
+#[derive(Clone)]
+pub struct MonitorHub {
+    tx: broadcast::Sender<Arc<Frame>>,
+}
+
+impl MonitorHub {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
+        Self { tx }
+    }
+
+    pub fn from_env() -> Self {
+        let cap = std::env::var("POIS_MONITOR_BUFFER")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(256);
+        Self::new(cap)
+    }
+
+    /// Publish to current subscribers; free when there are none.
+    pub fn publish(&self, ev: MonitorEvent) {
+        if self.tx.receiver_count() == 0 {
+            return;
+        }
+        let Ok(json) = serde_json::to_string(&ev) else { return };
+        let _ = self.tx.send(Arc::new(Frame { channel: ev.channel, action: ev.action, json: json.into() }));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Frame>> {
+        self.tx.subscribe()
+    }
+
+    pub fn subscribers(&self) -> usize {
+        self.tx.receiver_count()
+    }
+}
+
+// --------------------------------- filters --------------------------------
+
+/// Server-side subscription filters; empty = everything.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Filters {
+    #[serde(default)]
+    pub channels: Vec<String>,
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+impl Filters {
+    fn from_query(q: &MonitorQuery) -> Self {
+        let split = |s: &Option<String>| -> Vec<String> {
+            s.as_deref()
+                .unwrap_or("")
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        Self { channels: split(&q.channel), actions: split(&q.action) }
+    }
+
+    pub fn wants(&self, f: &Frame) -> bool {
+        (self.channels.is_empty() || self.channels.contains(&f.channel))
+            && (self.actions.is_empty() || self.actions.iter().any(|a| a.eq_ignore_ascii_case(&f.action)))
+    }
+}
+
+/// Channels a (non-super) subscriber may read, re-resolved on a timer so new
+/// shares and revocations take effect without reconnecting.
+struct Visibility {
+    eff: rbac::Eff,
+    names: HashSet<String>,
+    refreshed: Instant,
+}
+
+impl Visibility {
+    async fn load(db: &Pool<Sqlite>, eff: rbac::Eff) -> Self {
+        let mut v = Self { eff, names: HashSet::new(), refreshed: Instant::now() };
+        v.refresh(db).await;
+        v
+    }
+
+    async fn refresh(&mut self, db: &Pool<Sqlite>) {
+        let mut qb: sqlx::QueryBuilder<Sqlite> =
+            sqlx::QueryBuilder::new("SELECT name FROM channels WHERE deleted_at IS NULL");
+        rbac::push_read_predicate(&mut qb, &self.eff, "channels", "channel_groups", "channel_id");
+        if let Ok(rows) = qb.build_query_scalar::<String>().fetch_all(db).await {
+            self.names = rows.into_iter().collect();
+        }
+        self.refreshed = Instant::now();
+    }
+
+    async fn allows(&mut self, db: &Pool<Sqlite>, channel: &str) -> bool {
+        if rbac::event_scope(&self.eff).is_none() {
+            return true;
+        }
+        if self.refreshed.elapsed() >= SCOPE_REFRESH {
+            self.refresh(db).await;
+        }
+        self.names.contains(channel)
+    }
+}
+
+// --------------------------------- handler --------------------------------
+
+#[derive(Debug, Deserialize, Default)]
+pub struct MonitorQuery {
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Comma-separated channel names.
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Comma-separated actions.
+    #[serde(default)]
+    pub action: Option<String>,
+}
+
+/// GET /ws/monitor — upgrade to the live event stream.
+pub async fn monitor_ws(
+    State(st): State<Arc<AppState>>,
+    Extension(auth): Extension<Arc<AuthState>>,
+    Query(q): Query<MonitorQuery>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let Some(token) = q.token.clone().or(bearer) else {
+        return (StatusCode::UNAUTHORIZED, "Missing authorization token").into_response();
+    };
+    let claims = match auth.auth_service.validate_token(&token).await {
+        Ok(c) => c,
+        Err(_) => return (StatusCode::UNAUTHORIZED, "Invalid or expired token").into_response(),
+    };
+    // Same gate as the REST middleware: a forced password change blocks sessions.
+    if claims.token_type == "session" {
+        let must: Option<i64> = sqlx::query_scalar("SELECT must_change_password FROM users WHERE id = ?")
+            .bind(claims.sub.parse::<i64>().unwrap_or(0))
+            .fetch_optional(&st.db)
+            .await
+            .ok()
+            .flatten();
+        if must == Some(1) {
+            return (StatusCode::FORBIDDEN, "Password change required").into_response();
+        }
+    }
+
+    let eff = rbac::effective(&st.db, &claims).await;
+    let filters = Filters::from_query(&q);
+    let rx = st.event_logger.monitor.subscribe();
+    let username = claims.username.clone();
+    ws.on_upgrade(move |socket| async move {
+        let vis = Visibility::load(&st.db, eff).await;
+        info!(user = %username, subscribers = st.event_logger.monitor.subscribers(), "monitor client connected");
+        run(socket, &st.db, rx, vis, filters).await;
+        debug!(user = %username, "monitor client disconnected");
+    })
+}
+
+async fn close(mut socket: WebSocket, code: u16, reason: &str) {
+    let frame = CloseFrame { code, reason: reason.into() };
+    let _ = tokio::time::timeout(SEND_TIMEOUT, socket.send(Message::Close(Some(frame)))).await;
+}
+
+async fn run(
+    mut socket: WebSocket,
+    db: &Pool<Sqlite>,
+    mut rx: broadcast::Receiver<Arc<Frame>>,
+    mut vis: Visibility,
+    mut filters: Filters,
+) {
+    loop {
+        tokio::select! {
+            ev = rx.recv() => match ev {
+                Ok(frame) => {
+                    if !filters.wants(&frame) || !vis.allows(db, &frame.channel).await {
+                        continue;
+                    }
+                    match tokio::time::timeout(SEND_TIMEOUT, socket.send(Message::Text(frame.json.clone()))).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(_)) => return,
+                        Err(_) => {
+                            info!("monitor client dropped: send timed out");
+                            return close(socket, CLOSE_TRY_AGAIN, "client too slow").await;
+                        }
+                    }
+                }
+                Err(RecvError::Lagged(n)) => {
+                    info!(missed = n, "monitor client dropped: lagging");
+                    return close(socket, CLOSE_TRY_AGAIN, "client too slow").await;
+                }
+                Err(RecvError::Closed) => return close(socket, 1001, "server shutting down").await,
+            },
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    if let Ok(f) = serde_json::from_str::<Filters>(text.as_str()) {
+                        filters = f;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(channel: &str, action: &str) -> Frame {
+        Frame { channel: channel.into(), action: action.into(), json: "{}".into() }
+    }
+
+    #[test]
+    fn query_filters_split_and_match() {
+        let q = MonitorQuery { token: None, channel: Some("east, west,".into()), action: Some("DELETE".into()) };
+        let f = Filters::from_query(&q);
+        assert_eq!(f.channels, vec!["east", "west"]);
+        assert!(f.wants(&frame("west", "delete")));
+        assert!(!f.wants(&frame("west", "noop")));
+        assert!(!f.wants(&frame("north", "delete")));
+        assert!(Filters::default().wants(&frame("any", "noop")));
+    }
+
+    #[tokio::test]
+    async fn lagging_subscriber_is_detected() {
+        let hub = MonitorHub::new(2);
+        let mut rx = hub.subscribe();
+        for i in 0..5 {
+            hub.publish(MonitorEvent {
+                kind: "event",
+                event_id: i,
+                timestamp: String::new(),
+                channel: "c".into(),
+                action: "noop".into(),
+                command_type: None,
+                segmentation_type: None,
+                details: serde_json::Value::Null,
+            });
+        }
+        assert!(matches!(rx.recv().await, Err(RecvError::Lagged(3))));
+    }
+}
@@ -0,0 +1,491 @@
+// src/mpegts.rs
+//
+// MPEG-2 transport stream (ISO/IEC 13818-1) demultiplexer for SCTE-35 cues.
+//
+// SCTE-35 PIDs are found through the PAT and each program's PMT (stream_type
+// 0x86, SCTE 35 §8.1). Sections are reassembled across packets: the
+// pointer_field, sections continued over several packets and several sections
+// in one packet are all handled, and a continuity error drops the partial
+// section. Each cue carries the packet it started in, that packet's byte
+// offset, the last PCR seen on the program's PCR_PID and the last PES PTS seen
+// in the program, both before the cue (90 kHz; the PCR is its 33-bit base).
+//
+// `Demux` takes one 188-byte packet at a time (for live inputs); `extract`
+// scans a whole capture, detecting 188, 192 (M2TS) and 204-byte packets.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::splice_info::{crc32, TABLE_ID};
+
+pub const PACKET_SIZE: usize = 188;
+pub const SYNC_BYTE: u8 = 0x47;
+pub const SCTE35_STREAM_TYPE: u8 = 0x86;
+
+const PAT_PID: u16 = 0x0000;
+const NULL_PID: u16 = 0x1FFF;
+/// PMT stream types carried as sections, not PES (private sections).
+const SECTION_STREAM_TYPES: [u8; 2] = [0x05, SCTE35_STREAM_TYPE];
+/// Warnings kept per scan; the rest are only counted.
+const MAX_WARNINGS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Program {
+    pub program_number: u16,
+    pub pmt_pid: u16,
+    pub pcr_pid: Option<u16>,
+    pub scte35_pids: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub pid: u16,
+    pub program_number: u16,
+    /// Packet (counted from 0) the section starts in, and its byte offset.
+    pub packet: u64,
+    pub offset: u64,
+    pub pcr: Option<u64>,
+    pub pts: Option<u64>,
+    /// The complete splice_info_section.
+    pub section: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Scan {
+    pub packet_size: usize,
+    pub packets: u64,
+    pub programs: Vec<Program>,
+    pub cues: Vec<Cue>,
+    pub warnings: Vec<String>,
+}
+
+/// Scan a capture for SCTE-35 cues.
+pub fn extract(data: &[u8]) -> Result<Scan, String> {
+    let (packet_size, mut pos) = detect_packet_size(data)
+        .ok_or("no MPEG-TS sync found (expected 0x47 every 188, 192 or 204 bytes)")?;
+    let mut demux = Demux::new();
+    let mut cues = Vec::new();
+    while pos + PACKET_SIZE <= data.len() {
+        if data[pos] != SYNC_BYTE {
+            let lost = pos;
+            match (pos + 1..data.len())
+                .find(|&i| data[i] == SYNC_BYTE && !matches!(data.get(i + packet_size), Some(&b) if b != SYNC_BYTE))
+            {
+                Some(i) => pos = i,
+                None => break,
+            }
+            demux.warn(format!("lost sync at byte {lost}, resynchronised at byte {pos}"));
+            continue;
+        }
+        cues.extend(demux.push(&data[pos..pos + PACKET_SIZE], pos as u64));
+        pos += packet_size;
+    }
+    Ok(Scan { packet_size, packets: demux.packets, programs: demux.programs(), cues, warnings: demux.take_warnings() })
+}
+
+/// Packet size and offset of the first packet: the first sync byte followed by
+/// two more at the same spacing, or in a capture too short for that, the first
+/// sync byte with a whole 188-byte packet after it.
+pub fn detect_packet_size(data: &[u8]) -> Option<(usize, usize)> {
+    let sync = |i: usize| data.get(i) == Some(&SYNC_BYTE);
+    for size in [PACKET_SIZE, 192, 204] {
+        if let Some(start) = (0..size).find(|&s| sync(s) && sync(s + size) && sync(s + 2 * size)) {
+            return Some((size, start));
+        }
+    }
+    (0..PACKET_SIZE)
+        .find(|&s| sync(s) && s + PACKET_SIZE <= data.len() && data.len() < s + 3 * PACKET_SIZE)
+        .map(|s| (PACKET_SIZE, s))
+}
+
+#[derive(Debug, Default)]
+struct SectionBuffer {
+    data: Vec<u8>,
+    collecting: bool,
+    packet: u64,
+    offset: u64,
+}
+
+impl SectionBuffer {
+    fn reset(&mut self) {
+        self.data.clear();
+        self.collecting = false;
+    }
+
+    /// Complete sections at the front of the buffer, with where each started.
+    /// Any after the first start in the packet at (`packet`, `offset`).
+    fn drain(&mut self, packet: u64, offset: u64) -> Vec<(Vec<u8>, u64, u64)> {
+        let mut out = Vec::new();
+        while self.collecting && self.data.len() >= 3 {
+            if self.data[0] == 0xFF {
+                // Stuffing: no further section starts before the next pointer_field.
+                self.reset();
+                break;
+            }
+            let len = 3 + ((((self.data[1] & 0x0F) as usize) << 8) | self.data[2] as usize);
+            if self.data.len() < len {
+                break;
+            }
+            out.push((self.data.drain(..len).collect(), self.packet, self.offset));
+            (self.packet, self.offset) = (packet, offset);
+        }
+        out
+    }
+}
+
+/// Incremental demultiplexer; feed it every packet of the stream in order.
+#[derive(Debug, Default)]
+pub struct Demux {
+    /// PMT PID -> program_number.
+    pmt_pids: HashMap<u16, u16>,
+    programs: BTreeMap<u16, Program>,
+    /// SCTE-35 PID -> program_number.
+    scte35_pids: HashMap<u16, u16>,
+    /// PES PID -> program_number.
+    pes_pids: HashMap<u16, u16>,
+    sections: HashMap<u16, SectionBuffer>,
+    continuity: HashMap<u16, u8>,
+    /// Last PCR base per PCR PID, last PTS per program.
+    pcr: HashMap<u16, u64>,
+    pts: HashMap<u16, u64>,
+    packets: u64,
+    warnings: Vec<String>,
+    dropped_warnings: usize,
+}
+
+impl Demux {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Packets pushed so far.
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    /// Programs found in the PAT, with their PMT contents once seen.
+    pub fn programs(&self) -> Vec<Program> {
+        self.programs.values().cloned().collect()
+    }
+
+    /// Warnings since the last call (continuity errors, bad sections, ...).
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        if self.dropped_warnings > 0 {
+            self.warnings.push(format!("{} more warnings not shown", self.dropped_warnings));
+            self.dropped_warnings = 0;
+        }
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn warn(&mut self, w: String) {
+        if self.warnings.len() < MAX_WARNINGS {
+            self.warnings.push(w);
+        } else {
+            self.dropped_warnings += 1;
+        }
+    }
+
+    /// One 188-byte packet at byte `offset` of the stream; returns the SCTE-35
+    /// sections it completes.
+    pub fn push(&mut self, pkt: &[u8], offset: u64) -> Vec<Cue> {
+        let packet = self.packets;
+        self.packets += 1;
+        if pkt.len() < PACKET_SIZE || pkt[0] != SYNC_BYTE {
+            self.warn(format!("packet {packet}: not a transport packet"));
+            return Vec::new();
+        }
+        let pid = u16::from_be_bytes([pkt[1] & 0x1F, pkt[2]]);
+        if pkt[1] & 0x80 != 0 || pid == NULL_PID {
+            return Vec::new();
+        }
+        let pusi = pkt[1] & 0x40 != 0;
+        let afc = (pkt[3] >> 4) & 0x03;
+        let cc = pkt[3] & 0x0F;
+
+        let mut start = 4;
+        let mut discontinuity = false;
+        if afc & 0x02 != 0 {
+            let len = pkt[4] as usize;
+            if 5 + len > PACKET_SIZE {
+                self.warn(format!("packet {packet}: adaptation_field_length {len} overruns the packet"));
+                return Vec::new();
+            }
+            if len > 0 {
+                let flags = pkt[5];
+                discontinuity = flags & 0x80 != 0;
+                if flags & 0x10 != 0 && len >= 7 {
+                    let b = &pkt[6..11];
+                    let base = ((b[0] as u64) << 25)
+                        | ((b[1] as u64) << 17)
+                        | ((b[2] as u64) << 9)
+                        | ((b[3] as u64) << 1)
+                        | ((b[4] as u64) >> 7);
+                    self.pcr.insert(pid, base);
+                }
+            }
+            start = 5 + len;
+        }
+        if afc & 0x01 == 0 {
+            return Vec::new();
+        }
+
+        let tracked = pid == PAT_PID || self.pmt_pids.contains_key(&pid) || self.scte35_pids.contains_key(&pid);
+        if tracked {
+            let prev = self.continuity.insert(pid, cc);
+            match prev {
+                Some(p) if p == cc && !discontinuity => return Vec::new(), // duplicate packet
+                Some(p) if (p + 1) & 0x0F != cc && !discontinuity => {
+                    self.warn(format!("packet {packet}: continuity error on PID 0x{pid:04X} ({p} -> {cc})"));
+                    if let Some(buf) = self.sections.get_mut(&pid) {
+                        buf.reset();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let payload = &pkt[start..PACKET_SIZE];
+        if let Some(&program) = self.pes_pids.get(&pid) {
+            if pusi {
+                if let Some(pts) = pes_pts(payload) {
+                    self.pts.insert(program, pts);
+                }
+            }
+            return Vec::new();
+        }
+        if !tracked {
+            return Vec::new();
+        }
+
+        let mut cues = Vec::new();
+        for (section, start_packet, start_offset) in self.sections_of(pid, pusi, payload, packet, offset) {
+            if pid == PAT_PID {
+                self.on_pat(&section);
+            } else if let Some(&program) = self.pmt_pids.get(&pid) {
+                self.on_pmt(program, &section);
+            } else if let Some(&program) = self.scte35_pids.get(&pid) {
+                if section[0] != TABLE_ID {
+                    self.warn(format!("packet {start_packet}: table_id 0x{:02X} on SCTE-35 PID 0x{pid:04X}", section[0]));
+                    continue;
+                }
+                let pcr_pid = self.programs.get(&program).and_then(|p| p.pcr_pid);
+                cues.push(Cue {
+                    pid,
+                    program_number: program,
+                    packet: start_packet,
+                    offset: start_offset,
+                    pcr: pcr_pid.and_then(|p| self.pcr.get(&p).copied()),
+                    pts: self.pts.get(&program).copied(),
+                    section,
+                });
+            }
+        }
+        cues
+    }
+
+    fn sections_of(&mut self, pid: u16, pusi: bool, payload: &[u8], packet: u64, offset: u64) -> Vec<(Vec<u8>, u64, u64)> {
+        let buf = self.sections.entry(pid).or_default();
+        let mut out = Vec::new();
+        if pusi {
+            let Some((&pointer, rest)) = payload.split_first() else {
+                buf.reset();
+                return out;
+            };
+            let pointer = pointer as usize;
+            if pointer > rest.len() {
+                buf.reset();
+                self.warn(format!("packet {packet}: pointer_field {pointer} overruns the packet"));
+                return out;
+            }
+            if buf.collecting {
+                buf.data.extend_from_slice(&rest[..pointer]);
+                out.extend(buf.drain(packet, offset));
+            }
+            buf.data.clear();
+            buf.data.extend_from_slice(&rest[pointer..]);
+            (buf.collecting, buf.packet, buf.offset) = (true, packet, offset);
+        } else if buf.collecting {
+            buf.data.extend_from_slice(payload);
+        }
+        out.extend(buf.drain(packet, offset));
+        out
+    }
+
+    fn on_pat(&mut self, s: &[u8]) {
+        let Some(body) = self.psi_body(s, 0x00, "PAT") else { return };
+        self.pmt_pids.clear();
+        for entry in body.chunks_exact(4) {
+            let number = u16::from_be_bytes([entry[0], entry[1]]);
+            let pid = u16::from_be_bytes([entry[2] & 0x1F, entry[3]]);
+            if number == 0 {
+                continue; // network PID
+            }
+            self.pmt_pids.insert(pid, number);
+            let p = self.programs.entry(number).or_insert_with(|| Program { program_number: number, ..Default::default() });
+            p.pmt_pid = pid;
+        }
+    }
+
+    fn on_pmt(&mut self, program: u16, s: &[u8]) {
+        let Some(body) = self.psi_body(s, 0x02, "PMT") else { return };
+        if body.len() < 4 || u16::from_be_bytes([s[3], s[4]]) != program {
+            return;
+        }
+        let pcr_pid = u16::from_be_bytes([body[0] & 0x1F, body[1]]);
+        let info_len = (u16::from_be_bytes([body[2] & 0x0F, body[3]]) as usize).min(body.len() - 4);
+        self.scte35_pids.retain(|_, p| *p != program);
+        self.pes_pids.retain(|_, p| *p != program);
+        let mut scte35 = Vec::new();
+        let mut es = &body[4 + info_len..];
+        while es.len() >= 5 {
+            let stream_type = es[0];
+            let pid = u16::from_be_bytes([es[1] & 0x1F, es[2]]);
+            let es_info_len = u16::from_be_bytes([es[3] & 0x0F, es[4]]) as usize;
+            if stream_type == SCTE35_STREAM_TYPE {
+                self.scte35_pids.insert(pid, program);
+                scte35.push(pid);
+            } else if !SECTION_STREAM_TYPES.contains(&stream_type) {
+                self.pes_pids.insert(pid, program);
+            }
+            es = &es[(5 + es_info_len).min(es.len())..];
+        }
+        let p = self.programs.entry(program).or_default();
+        p.program_number = program;
+        p.pcr_pid = (pcr_pid != NULL_PID).then_some(pcr_pid);
+        p.scte35_pids = scte35;
+    }
+
+    /// The loop of a long-form PSI section (after the 8-byte header, before the
+    /// CRC), if the table_id and CRC_32 check out.
+    fn psi_body<'a>(&mut self, s: &'a [u8], table_id: u8, name: &str) -> Option<&'a [u8]> {
+        if s.len() < 12 || s[0] != table_id {
+            return None;
+        }
+        let (data, crc) = s.split_at(s.len() - 4);
+        if crc32(data) != u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+            self.warn(format!("{name} section with a bad CRC_32 ignored"));
+            return None;
+        }
+        Some(&data[8..])
+    }
+}
+
+/// PTS of a PES packet header at the start of `payload`, if it has one.
+fn pes_pts(payload: &[u8]) -> Option<u64> {
+    if payload.len() < 14 || payload[..3] != [0x00, 0x00, 0x01] || payload[7] & 0x80 == 0 {
+        return None;
+    }
+    let b = &payload[9..14];
+    Some(
+        ((((b[0] >> 1) & 0x07) as u64) << 30)
+            | ((b[1] as u64) << 22)
+            | (((b[2] >> 1) as u64) << 15)
+            | ((b[3] as u64) << 7)
+            | ((b[4] >> 1) as u64),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn psi(table_id: u8, id: u16, body: &[u8]) -> Vec<u8> {
+        let len = 5 + body.len() + 4;
+        let mut s = vec![table_id, 0xB0 | (len >> 8) as u8, len as u8];
+        s.extend(id.to_be_bytes());
+        s.extend([0xC1, 0x00, 0x00]);
+        s.extend(body);
+        let crc = crc32(&s);
+        s.extend(crc.to_be_bytes());
+        s
+    }
+
+    /// Packetise `section` on `pid`, starting with continuity counter `cc`.
+    fn packets(pid: u16, section: &[u8], cc: &mut u8) -> Vec<u8> {
+        let mut data = vec![0x00];
+        data.extend(section);
+        let mut out = Vec::new();
+        for (i, chunk) in data.chunks(PACKET_SIZE - 4).enumerate() {
+            let pusi = if i == 0 { 0x40 } else { 0 };
+            out.extend([SYNC_BYTE, pusi | (pid >> 8) as u8, pid as u8, 0x10 | *cc]);
+            out.extend(chunk);
+            out.resize(out.len() + PACKET_SIZE - 4 - chunk.len(), 0xFF);
+            *cc = (*cc + 1) & 0x0F;
+        }
+        out
+    }
+
+    fn pcr_pes_packet(pid: u16, pcr: u64, pts: u64) -> Vec<u8> {
+        let mut p = vec![SYNC_BYTE, 0x40 | (pid >> 8) as u8, pid as u8, 0x30, 7, 0x10];
+        p.extend([(pcr >> 25) as u8, (pcr >> 17) as u8, (pcr >> 9) as u8, (pcr >> 1) as u8, ((pcr & 1) << 7) as u8 | 0x7E, 0]);
+        p.extend([0, 0, 1, 0xE0, 0, 0, 0x80, 0x80, 5]);
+        p.extend([
+            0x21 | ((pts >> 29) & 0x0E) as u8,
+            (pts >> 22) as u8,
+            0x01 | ((pts >> 14) & 0xFE) as u8,
+            (pts >> 7) as u8,
+            0x01 | ((pts << 1) & 0xFE) as u8,
+        ]);
+        p.resize(PACKET_SIZE, 0xFF);
+        p
+    }
+
+    #[test]
+    fn finds_cues_through_pat_and_pmt() {
+        let cue = crate::splice_info::SpliceInfoSection::new(crate::splice_info::SpliceCommand::SpliceNull).encode().unwrap();
+        // A splice_info_section larger than one packet, to cover continuation.
+        let long = crate::splice_info::SpliceInfoSection::new(crate::splice_info::SpliceCommand::PrivateCommand {
+            identifier: 0x5445_5354,
+            private_bytes: vec![0xAB; 300],
+        })
+        .encode()
+        .unwrap();
+
+        let pat = psi(0x00, 1, &[0x00, 0x01, 0xE1, 0x00]);
+        let pmt = psi(0x02, 1, &[0xE1, 0x01, 0xF0, 0x00, 0x1B, 0xE1, 0x01, 0xF0, 0x00, 0x86, 0xE1, 0x02, 0xF0, 0x00]);
+        let (mut cc0, mut cc1, mut cc2) = (0, 0, 0);
+        let mut ts = vec![0u8; 192 - PACKET_SIZE]; // leading junk
+        ts.extend(packets(0x0000, &pat, &mut cc0));
+        ts.extend(packets(0x0100, &pmt, &mut cc1));
+        ts.extend(pcr_pes_packet(0x0101, 900_000, 903_003));
+        ts.extend(packets(0x0102, &cue, &mut cc2));
+        ts.extend(packets(0x0102, &long, &mut cc2));
+
+        let scan = extract(&ts).unwrap();
+        assert_eq!((scan.packet_size, scan.packets), (PACKET_SIZE, 6));
+        assert_eq!(
+            scan.programs,
+            vec![Program { program_number: 1, pmt_pid: 0x0100, pcr_pid: Some(0x0101), scte35_pids: vec![0x0102] }]
+        );
+        assert_eq!(scan.cues.len(), 2, "{:?}", scan.warnings);
+        assert_eq!(scan.cues[0].section, cue);
+        assert_eq!((scan.cues[0].packet, scan.cues[0].offset), (3, 4 + 3 * PACKET_SIZE as u64));
+        assert_eq!((scan.cues[0].pcr, scan.cues[0].pts), (Some(900_000), Some(903_003)));
+        assert_eq!(scan.cues[1].section, long);
+        assert_eq!(scan.cues[1].packet, 4);
+        assert!(scan.warnings.is_empty(), "{:?}", scan.warnings);
+    }
+
+    #[test]
+    fn continuity_error_drops_the_partial_section() {
+        let long = crate::splice_info::SpliceInfoSection::new(crate::splice_info::SpliceCommand::PrivateCommand {
+            identifier: 0x5445_5354,
+            private_bytes: vec![0xCD; 300],
+        })
+        .encode()
+        .unwrap();
+        let pat = psi(0x00, 1, &[0x00, 0x01, 0xE1, 0x00]);
+        let pmt = psi(0x02, 1, &[0xFF, 0xFF, 0xF0, 0x00, 0x86, 0xE1, 0x02, 0xF0, 0x00]);
+        let (mut cc0, mut cc1, mut cc2) = (0, 0, 0);
+        let mut ts = packets(0x0000, &pat, &mut cc0);
+        ts.extend(packets(0x0100, &pmt, &mut cc1));
+        let mut cue = packets(0x0102, &long, &mut cc2);
+        cue[PACKET_SIZE + 3] = 0x10 | 5; // second packet's counter skips
+        ts.extend(cue);
+
+        let scan = extract(&ts).unwrap();
+        assert!(scan.cues.is_empty());
+        assert_eq!(scan.programs[0].pcr_pid, None);
+        assert!(scan.warnings[0].contains("continuity error"), "{:?}", scan.warnings);
+        assert!(extract(&[0u8; 400]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+// src/otel.rs
+//! Logging/tracing setup and OpenTelemetry traces of ESAM transactions.
+//!
+//! Every ESAM request runs inside an `esam` span (attributes `pois.channel`,
+//! `esam.acquisition_signal_id`, `pois.rule_id`, `pois.action`, HTTP status)
+//! with child spans per stage: `sesame.verify`, `esam.extract_facts`,
+//! `esam.channel_lookup`, `esam.rule_eval`, `esam.apply_action`,
+//! `esam.build_notification`, `sesame.sign` and `esam.log_event`. A W3C
+//! `traceparent` header from the encoder makes the `esam` span a child of the
+//! caller's trace.
+//!
+//! Export is off unless `OTEL_EXPORTER_OTLP_ENDPOINT` (or
+//! `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is set; spans are then batched to the
+//! collector over OTLP/HTTP protobuf (usually port 4318). The standard
+//! `OTEL_*` variables apply (`OTEL_SERVICE_NAME`, `OTEL_EXPORTER_OTLP_HEADERS`,
+//! `OTEL_TRACES_SAMPLER`, …). Without an exporter the spans only add context
+//! to the log lines.
+
+use axum::http::HeaderMap;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use tracing::{field, info, info_span, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+const SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Keeps the tracer provider alive; `shutdown` flushes pending spans.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Flush and stop the exporter. Blocks until the batch is sent (or times out).
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("OpenTelemetry shutdown: {e}");
+            }
+        }
+    }
+}
+
+fn exporter_configured() -> bool {
+    ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"]
+        .iter()
+        .any(|k| std::env::var(k).is_ok_and(|v| !v.trim().is_empty()))
+}
+
+fn build_provider() -> anyhow::Result<SdkTracerProvider> {
+    use opentelemetry_otlp::WithExportConfig as _;
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_protocol(opentelemetry_otlp::Protocol::HttpBinary)
+        .build()?;
+    let mut resource = Resource::builder();
+    if std::env::var("OTEL_SERVICE_NAME").is_err() {
+        resource = resource.with_service_name(SERVICE_NAME);
+    }
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource.build())
+        .build())
+}
+
+/// Install the global subscriber: `RUST_LOG`-filtered fmt output, plus the
+/// OpenTelemetry layer when an OTLP endpoint is configured.
+pub fn init() -> anyhow::Result<Telemetry> {
+    let filter = EnvFilter::from_default_env().add_directive("pois_esam_server=info".parse()?);
+    let provider = if exporter_configured() { Some(build_provider()?) } else { None };
+    let otel_layer = provider
+        .as_ref()
+        .map(|p| tracing_opentelemetry::layer().with_tracer(p.tracer(SERVICE_NAME)));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(otel_layer)
+        .init();
+    if provider.is_some() {
+        info!("OpenTelemetry trace export enabled (OTLP/HTTP)");
+    }
+    Ok(Telemetry { provider })
+}
+
+struct HeaderCarrier<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderCarrier<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// Root span of one ESAM transaction, parented to the inbound `traceparent`
+/// when there is a valid one. Fields are recorded as the decision progresses.
+pub fn esam_span(headers: &HeaderMap) -> Span {
+    let span = info_span!(
+        "esam",
+        otel.kind = "server",
+        pois.channel = field::Empty,
+        esam.acquisition_signal_id = field::Empty,
+        pois.rule_id = field::Empty,
+        pois.action = field::Empty,
+        http.response.status_code = field::Empty,
+    );
+    let parent = TraceContextPropagator::new().extract(&HeaderCarrier(headers));
+    if parent.span().span_context().is_valid() {
+        // Fails only when no OpenTelemetry layer is installed; nothing to do then.
+        let _ = span.set_parent(parent);
+    }
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceId;
+
+    #[test]
+    fn traceparent_becomes_the_parent_trace() {
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        tracing::subscriber::with_default(subscriber, || {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".parse().unwrap(),
+            );
+            let span = esam_span(&headers);
+            let trace_id = span.context().span().span_context().trace_id();
+            assert_eq!(trace_id, TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap());
+
+            let fresh = esam_span(&HeaderMap::new());
+            assert_ne!(fresh.context().span().span_context().trace_id(), trace_id);
+        });
+    }
+}
@@ -21,7 +21,7 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, SqliteConnection};
 
 use sqlx::QueryBuilder;
 
@@ -102,8 +102,8 @@ pub fn push_read_predicate<'a>(
     qb.push(")");
 }
 
-async fn group_link_matches(
-    db: &Pool<Sqlite>,
+async fn group_link_matches<'c>(
+    db: impl sqlx::Executor<'c, Database = Sqlite>,
     link_table: &str,
     link_col: &str,
     id: i64,
@@ -179,9 +179,23 @@ pub async fn can_write(
     .ok()
     .flatten();
     match owner {
-        None => return false, // not found
-        Some((Some(o),)) if o == eff.uid => return true,
-        _ => {}
+        None => false, // not found
+        Some((owner,)) => can_write_owned(db, eff, owner, link_table, link_col, id).await,
+    }
+}
+
+/// `can_write` for a row whose owner the caller already loaded, including a
+/// soft-deleted one (which `can_write` reports as not found).
+pub async fn can_write_owned<'c>(
+    db: impl sqlx::Executor<'c, Database = Sqlite>,
+    eff: &Eff,
+    owner: Option<i64>,
+    link_table: &str,
+    link_col: &str,
+    id: i64,
+) -> bool {
+    if eff.super_admin || owner == Some(eff.uid) {
+        return true;
     }
     group_link_matches(db, link_table, link_col, id, &eff.admin_of).await
 }
@@ -193,6 +207,19 @@ pub async fn link_groups(
     link_col: &str,
     id: i64,
     group_ids: &[i64],
+) {
+    if let Ok(mut conn) = db.acquire().await {
+        link_groups_in(&mut conn, link_table, link_col, id, group_ids).await;
+    }
+}
+
+/// `link_groups` on the caller's connection (e.g. inside a transaction).
+pub async fn link_groups_in(
+    conn: &mut SqliteConnection,
+    link_table: &str,
+    link_col: &str,
+    id: i64,
+    group_ids: &[i64],
 ) {
     for g in group_ids {
         let _ = sqlx::query(&format!(
@@ -200,7 +227,7 @@ pub async fn link_groups(
         ))
         .bind(id)
         .bind(*g)
-        .execute(db)
+        .execute(&mut *conn)
         .await;
     }
 }
@@ -702,6 +729,12 @@ pub async fn set_share(
     if !can_write(&st.db, &eff, table, link_table, link_col, id).await {
         return (StatusCode::FORBIDDEN, "Not allowed to change sharing").into_response();
     }
+    // A config-managed channel's groups and visibility come from its document.
+    if kind == "channel" {
+        if let Some(src) = crate::config_as_code::channel_manager(&st.db, id).await {
+            return crate::config_as_code::read_only(&src);
+        }
+    }
     // Non-super callers may only target groups they belong to.
     if !eff.super_admin && !p.group_ids.iter().all(|g| eff.member_of.contains(g)) {
         return (StatusCode::FORBIDDEN, "Cannot share to a group you don't belong to")
@@ -38,18 +38,22 @@ fn eval(cond: &Value, facts: &Map<String, Value>) -> bool {
         }
     }
 
-    // NEW: segmentation_type_id equals (e.g., "0x34")
+    // NEW: segmentation_type_id equals (e.g., "0x34") on any segmentation descriptor
     if let Some(typ) = cond.get("scte35.segmentation_type_id").and_then(|v| v.as_str()) {
-        if let Some(Value::String(actual)) = facts.get("scte35.segmentation_type_id") {
-            return actual.eq_ignore_ascii_case(typ);
-        }
+        return segmentation_values(facts, "segmentation_type_id").any(|actual| actual.eq_ignore_ascii_case(typ));
     }
 
-    // NEW: segmentation_upid glob match (ASCII or "hex:..." form)
+    // NEW: segmentation_upid glob match (ASCII or "hex:..." form) on any segmentation descriptor
     if let Some(pat) = cond.get("scte35.segmentation_upid").and_then(|v| v.as_str()) {
-        if let Some(Value::String(actual)) = facts.get("scte35.segmentation_upid") {
-            return glob_match(pat, actual);
-        }
+        return segmentation_values(facts, "segmentation_upid").any(|actual| glob_match(pat, actual));
+    }
+
+    // lint finding code glob (e.g., "crc_32", "upid_*") on any `scte35.lint` finding
+    if let Some(pat) = cond.get("scte35.lint_code").and_then(|v| v.as_str()) {
+        return facts
+            .get("scte35.lint")
+            .and_then(|v| v.as_array())
+            .is_some_and(|fs| fs.iter().filter_map(|f| f.get("code").and_then(|v| v.as_str())).any(|c| glob_match(pat, c)));
     }
 
     // utcBetween window (lexicographic on ISO-8601 UTC strings)
@@ -65,12 +69,21 @@ fn eval(cond: &Value, facts: &Map<String, Value>) -> bool {
     false
 }
 
+/// One field across every segmentation descriptor in `scte35.segmentations`,
+/// falling back to the single `scte35.<field>` fact when that list is absent.
+fn segmentation_values<'a>(facts: &'a Map<String, Value>, field: &'a str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+    match facts.get("scte35.segmentations").and_then(|v| v.as_array()) {
+        Some(segs) => Box::new(segs.iter().filter_map(move |s| s.get(field).and_then(|v| v.as_str()))),
+        None => Box::new(facts.get(&format!("scte35.{field}")).and_then(|v| v.as_str()).into_iter()),
+    }
+}
+
 /// Glob match supporting any number of `*` wildcards, each matching any
 /// (possibly empty) run of characters. Examples:
 ///   "blk-*"   prefix      "*-end"   suffix
 ///   "*AFE1*"  contains     "a*b*c"   ordered segments
 /// With no `*`, this is an exact-equality test.
-fn glob_match(pat: &str, text: &str) -> bool {
+pub(crate) fn glob_match(pat: &str, text: &str) -> bool {
     if !pat.contains('*') {
         return pat == text;
     }
@@ -143,6 +156,36 @@ mod tests {
         assert!(!rule_matches(&m, &facts("go-9")));
     }
 
+    #[test]
+    fn segmentation_conditions_match_any_descriptor() {
+        let f = json!({
+            "scte35.segmentation_type_id": "0x35",
+            "scte35.segmentations": [
+                { "segmentation_type_id": "0x34", "segmentation_upid": "URI: https://a/1" },
+                { "segmentation_type_id": "0x35", "segmentation_upid": "Ad-ID: ABCD01234567" }
+            ]
+        });
+        let f = f.as_object().unwrap();
+        assert!(rule_matches(&json!({ "anyOf": [{ "scte35.segmentation_type_id": "0x34" }] }), f));
+        assert!(rule_matches(&json!({ "anyOf": [{ "scte35.segmentation_upid": "*https://a/*" }] }), f));
+        assert!(!rule_matches(&json!({ "anyOf": [{ "scte35.segmentation_type_id": "0x30" }] }), f));
+    }
+
+    #[test]
+    fn lint_code_matches_any_finding() {
+        let f = json!({
+            "scte35.lint": [
+                { "severity": "warning", "code": "upid_length" },
+                { "severity": "error", "code": "crc_32" }
+            ]
+        });
+        let f = f.as_object().unwrap();
+        assert!(rule_matches(&json!({ "anyOf": [{ "scte35.lint_code": "crc_32" }] }), f));
+        assert!(rule_matches(&json!({ "anyOf": [{ "scte35.lint_code": "upid_*" }] }), f));
+        assert!(!rule_matches(&json!({ "anyOf": [{ "scte35.lint_code": "segment_num" }] }), f));
+        assert!(!rule_matches(&json!({ "anyOf": [{ "scte35.lint_code": "*" }] }), &facts("x")));
+    }
+
     #[test]
     fn glob_supports_prefix_suffix_contains_and_exact() {
         // exact
@@ -0,0 +1,844 @@
+// src/scte104.rs
+//
+// SCTE 104 multiple_operation_message() codec (ANSI/SCTE 104 2019 §8.2, §9) and
+// conversion to and from the typed SCTE-35 model in `splice_info`.
+//
+// SCTE 104 is the automation -> injector side of the same cue: instead of a PTS
+// it carries a pre_roll_time (ms) counted from the message's timestamp() (VITC
+// timecode, UTC, GPI edge or "on receipt"), and durations in whole seconds or
+// tenths of a second. Conversion is therefore frame-rate aware: the VITC
+// timestamp is the splice point timecode minus the pre-roll, and a
+// segmentation_duration splits into whole seconds plus
+// duration_extension_frames. What SCTE 104 cannot carry (component splices,
+// time/audio/private descriptors, the exact PTS) comes back as a warning.
+
+use crate::splice_info::{
+    has_sub_segments, AvailDescriptor, BreakDuration, DeliveryRestrictions, DtmfDescriptor, SegmentationDescriptor,
+    SpliceCommand, SpliceDescriptor, SpliceInfoSection, SpliceInsert, SpliceTime, CUEI,
+};
+use crate::timecode::{FrameRate, Timecode, TICKS_PER_SECOND};
+
+pub const SPLICE_REQUEST_DATA: u16 = 0x0101;
+pub const SPLICE_NULL_REQUEST_DATA: u16 = 0x0102;
+pub const TIME_SIGNAL_REQUEST_DATA: u16 = 0x0104;
+pub const INSERT_DTMF_DESCRIPTOR_REQUEST_DATA: u16 = 0x0109;
+pub const INSERT_AVAIL_DESCRIPTOR_REQUEST_DATA: u16 = 0x010A;
+pub const INSERT_SEGMENTATION_DESCRIPTOR_REQUEST_DATA: u16 = 0x010B;
+
+/// splice_request_data() splice_insert_type values.
+pub const SPLICE_START_NORMAL: u8 = 1;
+pub const SPLICE_START_IMMEDIATE: u8 = 2;
+pub const SPLICE_END_NORMAL: u8 = 3;
+pub const SPLICE_END_IMMEDIATE: u8 = 4;
+pub const SPLICE_CANCEL: u8 = 5;
+
+// ============================================================================
+// MODEL
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipleOperationMessage {
+    pub protocol_version: u8,
+    pub as_index: u8,
+    pub message_number: u8,
+    pub dpi_pid_index: u16,
+    pub scte35_protocol_version: u8,
+    pub timestamp: Timestamp,
+    pub operations: Vec<Operation>,
+}
+
+/// timestamp(): when the injector should act on the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamp {
+    /// time_type 0: on receipt.
+    Immediate,
+    /// time_type 1.
+    Utc { seconds: u32, microseconds: u16 },
+    /// time_type 2: VITC timecode.
+    Vitc { hours: u8, minutes: u8, seconds: u8, frames: u8 },
+    /// time_type 3.
+    Gpi { number: u8, edge: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    SpliceRequest(SpliceRequest),
+    SpliceNull,
+    /// pre_roll_time in milliseconds.
+    TimeSignal { pre_roll_time: u16 },
+    /// pre_roll in tenths of a second, as in the DTMF_descriptor.
+    InsertDtmf { pre_roll: u8, dtmf: Vec<u8> },
+    InsertAvail { provider_avail_ids: Vec<u32> },
+    InsertSegmentation(SegmentationRequest),
+    /// Any other opID, kept as its raw data().
+    Other { op_id: u16, data: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpliceRequest {
+    pub splice_insert_type: u8,
+    pub splice_event_id: u32,
+    pub unique_program_id: u16,
+    /// Milliseconds from the message timestamp to the splice point.
+    pub pre_roll_time: u16,
+    /// Tenths of a second; 0 for none.
+    pub break_duration: u16,
+    pub avail_num: u8,
+    pub avails_expected: u8,
+    pub auto_return_flag: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SegmentationRequest {
+    pub segmentation_event_id: u32,
+    pub segmentation_event_cancel_indicator: bool,
+    /// Whole seconds; together with no extension frames, 0 means no duration.
+    pub duration: u16,
+    pub segmentation_upid_type: u8,
+    pub segmentation_upid: Vec<u8>,
+    pub segmentation_type_id: u8,
+    pub segment_num: u8,
+    pub segments_expected: u8,
+    pub duration_extension_frames: u8,
+    pub delivery_not_restricted_flag: bool,
+    pub web_delivery_allowed_flag: bool,
+    pub no_regional_blackout_flag: bool,
+    pub archive_allowed_flag: bool,
+    /// 2 bits.
+    pub device_restrictions: u8,
+    /// Older automation omits the three sub-segment bytes; they decode as 0.
+    pub insert_sub_segment_info: bool,
+    pub sub_segment_num: u8,
+    pub sub_segments_expected: u8,
+}
+
+impl MultipleOperationMessage {
+    /// A message acting on receipt, with zero indices.
+    pub fn new(operations: Vec<Operation>) -> Self {
+        Self {
+            protocol_version: 0,
+            as_index: 0,
+            message_number: 0,
+            dpi_pid_index: 0,
+            scte35_protocol_version: 0,
+            timestamp: Timestamp::Immediate,
+            operations,
+        }
+    }
+
+    /// pre_roll_time of the splice_request / time_signal_request operation.
+    pub fn pre_roll_time(&self) -> Option<u16> {
+        self.operations.iter().find_map(|op| match op {
+            Operation::SpliceRequest(r) => Some(r.pre_roll_time),
+            Operation::TimeSignal { pre_roll_time } => Some(*pre_roll_time),
+            _ => None,
+        })
+    }
+
+    /// Timecode of the splice point: a VITC timestamp plus the pre-roll.
+    /// None when the timestamp is not VITC.
+    pub fn splice_timecode(&self, frame_rate: &FrameRate) -> Result<Option<Timecode>, String> {
+        let Timestamp::Vitc { hours, minutes, seconds, frames } = self.timestamp else { return Ok(None) };
+        let at = frame_rate.frame_number(&Timecode { hours, minutes, seconds, frames, drop_frame: frame_rate.drop_frame })?;
+        let pre_roll = frame_rate.ms_to_frames(self.pre_roll_time().unwrap_or(0) as u64);
+        Ok(Some(frame_rate.timecode(at + pre_roll)))
+    }
+}
+
+impl Operation {
+    pub fn op_id(&self) -> u16 {
+        match self {
+            Operation::SpliceRequest(_) => SPLICE_REQUEST_DATA,
+            Operation::SpliceNull => SPLICE_NULL_REQUEST_DATA,
+            Operation::TimeSignal { .. } => TIME_SIGNAL_REQUEST_DATA,
+            Operation::InsertDtmf { .. } => INSERT_DTMF_DESCRIPTOR_REQUEST_DATA,
+            Operation::InsertAvail { .. } => INSERT_AVAIL_DESCRIPTOR_REQUEST_DATA,
+            Operation::InsertSegmentation(_) => INSERT_SEGMENTATION_DESCRIPTOR_REQUEST_DATA,
+            Operation::Other { op_id, .. } => *op_id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        op_name(self.op_id())
+    }
+}
+
+/// Name of a multiple_operation_message opID (SCTE 104 Table 8-4).
+pub fn op_name(op_id: u16) -> &'static str {
+    match op_id {
+        0x0100 => "inject_section_data_request",
+        SPLICE_REQUEST_DATA => "splice_request_data",
+        SPLICE_NULL_REQUEST_DATA => "splice_null_request_data",
+        0x0103 => "start_schedule_download_request_data",
+        TIME_SIGNAL_REQUEST_DATA => "time_signal_request_data",
+        0x0105 => "transmit_schedule_request_data",
+        0x0106 => "component_mode_DPI_request_data",
+        0x0107 => "encrypted_DPI_request_data",
+        0x0108 => "insert_descriptor_request_data",
+        INSERT_DTMF_DESCRIPTOR_REQUEST_DATA => "insert_DTMF_descriptor_request_data",
+        INSERT_AVAIL_DESCRIPTOR_REQUEST_DATA => "insert_avail_descriptor_request_data",
+        INSERT_SEGMENTATION_DESCRIPTOR_REQUEST_DATA => "insert_segmentation_descriptor_request_data",
+        0x010C => "proprietary_command_request_data",
+        0x010D => "schedule_component_mode_request_data",
+        0x010E => "schedule_definition_data_request",
+        0x010F => "insert_tier_data",
+        0x0110 => "insert_time_descriptor",
+        _ => "unknown",
+    }
+}
+
+pub fn splice_insert_type_name(t: u8) -> &'static str {
+    match t {
+        SPLICE_START_NORMAL => "spliceStart_normal",
+        SPLICE_START_IMMEDIATE => "spliceStart_immediate",
+        SPLICE_END_NORMAL => "spliceEnd_normal",
+        SPLICE_END_IMMEDIATE => "spliceEnd_immediate",
+        SPLICE_CANCEL => "splice_cancel",
+        _ => "reserved",
+    }
+}
+
+// ============================================================================
+// DECODE / ENCODE
+// ============================================================================
+
+impl MultipleOperationMessage {
+    /// Bytes past messageSize are ignored.
+    pub fn decode(input: &[u8]) -> Result<Self, String> {
+        let mut r = Reader::new(input);
+        let reserved = r.u16("reserved")?;
+        if reserved != 0xFFFF {
+            return Err(format!(
+                "not a multiple_operation_message (starts 0x{reserved:04X}, expected 0xFFFF; single_operation_message?)"
+            ));
+        }
+        let size = r.u16("messageSize")? as usize;
+        if size > input.len() {
+            return Err(format!("messageSize {size} but only {} bytes", input.len()));
+        }
+        let mut r = Reader { data: &input[..size], pos: 4 };
+        let protocol_version = r.u8("protocol_version")?;
+        let as_index = r.u8("AS_index")?;
+        let message_number = r.u8("message_number")?;
+        let dpi_pid_index = r.u16("DPI_PID_index")?;
+        let scte35_protocol_version = r.u8("SCTE35_protocol_version")?;
+        let timestamp = match r.u8("time_type")? {
+            0 => Timestamp::Immediate,
+            1 => Timestamp::Utc { seconds: r.u32("UTC_seconds")?, microseconds: r.u16("UTC_microseconds")? },
+            2 => Timestamp::Vitc {
+                hours: r.u8("hours")?,
+                minutes: r.u8("minutes")?,
+                seconds: r.u8("seconds")?,
+                frames: r.u8("frames")?,
+            },
+            3 => Timestamp::Gpi { number: r.u8("GPI_number")?, edge: r.u8("GPI_edge")? },
+            t => return Err(format!("time_type {t} is reserved")),
+        };
+        let num_ops = r.u8("num_ops")?;
+        let mut operations = Vec::with_capacity(num_ops as usize);
+        for i in 0..num_ops {
+            let op_id = r.u16("opID")?;
+            let len = r.u16("data_length")? as usize;
+            let data = r.bytes(len, "data")?;
+            let op = decode_op(op_id, data).map_err(|e| format!("operation {i} ({}): {e}", op_name(op_id)))?;
+            operations.push(op);
+        }
+        Ok(Self { protocol_version, as_index, message_number, dpi_pid_index, scte35_protocol_version, timestamp, operations })
+    }
+
+    /// messageSize and every data_length are computed.
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        if self.operations.len() > 255 {
+            return Err(format!("{} operations exceed 255", self.operations.len()));
+        }
+        let mut out = vec![0xFF, 0xFF, 0, 0, self.protocol_version, self.as_index, self.message_number];
+        out.extend(self.dpi_pid_index.to_be_bytes());
+        out.push(self.scte35_protocol_version);
+        match self.timestamp {
+            Timestamp::Immediate => out.push(0),
+            Timestamp::Utc { seconds, microseconds } => {
+                out.push(1);
+                out.extend(seconds.to_be_bytes());
+                out.extend(microseconds.to_be_bytes());
+            }
+            Timestamp::Vitc { hours, minutes, seconds, frames } => out.extend([2, hours, minutes, seconds, frames]),
+            Timestamp::Gpi { number, edge } => out.extend([3, number, edge]),
+        }
+        out.push(self.operations.len() as u8);
+        for op in &self.operations {
+            let data = encode_op(op)?;
+            let len = u16::try_from(data.len()).map_err(|_| format!("{} data of {} bytes", op.name(), data.len()))?;
+            out.extend(op.op_id().to_be_bytes());
+            out.extend(len.to_be_bytes());
+            out.extend(data);
+        }
+        let size = u16::try_from(out.len()).map_err(|_| format!("message of {} bytes exceeds 65535", out.len()))?;
+        out[2..4].copy_from_slice(&size.to_be_bytes());
+        Ok(out)
+    }
+}
+
+fn decode_op(op_id: u16, data: &[u8]) -> Result<Operation, String> {
+    let mut r = Reader::new(data);
+    Ok(match op_id {
+        SPLICE_REQUEST_DATA => Operation::SpliceRequest(SpliceRequest {
+            splice_insert_type: r.u8("splice_insert_type")?,
+            splice_event_id: r.u32("splice_event_id")?,
+            unique_program_id: r.u16("unique_program_id")?,
+            pre_roll_time: r.u16("pre_roll_time")?,
+            break_duration: r.u16("break_duration")?,
+            avail_num: r.u8("avail_num")?,
+            avails_expected: r.u8("avails_expected")?,
+            auto_return_flag: r.u8("auto_return_flag")? != 0,
+        }),
+        SPLICE_NULL_REQUEST_DATA => Operation::SpliceNull,
+        TIME_SIGNAL_REQUEST_DATA => Operation::TimeSignal { pre_roll_time: r.u16("pre_roll_time")? },
+        INSERT_DTMF_DESCRIPTOR_REQUEST_DATA => {
+            let pre_roll = r.u8("pre_roll")?;
+            let n = r.u8("dtmf_length")? as usize;
+            Operation::InsertDtmf { pre_roll, dtmf: r.bytes(n, "DTMF_char")?.to_vec() }
+        }
+        INSERT_AVAIL_DESCRIPTOR_REQUEST_DATA => {
+            let n = r.u8("num_provider_avails")?;
+            let provider_avail_ids = (0..n).map(|_| r.u32("provider_avail_id")).collect::<Result<_, _>>()?;
+            Operation::InsertAvail { provider_avail_ids }
+        }
+        INSERT_SEGMENTATION_DESCRIPTOR_REQUEST_DATA => {
+            let segmentation_event_id = r.u32("segmentation_event_id")?;
+            let segmentation_event_cancel_indicator = r.u8("segmentation_event_cancel_indicator")? != 0;
+            let duration = r.u16("duration")?;
+            let segmentation_upid_type = r.u8("segmentation_upid_type")?;
+            let upid_len = r.u8("segmentation_upid_length")? as usize;
+            let segmentation_upid = r.bytes(upid_len, "segmentation_upid")?.to_vec();
+            let mut s = SegmentationRequest {
+                segmentation_event_id,
+                segmentation_event_cancel_indicator,
+                duration,
+                segmentation_upid_type,
+                segmentation_upid,
+                segmentation_type_id: r.u8("segmentation_type_id")?,
+                segment_num: r.u8("segment_num")?,
+                segments_expected: r.u8("segments_expected")?,
+                duration_extension_frames: r.u8("duration_extension_frames")?,
+                delivery_not_restricted_flag: r.u8("delivery_not_restricted_flag")? != 0,
+                web_delivery_allowed_flag: r.u8("web_delivery_allowed_flag")? != 0,
+                no_regional_blackout_flag: r.u8("no_regional_blackout_flag")? != 0,
+                archive_allowed_flag: r.u8("archive_allowed_flag")? != 0,
+                device_restrictions: r.u8("device_restrictions")?,
+                ..Default::default()
+            };
+            if r.remaining() >= 3 {
+                s.insert_sub_segment_info = r.u8("insert_sub_segment_info")? != 0;
+                s.sub_segment_num = r.u8("sub_segment_num")?;
+                s.sub_segments_expected = r.u8("sub_segments_expected")?;
+            }
+            Operation::InsertSegmentation(s)
+        }
+        _ => Operation::Other { op_id, data: data.to_vec() },
+    })
+}
+
+fn encode_op(op: &Operation) -> Result<Vec<u8>, String> {
+    let mut d = Vec::new();
+    match op {
+        Operation::SpliceRequest(s) => {
+            d.push(s.splice_insert_type);
+            d.extend(s.splice_event_id.to_be_bytes());
+            d.extend(s.unique_program_id.to_be_bytes());
+            d.extend(s.pre_roll_time.to_be_bytes());
+            d.extend(s.break_duration.to_be_bytes());
+            d.extend([s.avail_num, s.avails_expected, s.auto_return_flag as u8]);
+        }
+        Operation::SpliceNull => {}
+        Operation::TimeSignal { pre_roll_time } => d.extend(pre_roll_time.to_be_bytes()),
+        Operation::InsertDtmf { pre_roll, dtmf } => {
+            let n = u8::try_from(dtmf.len()).map_err(|_| format!("{} DTMF characters exceed 255", dtmf.len()))?;
+            d.extend([*pre_roll, n]);
+            d.extend(dtmf);
+        }
+        Operation::InsertAvail { provider_avail_ids } => {
+            let n = u8::try_from(provider_avail_ids.len())
+                .map_err(|_| format!("{} provider avails exceed 255", provider_avail_ids.len()))?;
+            d.push(n);
+            for id in provider_avail_ids {
+                d.extend(id.to_be_bytes());
+            }
+        }
+        Operation::InsertSegmentation(s) => {
+            let n = u8::try_from(s.segmentation_upid.len())
+                .map_err(|_| format!("segmentation_upid of {} bytes exceeds 255", s.segmentation_upid.len()))?;
+            d.extend(s.segmentation_event_id.to_be_bytes());
+            d.push(s.segmentation_event_cancel_indicator as u8);
+            d.extend(s.duration.to_be_bytes());
+            d.extend([s.segmentation_upid_type, n]);
+            d.extend(&s.segmentation_upid);
+            d.extend([
+                s.segmentation_type_id,
+                s.segment_num,
+                s.segments_expected,
+                s.duration_extension_frames,
+                s.delivery_not_restricted_flag as u8,
+                s.web_delivery_allowed_flag as u8,
+                s.no_regional_blackout_flag as u8,
+                s.archive_allowed_flag as u8,
+                s.device_restrictions,
+                s.insert_sub_segment_info as u8,
+                s.sub_segment_num,
+                s.sub_segments_expected,
+            ]);
+        }
+        Operation::Other { data, .. } => d.extend(data),
+    }
+    Ok(d)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, n: usize, field: &str) -> Result<&'a [u8], String> {
+        let end = self.pos + n;
+        let b = self.data.get(self.pos..end).ok_or_else(|| format!("truncated at byte {} reading {field}", self.pos))?;
+        self.pos = end;
+        Ok(b)
+    }
+
+    fn u8(&mut self, field: &str) -> Result<u8, String> {
+        Ok(self.bytes(1, field)?[0])
+    }
+
+    fn u16(&mut self, field: &str) -> Result<u16, String> {
+        let b = self.bytes(2, field)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self, field: &str) -> Result<u32, String> {
+        let b = self.bytes(4, field)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+}
+
+// ============================================================================
+// SCTE-35 <-> SCTE-104
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvertOptions {
+    /// Counts pre-roll and timecode frames and duration_extension_frames.
+    pub frame_rate: FrameRate,
+    /// pre_roll_time written on splice_request / time_signal_request (ms).
+    pub pre_roll_ms: u16,
+    /// SCTE-35 -> 104: timecode of the splice point. The message gets a VITC
+    /// timestamp `pre_roll_ms` earlier; without one it acts on receipt.
+    pub splice_timecode: Option<Timecode>,
+    pub as_index: u8,
+    pub message_number: u8,
+    pub dpi_pid_index: u16,
+    /// SCTE-104 -> 35: PTS at the message timestamp. The splice_time becomes
+    /// this plus the pre-roll; without one it is left unspecified.
+    pub timestamp_pts: Option<u64>,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            frame_rate: FrameRate::NTSC,
+            pre_roll_ms: 0,
+            splice_timecode: None,
+            as_index: 0,
+            message_number: 0,
+            dpi_pid_index: 0,
+            timestamp_pts: None,
+        }
+    }
+}
+
+/// SCTE-35 section -> multiple_operation_message, plus what could not be carried.
+pub fn from_scte35(
+    section: &SpliceInfoSection,
+    opts: &ConvertOptions,
+) -> Result<(MultipleOperationMessage, Vec<String>), String> {
+    if section.encrypted_packet {
+        return Err("an encrypted section cannot be converted".into());
+    }
+    let mut warnings = Vec::new();
+    let mut operations = Vec::new();
+    match &section.splice_command {
+        SpliceCommand::SpliceNull => operations.push(Operation::SpliceNull),
+        SpliceCommand::TimeSignal(_) => operations.push(Operation::TimeSignal { pre_roll_time: opts.pre_roll_ms }),
+        SpliceCommand::SpliceInsert(si) => {
+            operations.push(Operation::SpliceRequest(splice_request(si, opts.pre_roll_ms, &mut warnings)))
+        }
+        other => return Err(format!("{} has no SCTE-104 equivalent", other.name())),
+    }
+    if section.splice_command.pts_time().is_some() {
+        warnings.push("pts_time is not carried: the splice point is the timestamp plus pre_roll_time".into());
+    }
+
+    for d in &section.descriptors {
+        match d {
+            SpliceDescriptor::Avail(a) => match operations.last_mut() {
+                Some(Operation::InsertAvail { provider_avail_ids }) => provider_avail_ids.push(a.provider_avail_id),
+                _ => operations.push(Operation::InsertAvail { provider_avail_ids: vec![a.provider_avail_id] }),
+            },
+            SpliceDescriptor::Dtmf(dt) => {
+                operations.push(Operation::InsertDtmf { pre_roll: dt.preroll, dtmf: dt.dtmf_chars.clone() })
+            }
+            SpliceDescriptor::Segmentation(s) => {
+                operations.push(Operation::InsertSegmentation(segmentation_request(s, &opts.frame_rate, &mut warnings)))
+            }
+            other => warnings.push(format!("{} is not converted (no SCTE-104 operation)", other.name())),
+        }
+    }
+
+    let timestamp = match &opts.splice_timecode {
+        Some(tc) => {
+            let fr = &opts.frame_rate;
+            let day = fr.frames_per_day();
+            let pre_roll = fr.ms_to_frames(opts.pre_roll_ms as u64) % day;
+            let t = fr.timecode(fr.frame_number(tc)? + day - pre_roll);
+            Timestamp::Vitc { hours: t.hours, minutes: t.minutes, seconds: t.seconds, frames: t.frames }
+        }
+        None => Timestamp::Immediate,
+    };
+    let msg = MultipleOperationMessage {
+        as_index: opts.as_index,
+        message_number: opts.message_number,
+        dpi_pid_index: opts.dpi_pid_index,
+        scte35_protocol_version: section.protocol_version,
+        timestamp,
+        ..MultipleOperationMessage::new(operations)
+    };
+    Ok((msg, warnings))
+}
+
+fn splice_request(si: &SpliceInsert, pre_roll_ms: u16, warnings: &mut Vec<String>) -> SpliceRequest {
+    if si.splice_event_cancel_indicator {
+        return SpliceRequest { splice_insert_type: SPLICE_CANCEL, splice_event_id: si.splice_event_id, ..Default::default() };
+    }
+    if !si.program_splice_flag {
+        warnings.push(format!("component splice ({} components) sent as a program splice", si.components.len()));
+    }
+    let splice_insert_type = match (si.out_of_network_indicator, si.splice_immediate_flag) {
+        (true, false) => SPLICE_START_NORMAL,
+        (true, true) => SPLICE_START_IMMEDIATE,
+        (false, false) => SPLICE_END_NORMAL,
+        (false, true) => SPLICE_END_IMMEDIATE,
+    };
+    let (break_duration, auto_return_flag) = match si.break_duration {
+        Some(b) => (tenths(b.duration, warnings), b.auto_return),
+        None => (0, false),
+    };
+    SpliceRequest {
+        splice_insert_type,
+        splice_event_id: si.splice_event_id,
+        unique_program_id: si.unique_program_id,
+        pre_roll_time: if si.splice_immediate_flag { 0 } else { pre_roll_ms },
+        break_duration,
+        avail_num: si.avail_num,
+        avails_expected: si.avails_expected,
+        auto_return_flag,
+    }
+}
+
+/// 90 kHz break_duration as SCTE-104 tenths of a second.
+fn tenths(ticks: u64, warnings: &mut Vec<String>) -> u16 {
+    let t = (ticks + 4_500) / 9_000;
+    if t > u16::MAX as u64 {
+        warnings.push(format!("break_duration {ticks} ticks clamped to {} s", u16::MAX / 10));
+        return u16::MAX;
+    }
+    if t * 9_000 != ticks {
+        warnings.push(format!("break_duration {ticks} ticks rounded to {:.1} s", t as f64 / 10.0));
+    }
+    t as u16
+}
+
+fn segmentation_request(s: &SegmentationDescriptor, fr: &FrameRate, warnings: &mut Vec<String>) -> SegmentationRequest {
+    let mut req = SegmentationRequest {
+        segmentation_event_id: s.segmentation_event_id,
+        segmentation_event_cancel_indicator: s.segmentation_event_cancel_indicator,
+        ..Default::default()
+    };
+    if s.segmentation_event_cancel_indicator {
+        return req;
+    }
+    if !s.program_segmentation_flag {
+        warnings.push(format!(
+            "segmentation event {}: component segmentation sent as program segmentation",
+            s.segmentation_event_id
+        ));
+    }
+    if let Some(ticks) = s.segmentation_duration {
+        let secs = ticks / TICKS_PER_SECOND;
+        let rem = ticks % TICKS_PER_SECOND;
+        let frames = fr.ticks_to_frames(rem);
+        if secs > u16::MAX as u64 {
+            warnings.push(format!("segmentation_duration {ticks} ticks clamped to {} s", u16::MAX));
+            req.duration = u16::MAX;
+        } else {
+            req.duration = secs as u16;
+            req.duration_extension_frames = frames as u8;
+            if fr.frames_to_ticks(frames) != rem {
+                warnings.push(format!(
+                    "segmentation_duration {ticks} ticks rounded to {secs} s + {frames} frames at {fr}"
+                ));
+            }
+        }
+    }
+    let r = &s.delivery_restrictions;
+    SegmentationRequest {
+        segmentation_upid_type: s.segmentation_upid_type,
+        segmentation_upid: s.segmentation_upid.clone(),
+        segmentation_type_id: s.segmentation_type_id,
+        segment_num: s.segment_num,
+        segments_expected: s.segments_expected,
+        delivery_not_restricted_flag: s.delivery_not_restricted_flag,
+        web_delivery_allowed_flag: r.web_delivery_allowed_flag,
+        no_regional_blackout_flag: r.no_regional_blackout_flag,
+        archive_allowed_flag: r.archive_allowed_flag,
+        device_restrictions: r.device_restrictions,
+        insert_sub_segment_info: s.sub_segment_num.is_some(),
+        sub_segment_num: s.sub_segment_num.unwrap_or(0),
+        sub_segments_expected: s.sub_segments_expected.unwrap_or(0),
+        ..req
+    }
+}
+
+/// multiple_operation_message -> SCTE-35 section, plus what could not be carried.
+/// The first splice_request / splice_null_request / time_signal_request is the
+/// splice command; the descriptor operations become its descriptors.
+pub fn to_scte35(
+    msg: &MultipleOperationMessage,
+    opts: &ConvertOptions,
+) -> Result<(SpliceInfoSection, Vec<String>), String> {
+    let mut warnings = Vec::new();
+    let mut command = None;
+    let mut descriptors = Vec::new();
+    for op in &msg.operations {
+        let cmd = match op {
+            Operation::SpliceRequest(r) => Some(splice_insert(r, opts)?),
+            Operation::SpliceNull => Some(SpliceCommand::SpliceNull),
+            Operation::TimeSignal { pre_roll_time } => Some(SpliceCommand::TimeSignal(splice_time(opts, *pre_roll_time))),
+            Operation::InsertDtmf { pre_roll, dtmf } => {
+                descriptors.push(SpliceDescriptor::Dtmf(DtmfDescriptor {
+                    identifier: CUEI,
+                    preroll: *pre_roll,
+                    reserved: 0x1F,
+                    dtmf_chars: dtmf.clone(),
+                    extra: Vec::new(),
+                }));
+                None
+            }
+            Operation::InsertAvail { provider_avail_ids } => {
+                descriptors.extend(provider_avail_ids.iter().map(|&provider_avail_id| {
+                    SpliceDescriptor::Avail(AvailDescriptor { identifier: CUEI, provider_avail_id, extra: Vec::new() })
+                }));
+                None
+            }
+            Operation::InsertSegmentation(s) => {
+                descriptors.push(SpliceDescriptor::Segmentation(segmentation_descriptor(s, &opts.frame_rate)));
+                None
+            }
+            Operation::Other { op_id, .. } => {
+                warnings.push(format!("{} (0x{op_id:04X}) is not converted", op_name(*op_id)));
+                None
+            }
+        };
+        if let Some(cmd) = cmd {
+            if command.is_some() {
+                warnings.push(format!("{} ignored: the message already has a splice command", op.name()));
+            } else {
+                command = Some(cmd);
+            }
+        }
+    }
+    let command = command.ok_or_else(|| {
+        "no splice_request_data, splice_null_request_data or time_signal_request_data operation".to_string()
+    })?;
+    let mut section = SpliceInfoSection::new(command);
+    section.protocol_version = msg.scte35_protocol_version;
+    section.descriptors = descriptors;
+    Ok((section, warnings))
+}
+
+fn splice_time(opts: &ConvertOptions, pre_roll_ms: u16) -> SpliceTime {
+    match opts.timestamp_pts {
+        Some(pts) => SpliceTime::at((pts + pre_roll_ms as u64 * 90) & 0x1_FFFF_FFFF),
+        None => SpliceTime::immediate(),
+    }
+}
+
+fn splice_insert(r: &SpliceRequest, opts: &ConvertOptions) -> Result<SpliceCommand, String> {
+    let (out, immediate) = match r.splice_insert_type {
+        SPLICE_START_NORMAL => (true, false),
+        SPLICE_START_IMMEDIATE => (true, true),
+        SPLICE_END_NORMAL => (false, false),
+        SPLICE_END_IMMEDIATE => (false, true),
+        SPLICE_CANCEL => {
+            return Ok(SpliceCommand::SpliceInsert(SpliceInsert {
+                splice_event_id: r.splice_event_id,
+                splice_event_cancel_indicator: true,
+                ..Default::default()
+            }))
+        }
+        t => return Err(format!("splice_insert_type {t} is reserved")),
+    };
+    Ok(SpliceCommand::SpliceInsert(SpliceInsert {
+        splice_event_id: r.splice_event_id,
+        out_of_network_indicator: out,
+        program_splice_flag: true,
+        splice_immediate_flag: immediate,
+        splice_time: (!immediate).then(|| splice_time(opts, r.pre_roll_time)),
+        break_duration: (r.break_duration > 0)
+            .then(|| BreakDuration::new(r.auto_return_flag, r.break_duration as u64 * 9_000)),
+        unique_program_id: r.unique_program_id,
+        avail_num: r.avail_num,
+        avails_expected: r.avails_expected,
+        ..Default::default()
+    }))
+}
+
+fn segmentation_descriptor(s: &SegmentationRequest, fr: &FrameRate) -> SegmentationDescriptor {
+    if s.segmentation_event_cancel_indicator {
+        return SegmentationDescriptor {
+            segmentation_event_id: s.segmentation_event_id,
+            segmentation_event_cancel_indicator: true,
+            ..Default::default()
+        };
+    }
+    let has_duration = s.duration > 0 || s.duration_extension_frames > 0;
+    let sub_segments = s.insert_sub_segment_info || has_sub_segments(s.segmentation_type_id);
+    SegmentationDescriptor {
+        segmentation_event_id: s.segmentation_event_id,
+        delivery_not_restricted_flag: s.delivery_not_restricted_flag,
+        delivery_restrictions: if s.delivery_not_restricted_flag {
+            DeliveryRestrictions::default()
+        } else {
+            DeliveryRestrictions {
+                web_delivery_allowed_flag: s.web_delivery_allowed_flag,
+                no_regional_blackout_flag: s.no_regional_blackout_flag,
+                archive_allowed_flag: s.archive_allowed_flag,
+                device_restrictions: s.device_restrictions & 0x03,
+            }
+        },
+        segmentation_duration: has_duration
+            .then(|| s.duration as u64 * TICKS_PER_SECOND + fr.frames_to_ticks(s.duration_extension_frames as u64)),
+        segmentation_upid_type: s.segmentation_upid_type,
+        segmentation_upid: s.segmentation_upid.clone(),
+        segmentation_type_id: s.segmentation_type_id,
+        segment_num: s.segment_num,
+        segments_expected: s.segments_expected,
+        sub_segment_num: sub_segments.then_some(s.sub_segment_num),
+        sub_segments_expected: sub_segments.then_some(s.sub_segments_expected),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement_opportunity() -> SpliceInfoSection {
+        let mut s = SpliceInfoSection::new(SpliceCommand::SpliceInsert(SpliceInsert {
+            splice_event_id: 42,
+            out_of_network_indicator: true,
+            program_splice_flag: true,
+            splice_time: Some(SpliceTime::at(900_000)),
+            break_duration: Some(BreakDuration::new(true, 30 * 90_000)),
+            unique_program_id: 7,
+            avail_num: 1,
+            avails_expected: 2,
+            ..Default::default()
+        }));
+        s.descriptors = vec![
+            SpliceDescriptor::Segmentation(SegmentationDescriptor {
+                segmentation_event_id: 9,
+                // 30 s + 15 frames at 29.97
+                segmentation_duration: Some(30 * 90_000 + 15 * 3003),
+                segmentation_upid_type: 0x03,
+                segmentation_upid: b"ABCD01234567".to_vec(),
+                segmentation_type_id: 0x34,
+                segment_num: 1,
+                segments_expected: 1,
+                sub_segment_num: Some(0),
+                sub_segments_expected: Some(0),
+                ..Default::default()
+            }),
+            SpliceDescriptor::Dtmf(DtmfDescriptor {
+                identifier: CUEI,
+                preroll: 50,
+                reserved: 0x1F,
+                dtmf_chars: b"123*".to_vec(),
+                extra: Vec::new(),
+            }),
+        ];
+        s
+    }
+
+    #[test]
+    fn scte35_to_scte104_and_back() {
+        let section = placement_opportunity();
+        let opts = ConvertOptions {
+            pre_roll_ms: 4_000,
+            splice_timecode: Some(Timecode::parse("01:00:00:00").unwrap()),
+            ..Default::default()
+        };
+        let (msg, warnings) = from_scte35(&section, &opts).unwrap();
+        assert_eq!(warnings.len(), 1, "{warnings:?}"); // pts_time only
+        assert_eq!(msg.timestamp, Timestamp::Vitc { hours: 0, minutes: 59, seconds: 56, frames: 0 });
+        let Operation::SpliceRequest(r) = msg.operations[0] else { panic!("{:?}", msg.operations[0]) };
+        assert_eq!((r.splice_insert_type, r.pre_roll_time, r.break_duration), (SPLICE_START_NORMAL, 4_000, 300));
+        let Operation::InsertSegmentation(s) = &msg.operations[1] else { panic!() };
+        assert_eq!((s.duration, s.duration_extension_frames), (30, 15));
+        assert!(matches!(msg.operations[2], Operation::InsertDtmf { pre_roll: 50, .. }));
+
+        let bytes = msg.encode().unwrap();
+        assert_eq!(&bytes[..2], &[0xFF, 0xFF]);
+        assert_eq!(u16::from_be_bytes([bytes[2], bytes[3]]) as usize, bytes.len());
+        let back = MultipleOperationMessage::decode(&bytes).unwrap();
+        assert_eq!(back, msg);
+        assert_eq!(back.splice_timecode(&opts.frame_rate).unwrap().unwrap().to_string(), "01:00:00:00");
+
+        let (sec35, warnings) =
+            to_scte35(&back, &ConvertOptions { timestamp_pts: Some(540_000), ..Default::default() }).unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(sec35.splice_command.pts_time(), Some(900_000), "timestamp PTS + 4 s pre-roll");
+        assert_eq!(sec35.descriptors, section.descriptors);
+        let SpliceCommand::SpliceInsert(si) = &sec35.splice_command else { panic!() };
+        assert_eq!(si.break_duration.unwrap().duration, 30 * 90_000);
+        assert_eq!((si.splice_event_id, si.unique_program_id, si.avails_expected), (42, 7, 2));
+    }
+
+    #[test]
+    fn decodes_wire_bytes_and_reports_what_cannot_convert() {
+        // time_signal_request (pre-roll 2000 ms) + an insert_tier_data operation.
+        let bytes = [
+            0xFF, 0xFF, 0x00, 0x17, 0x00, 0x01, 0x02, 0x00, 0x03, 0x00, 0x00, 0x02, //
+            0x01, 0x04, 0x00, 0x02, 0x07, 0xD0, //
+            0x01, 0x0F, 0x00, 0x01, 0x05,
+        ];
+        let msg = MultipleOperationMessage::decode(&bytes).unwrap();
+        assert_eq!((msg.as_index, msg.message_number, msg.dpi_pid_index), (1, 2, 3));
+        assert_eq!(msg.operations[0], Operation::TimeSignal { pre_roll_time: 2_000 });
+        assert_eq!(msg.encode().unwrap(), bytes);
+        let (section, warnings) = to_scte35(&msg, &ConvertOptions::default()).unwrap();
+        assert_eq!(section.splice_command, SpliceCommand::TimeSignal(SpliceTime::immediate()));
+        assert_eq!(warnings, vec!["insert_tier_data (0x010F) is not converted"]);
+
+        assert!(MultipleOperationMessage::decode(&bytes[..10]).unwrap_err().contains("messageSize"));
+        assert!(MultipleOperationMessage::decode(&[0x00, 0x01, 0x00, 0x04]).unwrap_err().contains("single_operation"));
+        let null_only = MultipleOperationMessage::new(vec![Operation::InsertAvail { provider_avail_ids: vec![1] }]);
+        assert!(to_scte35(&null_only, &ConvertOptions::default()).is_err());
+    }
+}
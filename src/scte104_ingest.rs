@@ -0,0 +1,575 @@
+// src/scte104_ingest.rs
+//! SCTE-104 automation ingest over TCP.
+//!
+//! Some automation still speaks SCTE 104 to an injector rather than ESAM to a
+//! POIS. `POIS_SCTE104_LISTEN` opens one TCP listener per channel, as comma
+//! separated `ADDR=CHANNEL[>TARGET]` entries, e.g.
+//! `0.0.0.0:5167=east,0.0.0.0:5168=west>10.0.0.9:5167`.
+//!
+//! Each session is answered the way an injector would: init_request gets an
+//! init_response, alive_request an alive_response and every
+//! multiple_operation_message an inject_response (result 100, or 108 when the
+//! message is rejected: undecodable, unconvertible or for an unknown channel).
+//! A multiple_operation_message is converted to SCTE-35 (`scte104::to_scte35`,
+//! frames counted at `POIS_SCTE104_FRAME_RATE`) and decided like an ESAM
+//! request: the same facts, the channel's optional lint, its first matching
+//! rule and that rule's action. The conditioned cue goes back to SCTE-104 with
+//! the inbound indices, timestamp and pre-roll; a pass-through keeps the
+//! original bytes and a `delete` drops the message. With a `>HOST:PORT` target
+//! the result is forwarded to that injector (one upstream session per
+//! automation session, opened with its own init_request) from a task of its
+//! own, so a slow or unreachable injector never holds up the automation's
+//! session; messages queue up to `FORWARD_QUEUE` deep and are dropped beyond
+//! that. With `>reply` the result is sent back on the automation's session
+//! after the inject_response. Without a target messages are only decided and
+//! logged.
+//!
+//! Events are written to `esam_events` like ESAM ones, from a rebuilt
+//! SignalProcessingEvent (so replay works on them). They are marked the way
+//! Quick Test marks its events: `source_ip` is `SCTE104:<peer ip>` and the user
+//! agent names the AS_index. SESAME does not apply to this path.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use pois_esam_server::scte104::{self, ConvertOptions, MultipleOperationMessage, Timestamp};
+use pois_esam_server::splice_info::{self, SpliceInfoSection};
+use pois_esam_server::timecode::FrameRate;
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use crate::esam::{esam_verb, extract_facts, xml_escape};
+use crate::event_logging::{ClientInfo, ProcessingMetrics};
+use crate::{decide, lint_facts, AppState};
+
+/// single_operation_message() opIDs (SCTE 104 Table 8-1).
+const INIT_REQUEST: u16 = 0x0001;
+const INIT_RESPONSE: u16 = 0x0002;
+const ALIVE_REQUEST: u16 = 0x0003;
+const ALIVE_RESPONSE: u16 = 0x0004;
+const INJECT_RESPONSE: u16 = 0x0007;
+
+/// Result codes (SCTE 104 Table 14-1).
+const RESULT_SUCCESS: u16 = 100;
+const RESULT_INVALID_MESSAGE: u16 = 108;
+
+/// opID, messageSize, result, result_extension, protocol_version, AS_index,
+/// message_number, DPI_PID_index.
+const SINGLE_HEADER_LEN: usize = 13;
+/// How long a forward target has to answer before its session is dropped.
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+/// Messages waiting for the forward target, per automation session.
+const FORWARD_QUEUE: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Decide and log only.
+    None,
+    /// Send the conditioned message back on the automation's session.
+    Reply,
+    /// Forward the conditioned message to the injector at this address.
+    Forward(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listener {
+    pub addr: String,
+    pub channel: String,
+    pub target: Target,
+}
+
+pub struct Scte104Ingest {
+    pub listeners: Vec<Listener>,
+    pub frame_rate: FrameRate,
+}
+
+impl Scte104Ingest {
+    /// `None` unless `POIS_SCTE104_LISTEN` is set; an invalid setting is an error.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(spec) = std::env::var("POIS_SCTE104_LISTEN").ok().filter(|s| !s.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let frame_rate = match std::env::var("POIS_SCTE104_FRAME_RATE") {
+            Ok(v) if !v.trim().is_empty() => FrameRate::parse(&v).map_err(|e| format!("POIS_SCTE104_FRAME_RATE: {e}"))?,
+            _ => FrameRate::NTSC,
+        };
+        Ok(Some(Self { listeners: parse_listeners(&spec)?, frame_rate }))
+    }
+
+    pub fn spawn(self: Arc<Self>, st: Arc<AppState>) {
+        for listener in self.listeners.iter().cloned().map(Arc::new) {
+            let this = self.clone();
+            let st = st.clone();
+            tokio::spawn(async move {
+                let socket = match TcpListener::bind(&listener.addr).await {
+                    Ok(s) => s,
+                    Err(e) => {
+                        error!("scte104: cannot listen on {} for channel '{}': {}", listener.addr, listener.channel, e);
+                        return;
+                    }
+                };
+                loop {
+                    let (sock, peer) = match socket.accept().await {
+                        Ok(v) => v,
+                        Err(e) => {
+                            warn!("scte104: accept on {} failed: {}", listener.addr, e);
+                            tokio::time::sleep(Duration::from_secs(1)).await;
+                            continue;
+                        }
+                    };
+                    info!("scte104: session from {} on channel '{}'", peer, listener.channel);
+                    let (this, st, listener) = (this.clone(), st.clone(), listener.clone());
+                    tokio::spawn(async move {
+                        match this.session(&st, &listener, sock, peer).await {
+                            Ok(()) => info!("scte104: session from {} closed", peer),
+                            Err(e) => warn!("scte104: session from {} ended: {}", peer, e),
+                        }
+                    });
+                }
+            });
+        }
+    }
+
+    async fn session(&self, st: &AppState, listener: &Listener, mut sock: TcpStream, peer: SocketAddr) -> Result<(), String> {
+        let forwarder = match &listener.target {
+            Target::Forward(addr) => Some(spawn_forwarder(addr.clone())),
+            _ => None,
+        };
+        while let Some(frame) = read_message(&mut sock).await? {
+            if frame.starts_with(&[0xFF, 0xFF]) {
+                let (_, message_number, _) = indices(&frame);
+                let (result, out) = match self.inject(st, listener, peer, &frame).await {
+                    Ok(out) => (RESULT_SUCCESS, out),
+                    Err(e) => {
+                        warn!("scte104: message {} from {} rejected: {}", message_number, peer, e);
+                        (RESULT_INVALID_MESSAGE, None)
+                    }
+                };
+                let mut response = SingleOperation::response(INJECT_RESPONSE, result, &frame);
+                response.data = vec![message_number];
+                write(&mut sock, &response.encode()).await?;
+
+                let Some(out) = out else { continue };
+                match &listener.target {
+                    Target::None => {}
+                    Target::Reply => write(&mut sock, &out).await?,
+                    Target::Forward(addr) => {
+                        let sent = forwarder.as_ref().map(|tx| tx.try_send(out));
+                        if let Some(Err(e)) = sent {
+                            warn!("scte104: message {} not forwarded to {}: {}", message_number, addr, e);
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let request = SingleOperation::decode(&frame)?;
+            let response = match request.op_id {
+                INIT_REQUEST => SingleOperation::response(INIT_RESPONSE, RESULT_SUCCESS, &frame),
+                ALIVE_REQUEST => {
+                    let mut r = SingleOperation::response(ALIVE_RESPONSE, RESULT_SUCCESS, &frame);
+                    r.data = now_time();
+                    r
+                }
+                op => {
+                    debug!("scte104: ignoring single_operation_message 0x{:04X} from {}", op, peer);
+                    continue;
+                }
+            };
+            write(&mut sock, &response.encode()).await?;
+        }
+        Ok(())
+    }
+
+    /// Decide one multiple_operation_message. Ok(None) = nothing to forward.
+    async fn inject(&self, st: &AppState, listener: &Listener, peer: SocketAddr, frame: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let start = Instant::now();
+        let channel = listener.channel.as_str();
+        let mut client_info = ClientInfo {
+            source_ip: Some(format!("SCTE104:{}", peer.ip())),
+            user_agent: Some("SCTE-104 automation".to_string()),
+            sesame_tier: None,
+        };
+        let metrics = |status: i32, error: Option<String>| ProcessingMetrics {
+            request_size: Some(frame.len() as i32),
+            processing_time_ms: Some(start.elapsed().as_millis() as i32),
+            response_status: status,
+            error_message: error,
+        };
+
+        let converted = MultipleOperationMessage::decode(frame).and_then(|msg| {
+            let opts = ConvertOptions {
+                frame_rate: self.frame_rate,
+                pre_roll_ms: msg.pre_roll_time().unwrap_or(0),
+                as_index: msg.as_index,
+                message_number: msg.message_number,
+                dpi_pid_index: msg.dpi_pid_index,
+                ..Default::default()
+            };
+            let (section, warnings) = scte104::to_scte35(&msg, &opts)?;
+            for w in warnings {
+                debug!("scte104: {}", w);
+            }
+            Ok((msg, opts, B64.encode(section.encode()?)))
+        });
+        let (msg, opts, b64) = match converted {
+            Ok(v) => v,
+            Err(e) => {
+                let _ = st
+                    .event_logger
+                    .log_esam_event(channel, &json!({"error": "parse_error"}), None, client_info, metrics(400, Some(format!("SCTE-104: {e}"))), None, None)
+                    .await;
+                return Err(e);
+            }
+        };
+        client_info.user_agent = Some(format!("SCTE-104 automation (AS_index {})", msg.as_index));
+
+        let signal_id = format!("SCTE104-{}-{}-{}", msg.as_index, msg.message_number, chrono::Utc::now().timestamp_millis());
+        let at = match msg.timestamp {
+            Timestamp::Utc { seconds, microseconds } => {
+                chrono::DateTime::from_timestamp(splice_info::utc_splice_time_to_unix(seconds), microseconds as u32 * 1000)
+            }
+            _ => None,
+        };
+        let utc_point = at.unwrap_or_else(chrono::Utc::now).format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        let request = signal_processing_event(&signal_id, channel, &utc_point, &b64);
+        let mut facts = extract_facts(&request)?;
+        let mut obj = facts.as_object().cloned().unwrap_or_default();
+
+        let ch: Option<(i64, i64)> =
+            sqlx::query_as("SELECT id, scte35_lint FROM channels WHERE name=? AND enabled=1 AND deleted_at IS NULL")
+                .bind(channel)
+                .fetch_optional(&st.db)
+                .await
+                .ok()
+                .flatten();
+        let Some((channel_id, channel_lint)) = ch else {
+            let error = "Channel not found or disabled".to_string();
+            let _ = st
+                .event_logger
+                .log_esam_event(channel, &facts, None, client_info, metrics(404, Some(error.clone())), Some(&request), None)
+                .await;
+            return Err(error);
+        };
+        if channel_lint != 0 {
+            lint_facts(st, channel_id, channel, &mut facts, &mut obj);
+        }
+
+        let decided = match decide(st, channel_id, channel, &obj, "scte104").await {
+            Ok(d) => d,
+            Err(e) => {
+                let _ = st
+                    .event_logger
+                    .log_esam_event(channel, &facts, None, client_info, metrics(500, Some(format!("DB error: {e}"))), Some(&request), None)
+                    .await;
+                return Err(e.to_string());
+            }
+        };
+
+        // Back to SCTE-104: untouched cues keep their original bytes.
+        let out = match (esam_verb(&decided.action), decided.params.get("scte35_b64").and_then(|v| v.as_str())) {
+            ("delete", _) | (_, None) => Ok(None),
+            (_, Some(out_b64)) if out_b64 == b64 => Ok(Some(frame.to_vec())),
+            (_, Some(out_b64)) => conditioned_message(&msg, &opts, out_b64).map(Some),
+        };
+        let error = out.as_ref().err().map(|e| format!("SCTE-104 conversion: {e}"));
+
+        let _ = st
+            .event_logger
+            .log_esam_event(
+                channel,
+                &facts,
+                decided.rule.as_ref().map(|r| (r, r.action.as_str())),
+                client_info,
+                metrics(200, error),
+                Some(&request),
+                Some(&decided.resp_xml),
+            )
+            .await;
+        out
+    }
+}
+
+/// `ADDR=CHANNEL[>HOST:PORT|>reply]`, comma separated.
+fn parse_listeners(spec: &str) -> Result<Vec<Listener>, String> {
+    let mut out: Vec<Listener> = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let bad = || format!("POIS_SCTE104_LISTEN: invalid entry '{entry}' (expected ADDR=CHANNEL[>TARGET])");
+        let (addr, rest) = entry.split_once('=').ok_or_else(bad)?;
+        let (channel, target) = match rest.split_once('>') {
+            Some((c, t)) if t.trim().eq_ignore_ascii_case("reply") => (c, Target::Reply),
+            Some((c, t)) if t.trim().contains(':') => (c, Target::Forward(t.trim().to_string())),
+            Some(_) => return Err(bad()),
+            None => (rest, Target::None),
+        };
+        let (addr, channel) = (addr.trim(), channel.trim());
+        if !addr.contains(':') || channel.is_empty() {
+            return Err(bad());
+        }
+        if out.iter().any(|l| l.addr == addr) {
+            return Err(format!("POIS_SCTE104_LISTEN: {addr} is listed twice"));
+        }
+        out.push(Listener { addr: addr.to_string(), channel: channel.to_string(), target });
+    }
+    if out.is_empty() {
+        return Err("POIS_SCTE104_LISTEN has no entries".into());
+    }
+    Ok(out)
+}
+
+/// The SignalProcessingEvent an ESAM client would have sent for this cue.
+fn signal_processing_event(signal_id: &str, channel: &str, utc_point: &str, b64: &str) -> String {
+    format!(
+        r#"<SignalProcessingEvent xmlns="urn:cablelabs:iptvservices:esam:xsd:signal:1" xmlns:sig="urn:cablelabs:md:xsd:signaling:3.0"><AcquiredSignal acquisitionSignalID="{}" acquisitionPointIdentity="{}"><sig:UTCPoint utcPoint="{}"/><sig:BinaryData signalType="SCTE35">{}</sig:BinaryData></AcquiredSignal></SignalProcessingEvent>"#,
+        xml_escape(signal_id),
+        xml_escape(channel),
+        xml_escape(utc_point),
+        xml_escape(b64)
+    )
+}
+
+/// The conditioned cue as a multiple_operation_message with the inbound
+/// message's indices, timestamp and pre-roll.
+fn conditioned_message(inbound: &MultipleOperationMessage, opts: &ConvertOptions, b64: &str) -> Result<Vec<u8>, String> {
+    let bytes = B64.decode(b64).map_err(|e| format!("invalid base64: {e}"))?;
+    let section = SpliceInfoSection::decode(&bytes).map_err(|e| e.to_string())?;
+    let (mut msg, warnings) = scte104::from_scte35(&section, opts)?;
+    for w in warnings {
+        debug!("scte104: {}", w);
+    }
+    msg.protocol_version = inbound.protocol_version;
+    msg.timestamp = inbound.timestamp;
+    msg.encode()
+}
+
+/// single_operation_message() (SCTE 104 §8.1).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SingleOperation {
+    op_id: u16,
+    result: u16,
+    result_extension: u16,
+    protocol_version: u8,
+    as_index: u8,
+    message_number: u8,
+    dpi_pid_index: u16,
+    data: Vec<u8>,
+}
+
+impl SingleOperation {
+    fn decode(b: &[u8]) -> Result<Self, String> {
+        if b.len() < SINGLE_HEADER_LEN {
+            return Err(format!("single_operation_message of {} bytes", b.len()));
+        }
+        let be = |i: usize| u16::from_be_bytes([b[i], b[i + 1]]);
+        let size = (be(2) as usize).clamp(SINGLE_HEADER_LEN, b.len());
+        Ok(Self {
+            op_id: be(0),
+            result: be(4),
+            result_extension: be(6),
+            protocol_version: b[8],
+            as_index: b[9],
+            message_number: b[10],
+            dpi_pid_index: be(11),
+            data: b[SINGLE_HEADER_LEN..size].to_vec(),
+        })
+    }
+
+    /// A request with no data (result fields unused).
+    fn request(op_id: u16) -> Self {
+        Self {
+            op_id,
+            result: 0xFFFF,
+            result_extension: 0xFFFF,
+            protocol_version: 0,
+            as_index: 0,
+            message_number: 0,
+            dpi_pid_index: 0,
+            data: Vec::new(),
+        }
+    }
+
+    /// A response echoing the indices of `request` (a single or multiple
+    /// operation message).
+    fn response(op_id: u16, result: u16, request: &[u8]) -> Self {
+        let (as_index, message_number, dpi_pid_index) = indices(request);
+        Self { result, as_index, message_number, dpi_pid_index, ..Self::request(op_id) }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let size = (SINGLE_HEADER_LEN + self.data.len()) as u16;
+        let mut out = Vec::with_capacity(size as usize);
+        out.extend(self.op_id.to_be_bytes());
+        out.extend(size.to_be_bytes());
+        out.extend(self.result.to_be_bytes());
+        out.extend(self.result_extension.to_be_bytes());
+        out.extend([self.protocol_version, self.as_index, self.message_number]);
+        out.extend(self.dpi_pid_index.to_be_bytes());
+        out.extend(&self.data);
+        out
+    }
+}
+
+/// AS_index, message_number and DPI_PID_index of a received message; they
+/// follow the 4-byte header of a multiple_operation_message and the 8-byte one
+/// of a single_operation_message.
+fn indices(frame: &[u8]) -> (u8, u8, u16) {
+    let at = |i: usize| frame.get(i).copied().unwrap_or(0);
+    let o = if frame.starts_with(&[0xFF, 0xFF]) { 5 } else { 9 };
+    (at(o), at(o + 1), u16::from_be_bytes([at(o + 2), at(o + 3)]))
+}
+
+/// time(): UTC_seconds (GPS epoch) and UTC_microseconds, as in alive messages.
+fn now_time() -> Vec<u8> {
+    let now = chrono::Utc::now();
+    let seconds = splice_info::unix_to_utc_splice_time(now.timestamp()).unwrap_or(0);
+    let mut out = seconds.to_be_bytes().to_vec();
+    // UTC_microseconds is only 16 bits wide and cannot hold most sub-second
+    // offsets in microseconds, so send the whole second.
+    out.extend(0u16.to_be_bytes());
+    out
+}
+
+/// One message framed by its messageSize (bytes 2-3 of either message type).
+/// Ok(None) on a clean end of stream.
+async fn read_message<R: AsyncRead + Unpin>(r: &mut R) -> Result<Option<Vec<u8>>, String> {
+    let mut head = [0u8; 4];
+    match r.read_exact(&mut head).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    }
+    let size = u16::from_be_bytes([head[2], head[3]]) as usize;
+    let min = if head[..2] == [0xFF, 0xFF] { 4 } else { SINGLE_HEADER_LEN };
+    if size < min {
+        return Err(format!("messageSize {size} is shorter than the message header"));
+    }
+    let mut frame = head.to_vec();
+    frame.resize(size, 0);
+    r.read_exact(&mut frame[4..]).await.map_err(|e| e.to_string())?;
+    Ok(Some(frame))
+}
+
+async fn write(sock: &mut TcpStream, bytes: &[u8]) -> Result<(), String> {
+    sock.write_all(bytes).await.map_err(|e| e.to_string())
+}
+
+/// Start the task that forwards an automation session's messages to `addr`,
+/// in order. It ends once the returned sender is dropped and the queue drained.
+fn spawn_forwarder(addr: String) -> mpsc::Sender<Vec<u8>> {
+    let (tx, mut rx) = mpsc::channel::<Vec<u8>>(FORWARD_QUEUE);
+    tokio::spawn(async move {
+        let mut upstream: Option<TcpStream> = None;
+        while let Some(message) = rx.recv().await {
+            if let Err(e) = forward(&mut upstream, &addr, &message).await {
+                warn!("scte104: forward to {} failed: {}", addr, e);
+            }
+        }
+    });
+    tx
+}
+
+/// Send one message to the forward target, opening (and initialising) the
+/// upstream session first if needed. A failed session is dropped and reopened
+/// for the next message.
+async fn forward(upstream: &mut Option<TcpStream>, addr: &str, message: &[u8]) -> Result<(), String> {
+    let result = async {
+        if upstream.is_none() {
+            let mut sock = tokio::time::timeout(UPSTREAM_TIMEOUT, TcpStream::connect(addr))
+                .await
+                .map_err(|_| "connect timed out".to_string())?
+                .map_err(|e| e.to_string())?;
+            write(&mut sock, &SingleOperation::request(INIT_REQUEST).encode()).await?;
+            expect(&mut sock, INIT_RESPONSE).await?;
+            *upstream = Some(sock);
+        }
+        let sock = upstream.as_mut().expect("upstream session");
+        write(sock, message).await?;
+        expect(sock, INJECT_RESPONSE).await
+    }
+    .await;
+    if result.is_err() {
+        *upstream = None;
+    }
+    result
+}
+
+/// Wait for the injector's `op_id` response, answering its alive_requests.
+async fn expect(sock: &mut TcpStream, op_id: u16) -> Result<(), String> {
+    loop {
+        let frame = tokio::time::timeout(UPSTREAM_TIMEOUT, read_message(sock))
+            .await
+            .map_err(|_| "no response from injector".to_string())??
+            .ok_or("injector closed the session")?;
+        let msg = SingleOperation::decode(&frame)?;
+        match msg.op_id {
+            ALIVE_REQUEST => {
+                let mut r = SingleOperation::response(ALIVE_RESPONSE, RESULT_SUCCESS, &frame);
+                r.data = now_time();
+                write(sock, &r.encode()).await?;
+            }
+            op if op == op_id && msg.result == RESULT_SUCCESS => return Ok(()),
+            op if op == op_id => return Err(format!("injector answered with result {}", msg.result)),
+            op => return Err(format!("unexpected opID 0x{op:04X} from injector")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_listen_spec() {
+        let l = parse_listeners("0.0.0.0:5167=east, 0.0.0.0:5168=west>10.0.0.9:5167,[::]:5169=north>reply").unwrap();
+        assert_eq!(l.len(), 3);
+        assert_eq!(l[0], Listener { addr: "0.0.0.0:5167".into(), channel: "east".into(), target: Target::None });
+        assert_eq!(l[1].target, Target::Forward("10.0.0.9:5167".into()));
+        assert_eq!(l[2].addr, "[::]:5169");
+        assert_eq!(l[2].target, Target::Reply);
+        assert!(parse_listeners("5167=east").is_err());
+        assert!(parse_listeners("0.0.0.0:5167=").is_err());
+        assert!(parse_listeners("0.0.0.0:5167=east>nowhere").is_err());
+        assert!(parse_listeners("0.0.0.0:5167=a,0.0.0.0:5167=b").is_err());
+    }
+
+    #[tokio::test]
+    async fn frames_single_and_multiple_operation_messages() {
+        let mut init = SingleOperation::request(INIT_REQUEST);
+        init.as_index = 3;
+        init.message_number = 9;
+        let mom = MultipleOperationMessage::new(vec![scte104::Operation::SpliceNull]).encode().unwrap();
+        let stream = [init.encode(), mom.clone()].concat();
+
+        let mut r = stream.as_slice();
+        let first = read_message(&mut r).await.unwrap().unwrap();
+        assert_eq!(SingleOperation::decode(&first).unwrap(), init);
+        assert_eq!(read_message(&mut r).await.unwrap().unwrap(), mom);
+        assert_eq!(read_message(&mut r).await.unwrap(), None);
+
+        let resp = SingleOperation::response(INIT_RESPONSE, RESULT_SUCCESS, &first);
+        let decoded = SingleOperation::decode(&resp.encode()).unwrap();
+        assert_eq!((decoded.op_id, decoded.result, decoded.as_index, decoded.message_number), (INIT_RESPONSE, 100, 3, 9));
+
+        let mut numbered = MultipleOperationMessage::new(vec![scte104::Operation::SpliceNull]);
+        (numbered.as_index, numbered.message_number, numbered.dpi_pid_index) = (1, 42, 0x0203);
+        assert_eq!(indices(&numbered.encode().unwrap()), (1, 42, 0x0203));
+
+        let short = [0x00, 0x01, 0x00, 0x05, 0x00];
+        assert!(read_message(&mut short.as_slice()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stalled_injector_fills_the_queue_instead_of_blocking() {
+        // Accepts but never answers the init_request.
+        let injector = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tx = spawn_forwarder(injector.local_addr().unwrap().to_string());
+        let dropped = (0..2 * FORWARD_QUEUE).filter(|_| tx.try_send(vec![0xFF, 0xFF]).is_err()).count();
+        assert!(dropped >= FORWARD_QUEUE - 1, "{dropped} dropped");
+    }
+}
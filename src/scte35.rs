@@ -1,6 +1,9 @@
 // src/scte35.rs
-// Version: 2.1.0 - splice_command_length now excludes the splice_command_type byte
-// Updated: 2026-06-08
+// Version: 2.3.0 - build_section_b64 for fully specified splice_insert / time_signal
+//                   sections; encode_mid; UUID UPIDs are type 0x10
+// Updated: 2026-10-18
+// v2.2.0 (2026-10-18): builders fill a typed splice_info::SpliceInfoSection and encode it
+// v2.1.0 (2026-06-08): splice_command_length now excludes the splice_command_type byte
 // (Previously off by one: it counted the type byte, so a time_signal immediate
 //  emitted scl=2 over a 1-byte body. Spec-strict decoders like scte35-reader use
 //  splice_command_length to delimit the command, so they dropped the section. Now
@@ -8,6 +11,10 @@
 
 use base64::engine::general_purpose::STANDARD as B64;
 use base64::Engine;
+use pois_esam_server::splice_info::{
+    has_sub_segments, BreakDuration, ScheduledEvent, SegmentationDescriptor, SpliceCommand, SpliceDescriptor,
+    SpliceInfoSection, SpliceInsert, SpliceSchedule, SpliceTime,
+};
 
 /// Public API: return base64 SCTE-35 payloads.
 pub fn build_time_signal_immediate_b64() -> String {
@@ -55,7 +62,44 @@ pub fn build_splice_insert_in_with_pts_b64(pts_time: u64) -> String {
     B64.encode(sec)
 }
 
+/// splice_null (heartbeat) with no descriptors.
+pub fn build_splice_null_b64() -> String {
+    B64.encode(encode(&SpliceInfoSection::new(SpliceCommand::SpliceNull)))
+}
+
+/// bandwidth_reservation (an empty command) with no descriptors.
+pub fn build_bandwidth_reservation_b64() -> String {
+    B64.encode(encode(&SpliceInfoSection::new(SpliceCommand::BandwidthReservation)))
+}
+
+/// splice_schedule carrying `events` (program or component mode per event).
+/// Fails when the section can't be represented (e.g. over 255 events).
+pub fn build_splice_schedule_b64(events: Vec<ScheduledEvent>) -> Result<String, String> {
+    let sec = SpliceInfoSection::new(SpliceCommand::SpliceSchedule(SpliceSchedule { events }));
+    sec.encode().map(|b| B64.encode(b))
+}
+
+/// private_command with a 32-bit `identifier` (normally four ASCII characters
+/// registered with SMPTE) followed by an opaque payload.
+pub fn build_private_command_b64(identifier: u32, private_bytes: Vec<u8>) -> Result<String, String> {
+    let sec = SpliceInfoSection::new(SpliceCommand::PrivateCommand { identifier, private_bytes });
+    sec.encode().map(|b| B64.encode(b))
+}
+
+/// Any command with caller-built descriptors (e.g. a fully specified
+/// splice_insert carrying several segmentation descriptors). Fails when the
+/// section can't be represented (a descriptor body over 255 bytes, ...).
+pub fn build_section_b64(command: SpliceCommand, descriptors: Vec<SpliceDescriptor>) -> Result<String, String> {
+    let mut sec = SpliceInfoSection::new(command);
+    sec.descriptors = descriptors;
+    sec.encode().map(|b| B64.encode(b))
+}
+
 // ---- Internal: section builders (binary) ----
+//
+// Each builder fills a typed `SpliceInfoSection` and lets `encode()` compute
+// the lengths and CRC-32. Reserved bits are written as 1, as SCTE 35 requires
+// (the `splice_info` defaults).
 
 fn build_time_signal_immediate_section() -> Vec<u8> {
     build_time_signal_section(None, None, None)
@@ -66,41 +110,12 @@ fn build_time_signal_section(
     upid_type: Option<u8>,
     upid_value: Option<&str>,
 ) -> Vec<u8> {
-    let mut w = BitWriter::new();
-    w.u8(0xFC);
-    w.u1(0);
-    w.u1(0);
-    w.u2(3);
-    let section_length_pos = w.reserve_u12();
-
-    w.u8(0);      // protocol_version
-    w.u1(0);      // encrypted_packet
-    w.u6(0);      // encryption_algorithm
-    w.u33(0);     // pts_adjustment
-    w.u8(0);      // cw_index
-    w.u12(0x0FFF);// tier
-
-    let splice_cmd_len_pos = w.bitpos();
-    w.u12(0);     // splice_command_length (patch later)
-    w.u8(0x06);   // splice_command_type = time_signal (NOT part of splice_command_length)
-    // splice_command_length measures the splice_command() body only, after the
-    // type byte (per spec; e.g. splice_null has length 0). A strict decoder uses
-    // it to delimit the command, so it must exclude the type byte.
-    let splice_cmd_start = w.bitpos();
-    w.u1(0);      // time_specified_flag = 0 (immediate)
-    w.u7(0);      // reserved
-
-    let splice_cmd_bits = w.bitpos() - splice_cmd_start;
-    w.patch_u12(splice_cmd_len_pos, (splice_cmd_bits/8) as u16);
-
-    // Add segmentation descriptor if params provided
+    // time_specified_flag = 0 (immediate)
+    let mut sec = SpliceInfoSection::new(SpliceCommand::TimeSignal(SpliceTime::immediate()));
     if seg_type_id.is_some() || upid_type.is_some() {
-        add_segmentation_descriptor(&mut w, None, seg_type_id, upid_type, upid_value);
-    } else {
-        w.u16(0); // descriptor_loop_length = 0
+        sec.descriptors.push(segmentation_descriptor(None, seg_type_id, upid_type, upid_value));
     }
-
-    finalize_with_crc32(&mut w, section_length_pos)
+    encode(&sec)
 }
 
 fn build_splice_insert_out_section(
@@ -109,125 +124,50 @@ fn build_splice_insert_out_section(
     upid_type: Option<u8>,
     upid_value: Option<&str>,
 ) -> Vec<u8> {
-    let mut w = BitWriter::new();
-    w.u8(0xFC);
-    w.u1(0);
-    w.u1(0);
-    w.u2(3);
-    let section_length_pos = w.reserve_u12();
-
-    w.u8(0);      // protocol_version
-    w.u1(0);      // encrypted_packet
-    w.u6(0);      // encryption_algorithm
-    w.u33(0);     // pts_adjustment
-    w.u8(0);      // cw_index
-    w.u12(0x0FFF);// tier
-
-    let splice_cmd_len_pos = w.bitpos();
-    w.u12(0);           // splice_command_length (patch later)
-    w.u8(0x05);         // splice_command_type = splice_insert (NOT part of splice_command_length)
-    let splice_cmd_start = w.bitpos();
-    w.u32(1);           // splice_event_id
-    w.u1(0);            // splice_event_cancel_indicator
-    w.u7(0);            // reserved
-    
-    // flags
-    w.u1(1); // out_of_network_indicator = 1 (OUT)
-    w.u1(1); // program_splice_flag = 1
-    w.u1(1); // duration_flag = 1
-    w.u1(1); // splice_immediate_flag = 1
-    w.u4(0); // reserved
-
-    // break_duration
-    w.u1(1); // auto_return
-    w.u6(0); // reserved
     let dur90k = duration_s as u64 * 90000;
-    w.u33(dur90k);
-
-    // unique_program_id, avail_num, avails_expected
-    w.u16(1);
-    w.u8(0);
-    w.u8(0);
-
-    let splice_cmd_bits = w.bitpos() - splice_cmd_start;
-    w.patch_u12(splice_cmd_len_pos, (splice_cmd_bits/8) as u16);
-
-    // Add segmentation descriptor
-    add_segmentation_descriptor(&mut w, Some(dur90k), seg_type_id, upid_type, upid_value);
-
-    finalize_with_crc32(&mut w, section_length_pos)
+    let mut sec = SpliceInfoSection::new(SpliceCommand::SpliceInsert(SpliceInsert {
+        splice_event_id: 1,
+        out_of_network_indicator: true, // OUT
+        program_splice_flag: true,
+        splice_immediate_flag: true,
+        break_duration: Some(BreakDuration::new(true, dur90k)),
+        unique_program_id: 1,
+        ..Default::default()
+    }));
+    sec.descriptors.push(segmentation_descriptor(Some(dur90k), seg_type_id, upid_type, upid_value));
+    encode(&sec)
 }
 
-/// NEW: Helper to add segmentation descriptor with custom parameters
-fn add_segmentation_descriptor(
-    w: &mut BitWriter,
+/// Segmentation descriptor with custom parameters (program-level, delivery not
+/// restricted, MID "POIS-OUT" / Program Start unless overridden).
+fn segmentation_descriptor(
     duration_90k: Option<u64>,
     seg_type_id: Option<u8>,
     upid_type: Option<u8>,
     upid_value: Option<&str>,
-) {
-    let desc_loop_start = w.bitpos();
-    w.u16(0); // descriptor_loop_length placeholder
-
-    // segmentation_descriptor (tag 0x02)
-    w.u8(0x02);
-    let seg_len_pos = w.reserve_u8();
-    w.u32(0x43554549); // "CUEI"
-    w.u32(1); // segmentation_event_id
-    w.u1(0);  // segmentation_event_cancel_indicator
-    w.u7(0);  // reserved
-
-    // flags
-    w.u1(1); // program_segmentation_flag
-    w.u1(if duration_90k.is_some() { 1 } else { 0 }); // segmentation_duration_flag
-    w.u1(1); // delivery_not_restricted_flag
-    w.u5(0); // reserved (no restriction flags)
-
-    // no components (program_segmentation_flag=1)
-
-    // segmentation_duration (if provided)
-    if let Some(dur) = duration_90k {
-        w.u40(dur);
-    }
-
-    // UPID
+) -> SpliceDescriptor {
     let upid_type_val = upid_type.unwrap_or(0x0C); // Default to MID
-    w.u8(upid_type_val);
-    
-    let upid_bytes = if let Some(val) = upid_value {
-        encode_upid(upid_type_val, val)
-    } else {
-        // Default UPID value
-        b"POIS-OUT".to_vec()
+    let mut upid = match upid_value {
+        Some(val) => encode_upid(upid_type_val, val),
+        None => b"POIS-OUT".to_vec(), // Default UPID value
     };
-    
-    w.u8(upid_bytes.len() as u8);
-    for b in &upid_bytes {
-        w.u8(*b);
-    }
-
-    // segmentation_type_id
     let seg_type = seg_type_id.unwrap_or(0x10); // Default to Program Start
-    w.u8(seg_type);
-    
-    // segment_num, segments_expected
-    w.u8(0);
-    w.u8(0);
-
-    // Handle sub-segment fields for certain types
-    if matches!(seg_type, 0x34 | 0x36 | 0x38 | 0x3A) {
-        // Distributor types that may have sub-segments
-        w.u8(0); // sub_segment_num
-        w.u8(0); // sub_segments_expected
-    }
-
-    // patch segmentation_descriptor length
-    let seg_bits = w.bitpos() - (seg_len_pos + 8);
-    w.patch_u8(seg_len_pos, (seg_bits / 8) as u8);
-
-    // patch descriptor_loop_length
-    let loop_bits = w.bitpos() - (desc_loop_start + 16);
-    w.patch_u16(desc_loop_start, (loop_bits / 8) as u16);
+    let sub = has_sub_segments(seg_type).then_some(0);
+
+    // descriptor_length is a u8: keep the UPID short enough for the body to fit.
+    let fixed = 4 + 4 + 1 + 1 + if duration_90k.is_some() { 5 } else { 0 } + 2 + 3 + if sub.is_some() { 2 } else { 0 };
+    upid.truncate(255 - fixed);
+
+    SpliceDescriptor::Segmentation(SegmentationDescriptor {
+        segmentation_event_id: 1,
+        segmentation_duration: duration_90k,
+        segmentation_upid_type: upid_type_val,
+        segmentation_upid: upid,
+        segmentation_type_id: seg_type,
+        sub_segment_num: sub,
+        sub_segments_expected: sub,
+        ..Default::default()
+    })
 }
 
 /// NEW: Encode UPID value based on type
@@ -245,7 +185,7 @@ pub(crate) fn encode_upid(upid_type: u8, value: &str) -> Vec<u8> {
             // ADI - variable length binary
             hex_decode(value).unwrap_or_else(|| value.as_bytes().to_vec())
         }
-        0x0B => {
+        0x10 => {
             // UUID - 16 bytes
             parse_uuid(value).unwrap_or_else(|| value.as_bytes().to_vec())
         }
@@ -256,6 +196,25 @@ pub(crate) fn encode_upid(upid_type: u8, value: &str) -> Vec<u8> {
     }
 }
 
+/// MID (segmentation_upid_type 0x0D) body: each child UPID as
+/// upid_type(8) + upid_length(8) + bytes, in order.
+pub(crate) fn encode_mid(children: &[(u8, &str)]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    for &(upid_type, value) in children {
+        if upid_type == 0x0D {
+            return Err("a MID cannot contain another MID".to_string());
+        }
+        let upid = encode_upid(upid_type, value);
+        if upid.len() > 255 {
+            return Err(format!("MID child UPID of {} bytes exceeds 255", upid.len()));
+        }
+        out.push(upid_type);
+        out.push(upid.len() as u8);
+        out.extend(upid);
+    }
+    Ok(out)
+}
+
 fn hex_decode(s: &str) -> Option<Vec<u8>> {
     let s = s.replace(['-', ' ', ':'], "");
     if !s.len().is_multiple_of(2) {
@@ -283,212 +242,31 @@ fn parse_uuid(s: &str) -> Option<Vec<u8>> {
 
 #[allow(dead_code)]
 fn build_splice_insert_in_section() -> Vec<u8> {
-    let mut w = BitWriter::new();
-    w.u8(0xFC);
-    w.u1(0);
-    w.u1(0);
-    w.u2(3);
-    let section_length_pos = w.reserve_u12();
-
-    w.u8(0);      // protocol_version
-    w.u1(0);      // encrypted_packet
-    w.u6(0);      // encryption_algorithm
-    w.u33(0);     // pts_adjustment
-    w.u8(0);      // cw_index
-    w.u12(0x0FFF);// tier
-
-    let splice_cmd_len_pos = w.bitpos();
-    w.u12(0);           // splice_command_length (patch later)
-    w.u8(0x05);         // splice_command_type = splice_insert (NOT part of splice_command_length)
-    let splice_cmd_start = w.bitpos();
-    w.u32(2);           // splice_event_id
-    w.u1(0);            // splice_event_cancel_indicator
-    w.u7(0);            // reserved
-    
-    // flags
-    w.u1(0); // out_of_network_indicator = 0 (IN)
-    w.u1(1); // program_splice_flag = 1
-    w.u1(0); // duration_flag = 0
-    w.u1(1); // splice_immediate_flag = 1
-    w.u4(0); // reserved
-
-    // unique_program_id, avail_num, avails_expected
-    w.u16(1);
-    w.u8(0);
-    w.u8(0);
-
-    let splice_cmd_bits = w.bitpos() - splice_cmd_start;
-    w.patch_u12(splice_cmd_len_pos, (splice_cmd_bits/8) as u16);
-
-    // descriptor_loop_length = 0
-    w.u16(0);
-
-    finalize_with_crc32(&mut w, section_length_pos)
+    build_splice_insert_in(None)
 }
 
 #[allow(dead_code)]
 fn build_splice_insert_in_with_pts_section(pts_time: u64) -> Vec<u8> {
-    let mut w = BitWriter::new();
-    w.u8(0xFC);
-    w.u1(0);
-    w.u1(0);
-    w.u2(3);
-    let section_length_pos = w.reserve_u12();
-
-    w.u8(0);      // protocol_version
-    w.u1(0);      // encrypted_packet
-    w.u6(0);      // encryption_algorithm
-    w.u33(0);     // pts_adjustment
-    w.u8(0);      // cw_index
-    w.u12(0x0FFF);// tier
-
-    let splice_cmd_len_pos = w.bitpos();
-    w.u12(0);           // splice_command_length (patch later)
-    w.u8(0x05);         // splice_command_type = splice_insert (NOT part of splice_command_length)
-    let splice_cmd_start = w.bitpos();
-    w.u32(2);           // splice_event_id
-    w.u1(0);            // splice_event_cancel_indicator
-    w.u7(0);            // reserved
-    
-    // flags
-    w.u1(0); // out_of_network_indicator = 0 (IN)
-    w.u1(1); // program_splice_flag = 1
-    w.u1(0); // duration_flag = 0
-    w.u1(0); // splice_immediate_flag = 0 (using PTS)
-    w.u4(0); // reserved
-
-    // splice_time() with PTS
-    w.u1(1); // time_specified_flag = 1
-    w.u6(0); // reserved
-    w.u33(pts_time);
-
-    // unique_program_id, avail_num, avails_expected
-    w.u16(1);
-    w.u8(0);
-    w.u8(0);
-
-    let splice_cmd_bits = w.bitpos() - splice_cmd_start;
-    w.patch_u12(splice_cmd_len_pos, (splice_cmd_bits/8) as u16);
-
-    // descriptor_loop_length = 0
-    w.u16(0);
-
-    finalize_with_crc32(&mut w, section_length_pos)
+    build_splice_insert_in(Some(pts_time))
 }
 
-// ---- BitWriter helper ----
-
-struct BitWriter {
-    bytes: Vec<u8>,
-    bitpos: usize,
+/// splice_insert IN (event 2): immediate, or at `pts_time` when given.
+fn build_splice_insert_in(pts_time: Option<u64>) -> Vec<u8> {
+    let sec = SpliceInfoSection::new(SpliceCommand::SpliceInsert(SpliceInsert {
+        splice_event_id: 2,
+        out_of_network_indicator: false, // IN
+        program_splice_flag: true,
+        splice_immediate_flag: pts_time.is_none(),
+        splice_time: pts_time.map(SpliceTime::at),
+        unique_program_id: 1,
+        ..Default::default()
+    }));
+    encode(&sec)
 }
 
-impl BitWriter {
-    fn new() -> Self { Self { bytes: Vec::new(), bitpos: 0 } }
-    
-    fn u1(&mut self, v: u8) { self.write_bits(v as u64, 1); }
-    fn u2(&mut self, v: u8) { self.write_bits(v as u64, 2); }
-    fn u4(&mut self, v: u8) { self.write_bits(v as u64, 4); }
-    fn u5(&mut self, v: u8) { self.write_bits(v as u64, 5); }
-    fn u6(&mut self, v: u8) { self.write_bits(v as u64, 6); }
-    fn u7(&mut self, v: u8) { self.write_bits(v as u64, 7); }
-    fn u8(&mut self, v: u8) { self.write_bits(v as u64, 8); }
-    fn u12(&mut self, v: u16) { self.write_bits(v as u64, 12); }
-    fn u16(&mut self, v: u16) { self.write_bits(v as u64, 16); }
-    fn u32(&mut self, v: u32) { self.write_bits(v as u64, 32); }
-    fn u33(&mut self, v: u64) { self.write_bits(v, 33); }
-    fn u40(&mut self, v: u64) { self.write_bits(v, 40); }
-
-    fn write_bits(&mut self, val: u64, nbits: usize) {
-        for i in (0..nbits).rev() {
-            let bit = ((val >> i) & 1) as u8;
-            let byte_idx = self.bitpos / 8;
-            let bit_idx = 7 - (self.bitpos % 8);
-            
-            if byte_idx >= self.bytes.len() {
-                self.bytes.push(0);
-            }
-            self.bytes[byte_idx] |= bit << bit_idx;
-            self.bitpos += 1;
-        }
-    }
-
-    fn reserve_u8(&mut self) -> usize {
-        let pos = self.bitpos();
-        self.u8(0);
-        pos
-    }
-
-    fn reserve_u12(&mut self) -> usize {
-        let pos = self.bitpos();
-        self.u12(0);
-        pos
-    }
-
-    fn patch_u1(&mut self, bitpos: usize, val: u8) {
-        let byte_idx = bitpos / 8;
-        let bit_idx = 7 - (bitpos % 8);
-        if byte_idx < self.bytes.len() {
-            self.bytes[byte_idx] &= !(1 << bit_idx);
-            self.bytes[byte_idx] |= (val & 1) << bit_idx;
-        }
-    }
-
-    fn patch_u8(&mut self, bitpos: usize, val: u8) {
-        for i in 0..8 {
-            let bit = (val >> (7 - i)) & 1;
-            self.patch_u1(bitpos + i, bit);
-        }
-    }
-
-    fn patch_u12(&mut self, bitpos: usize, val: u16) {
-        for i in 0..12 {
-            let bit = ((val >> (11 - i)) & 1) as u8;
-            self.patch_u1(bitpos + i, bit);
-        }
-    }
-
-    fn patch_u16(&mut self, bitpos: usize, val: u16) {
-        for i in 0..16 {
-            let bit = ((val >> (15 - i)) & 1) as u8;
-            self.patch_u1(bitpos + i, bit);
-        }
-    }
-
-    fn bitpos(&self) -> usize { self.bitpos }
-}
-
-fn finalize_with_crc32(w: &mut BitWriter, section_length_pos: usize) -> Vec<u8> {
-    // Align to byte
-    while !w.bitpos.is_multiple_of(8) {
-        w.u1(1);
-    }
-
-    // Compute section_length (from after section_length field to end, including 4-byte CRC)
-    let section_start_byte = (section_length_pos + 12) / 8;
-    let section_length = (w.bytes.len() - section_start_byte) + 4;
-    w.patch_u12(section_length_pos, section_length as u16);
-
-    // Compute CRC-32 over entire section (from table_id to end before CRC)
-    let crc = compute_crc32(&w.bytes);
-    w.u32(crc);
-
-    w.bytes.clone()
-}
-
-pub(crate) fn compute_crc32(data: &[u8]) -> u32 {
-    let mut crc = 0xFFFFFFFF_u32;
-    for &byte in data {
-        crc ^= (byte as u32) << 24;
-        for _ in 0..8 {
-            if crc & 0x80000000 != 0 {
-                crc = (crc << 1) ^ 0x04C11DB7;
-            } else {
-                crc <<= 1;
-            }
-        }
-    }
-    crc
+/// Our builders only emit representable sections (the UPID is capped above).
+fn encode(sec: &SpliceInfoSection) -> Vec<u8> {
+    sec.encode().expect("builder sections are always encodable")
 }
 
 #[cfg(test)]
@@ -524,7 +302,7 @@ mod splice_command_length_tests {
         if dll_pos + 2 > b.len() {
             return false;
         }
-        let dll = ((b[dll_pos] as usize) << 8) | b[dll_pos + 1] as usize;
+        let dll = (((b[dll_pos] & 0x03) as usize) << 8) | b[dll_pos + 1] as usize;
         dll_pos + 2 + dll + 4 == b.len()
     }
 
@@ -589,4 +367,21 @@ mod splice_command_length_tests {
         assert_eq!(scl(&b), 15);
         assert!(descriptor_loop_well_formed(&b));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn builder_bytes_are_stable() {
+        // Golden output of the builders; every reserved bit is written as 1.
+        assert_eq!(build_splice_insert_out_b64(30), "/DA+AAAAAAAAAP/wDwUAAAABf/f+ACky4AABAAD8HgIcQ1VFSQAAAAE//wAAKTLgDAhQT0lTLU9VVBAAAN1HgMU=");
+        assert_eq!(
+            build_splice_insert_out_advanced_b64(60, Some(0x34), Some(0x09), Some("test-upid")),
+            "/DBBAAAAAAAAAP/wDwUAAAABf/f+AFJlwAABAAD8IQIfQ1VFSQAAAAE//wAAUmXACQl0ZXN0LXVwaWQ0AAAAAOnN3UE="
+        );
+        assert_eq!(
+            build_time_signal_advanced_b64(Some(0x36), Some(0x0F), Some("550e8400-e29b-41d4-a716-446655440000")),
+            "/DBJAAAAAAAAAP/wAQZ//DcCNUNVRUkAAAABP78PJDU1MGU4NDAwLWUyOWItNDFkNC1hNzE2LTQ0NjY1NTQ0MDAwMDYAAAAAVb3EUg=="
+        );
+        assert_eq!(build_splice_insert_in_b64(), "/DAbAAAAAAAAAP/wCgUAAAACf1cAAQAA/ADMCm1/");
+        assert_eq!(build_splice_insert_in_with_pts_b64(0x1_2345_6789), "/DAgAAAAAAAAAP/wDwUAAAACf0f/I0VniQABAAD8AI1N3iM=");
+        assert_eq!(build_time_signal_advanced_b64(Some(0x10), None, None), "/DArAAAAAAAAAP/wAQZ//BkCF0NVRUkAAAABP78MCFBPSVMtT1VUEAAAQsjvxw==");
+    }
+}
@@ -12,6 +12,7 @@
 // Runtime configuration is loaded from environment (key distribution is out of
 // band per §8.2.5). See `SesameRuntime::from_env`.
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
@@ -41,6 +42,9 @@ pub struct SesameRuntime {
     pub response_key_id: Option<String>,
     /// This POIS's encryption key-id for Tier 3 responses (X-SESAME-EncKeyId).
     pub response_enc_key_id: Option<String>,
+    /// Configured signing key ids; any other key id is labelled
+    /// `metrics::UNKNOWN_KEY` in rejection metrics.
+    signing_key_ids: HashSet<String>,
     /// Master switch. When false the adapter is a transparent passthrough so
     /// existing (Tier 0) deployments behave exactly as before SESAME existed.
     enabled: bool,
@@ -66,7 +70,7 @@ impl SesameRuntime {
             .unwrap_or(300);
 
         let keys_env = std::env::var("POIS_SESAME_KEYS").ok();
-        let provider = keys_env
+        let (provider, signing_key_ids) = keys_env
             .as_deref()
             .and_then(parse_keys_json)
             .unwrap_or_default();
@@ -88,6 +92,7 @@ impl SesameRuntime {
             default_min_tier,
             response_key_id,
             response_enc_key_id: std::env::var("POIS_SESAME_RESPONSE_ENCID").ok(),
+            signing_key_ids,
             enabled,
         }
     }
@@ -97,6 +102,15 @@ impl SesameRuntime {
     pub fn is_enabled(&self) -> bool {
         self.enabled
     }
+
+    /// Metric label for an unverified key id: itself only if it is configured.
+    fn key_label(&self, key_id: Option<&str>) -> String {
+        match key_id {
+            None => String::new(),
+            Some(k) if self.signing_key_ids.contains(k) => k.to_string(),
+            Some(_) => crate::metrics::UNKNOWN_KEY.to_string(),
+        }
+    }
 }
 
 /// A SESAME rejection rendered as the paper's JSON error body (Appendix A.7).
@@ -107,7 +121,10 @@ pub struct SesameRejection {
 }
 
 impl SesameRejection {
-    fn new(err: SesameError, key_id: Option<String>, detail: impl Into<String>) -> Self {
+    /// `label` is the `key_id` recorded in metrics, which must not come
+    /// straight from an unverified header (see `SesameRuntime::key_label`).
+    fn new(err: SesameError, key_id: Option<String>, label: &str, detail: impl Into<String>) -> Self {
+        crate::metrics::sesame_rejected(err.code(), label);
         SesameRejection {
             err,
             key_id,
@@ -124,9 +141,11 @@ impl SesameRejection {
 /// minimum (§9.3). Surfaced as `sesame_missing_headers` (the request lacked the
 /// SESAME protection the channel requires).
 pub fn reject_insufficient_tier(key_id: Option<String>, required: Tier, achieved: Tier) -> SesameRejection {
+    let label = key_id.clone().unwrap_or_default();
     SesameRejection::new(
         SesameError::MissingHeaders,
         key_id,
+        &label,
         format!(
             "Channel requires SESAME tier {} but request achieved tier {}",
             required.level(),
@@ -138,9 +157,11 @@ pub fn reject_insufficient_tier(key_id: Option<String>, required: Tier, achieved
 /// Reject because the Tier-2 declared scope does not match the channel the
 /// request actually resolved to.
 pub fn reject_scope_mismatch(key_id: Option<String>, declared: &str, resolved: &str) -> SesameRejection {
+    let label = key_id.clone().unwrap_or_default();
     SesameRejection::new(
         SesameError::ScopeDenied,
         key_id,
+        &label,
         format!("Declared scope channel {declared} does not match target channel {resolved}"),
     )
 }
@@ -191,16 +212,21 @@ pub fn verify_incoming(
     };
     let min_tier = rt.default_min_tier;
 
-    match sesame::verify_request(
-        &rt.cfg,
-        rt.provider.as_ref(),
-        rt.replay.as_ref(),
-        &ctx,
-        &parsed,
-        raw_body,
-        now,
-        min_tier,
-    ) {
+    let verified = tracing::info_span!("sesame.verify").in_scope(|| {
+        crate::metrics::timed("sesame_verify", || {
+            sesame::verify_request(
+                &rt.cfg,
+                rt.provider.as_ref(),
+                rt.replay.as_ref(),
+                &ctx,
+                &parsed,
+                raw_body,
+                now,
+                min_tier,
+            )
+        })
+    });
+    match verified {
         Ok(VerifiedRequest {
             plaintext,
             key_id,
@@ -224,6 +250,7 @@ pub fn verify_incoming(
         Err(err) => Err(SesameRejection::new(
             err,
             parsed.key_id.clone(),
+            &rt.key_label(parsed.key_id.as_deref()),
             describe(err, &parsed),
         )),
     }
@@ -249,7 +276,12 @@ pub fn sign_outgoing(
         enc_key_id: rt.response_enc_key_id.as_deref(),
     };
     // Fail open on signing only if misconfigured; the error is logged by the caller.
-    sesame::sign_response(&rt.cfg, rt.provider.as_ref(), &params, response_xml, OffsetDateTime::now_utc()).ok()
+    tracing::info_span!("sesame.sign").in_scope(|| {
+        crate::metrics::timed("sesame_sign", || {
+            sesame::sign_response(&rt.cfg, rt.provider.as_ref(), &params, response_xml, OffsetDateTime::now_utc())
+        })
+    })
+    .ok()
 }
 
 /// Build the final ESAM HTTP response, signing (and optionally encrypting) it
@@ -331,7 +363,8 @@ fn describe(err: SesameError, h: &SesameHeaders) -> String {
     }
 }
 
-/// Parse `POIS_SESAME_KEYS` JSON into a `StaticKeyProvider`. Format:
+/// Parse `POIS_SESAME_KEYS` JSON into a `StaticKeyProvider` and the set of
+/// signing key ids it holds. Format:
 /// ```json
 /// {
 ///   "signing": [
@@ -343,9 +376,10 @@ fn describe(err: SesameError, h: &SesameHeaders) -> String {
 ///   ]
 /// }
 /// ```
-fn parse_keys_json(json: &str) -> Option<StaticKeyProvider> {
+fn parse_keys_json(json: &str) -> Option<(StaticKeyProvider, HashSet<String>)> {
     let v: serde_json::Value = serde_json::from_str(json).ok()?;
     let mut provider = StaticKeyProvider::new();
+    let mut signing_key_ids = HashSet::new();
 
     if let Some(signing) = v.get("signing").and_then(|s| s.as_array()) {
         for entry in signing {
@@ -363,6 +397,7 @@ fn parse_keys_json(json: &str) -> Option<StaticKeyProvider> {
                 ChannelScope::list(channels)
             };
             provider = provider.with_signing_key(key_id, HmacKey(secret), scope);
+            signing_key_ids.insert(key_id.to_string());
         }
     }
 
@@ -380,5 +415,5 @@ fn parse_keys_json(json: &str) -> Option<StaticKeyProvider> {
         }
     }
 
-    Some(provider)
+    Some((provider, signing_key_ids))
 }
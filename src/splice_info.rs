@@ -0,0 +1,1641 @@
+// src/splice_info.rs
+//
+// Typed SCTE-35 splice_info_section() model (ANSI/SCTE 35 2022 §9) with a
+// lossless decode/encode pair. This is the one SCTE-35 codec in the server: the
+// ESAM fact extractor (`esam::decode_scte35_details`), the Tools API decoder and
+// in-place rewriters (`tools_api`) and the cue builders (`scte35`) are all built
+// on it, and the library crate exports it for external use.
+//
+// Every field on the wire is kept, reserved bits included, so
+// `SpliceInfoSection::decode(b)?.encode()? == b` for any well-formed section.
+// `encode()` recomputes section_length, splice_command_length, each
+// descriptor_length, descriptor_loop_length and the CRC-32; the values actually
+// found on the wire are kept in `SpliceInfoSection::wire` for callers that audit
+// them. An encrypted section keeps everything from splice_command_type through
+// E_CRC_32 as opaque bytes.
+
+use std::fmt;
+
+pub const TABLE_ID: u8 = 0xFC;
+/// "CUEI", the identifier carried by every SCTE-35 splice descriptor.
+pub const CUEI: u32 = 0x4355_4549;
+
+pub const SPLICE_NULL: u8 = 0x00;
+pub const SPLICE_SCHEDULE: u8 = 0x04;
+pub const SPLICE_INSERT: u8 = 0x05;
+pub const TIME_SIGNAL: u8 = 0x06;
+pub const BANDWIDTH_RESERVATION: u8 = 0x07;
+pub const PRIVATE_COMMAND: u8 = 0xFF;
+
+pub const AVAIL_DESCRIPTOR: u8 = 0x00;
+pub const DTMF_DESCRIPTOR: u8 = 0x01;
+pub const SEGMENTATION_DESCRIPTOR: u8 = 0x02;
+pub const TIME_DESCRIPTOR: u8 = 0x03;
+pub const AUDIO_DESCRIPTOR: u8 = 0x04;
+
+/// splice_command_length value used by legacy encoders for "not specified".
+pub const LEGACY_COMMAND_LENGTH: u16 = 0x0FFF;
+
+/// Unix time of the GPS epoch (1980-01-06T00:00:00Z), the origin of
+/// splice_schedule's utc_splice_time.
+pub const GPS_EPOCH_UNIX: i64 = 315_964_800;
+
+/// utc_splice_time (seconds since the GPS epoch) as Unix seconds. Leap seconds
+/// are not applied: the field is treated as a plain UTC offset from 1980-01-06.
+pub fn utc_splice_time_to_unix(utc_splice_time: u32) -> i64 {
+    GPS_EPOCH_UNIX + utc_splice_time as i64
+}
+
+/// Inverse of `utc_splice_time_to_unix`; None before 1980-01-06 or past the
+/// 32-bit range (2116).
+pub fn unix_to_utc_splice_time(unix: i64) -> Option<u32> {
+    u32::try_from(unix - GPS_EPOCH_UNIX).ok()
+}
+
+/// MPEG-2 CRC-32 (poly 0x04C11DB7, init 0xFFFFFFFF, no reflection, no final xor),
+/// as used by splice_info_section().
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for &byte in data {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            if crc & 0x8000_0000 != 0 {
+                crc = (crc << 1) ^ 0x04C1_1DB7;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+    crc
+}
+
+// ============================================================================
+// MODEL
+// ============================================================================
+
+/// Length fields and CRC as read from the wire by `decode()`. `encode()` never
+/// uses them (it recomputes everything); they exist so that a caller can report
+/// a section whose declared lengths or CRC disagree with its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WireInfo {
+    pub section_length: u16,
+    pub splice_command_length: u16,
+    /// Bytes the splice_command() body actually occupied when parsed.
+    pub splice_command_body_length: u16,
+    pub descriptor_loop_length: u16,
+    pub crc_32: u32,
+    /// True when the stored CRC-32 matches the section content.
+    pub crc_valid: bool,
+    /// Bytes supplied to `decode()` (may exceed section_length + 3 when the
+    /// buffer carries trailing stuffing, or fall short when it is truncated).
+    pub input_length: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpliceInfoSection {
+    pub table_id: u8,
+    pub section_syntax_indicator: bool,
+    pub private_indicator: bool,
+    /// 2 bits; "reserved" before SCTE 35 2019.
+    pub sap_type: u8,
+    pub protocol_version: u8,
+    pub encrypted_packet: bool,
+    /// 6 bits.
+    pub encryption_algorithm: u8,
+    /// 33 bits, 90 kHz.
+    pub pts_adjustment: u64,
+    pub cw_index: u8,
+    /// 12 bits.
+    pub tier: u16,
+    /// splice_command_length carried the legacy 0xFFF "unspecified" value;
+    /// `encode()` writes it back instead of the real body length.
+    pub legacy_command_length: bool,
+    pub splice_command: SpliceCommand,
+    /// The 6 reserved bits in front of descriptor_loop_length.
+    pub descriptor_loop_reserved: u8,
+    pub descriptors: Vec<SpliceDescriptor>,
+    /// Bytes between the descriptor loop and the CRC-32 (alignment stuffing).
+    pub alignment_stuffing: Vec<u8>,
+    /// For `encrypted_packet` sections: every byte from splice_command_type
+    /// through E_CRC_32, kept opaque. `splice_command`/`descriptors` are then
+    /// placeholders and are not encoded.
+    pub encrypted_payload: Option<Vec<u8>>,
+    /// Populated by `decode()`; None for sections built in code.
+    pub wire: Option<WireInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpliceCommand {
+    SpliceNull,
+    SpliceSchedule(SpliceSchedule),
+    SpliceInsert(SpliceInsert),
+    TimeSignal(SpliceTime),
+    BandwidthReservation,
+    PrivateCommand { identifier: u32, private_bytes: Vec<u8> },
+    /// A reserved splice_command_type, kept as raw body bytes.
+    Unknown { command_type: u8, body: Vec<u8> },
+}
+
+/// splice_time(): `pts_time` is Some when time_specified_flag is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpliceTime {
+    pub pts_time: Option<u64>,
+    /// 6 reserved bits when a time is specified, 7 otherwise.
+    pub reserved: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakDuration {
+    pub auto_return: bool,
+    /// 6 bits.
+    pub reserved: u8,
+    /// 33 bits, 90 kHz.
+    pub duration: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpliceInsert {
+    pub splice_event_id: u32,
+    pub splice_event_cancel_indicator: bool,
+    /// 7 bits.
+    pub reserved: u8,
+    // The remaining fields are only on the wire when the event is not cancelled.
+    pub out_of_network_indicator: bool,
+    pub program_splice_flag: bool,
+    pub splice_immediate_flag: bool,
+    pub event_id_compliance_flag: bool,
+    /// 3 bits.
+    pub flags_reserved: u8,
+    /// Present when program_splice_flag is set and splice_immediate_flag is not.
+    pub splice_time: Option<SpliceTime>,
+    /// Present when program_splice_flag is clear.
+    pub components: Vec<SpliceInsertComponent>,
+    /// duration_flag is set exactly when this is Some.
+    pub break_duration: Option<BreakDuration>,
+    pub unique_program_id: u16,
+    pub avail_num: u8,
+    pub avails_expected: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpliceInsertComponent {
+    pub component_tag: u8,
+    /// None in splice_immediate mode.
+    pub splice_time: Option<SpliceTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SpliceSchedule {
+    pub events: Vec<ScheduledEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledEvent {
+    pub splice_event_id: u32,
+    pub splice_event_cancel_indicator: bool,
+    /// 7 bits.
+    pub reserved: u8,
+    pub out_of_network_indicator: bool,
+    pub program_splice_flag: bool,
+    /// 5 bits.
+    pub flags_reserved: u8,
+    /// utc_splice_time (GPS seconds) when program_splice_flag is set.
+    pub utc_splice_time: Option<u32>,
+    /// (component_tag, utc_splice_time) when program_splice_flag is clear.
+    pub components: Vec<(u8, u32)>,
+    pub break_duration: Option<BreakDuration>,
+    pub unique_program_id: u16,
+    pub avail_num: u8,
+    pub avails_expected: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpliceDescriptor {
+    Avail(AvailDescriptor),
+    Dtmf(DtmfDescriptor),
+    Segmentation(SegmentationDescriptor),
+    Time(TimeDescriptor),
+    Audio(AudioDescriptor),
+    /// Any other tag, or a known tag whose body could not be parsed. `data` is
+    /// the whole descriptor body (identifier included).
+    Other { tag: u8, data: Vec<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvailDescriptor {
+    pub identifier: u32,
+    pub provider_avail_id: u32,
+    pub extra: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DtmfDescriptor {
+    pub identifier: u32,
+    /// Tenths of a second.
+    pub preroll: u8,
+    /// 5 bits.
+    pub reserved: u8,
+    pub dtmf_chars: Vec<u8>,
+    pub extra: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeDescriptor {
+    pub identifier: u32,
+    /// 48 bits.
+    pub tai_seconds: u64,
+    pub tai_ns: u32,
+    pub utc_offset: u16,
+    pub extra: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioDescriptor {
+    pub identifier: u32,
+    /// 4 bits.
+    pub reserved: u8,
+    pub components: Vec<AudioComponent>,
+    pub extra: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioComponent {
+    pub component_tag: u8,
+    /// ISO 639-2 language code, 24 bits.
+    pub iso_code: u32,
+    /// 3 bits.
+    pub bit_stream_mode: u8,
+    /// 4 bits.
+    pub num_channels: u8,
+    pub full_srvc_audio: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentationDescriptor {
+    pub identifier: u32,
+    pub segmentation_event_id: u32,
+    pub segmentation_event_cancel_indicator: bool,
+    pub segmentation_event_id_compliance_indicator: bool,
+    /// 6 bits.
+    pub reserved: u8,
+    // The remaining fields are only on the wire when the event is not cancelled.
+    pub program_segmentation_flag: bool,
+    pub delivery_not_restricted_flag: bool,
+    /// The 5 restriction bits; reserved (kept raw in `restriction_reserved`)
+    /// when delivery_not_restricted_flag is set.
+    pub delivery_restrictions: DeliveryRestrictions,
+    pub restriction_reserved: u8,
+    /// Present when program_segmentation_flag is clear.
+    pub components: Vec<SegmentationComponent>,
+    /// 40 bits, 90 kHz. segmentation_duration_flag is set exactly when Some.
+    pub segmentation_duration: Option<u64>,
+    pub segmentation_upid_type: u8,
+    pub segmentation_upid: Vec<u8>,
+    pub segmentation_type_id: u8,
+    pub segment_num: u8,
+    pub segments_expected: u8,
+    /// Only carried for the placement-opportunity / ad-block start types.
+    pub sub_segment_num: Option<u8>,
+    pub sub_segments_expected: Option<u8>,
+    /// Bytes after the last parsed field, up to descriptor_length.
+    pub extra: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeliveryRestrictions {
+    pub web_delivery_allowed_flag: bool,
+    pub no_regional_blackout_flag: bool,
+    pub archive_allowed_flag: bool,
+    /// 2 bits.
+    pub device_restrictions: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentationComponent {
+    pub component_tag: u8,
+    /// 7 bits.
+    pub reserved: u8,
+    /// 33 bits, 90 kHz.
+    pub pts_offset: u64,
+}
+
+/// Segmentation types that carry sub_segment_num / sub_segments_expected.
+pub fn has_sub_segments(segmentation_type_id: u8) -> bool {
+    matches!(segmentation_type_id, 0x34 | 0x36 | 0x38 | 0x3A | 0x44 | 0x46)
+}
+
+// ============================================================================
+// CONSTRUCTORS / ACCESSORS
+// ============================================================================
+
+impl SpliceInfoSection {
+    /// A clear (unencrypted) section around `command` with the header values
+    /// this server emits: SAP type 3 (unspecified), tier 0xFFF, no adjustment.
+    /// Reserved bits are set to 1 here and in every `Default` below, as SCTE 35
+    /// requires of an encoder.
+    pub fn new(command: SpliceCommand) -> Self {
+        Self {
+            table_id: TABLE_ID,
+            section_syntax_indicator: false,
+            private_indicator: false,
+            sap_type: 3,
+            protocol_version: 0,
+            encrypted_packet: false,
+            encryption_algorithm: 0,
+            pts_adjustment: 0,
+            cw_index: 0,
+            tier: 0x0FFF,
+            legacy_command_length: false,
+            splice_command: command,
+            descriptor_loop_reserved: 0x3F,
+            descriptors: Vec::new(),
+            alignment_stuffing: Vec::new(),
+            encrypted_payload: None,
+            wire: None,
+        }
+    }
+
+    pub fn segmentation_descriptors(&self) -> impl Iterator<Item = &SegmentationDescriptor> {
+        self.descriptors.iter().filter_map(|d| match d {
+            SpliceDescriptor::Segmentation(s) => Some(s),
+            _ => None,
+        })
+    }
+
+    pub fn segmentation_descriptors_mut(&mut self) -> impl Iterator<Item = &mut SegmentationDescriptor> {
+        self.descriptors.iter_mut().filter_map(|d| match d {
+            SpliceDescriptor::Segmentation(s) => Some(s),
+            _ => None,
+        })
+    }
+}
+
+impl SpliceCommand {
+    pub fn command_type(&self) -> u8 {
+        match self {
+            SpliceCommand::SpliceNull => SPLICE_NULL,
+            SpliceCommand::SpliceSchedule(_) => SPLICE_SCHEDULE,
+            SpliceCommand::SpliceInsert(_) => SPLICE_INSERT,
+            SpliceCommand::TimeSignal(_) => TIME_SIGNAL,
+            SpliceCommand::BandwidthReservation => BANDWIDTH_RESERVATION,
+            SpliceCommand::PrivateCommand { .. } => PRIVATE_COMMAND,
+            SpliceCommand::Unknown { command_type, .. } => *command_type,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpliceCommand::SpliceNull => "splice_null",
+            SpliceCommand::SpliceSchedule(_) => "splice_schedule",
+            SpliceCommand::SpliceInsert(_) => "splice_insert",
+            SpliceCommand::TimeSignal(_) => "time_signal",
+            SpliceCommand::BandwidthReservation => "bandwidth_reservation",
+            SpliceCommand::PrivateCommand { .. } => "private_command",
+            SpliceCommand::Unknown { .. } => "unknown",
+        }
+    }
+
+    /// The splice point PTS (before pts_adjustment): time_signal's splice_time,
+    /// or for splice_insert the program splice_time / first component time.
+    pub fn pts_time(&self) -> Option<u64> {
+        match self {
+            SpliceCommand::TimeSignal(t) => t.pts_time,
+            SpliceCommand::SpliceInsert(si) if !si.splice_event_cancel_indicator => si
+                .splice_time
+                .and_then(|t| t.pts_time)
+                .or_else(|| si.components.iter().find_map(|c| c.splice_time.and_then(|t| t.pts_time))),
+            _ => None,
+        }
+    }
+}
+
+impl SpliceTime {
+    /// A splice_time with its reserved bits set (6 with a time, 7 without).
+    pub fn new(pts_time: Option<u64>) -> Self {
+        Self { pts_time, reserved: if pts_time.is_some() { 0x3F } else { 0x7F } }
+    }
+
+    pub fn immediate() -> Self {
+        Self::new(None)
+    }
+
+    pub fn at(pts_time: u64) -> Self {
+        Self::new(Some(pts_time))
+    }
+}
+
+impl Default for SpliceTime {
+    fn default() -> Self {
+        Self::immediate()
+    }
+}
+
+impl BreakDuration {
+    pub fn new(auto_return: bool, duration: u64) -> Self {
+        Self { auto_return, reserved: 0x3F, duration }
+    }
+}
+
+impl Default for BreakDuration {
+    fn default() -> Self {
+        Self::new(false, 0)
+    }
+}
+
+impl Default for SpliceInsert {
+    fn default() -> Self {
+        Self {
+            splice_event_id: 0,
+            splice_event_cancel_indicator: false,
+            reserved: 0x7F,
+            out_of_network_indicator: false,
+            program_splice_flag: false,
+            splice_immediate_flag: false,
+            event_id_compliance_flag: false,
+            flags_reserved: 0x07,
+            splice_time: None,
+            components: Vec::new(),
+            break_duration: None,
+            unique_program_id: 0,
+            avail_num: 0,
+            avails_expected: 0,
+        }
+    }
+}
+
+impl Default for ScheduledEvent {
+    fn default() -> Self {
+        Self {
+            splice_event_id: 0,
+            splice_event_cancel_indicator: false,
+            reserved: 0x7F,
+            out_of_network_indicator: false,
+            program_splice_flag: false,
+            flags_reserved: 0x1F,
+            utc_splice_time: None,
+            components: Vec::new(),
+            break_duration: None,
+            unique_program_id: 0,
+            avail_num: 0,
+            avails_expected: 0,
+        }
+    }
+}
+
+impl Default for SegmentationComponent {
+    fn default() -> Self {
+        Self { component_tag: 0, reserved: 0x7F, pts_offset: 0 }
+    }
+}
+
+impl Default for DeliveryRestrictions {
+    /// All-permissive values, as reported when delivery is not restricted.
+    fn default() -> Self {
+        Self {
+            web_delivery_allowed_flag: true,
+            no_regional_blackout_flag: true,
+            archive_allowed_flag: true,
+            device_restrictions: 3,
+        }
+    }
+}
+
+impl Default for SegmentationDescriptor {
+    fn default() -> Self {
+        Self {
+            identifier: CUEI,
+            segmentation_event_id: 0,
+            segmentation_event_cancel_indicator: false,
+            segmentation_event_id_compliance_indicator: false,
+            reserved: 0x3F,
+            program_segmentation_flag: true,
+            delivery_not_restricted_flag: true,
+            delivery_restrictions: DeliveryRestrictions::default(),
+            restriction_reserved: 0x1F,
+            components: Vec::new(),
+            segmentation_duration: None,
+            segmentation_upid_type: 0,
+            segmentation_upid: Vec::new(),
+            segmentation_type_id: 0,
+            segment_num: 0,
+            segments_expected: 0,
+            sub_segment_num: None,
+            sub_segments_expected: None,
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl SpliceDescriptor {
+    pub fn tag(&self) -> u8 {
+        match self {
+            SpliceDescriptor::Avail(_) => AVAIL_DESCRIPTOR,
+            SpliceDescriptor::Dtmf(_) => DTMF_DESCRIPTOR,
+            SpliceDescriptor::Segmentation(_) => SEGMENTATION_DESCRIPTOR,
+            SpliceDescriptor::Time(_) => TIME_DESCRIPTOR,
+            SpliceDescriptor::Audio(_) => AUDIO_DESCRIPTOR,
+            SpliceDescriptor::Other { tag, .. } => *tag,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.identifier() {
+            Some(id) if id != CUEI => "private_descriptor",
+            _ => descriptor_name(self.tag()),
+        }
+    }
+
+    /// The 32-bit identifier leading the body (None for an `Other` body shorter
+    /// than 4 bytes).
+    pub fn identifier(&self) -> Option<u32> {
+        match self {
+            SpliceDescriptor::Avail(d) => Some(d.identifier),
+            SpliceDescriptor::Dtmf(d) => Some(d.identifier),
+            SpliceDescriptor::Segmentation(d) => Some(d.identifier),
+            SpliceDescriptor::Time(d) => Some(d.identifier),
+            SpliceDescriptor::Audio(d) => Some(d.identifier),
+            SpliceDescriptor::Other { data, .. } => {
+                data.get(..4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            }
+        }
+    }
+}
+
+pub fn descriptor_name(tag: u8) -> &'static str {
+    match tag {
+        AVAIL_DESCRIPTOR => "avail_descriptor",
+        DTMF_DESCRIPTOR => "DTMF_descriptor",
+        SEGMENTATION_DESCRIPTOR => "segmentation_descriptor",
+        TIME_DESCRIPTOR => "time_descriptor",
+        AUDIO_DESCRIPTOR => "audio_descriptor",
+        _ => "unknown",
+    }
+}
+
+/// Split a MID (segmentation_upid_type 0x0D) into its child UPIDs: a
+/// sequence of (type, length, bytes). None when the body does not tile
+/// exactly into children.
+pub fn mid_children(upid: &[u8]) -> Option<Vec<(u8, &[u8])>> {
+    let mut out = Vec::new();
+    let mut at = 0;
+    while at < upid.len() {
+        let upid_type = *upid.get(at)?;
+        let len = *upid.get(at + 1)? as usize;
+        let child = upid.get(at + 2..at + 2 + len)?;
+        out.push((upid_type, child));
+        at += 2 + len;
+    }
+    Some(out)
+}
+
+// ============================================================================
+// DECODE
+// ============================================================================
+
+/// Fixed bytes before splice_command_type.
+const HEADER_LEN: usize = 13;
+
+impl SpliceInfoSection {
+    /// Parse a splice_info_section(). The section is delimited by section_length
+    /// when the buffer holds at least that many bytes (trailing bytes such as TS
+    /// stuffing are ignored), otherwise by the buffer itself. The splice command
+    /// is delimited by its own syntax, not by splice_command_length, so sections
+    /// from encoders that mis-state (or leave 0xFFF in) that field still decode;
+    /// only private and reserved command types, whose body has no syntax, need
+    /// a usable splice_command_length.
+    pub fn decode(input: &[u8]) -> Result<Self, DecodeError> {
+        if input.len() < HEADER_LEN + 4 {
+            return Err(DecodeError::new(0, format!("too short: {} bytes", input.len())));
+        }
+        if input[0] != TABLE_ID {
+            return Err(DecodeError::new(0, format!("table_id 0x{:02X} is not 0xFC", input[0])));
+        }
+
+        let mut r = BitReader::new(input);
+        let table_id = r.u8(8, "table_id")?;
+        let section_syntax_indicator = r.flag("section_syntax_indicator")?;
+        let private_indicator = r.flag("private_indicator")?;
+        let sap_type = r.u8(2, "sap_type")?;
+        let section_length = r.bits(12, "section_length")? as u16;
+        let declared_end = 3 + section_length as usize;
+        let bytes = if declared_end >= HEADER_LEN + 4 && declared_end <= input.len() {
+            &input[..declared_end]
+        } else {
+            input
+        };
+        let crc_at = bytes.len() - 4;
+        let crc_32 = u32::from_be_bytes([bytes[crc_at], bytes[crc_at + 1], bytes[crc_at + 2], bytes[crc_at + 3]]);
+
+        // Re-read the header against the delimited section so every later read
+        // is bounded by the CRC rather than by the caller's buffer.
+        let mut r = BitReader::new(&bytes[..crc_at]);
+        r.skip(24);
+        let protocol_version = r.u8(8, "protocol_version")?;
+        let encrypted_packet = r.flag("encrypted_packet")?;
+        let encryption_algorithm = r.u8(6, "encryption_algorithm")?;
+        let pts_adjustment = r.bits(33, "pts_adjustment")?;
+        let cw_index = r.u8(8, "cw_index")?;
+        let tier = r.bits(12, "tier")? as u16;
+        let splice_command_length = r.bits(12, "splice_command_length")? as u16;
+
+        let mut section = SpliceInfoSection {
+            table_id,
+            section_syntax_indicator,
+            private_indicator,
+            sap_type,
+            protocol_version,
+            encrypted_packet,
+            encryption_algorithm,
+            pts_adjustment,
+            cw_index,
+            tier,
+            legacy_command_length: splice_command_length == LEGACY_COMMAND_LENGTH,
+            splice_command: SpliceCommand::SpliceNull,
+            descriptor_loop_reserved: 0,
+            descriptors: Vec::new(),
+            alignment_stuffing: Vec::new(),
+            encrypted_payload: None,
+            wire: None,
+        };
+        let mut wire = WireInfo {
+            section_length,
+            splice_command_length,
+            splice_command_body_length: 0,
+            descriptor_loop_length: 0,
+            crc_32,
+            crc_valid: crc32(&bytes[..crc_at]) == crc_32,
+            input_length: input.len(),
+        };
+
+        if encrypted_packet {
+            section.encrypted_payload = Some(bytes[HEADER_LEN..crc_at].to_vec());
+            section.wire = Some(wire);
+            return Ok(section);
+        }
+
+        let command_type = r.u8(8, "splice_command_type")?;
+        let body_start = r.byte_pos();
+        section.splice_command = decode_command(&mut r, command_type, splice_command_length)?;
+        wire.splice_command_body_length = (r.byte_pos() - body_start) as u16;
+
+        let loop_word = r.bits(16, "descriptor_loop_length")? as u16;
+        section.descriptor_loop_reserved = (loop_word >> 10) as u8;
+        let loop_length = (loop_word & 0x03FF) as usize;
+        wire.descriptor_loop_length = loop_length as u16;
+
+        let loop_start = r.byte_pos();
+        let loop_end = loop_start + loop_length;
+        if loop_end > crc_at {
+            return Err(DecodeError::new(
+                loop_start,
+                format!("descriptor_loop_length {loop_length} overruns the section by {} bytes", loop_end - crc_at),
+            )
+            .with_kind(DecodeErrorKind::DescriptorLoopLength));
+        }
+        let mut at = loop_start;
+        while at < loop_end {
+            if at + 2 > loop_end {
+                return Err(DecodeError::new(at, "descriptor header overruns descriptor loop".into())
+                    .with_kind(DecodeErrorKind::DescriptorLength));
+            }
+            let tag = bytes[at];
+            let len = bytes[at + 1] as usize;
+            let body_end = at + 2 + len;
+            if body_end > loop_end {
+                return Err(DecodeError::new(
+                    at,
+                    format!("descriptor 0x{tag:02X} length {len} overruns descriptor loop by {} bytes", body_end - loop_end),
+                )
+                .with_kind(DecodeErrorKind::DescriptorLength));
+            }
+            section.descriptors.push(decode_descriptor(tag, &bytes[at + 2..body_end]));
+            at = body_end;
+        }
+        section.alignment_stuffing = bytes[loop_end..crc_at].to_vec();
+        section.wire = Some(wire);
+        Ok(section)
+    }
+}
+
+/// Where and why a section failed to decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub kind: DecodeErrorKind,
+    /// Byte offset into the section.
+    pub offset: usize,
+    pub message: String,
+}
+
+/// Which length field a [`DecodeError`] blames, when it is one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeErrorKind {
+    /// Truncated or otherwise malformed syntax.
+    Malformed,
+    /// An opaque command body with no usable splice_command_length.
+    SpliceCommandLength,
+    /// descriptor_loop_length runs past the CRC.
+    DescriptorLoopLength,
+    /// A descriptor header or descriptor_length runs past the descriptor loop.
+    DescriptorLength,
+}
+
+impl DecodeError {
+    fn new(offset: usize, message: String) -> Self {
+        Self { kind: DecodeErrorKind::Malformed, offset, message }
+    }
+
+    fn with_kind(mut self, kind: DecodeErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at byte {})", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for String {
+    fn from(e: DecodeError) -> Self {
+        e.to_string()
+    }
+}
+
+fn decode_command(r: &mut BitReader, command_type: u8, declared_len: u16) -> Result<SpliceCommand, DecodeError> {
+    // Opaque bodies can only be delimited by splice_command_length.
+    let opaque = |r: &mut BitReader, what: &str| -> Result<Vec<u8>, DecodeError> {
+        if declared_len == LEGACY_COMMAND_LENGTH {
+            return Err(DecodeError::new(r.byte_pos(), format!("{what} needs a splice_command_length, found 0xFFF"))
+                .with_kind(DecodeErrorKind::SpliceCommandLength));
+        }
+        r.bytes(declared_len as usize, what)
+    };
+    Ok(match command_type {
+        SPLICE_NULL => SpliceCommand::SpliceNull,
+        SPLICE_SCHEDULE => {
+            let count = r.u8(8, "splice_count")?;
+            let mut events = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                events.push(decode_scheduled_event(r)?);
+            }
+            SpliceCommand::SpliceSchedule(SpliceSchedule { events })
+        }
+        SPLICE_INSERT => SpliceCommand::SpliceInsert(decode_splice_insert(r)?),
+        TIME_SIGNAL => SpliceCommand::TimeSignal(decode_splice_time(r)?),
+        BANDWIDTH_RESERVATION => SpliceCommand::BandwidthReservation,
+        PRIVATE_COMMAND => {
+            let mut body = opaque(r, "private_command")?;
+            if body.len() < 4 {
+                return Err(DecodeError::new(r.byte_pos(), "private_command shorter than its identifier".into()));
+            }
+            let private_bytes = body.split_off(4);
+            SpliceCommand::PrivateCommand {
+                identifier: u32::from_be_bytes([body[0], body[1], body[2], body[3]]),
+                private_bytes,
+            }
+        }
+        other => SpliceCommand::Unknown {
+            command_type: other,
+            body: opaque(r, "reserved splice command")?,
+        },
+    })
+}
+
+fn decode_splice_time(r: &mut BitReader) -> Result<SpliceTime, DecodeError> {
+    if r.flag("time_specified_flag")? {
+        let reserved = r.u8(6, "splice_time reserved")?;
+        Ok(SpliceTime { pts_time: Some(r.bits(33, "pts_time")?), reserved })
+    } else {
+        Ok(SpliceTime { pts_time: None, reserved: r.u8(7, "splice_time reserved")? })
+    }
+}
+
+fn decode_break_duration(r: &mut BitReader) -> Result<BreakDuration, DecodeError> {
+    Ok(BreakDuration {
+        auto_return: r.flag("auto_return")?,
+        reserved: r.u8(6, "break_duration reserved")?,
+        duration: r.bits(33, "duration")?,
+    })
+}
+
+fn decode_splice_insert(r: &mut BitReader) -> Result<SpliceInsert, DecodeError> {
+    let mut si = SpliceInsert {
+        splice_event_id: r.bits(32, "splice_event_id")? as u32,
+        splice_event_cancel_indicator: r.flag("splice_event_cancel_indicator")?,
+        reserved: r.u8(7, "splice_insert reserved")?,
+        ..Default::default()
+    };
+    if si.splice_event_cancel_indicator {
+        return Ok(si);
+    }
+    si.out_of_network_indicator = r.flag("out_of_network_indicator")?;
+    si.program_splice_flag = r.flag("program_splice_flag")?;
+    let duration_flag = r.flag("duration_flag")?;
+    si.splice_immediate_flag = r.flag("splice_immediate_flag")?;
+    si.event_id_compliance_flag = r.flag("event_id_compliance_flag")?;
+    si.flags_reserved = r.u8(3, "splice_insert reserved")?;
+    if si.program_splice_flag {
+        if !si.splice_immediate_flag {
+            si.splice_time = Some(decode_splice_time(r)?);
+        }
+    } else {
+        let count = r.u8(8, "component_count")?;
+        for _ in 0..count {
+            let component_tag = r.u8(8, "component_tag")?;
+            let splice_time = if si.splice_immediate_flag { None } else { Some(decode_splice_time(r)?) };
+            si.components.push(SpliceInsertComponent { component_tag, splice_time });
+        }
+    }
+    if duration_flag {
+        si.break_duration = Some(decode_break_duration(r)?);
+    }
+    si.unique_program_id = r.bits(16, "unique_program_id")? as u16;
+    si.avail_num = r.u8(8, "avail_num")?;
+    si.avails_expected = r.u8(8, "avails_expected")?;
+    Ok(si)
+}
+
+fn decode_scheduled_event(r: &mut BitReader) -> Result<ScheduledEvent, DecodeError> {
+    let mut ev = ScheduledEvent {
+        splice_event_id: r.bits(32, "splice_event_id")? as u32,
+        splice_event_cancel_indicator: r.flag("splice_event_cancel_indicator")?,
+        reserved: r.u8(7, "splice_schedule reserved")?,
+        ..Default::default()
+    };
+    if ev.splice_event_cancel_indicator {
+        return Ok(ev);
+    }
+    ev.out_of_network_indicator = r.flag("out_of_network_indicator")?;
+    ev.program_splice_flag = r.flag("program_splice_flag")?;
+    let duration_flag = r.flag("duration_flag")?;
+    ev.flags_reserved = r.u8(5, "splice_schedule reserved")?;
+    if ev.program_splice_flag {
+        ev.utc_splice_time = Some(r.bits(32, "utc_splice_time")? as u32);
+    } else {
+        let count = r.u8(8, "component_count")?;
+        for _ in 0..count {
+            let tag = r.u8(8, "component_tag")?;
+            ev.components.push((tag, r.bits(32, "utc_splice_time")? as u32));
+        }
+    }
+    if duration_flag {
+        ev.break_duration = Some(decode_break_duration(r)?);
+    }
+    ev.unique_program_id = r.bits(16, "unique_program_id")? as u16;
+    ev.avail_num = r.u8(8, "avail_num")?;
+    ev.avails_expected = r.u8(8, "avails_expected")?;
+    Ok(ev)
+}
+
+/// Parse one descriptor body. Tags are only meaningful under the CUEI
+/// identifier; a private descriptor (any other identifier), or a body that does
+/// not fit its tag's syntax, is kept as `Other` so the section still decodes
+/// (and re-encodes unchanged).
+fn decode_descriptor(tag: u8, body: &[u8]) -> SpliceDescriptor {
+    if body.get(..4).is_some_and(|id| id != CUEI.to_be_bytes()) {
+        return SpliceDescriptor::Other { tag, data: body.to_vec() };
+    }
+    let typed = match tag {
+        AVAIL_DESCRIPTOR => decode_avail(body).map(SpliceDescriptor::Avail),
+        DTMF_DESCRIPTOR => decode_dtmf(body).map(SpliceDescriptor::Dtmf),
+        SEGMENTATION_DESCRIPTOR => decode_segmentation(body).map(SpliceDescriptor::Segmentation),
+        TIME_DESCRIPTOR => decode_time(body).map(SpliceDescriptor::Time),
+        AUDIO_DESCRIPTOR => decode_audio(body).map(SpliceDescriptor::Audio),
+        _ => return SpliceDescriptor::Other { tag, data: body.to_vec() },
+    };
+    typed.unwrap_or_else(|_| SpliceDescriptor::Other { tag, data: body.to_vec() })
+}
+
+fn decode_avail(body: &[u8]) -> Result<AvailDescriptor, DecodeError> {
+    let mut r = BitReader::new(body);
+    Ok(AvailDescriptor {
+        identifier: r.bits(32, "identifier")? as u32,
+        provider_avail_id: r.bits(32, "provider_avail_id")? as u32,
+        extra: r.rest(),
+    })
+}
+
+fn decode_dtmf(body: &[u8]) -> Result<DtmfDescriptor, DecodeError> {
+    let mut r = BitReader::new(body);
+    let identifier = r.bits(32, "identifier")? as u32;
+    let preroll = r.u8(8, "preroll")?;
+    let count = r.u8(3, "dtmf_count")? as usize;
+    let reserved = r.u8(5, "DTMF reserved")?;
+    Ok(DtmfDescriptor { identifier, preroll, reserved, dtmf_chars: r.bytes(count, "DTMF_char")?, extra: r.rest() })
+}
+
+fn decode_time(body: &[u8]) -> Result<TimeDescriptor, DecodeError> {
+    let mut r = BitReader::new(body);
+    Ok(TimeDescriptor {
+        identifier: r.bits(32, "identifier")? as u32,
+        tai_seconds: r.bits(48, "TAI_seconds")?,
+        tai_ns: r.bits(32, "TAI_ns")? as u32,
+        utc_offset: r.bits(16, "UTC_offset")? as u16,
+        extra: r.rest(),
+    })
+}
+
+fn decode_audio(body: &[u8]) -> Result<AudioDescriptor, DecodeError> {
+    let mut r = BitReader::new(body);
+    let identifier = r.bits(32, "identifier")? as u32;
+    let count = r.u8(4, "audio_count")?;
+    let reserved = r.u8(4, "audio reserved")?;
+    let mut components = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        components.push(AudioComponent {
+            component_tag: r.u8(8, "component_tag")?,
+            iso_code: r.bits(24, "ISO_code")? as u32,
+            bit_stream_mode: r.u8(3, "Bit_Stream_Mode")?,
+            num_channels: r.u8(4, "Num_Channels")?,
+            full_srvc_audio: r.flag("Full_Srvc_Audio")?,
+        });
+    }
+    Ok(AudioDescriptor { identifier, reserved, components, extra: r.rest() })
+}
+
+fn decode_segmentation(body: &[u8]) -> Result<SegmentationDescriptor, DecodeError> {
+    let mut r = BitReader::new(body);
+    let mut d = SegmentationDescriptor {
+        identifier: r.bits(32, "identifier")? as u32,
+        segmentation_event_id: r.bits(32, "segmentation_event_id")? as u32,
+        segmentation_event_cancel_indicator: r.flag("segmentation_event_cancel_indicator")?,
+        segmentation_event_id_compliance_indicator: r.flag("segmentation_event_id_compliance_indicator")?,
+        reserved: r.u8(6, "segmentation reserved")?,
+        ..Default::default()
+    };
+    if d.segmentation_event_cancel_indicator {
+        d.extra = r.rest();
+        return Ok(d);
+    }
+    d.program_segmentation_flag = r.flag("program_segmentation_flag")?;
+    let duration_flag = r.flag("segmentation_duration_flag")?;
+    d.delivery_not_restricted_flag = r.flag("delivery_not_restricted_flag")?;
+    if d.delivery_not_restricted_flag {
+        d.restriction_reserved = r.u8(5, "segmentation reserved")?;
+    } else {
+        d.delivery_restrictions = DeliveryRestrictions {
+            web_delivery_allowed_flag: r.flag("web_delivery_allowed_flag")?,
+            no_regional_blackout_flag: r.flag("no_regional_blackout_flag")?,
+            archive_allowed_flag: r.flag("archive_allowed_flag")?,
+            device_restrictions: r.u8(2, "device_restrictions")?,
+        };
+    }
+    if !d.program_segmentation_flag {
+        let count = r.u8(8, "component_count")?;
+        for _ in 0..count {
+            d.components.push(SegmentationComponent {
+                component_tag: r.u8(8, "component_tag")?,
+                reserved: r.u8(7, "component reserved")?,
+                pts_offset: r.bits(33, "pts_offset")?,
+            });
+        }
+    }
+    if duration_flag {
+        d.segmentation_duration = Some(r.bits(40, "segmentation_duration")?);
+    }
+    d.segmentation_upid_type = r.u8(8, "segmentation_upid_type")?;
+    let upid_len = r.u8(8, "segmentation_upid_length")? as usize;
+    d.segmentation_upid = r.bytes(upid_len, "segmentation_upid")?;
+    d.segmentation_type_id = r.u8(8, "segmentation_type_id")?;
+    d.segment_num = r.u8(8, "segment_num")?;
+    d.segments_expected = r.u8(8, "segments_expected")?;
+    if has_sub_segments(d.segmentation_type_id) && r.remaining_bytes() >= 2 {
+        d.sub_segment_num = Some(r.u8(8, "sub_segment_num")?);
+        d.sub_segments_expected = Some(r.u8(8, "sub_segments_expected")?);
+    }
+    d.extra = r.rest();
+    Ok(d)
+}
+
+// ============================================================================
+// ENCODE
+// ============================================================================
+
+impl SpliceInfoSection {
+    /// Serialize the section, recomputing every length field and the CRC-32.
+    /// Fails only when a value cannot be represented (a descriptor body over
+    /// 255 bytes, a descriptor loop over 1023, a section over 4093).
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let mut w = BitWriter::new();
+        w.put(self.table_id as u64, 8);
+        w.flag(self.section_syntax_indicator);
+        w.flag(self.private_indicator);
+        w.put(self.sap_type as u64, 2);
+        w.put(0, 12); // section_length, patched below
+        w.put(self.protocol_version as u64, 8);
+        w.flag(self.encrypted_packet);
+        w.put(self.encryption_algorithm as u64, 6);
+        w.put(self.pts_adjustment, 33);
+        w.put(self.cw_index as u64, 8);
+        w.put(self.tier as u64, 12);
+
+        if let Some(payload) = self.encrypted_payload.as_ref() {
+            // The command length is inside the clear header but describes the
+            // encrypted command; it can only be taken from the wire.
+            let scl = match self.wire {
+                Some(wi) => wi.splice_command_length,
+                None if self.legacy_command_length => LEGACY_COMMAND_LENGTH,
+                None => return Err("encrypted section without a known splice_command_length".into()),
+            };
+            w.put(scl as u64, 12);
+            w.extend(payload);
+        } else {
+            let body = self.splice_command.encode_body()?;
+            let scl = if self.legacy_command_length { LEGACY_COMMAND_LENGTH as usize } else { body.len() };
+            if scl > LEGACY_COMMAND_LENGTH as usize {
+                return Err(format!("splice command body of {} bytes exceeds splice_command_length", body.len()));
+            }
+            w.put(scl as u64, 12);
+            w.put(self.splice_command.command_type() as u64, 8);
+            w.extend(&body);
+
+            let mut descriptors = Vec::new();
+            for d in &self.descriptors {
+                let db = d.encode_body()?;
+                if db.len() > 255 {
+                    return Err(format!("{} body of {} bytes exceeds 255", d.name(), db.len()));
+                }
+                descriptors.push(d.tag());
+                descriptors.push(db.len() as u8);
+                descriptors.extend_from_slice(&db);
+            }
+            if descriptors.len() > 0x03FF {
+                return Err(format!("descriptor loop of {} bytes exceeds 1023", descriptors.len()));
+            }
+            w.put(self.descriptor_loop_reserved as u64, 6);
+            w.put(descriptors.len() as u64, 10);
+            w.extend(&descriptors);
+            w.extend(&self.alignment_stuffing);
+        }
+
+        let mut out = w.into_bytes();
+        // section_length counts everything after itself, CRC-32 included.
+        let section_length = out.len() + 4 - 3;
+        if section_length > 0x0FFD {
+            return Err(format!("section of {} bytes exceeds 4096", out.len() + 4));
+        }
+        out[1] = (out[1] & 0xF0) | ((section_length >> 8) as u8 & 0x0F);
+        out[2] = section_length as u8;
+        let crc = crc32(&out);
+        out.extend_from_slice(&crc.to_be_bytes());
+        Ok(out)
+    }
+}
+
+impl SpliceCommand {
+    /// The splice_command() body after splice_command_type.
+    pub fn encode_body(&self) -> Result<Vec<u8>, String> {
+        let mut w = BitWriter::new();
+        match self {
+            SpliceCommand::SpliceNull | SpliceCommand::BandwidthReservation => {}
+            SpliceCommand::SpliceSchedule(s) => {
+                if s.events.len() > 255 {
+                    return Err(format!("splice_schedule with {} events exceeds 255", s.events.len()));
+                }
+                w.put(s.events.len() as u64, 8);
+                for ev in &s.events {
+                    encode_scheduled_event(&mut w, ev)?;
+                }
+            }
+            SpliceCommand::SpliceInsert(si) => encode_splice_insert(&mut w, si)?,
+            SpliceCommand::TimeSignal(t) => encode_splice_time(&mut w, t),
+            SpliceCommand::PrivateCommand { identifier, private_bytes } => {
+                w.put(*identifier as u64, 32);
+                w.extend(private_bytes);
+            }
+            SpliceCommand::Unknown { body, .. } => w.extend(body),
+        }
+        Ok(w.into_bytes())
+    }
+}
+
+fn encode_splice_time(w: &mut BitWriter, t: &SpliceTime) {
+    match t.pts_time {
+        Some(pts) => {
+            w.flag(true);
+            w.put(t.reserved as u64, 6);
+            w.put(pts, 33);
+        }
+        None => {
+            w.flag(false);
+            w.put(t.reserved as u64, 7);
+        }
+    }
+}
+
+fn encode_break_duration(w: &mut BitWriter, b: &BreakDuration) {
+    w.flag(b.auto_return);
+    w.put(b.reserved as u64, 6);
+    w.put(b.duration, 33);
+}
+
+fn encode_splice_insert(w: &mut BitWriter, si: &SpliceInsert) -> Result<(), String> {
+    w.put(si.splice_event_id as u64, 32);
+    w.flag(si.splice_event_cancel_indicator);
+    w.put(si.reserved as u64, 7);
+    if si.splice_event_cancel_indicator {
+        return Ok(());
+    }
+    w.flag(si.out_of_network_indicator);
+    w.flag(si.program_splice_flag);
+    w.flag(si.break_duration.is_some());
+    w.flag(si.splice_immediate_flag);
+    w.flag(si.event_id_compliance_flag);
+    w.put(si.flags_reserved as u64, 3);
+    if si.program_splice_flag {
+        if !si.splice_immediate_flag {
+            encode_splice_time(w, &si.splice_time.unwrap_or_default());
+        }
+    } else {
+        if si.components.len() > 255 {
+            return Err(format!("splice_insert with {} components exceeds 255", si.components.len()));
+        }
+        w.put(si.components.len() as u64, 8);
+        for c in &si.components {
+            w.put(c.component_tag as u64, 8);
+            if !si.splice_immediate_flag {
+                encode_splice_time(w, &c.splice_time.unwrap_or_default());
+            }
+        }
+    }
+    if let Some(b) = si.break_duration.as_ref() {
+        encode_break_duration(w, b);
+    }
+    w.put(si.unique_program_id as u64, 16);
+    w.put(si.avail_num as u64, 8);
+    w.put(si.avails_expected as u64, 8);
+    Ok(())
+}
+
+fn encode_scheduled_event(w: &mut BitWriter, ev: &ScheduledEvent) -> Result<(), String> {
+    w.put(ev.splice_event_id as u64, 32);
+    w.flag(ev.splice_event_cancel_indicator);
+    w.put(ev.reserved as u64, 7);
+    if ev.splice_event_cancel_indicator {
+        return Ok(());
+    }
+    w.flag(ev.out_of_network_indicator);
+    w.flag(ev.program_splice_flag);
+    w.flag(ev.break_duration.is_some());
+    w.put(ev.flags_reserved as u64, 5);
+    if ev.program_splice_flag {
+        w.put(ev.utc_splice_time.unwrap_or(0) as u64, 32);
+    } else {
+        if ev.components.len() > 255 {
+            return Err(format!("scheduled event with {} components exceeds 255", ev.components.len()));
+        }
+        w.put(ev.components.len() as u64, 8);
+        for (tag, utc) in &ev.components {
+            w.put(*tag as u64, 8);
+            w.put(*utc as u64, 32);
+        }
+    }
+    if let Some(b) = ev.break_duration.as_ref() {
+        encode_break_duration(w, b);
+    }
+    w.put(ev.unique_program_id as u64, 16);
+    w.put(ev.avail_num as u64, 8);
+    w.put(ev.avails_expected as u64, 8);
+    Ok(())
+}
+
+impl SpliceDescriptor {
+    /// The descriptor body after splice_descriptor_tag and descriptor_length.
+    pub fn encode_body(&self) -> Result<Vec<u8>, String> {
+        let mut w = BitWriter::new();
+        match self {
+            SpliceDescriptor::Avail(d) => {
+                w.put(d.identifier as u64, 32);
+                w.put(d.provider_avail_id as u64, 32);
+                w.extend(&d.extra);
+            }
+            SpliceDescriptor::Dtmf(d) => {
+                w.put(d.identifier as u64, 32);
+                w.put(d.preroll as u64, 8);
+                if d.dtmf_chars.len() > 7 {
+                    return Err(format!("DTMF_descriptor with {} chars exceeds 7", d.dtmf_chars.len()));
+                }
+                w.put(d.dtmf_chars.len() as u64, 3);
+                w.put(d.reserved as u64, 5);
+                w.extend(&d.dtmf_chars);
+                w.extend(&d.extra);
+            }
+            SpliceDescriptor::Segmentation(d) => encode_segmentation(&mut w, d)?,
+            SpliceDescriptor::Time(d) => {
+                w.put(d.identifier as u64, 32);
+                w.put(d.tai_seconds, 48);
+                w.put(d.tai_ns as u64, 32);
+                w.put(d.utc_offset as u64, 16);
+                w.extend(&d.extra);
+            }
+            SpliceDescriptor::Audio(d) => {
+                w.put(d.identifier as u64, 32);
+                if d.components.len() > 15 {
+                    return Err(format!("audio_descriptor with {} components exceeds 15", d.components.len()));
+                }
+                w.put(d.components.len() as u64, 4);
+                w.put(d.reserved as u64, 4);
+                for c in &d.components {
+                    w.put(c.component_tag as u64, 8);
+                    w.put(c.iso_code as u64, 24);
+                    w.put(c.bit_stream_mode as u64, 3);
+                    w.put(c.num_channels as u64, 4);
+                    w.flag(c.full_srvc_audio);
+                }
+                w.extend(&d.extra);
+            }
+            SpliceDescriptor::Other { data, .. } => w.extend(data),
+        }
+        Ok(w.into_bytes())
+    }
+}
+
+fn encode_segmentation(w: &mut BitWriter, d: &SegmentationDescriptor) -> Result<(), String> {
+    w.put(d.identifier as u64, 32);
+    w.put(d.segmentation_event_id as u64, 32);
+    w.flag(d.segmentation_event_cancel_indicator);
+    w.flag(d.segmentation_event_id_compliance_indicator);
+    w.put(d.reserved as u64, 6);
+    if !d.segmentation_event_cancel_indicator {
+        w.flag(d.program_segmentation_flag);
+        w.flag(d.segmentation_duration.is_some());
+        w.flag(d.delivery_not_restricted_flag);
+        if d.delivery_not_restricted_flag {
+            w.put(d.restriction_reserved as u64, 5);
+        } else {
+            let r = &d.delivery_restrictions;
+            w.flag(r.web_delivery_allowed_flag);
+            w.flag(r.no_regional_blackout_flag);
+            w.flag(r.archive_allowed_flag);
+            w.put(r.device_restrictions as u64, 2);
+        }
+        if !d.program_segmentation_flag {
+            if d.components.len() > 255 {
+                return Err(format!("segmentation_descriptor with {} components exceeds 255", d.components.len()));
+            }
+            w.put(d.components.len() as u64, 8);
+            for c in &d.components {
+                w.put(c.component_tag as u64, 8);
+                w.put(c.reserved as u64, 7);
+                w.put(c.pts_offset, 33);
+            }
+        }
+        if let Some(dur) = d.segmentation_duration {
+            w.put(dur, 40);
+        }
+        if d.segmentation_upid.len() > 255 {
+            return Err(format!("segmentation_upid of {} bytes exceeds 255", d.segmentation_upid.len()));
+        }
+        w.put(d.segmentation_upid_type as u64, 8);
+        w.put(d.segmentation_upid.len() as u64, 8);
+        w.extend(&d.segmentation_upid);
+        w.put(d.segmentation_type_id as u64, 8);
+        w.put(d.segment_num as u64, 8);
+        w.put(d.segments_expected as u64, 8);
+        if let (Some(n), Some(e)) = (d.sub_segment_num, d.sub_segments_expected) {
+            w.put(n as u64, 8);
+            w.put(e as u64, 8);
+        }
+    }
+    w.extend(&d.extra);
+    Ok(())
+}
+
+// ============================================================================
+// BIT I/O
+// ============================================================================
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    bitpos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, bitpos: 0 }
+    }
+
+    fn bits(&mut self, nbits: u32, field: &str) -> Result<u64, DecodeError> {
+        if self.bitpos + nbits as usize > self.data.len() * 8 {
+            return Err(DecodeError::new(self.bitpos / 8, format!("truncated reading {field}")));
+        }
+        let mut v = 0u64;
+        for _ in 0..nbits {
+            let byte = self.data[self.bitpos / 8];
+            v = (v << 1) | ((byte >> (7 - (self.bitpos % 8))) & 1) as u64;
+            self.bitpos += 1;
+        }
+        Ok(v)
+    }
+
+    fn u8(&mut self, nbits: u32, field: &str) -> Result<u8, DecodeError> {
+        Ok(self.bits(nbits, field)? as u8)
+    }
+
+    fn flag(&mut self, field: &str) -> Result<bool, DecodeError> {
+        Ok(self.bits(1, field)? == 1)
+    }
+
+    fn skip(&mut self, nbits: usize) {
+        self.bitpos += nbits;
+    }
+
+    /// `n` whole bytes; every caller is byte-aligned by the SCTE-35 syntax.
+    fn bytes(&mut self, n: usize, field: &str) -> Result<Vec<u8>, DecodeError> {
+        let start = self.bitpos / 8;
+        if start + n > self.data.len() {
+            return Err(DecodeError::new(start, format!("truncated reading {field}")));
+        }
+        self.bitpos += n * 8;
+        Ok(self.data[start..start + n].to_vec())
+    }
+
+    fn byte_pos(&self) -> usize {
+        self.bitpos / 8
+    }
+
+    fn remaining_bytes(&self) -> usize {
+        self.data.len().saturating_sub(self.bitpos.div_ceil(8))
+    }
+
+    /// Everything from the next whole byte to the end.
+    fn rest(&mut self) -> Vec<u8> {
+        let start = self.bitpos.div_ceil(8).min(self.data.len());
+        self.bitpos = self.data.len() * 8;
+        self.data[start..].to_vec()
+    }
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    bitpos: usize,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self { bytes: Vec::new(), bitpos: 0 }
+    }
+
+    fn put(&mut self, val: u64, nbits: u32) {
+        for i in (0..nbits).rev() {
+            if self.bitpos.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = ((val >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - (self.bitpos % 8));
+            self.bitpos += 1;
+        }
+    }
+
+    fn flag(&mut self, v: bool) {
+        self.put(v as u64, 1);
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        debug_assert!(self.bitpos.is_multiple_of(8));
+        self.bytes.extend_from_slice(bytes);
+        self.bitpos += bytes.len() * 8;
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD as B64;
+    use base64::Engine;
+
+    fn round_trip(bytes: &[u8]) -> SpliceInfoSection {
+        let s = SpliceInfoSection::decode(bytes).expect("decode");
+        assert_eq!(s.encode().expect("encode"), bytes, "byte-for-byte round trip");
+        s
+    }
+
+    #[test]
+    fn real_splice_insert_round_trips() {
+        // The Tools API sample cue; its stored CRC-32 is not valid, so everything
+        // but the CRC must come back unchanged.
+        let b = B64.decode("/DAlAAAAAAAAAP/wFAUAAAABf+/+ANSrgP4AKTLgAAEBAQAArQrwxg==").unwrap();
+        let s = SpliceInfoSection::decode(&b).unwrap();
+        let n = b.len();
+        assert_eq!(s.encode().unwrap()[..n - 4], b[..n - 4]);
+        assert!(!s.wire.unwrap().crc_valid);
+        round_trip(&s.encode().unwrap());
+        assert_eq!(s.wire.unwrap().splice_command_length, 20);
+        let SpliceCommand::SpliceInsert(si) = &s.splice_command else { panic!("splice_insert") };
+        assert!(si.out_of_network_indicator);
+        assert_eq!(si.break_duration.unwrap().duration, 0x0029_32E0);
+        assert_eq!(s.splice_command.pts_time(), Some(0x00D4_AB80));
+    }
+
+    #[test]
+    fn every_command_and_descriptor_round_trips() {
+        let seg = SegmentationDescriptor {
+            segmentation_event_id: 0x4800_0008,
+            program_segmentation_flag: false,
+            delivery_not_restricted_flag: false,
+            delivery_restrictions: DeliveryRestrictions {
+                web_delivery_allowed_flag: false,
+                no_regional_blackout_flag: true,
+                archive_allowed_flag: false,
+                device_restrictions: 2,
+            },
+            components: vec![SegmentationComponent { component_tag: 7, reserved: 0x7F, pts_offset: 1234 }],
+            segmentation_duration: Some(2_700_000),
+            segmentation_upid_type: 0x0C,
+            segmentation_upid: b"MPU-DATA".to_vec(),
+            segmentation_type_id: 0x34,
+            segment_num: 1,
+            segments_expected: 2,
+            sub_segment_num: Some(1),
+            sub_segments_expected: Some(4),
+            ..Default::default()
+        };
+        let descriptors = vec![
+            SpliceDescriptor::Avail(AvailDescriptor { identifier: CUEI, provider_avail_id: 309, extra: vec![] }),
+            SpliceDescriptor::Dtmf(DtmfDescriptor {
+                identifier: CUEI,
+                preroll: 177,
+                reserved: 0x1F,
+                dtmf_chars: b"121#".to_vec(),
+                extra: vec![],
+            }),
+            SpliceDescriptor::Segmentation(seg),
+            SpliceDescriptor::Segmentation(SegmentationDescriptor {
+                segmentation_event_id: 9,
+                segmentation_event_cancel_indicator: true,
+                reserved: 0x3F,
+                ..Default::default()
+            }),
+            SpliceDescriptor::Time(TimeDescriptor {
+                identifier: CUEI,
+                tai_seconds: 0x0000_6000_0000,
+                tai_ns: 5,
+                utc_offset: 37,
+                extra: vec![],
+            }),
+            SpliceDescriptor::Audio(AudioDescriptor {
+                identifier: CUEI,
+                reserved: 0xF,
+                components: vec![AudioComponent {
+                    component_tag: 1,
+                    iso_code: 0x656E67,
+                    bit_stream_mode: 2,
+                    num_channels: 5,
+                    full_srvc_audio: true,
+                }],
+                extra: vec![],
+            }),
+            SpliceDescriptor::Other { tag: 0xF0, data: vec![0x41, 0x42, 0x43, 0x44, 1, 2, 3] },
+        ];
+        let commands = vec![
+            SpliceCommand::SpliceNull,
+            SpliceCommand::SpliceSchedule(SpliceSchedule {
+                events: vec![
+                    ScheduledEvent {
+                        splice_event_id: 1,
+                        out_of_network_indicator: true,
+                        program_splice_flag: true,
+                        flags_reserved: 0x1F,
+                        utc_splice_time: Some(1_400_000_000),
+                        break_duration: Some(BreakDuration { auto_return: true, reserved: 0x3F, duration: 5_400_000 }),
+                        unique_program_id: 3,
+                        ..Default::default()
+                    },
+                    ScheduledEvent { splice_event_id: 2, splice_event_cancel_indicator: true, ..Default::default() },
+                    ScheduledEvent { splice_event_id: 3, components: vec![(1, 10), (2, 20)], ..Default::default() },
+                ],
+            }),
+            SpliceCommand::SpliceInsert(SpliceInsert {
+                splice_event_id: 7,
+                out_of_network_indicator: true,
+                program_splice_flag: false,
+                event_id_compliance_flag: true,
+                components: vec![
+                    SpliceInsertComponent { component_tag: 1, splice_time: Some(SpliceTime::at(90_000)) },
+                    SpliceInsertComponent { component_tag: 2, splice_time: Some(SpliceTime::immediate()) },
+                ],
+                break_duration: Some(BreakDuration { auto_return: false, reserved: 0, duration: 1 }),
+                unique_program_id: 0xBEEF,
+                avail_num: 1,
+                avails_expected: 2,
+                ..Default::default()
+            }),
+            SpliceCommand::SpliceInsert(SpliceInsert {
+                splice_event_id: 8,
+                splice_event_cancel_indicator: true,
+                reserved: 0x7F,
+                ..Default::default()
+            }),
+            SpliceCommand::TimeSignal(SpliceTime { pts_time: Some(0x1_FFFF_FFFF), reserved: 0x3F }),
+            SpliceCommand::BandwidthReservation,
+            SpliceCommand::PrivateCommand { identifier: 0x5449_4D45, private_bytes: vec![9, 8, 7] },
+            SpliceCommand::Unknown { command_type: 0x42, body: vec![1, 2, 3, 4] },
+        ];
+        for command in commands {
+            let mut s = SpliceInfoSection::new(command);
+            s.pts_adjustment = 0x1_0000_0001;
+            s.descriptor_loop_reserved = 0x3F;
+            s.descriptors = descriptors.clone();
+            let bytes = s.encode().expect("encode");
+            let back = round_trip(&bytes);
+            assert_eq!(back.splice_command, s.splice_command);
+            assert_eq!(back.descriptors, s.descriptors);
+            let wire = back.wire.unwrap();
+            assert!(wire.crc_valid);
+            assert_eq!(wire.splice_command_length, wire.splice_command_body_length);
+        }
+    }
+
+    #[test]
+    fn encrypted_payload_and_stuffing_are_kept_opaque() {
+        let mut s = SpliceInfoSection::new(SpliceCommand::SpliceNull);
+        s.alignment_stuffing = vec![0xFF, 0xFF];
+        let b = s.encode().unwrap();
+        assert_eq!(round_trip(&b).alignment_stuffing, vec![0xFF, 0xFF]);
+
+        // Flip encrypted_packet and fix the CRC: the body is no longer parsed.
+        let mut e = b.clone();
+        e[4] |= 0x80;
+        let n = e.len();
+        let crc = crc32(&e[..n - 4]);
+        e[n - 4..].copy_from_slice(&crc.to_be_bytes());
+        let d = round_trip(&e);
+        assert!(d.encrypted_packet);
+        assert_eq!(d.encrypted_payload.as_deref(), Some(&b[13..n - 4]));
+
+        // A legacy splice_command_length of 0xFFF survives the round trip.
+        let mut legacy = SpliceInfoSection::new(SpliceCommand::TimeSignal(SpliceTime::at(42)));
+        legacy.legacy_command_length = true;
+        let l = round_trip(&legacy.encode().unwrap());
+        assert_eq!(l.wire.unwrap().splice_command_length, LEGACY_COMMAND_LENGTH);
+        assert_eq!(l.splice_command.pts_time(), Some(42));
+    }
+
+    #[test]
+    fn trailing_bytes_past_section_length_are_ignored_and_bad_crc_reported() {
+        let b = SpliceInfoSection::new(SpliceCommand::TimeSignal(SpliceTime::immediate())).encode().unwrap();
+        let mut padded = b.clone();
+        padded.extend_from_slice(&[0xFF; 5]);
+        let s = SpliceInfoSection::decode(&padded).unwrap();
+        assert_eq!(s.encode().unwrap(), b);
+        assert_eq!(s.wire.unwrap().input_length, b.len() + 5);
+
+        let mut corrupt = b.clone();
+        let n = corrupt.len();
+        corrupt[n - 1] ^= 0x01;
+        let s = SpliceInfoSection::decode(&corrupt).unwrap();
+        assert!(!s.wire.unwrap().crc_valid);
+        assert_eq!(s.encode().unwrap(), b, "encode repairs the CRC");
+    }
+
+    #[test]
+    fn malformed_sections_are_rejected_with_an_offset() {
+        let mut s = SpliceInfoSection::new(SpliceCommand::SpliceNull);
+        s.descriptors.push(SpliceDescriptor::Avail(AvailDescriptor { identifier: CUEI, provider_avail_id: 1, extra: vec![] }));
+        let mut b = s.encode().unwrap();
+        b[15] = 0x0C; // descriptor_loop_length 12 > the 10 bytes present
+        let err = SpliceInfoSection::decode(&b).unwrap_err();
+        assert!(err.message.contains("overruns"), "{err}");
+        assert_eq!(err.offset, 16);
+
+        assert!(SpliceInfoSection::decode(&[0xFC, 0x30]).is_err());
+        assert!(SpliceInfoSection::decode(&[0x00; 20]).is_err());
+    }
+
+    #[test]
+    fn unparseable_descriptor_body_is_kept_as_other() {
+        let mut s = SpliceInfoSection::new(SpliceCommand::SpliceNull);
+        s.descriptors.push(SpliceDescriptor::Other { tag: SEGMENTATION_DESCRIPTOR, data: vec![0x43, 0x55, 0x45, 0x49, 0, 0] });
+        let b = s.encode().unwrap();
+        let back = round_trip(&b);
+        assert!(matches!(back.descriptors[0], SpliceDescriptor::Other { tag: 0x02, .. }));
+        assert_eq!(back.descriptors[0].identifier(), Some(CUEI));
+    }
+
+    #[test]
+    fn utc_splice_time_is_seconds_since_gps_epoch() {
+        assert_eq!(utc_splice_time_to_unix(0), GPS_EPOCH_UNIX);
+        assert_eq!(unix_to_utc_splice_time(GPS_EPOCH_UNIX + 86_400), Some(86_400));
+        assert_eq!(unix_to_utc_splice_time(0), None);
+    }
+
+    #[test]
+    fn private_descriptors_and_mid_children() {
+        // A segmentation tag under a non-CUEI identifier is private, not parsed.
+        let mut s = SpliceInfoSection::new(SpliceCommand::SpliceNull);
+        s.descriptors.push(SpliceDescriptor::Other { tag: SEGMENTATION_DESCRIPTOR, data: b"ACME\x01\x02".to_vec() });
+        let back = round_trip(&s.encode().unwrap());
+        assert_eq!(back.descriptors[0].name(), "private_descriptor");
+
+        let mid = [0x03, 0x02, b'A', b'B', 0x0F, 0x01, b'x'];
+        assert_eq!(mid_children(&mid), Some(vec![(0x03, &b"AB"[..]), (0x0F, &b"x"[..])]));
+        assert_eq!(mid_children(&mid[..5]), None, "truncated child");
+    }
+}
@@ -0,0 +1,726 @@
+// src/splice_lint.rs
+//
+// SCTE-35 conformance linter. Goes past the CRC: every finding carries a
+// severity, a stable code and the part of the section it concerns.
+//
+// Structural checks (ANSI/SCTE 35 2022 §9): section_syntax_indicator /
+// private_indicator, section_length against the bytes supplied, CRC-32,
+// protocol_version, splice_command_length against the parsed command body,
+// descriptor_loop_length and descriptor_length overruns, reserved bits (which
+// the standard sets to 1), descriptor identifiers ("CUEI" vs private), bytes a
+// descriptor carries past its syntax, and each segmentation_upid's length
+// against the length its type defines (Table 21), MID children included.
+//
+// Semantic checks in the spirit of SCTE 67: start types that should declare a
+// duration, segment/sub-segment numbering, segmentation types that do not fit
+// the splice command (an end type on a splice_insert OUT, a start type on an
+// IN, segmentation on a splice_null), a time_signal with nothing to signal and
+// a splice_insert OUT without a break_duration. An end type without a
+// preceding start needs the cues that came before it, so it is only reported
+// through a `LintState` that is fed a channel's cues in order.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::splice_info::{
+    self, has_sub_segments, DecodeErrorKind, SegmentationDescriptor, SpliceCommand, SpliceDescriptor, SpliceInfoSection,
+    SpliceTime, CUEI,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Stable, machine-readable check name (e.g. `splice_command_length`).
+    pub code: &'static str,
+    /// Where in the section: `header`, `splice_command`, `descriptor[1]`, ...
+    pub location: String,
+    pub message: String,
+}
+
+/// True when no finding is an error.
+pub fn is_conformant(findings: &[Finding]) -> bool {
+    findings.iter().all(|f| f.severity != Severity::Error)
+}
+
+/// Lint one section on its own.
+pub fn lint(input: &[u8]) -> Vec<Finding> {
+    lint_section(input).0
+}
+
+/// Open segmentation starts of one channel, so that an end type can be checked
+/// against the cues before it. Feed every cue of the channel, in order.
+#[derive(Debug, Default)]
+pub struct LintState {
+    /// (start segmentation_type_id, segmentation_event_id)
+    open: HashSet<(u8, u32)>,
+}
+
+/// Bound on remembered starts; a channel that never closes its breaks must not
+/// grow the state forever.
+const MAX_OPEN: usize = 1024;
+
+impl LintState {
+    pub fn lint(&mut self, input: &[u8]) -> Vec<Finding> {
+        let (mut findings, section) = lint_section(input);
+        let Some(section) = section else { return findings };
+        for (i, seg) in segmentations(&section) {
+            if seg.segmentation_event_cancel_indicator {
+                self.open.retain(|&(_, id)| id != seg.segmentation_event_id);
+                continue;
+            }
+            let t = seg.segmentation_type_id;
+            if let Some(start) = start_of(t) {
+                if !self.open.remove(&(start, seg.segmentation_event_id)) {
+                    findings.push(finding(
+                        Severity::Warning,
+                        "end_without_start",
+                        format!("descriptor[{i}]"),
+                        format!(
+                            "segmentation_type_id 0x{t:02X} (event {}) has no preceding 0x{start:02X} start",
+                            seg.segmentation_event_id
+                        ),
+                    ));
+                }
+            } else if end_of(t).is_some() {
+                if self.open.len() >= MAX_OPEN {
+                    self.open.clear();
+                }
+                self.open.insert((t, seg.segmentation_event_id));
+            }
+        }
+        findings
+    }
+}
+
+fn finding(severity: Severity, code: &'static str, location: impl Into<String>, message: String) -> Finding {
+    Finding { severity, code, location: location.into(), message }
+}
+
+fn segmentations(section: &SpliceInfoSection) -> impl Iterator<Item = (usize, &SegmentationDescriptor)> {
+    section.descriptors.iter().enumerate().filter_map(|(i, d)| match d {
+        SpliceDescriptor::Segmentation(s) => Some((i, s)),
+        _ => None,
+    })
+}
+
+/// The findings, plus the decoded section when it decoded.
+fn lint_section(input: &[u8]) -> (Vec<Finding>, Option<SpliceInfoSection>) {
+    let mut out = Vec::new();
+    if let Some(&b1) = input.get(1) {
+        if b1 & 0x80 != 0 {
+            out.push(finding(Severity::Error, "section_syntax_indicator", "header", "section_syntax_indicator must be 0".into()));
+        }
+        if b1 & 0x40 != 0 {
+            out.push(finding(Severity::Error, "private_indicator", "header", "private_indicator must be 0".into()));
+        }
+    }
+
+    let section = match SpliceInfoSection::decode(input) {
+        Ok(s) => s,
+        Err(e) => {
+            let code = match e.kind {
+                DecodeErrorKind::DescriptorLoopLength => "descriptor_loop_length",
+                DecodeErrorKind::DescriptorLength => "descriptor_length",
+                DecodeErrorKind::SpliceCommandLength => "splice_command_length",
+                DecodeErrorKind::Malformed => "malformed",
+            };
+            out.push(finding(Severity::Error, code, format!("byte {}", e.offset), e.message));
+            return (out, None);
+        }
+    };
+    let Some(wire) = section.wire else { return (out, Some(section)) };
+
+    let declared = 3 + wire.section_length as usize;
+    if wire.input_length < declared {
+        out.push(finding(
+            Severity::Error,
+            "section_length",
+            "header",
+            format!("section_length declares {declared} bytes but only {} are present", wire.input_length),
+        ));
+    } else if wire.input_length > declared {
+        out.push(finding(
+            Severity::Info,
+            "trailing_bytes",
+            "header",
+            format!("{} bytes follow the section", wire.input_length - declared),
+        ));
+    }
+    if !wire.crc_valid {
+        out.push(finding(
+            Severity::Error,
+            "crc_32",
+            "CRC_32",
+            format!("stored CRC 0x{:08X} does not match the section", wire.crc_32),
+        ));
+    }
+    if section.protocol_version != 0 {
+        out.push(finding(
+            Severity::Error,
+            "protocol_version",
+            "header",
+            format!("protocol_version {} (only 0 is defined)", section.protocol_version),
+        ));
+    }
+
+    if section.encrypted_packet {
+        if (4..32).contains(&section.encryption_algorithm) {
+            out.push(finding(
+                Severity::Warning,
+                "encryption_algorithm",
+                "header",
+                format!("encryption_algorithm {} is reserved", section.encryption_algorithm),
+            ));
+        }
+        out.push(finding(
+            Severity::Info,
+            "encrypted",
+            "splice_command",
+            "encrypted section: command and descriptors not checked".into(),
+        ));
+        return (out, Some(section));
+    }
+
+    if section.legacy_command_length {
+        out.push(finding(
+            Severity::Warning,
+            "splice_command_length",
+            "header",
+            "splice_command_length is the legacy 0xFFF (unspecified)".into(),
+        ));
+    } else if wire.splice_command_length != wire.splice_command_body_length {
+        out.push(finding(
+            Severity::Error,
+            "splice_command_length",
+            "header",
+            format!(
+                "splice_command_length {} but the {} body is {} bytes",
+                wire.splice_command_length,
+                section.splice_command.name(),
+                wire.splice_command_body_length
+            ),
+        ));
+    }
+    if section.descriptor_loop_reserved != 0x3F {
+        reserved(&mut out, "descriptor_loop", "descriptor_loop_length reserved bits");
+    }
+    if !section.alignment_stuffing.is_empty() {
+        out.push(finding(
+            Severity::Info,
+            "alignment_stuffing",
+            "descriptor_loop",
+            format!("{} stuffing bytes in an unencrypted section", section.alignment_stuffing.len()),
+        ));
+    }
+
+    lint_command(&mut out, &section);
+    for (i, d) in section.descriptors.iter().enumerate() {
+        lint_descriptor(&mut out, &section.splice_command, &format!("descriptor[{i}]"), d);
+    }
+    (out, Some(section))
+}
+
+fn reserved(out: &mut Vec<Finding>, location: &str, what: &str) {
+    out.push(finding(Severity::Warning, "reserved_bits", location, format!("{what} are not all 1")));
+}
+
+fn lint_splice_time(out: &mut Vec<Finding>, location: &str, t: &SpliceTime) {
+    let all_ones = if t.pts_time.is_some() { 0x3F } else { 0x7F };
+    if t.reserved != all_ones {
+        reserved(out, location, "splice_time() reserved bits");
+    }
+}
+
+fn lint_command(out: &mut Vec<Finding>, section: &SpliceInfoSection) {
+    const AT: &str = "splice_command";
+    match &section.splice_command {
+        SpliceCommand::SpliceInsert(si) => {
+            if si.reserved != 0x7F {
+                reserved(out, AT, "splice_insert reserved bits");
+            }
+            if si.splice_event_cancel_indicator {
+                return;
+            }
+            if si.flags_reserved != 0x07 {
+                reserved(out, AT, "splice_insert flag reserved bits");
+            }
+            if let Some(t) = &si.splice_time {
+                lint_splice_time(out, AT, t);
+            }
+            for c in &si.components {
+                if let Some(t) = &c.splice_time {
+                    lint_splice_time(out, AT, t);
+                }
+            }
+            if let Some(b) = &si.break_duration {
+                if b.reserved != 0x3F {
+                    reserved(out, AT, "break_duration reserved bits");
+                }
+            }
+            if si.out_of_network_indicator && si.break_duration.is_none() {
+                out.push(finding(
+                    Severity::Warning,
+                    "missing_break_duration",
+                    AT,
+                    "splice_insert OUT without a break_duration".into(),
+                ));
+            }
+            if !si.program_splice_flag && si.components.is_empty() {
+                out.push(finding(Severity::Warning, "empty_component_list", AT, "component splice with no components".into()));
+            }
+        }
+        SpliceCommand::TimeSignal(t) => {
+            lint_splice_time(out, AT, t);
+            if section.descriptors.is_empty() {
+                out.push(finding(
+                    Severity::Warning,
+                    "time_signal_without_descriptor",
+                    AT,
+                    "time_signal carries no descriptor to signal".into(),
+                ));
+            }
+        }
+        SpliceCommand::SpliceSchedule(sched) => {
+            for ev in &sched.events {
+                if ev.reserved != 0x7F || (!ev.splice_event_cancel_indicator && ev.flags_reserved != 0x1F) {
+                    reserved(out, AT, &format!("splice_schedule event {} reserved bits", ev.splice_event_id));
+                }
+                if let Some(b) = &ev.break_duration {
+                    if b.reserved != 0x3F {
+                        reserved(out, AT, "break_duration reserved bits");
+                    }
+                }
+            }
+        }
+        SpliceCommand::Unknown { command_type, .. } => out.push(finding(
+            Severity::Error,
+            "splice_command_type",
+            AT,
+            format!("splice_command_type 0x{command_type:02X} is reserved"),
+        )),
+        SpliceCommand::SpliceNull | SpliceCommand::BandwidthReservation | SpliceCommand::PrivateCommand { .. } => {}
+    }
+}
+
+fn lint_descriptor(out: &mut Vec<Finding>, command: &SpliceCommand, at: &str, d: &SpliceDescriptor) {
+    let extra = match d {
+        SpliceDescriptor::Avail(a) => &a.extra,
+        SpliceDescriptor::Dtmf(dt) => {
+            if dt.reserved != 0x1F {
+                reserved(out, at, "DTMF_descriptor reserved bits");
+            }
+            if let Some(c) = dt.dtmf_chars.iter().find(|&&c| !matches!(c, b'0'..=b'9' | b'*' | b'#')) {
+                out.push(finding(Severity::Error, "dtmf_char", at, format!("DTMF character 0x{c:02X} is not 0-9, * or #")));
+            }
+            &dt.extra
+        }
+        SpliceDescriptor::Time(t) => &t.extra,
+        SpliceDescriptor::Audio(a) => {
+            if a.reserved != 0x0F {
+                reserved(out, at, "audio_descriptor reserved bits");
+            }
+            &a.extra
+        }
+        SpliceDescriptor::Segmentation(s) => {
+            lint_segmentation(out, command, at, s);
+            &s.extra
+        }
+        SpliceDescriptor::Other { tag, data } => {
+            match d.identifier() {
+                None => out.push(finding(
+                    Severity::Error,
+                    "descriptor_identifier",
+                    at,
+                    format!("descriptor 0x{tag:02X} is {} bytes, too short for an identifier", data.len()),
+                )),
+                Some(id) if id != CUEI => out.push(finding(
+                    Severity::Info,
+                    "private_descriptor",
+                    at,
+                    format!("private descriptor 0x{tag:02X} with identifier 0x{id:08X}"),
+                )),
+                Some(_) if *tag <= splice_info::AUDIO_DESCRIPTOR => out.push(finding(
+                    Severity::Error,
+                    "descriptor_syntax",
+                    at,
+                    format!("{} body does not match its syntax", splice_info::descriptor_name(*tag)),
+                )),
+                Some(_) => out.push(finding(
+                    Severity::Warning,
+                    "descriptor_tag",
+                    at,
+                    format!("splice_descriptor_tag 0x{tag:02X} is reserved under \"CUEI\""),
+                )),
+            }
+            return;
+        }
+    };
+    if !extra.is_empty() {
+        out.push(finding(
+            Severity::Warning,
+            "descriptor_trailing_bytes",
+            at,
+            format!("{} bytes past the end of {}", extra.len(), d.name()),
+        ));
+    }
+}
+
+fn lint_segmentation(out: &mut Vec<Finding>, command: &SpliceCommand, at: &str, s: &SegmentationDescriptor) {
+    if s.reserved != 0x3F {
+        reserved(out, at, "segmentation_descriptor reserved bits");
+    }
+    if s.segmentation_event_cancel_indicator {
+        return;
+    }
+    if s.delivery_not_restricted_flag && s.restriction_reserved != 0x1F {
+        reserved(out, at, "delivery restriction reserved bits");
+    }
+    if s.components.iter().any(|c| c.reserved != 0x7F) {
+        reserved(out, at, "segmentation component reserved bits");
+    }
+    lint_upid(out, at, s.segmentation_upid_type, &s.segmentation_upid, false);
+
+    let t = s.segmentation_type_id;
+    if segmentation_type_name(t).is_none() {
+        out.push(finding(Severity::Warning, "segmentation_type_id", at, format!("segmentation_type_id 0x{t:02X} is reserved")));
+    }
+    if needs_duration(t) && s.segmentation_duration.is_none() {
+        out.push(finding(
+            Severity::Warning,
+            "start_without_duration",
+            at,
+            format!("{} (0x{t:02X}) without a segmentation_duration", segmentation_type_name(t).unwrap_or("start")),
+        ));
+    }
+    if s.segments_expected != 0 && s.segment_num > s.segments_expected {
+        out.push(finding(
+            Severity::Warning,
+            "segment_num",
+            at,
+            format!("segment_num {} exceeds segments_expected {}", s.segment_num, s.segments_expected),
+        ));
+    }
+    if let (Some(n), Some(e)) = (s.sub_segment_num, s.sub_segments_expected) {
+        if e != 0 && n > e {
+            out.push(finding(
+                Severity::Warning,
+                "sub_segment_num",
+                at,
+                format!("sub_segment_num {n} exceeds sub_segments_expected {e}"),
+            ));
+        }
+    } else if has_sub_segments(t) {
+        out.push(finding(
+            Severity::Info,
+            "sub_segments_absent",
+            at,
+            format!("0x{t:02X} omits sub_segment_num / sub_segments_expected"),
+        ));
+    }
+
+    let misfit = match command {
+        SpliceCommand::SpliceNull | SpliceCommand::BandwidthReservation => {
+            Some(format!("segmentation_descriptor on a {}", command.name()))
+        }
+        SpliceCommand::SpliceInsert(si) if si.out_of_network_indicator && start_of(t).is_some() => {
+            Some(format!("end type 0x{t:02X} on a splice_insert OUT"))
+        }
+        // A cancel carries no out_of_network_indicator, so it is neither OUT nor IN.
+        SpliceCommand::SpliceInsert(si)
+            if !si.out_of_network_indicator && !si.splice_event_cancel_indicator && end_of(t).is_some() =>
+        {
+            Some(format!("start type 0x{t:02X} on a splice_insert IN"))
+        }
+        _ => None,
+    };
+    if let Some(m) = misfit {
+        out.push(finding(Severity::Warning, "segmentation_type_for_command", at, m));
+    }
+}
+
+fn lint_upid(out: &mut Vec<Finding>, at: &str, upid_type: u8, upid: &[u8], in_mid: bool) {
+    let Some(name) = upid_type_name(upid_type) else {
+        out.push(finding(Severity::Warning, "upid_type", at, format!("segmentation_upid_type 0x{upid_type:02X} is reserved")));
+        return;
+    };
+    if matches!(upid_type, 0x01 | 0x02 | 0x05) {
+        out.push(finding(Severity::Info, "upid_deprecated", at, format!("{name} (0x{upid_type:02X}) is deprecated")));
+    }
+    if let Some(len) = upid_length(upid_type) {
+        if upid.len() != len {
+            out.push(finding(
+                Severity::Error,
+                "upid_length",
+                at,
+                format!("{name} UPID is {} bytes, type 0x{upid_type:02X} defines {len}", upid.len()),
+            ));
+        }
+    }
+    if upid_type == 0x0D {
+        if in_mid {
+            out.push(finding(Severity::Error, "upid_mid", at, "MID nested inside a MID".into()));
+            return;
+        }
+        match splice_info::mid_children(upid) {
+            Some(children) => {
+                for (child_type, child) in children {
+                    lint_upid(out, at, child_type, child, true);
+                }
+            }
+            None => out.push(finding(Severity::Error, "upid_mid", at, "MID body does not split into child UPIDs".into())),
+        }
+    }
+}
+
+/// Fixed UPID lengths from SCTE 35 Table 21; None for variable-length types.
+fn upid_length(upid_type: u8) -> Option<usize> {
+    match upid_type {
+        0x00 => Some(0),
+        0x02 => Some(8),  // ISCI
+        0x03 => Some(12), // Ad-ID
+        0x04 => Some(32), // UMID
+        0x05 => Some(8),  // ISAN (deprecated)
+        0x06 => Some(12), // ISAN
+        0x07 => Some(12), // TID
+        0x08 => Some(8),  // TI
+        0x0A => Some(12), // EIDR
+        0x10 => Some(16), // UUID
+        _ => None,
+    }
+}
+
+fn upid_type_name(upid_type: u8) -> Option<&'static str> {
+    Some(match upid_type {
+        0x00 => "Not Used",
+        0x01 => "User Defined",
+        0x02 => "ISCI",
+        0x03 => "Ad-ID",
+        0x04 => "UMID",
+        0x05 => "ISAN (deprecated)",
+        0x06 => "ISAN",
+        0x07 => "TID",
+        0x08 => "TI",
+        0x09 => "ADI",
+        0x0A => "EIDR",
+        0x0B => "ATSC Content ID",
+        0x0C => "MPU",
+        0x0D => "MID",
+        0x0E => "ADS Info",
+        0x0F => "URI",
+        0x10 => "UUID",
+        0x11 => "SCR",
+        _ => return None,
+    })
+}
+
+fn segmentation_type_name(t: u8) -> Option<&'static str> {
+    Some(match t {
+        0x00 => "Not Indicated",
+        0x01 => "Content Identification",
+        0x10 => "Program Start",
+        0x11 => "Program End",
+        0x12 => "Program Early Termination",
+        0x13 => "Program Breakaway",
+        0x14 => "Program Resumption",
+        0x15 => "Program Runover Planned",
+        0x16 => "Program Runover Unplanned",
+        0x17 => "Program Overlap Start",
+        0x18 => "Program Blackout Override",
+        0x19 => "Program Start - In Progress",
+        0x20 => "Chapter Start",
+        0x21 => "Chapter End",
+        0x22 => "Break Start",
+        0x23 => "Break End",
+        0x24 => "Opening Credit Start",
+        0x25 => "Opening Credit End",
+        0x26 => "Closing Credit Start",
+        0x27 => "Closing Credit End",
+        0x30 => "Provider Advertisement Start",
+        0x31 => "Provider Advertisement End",
+        0x32 => "Distributor Advertisement Start",
+        0x33 => "Distributor Advertisement End",
+        0x34 => "Provider Placement Opportunity Start",
+        0x35 => "Provider Placement Opportunity End",
+        0x36 => "Distributor Placement Opportunity Start",
+        0x37 => "Distributor Placement Opportunity End",
+        0x38 => "Provider Overlay Placement Opportunity Start",
+        0x39 => "Provider Overlay Placement Opportunity End",
+        0x3A => "Distributor Overlay Placement Opportunity Start",
+        0x3B => "Distributor Overlay Placement Opportunity End",
+        0x3C => "Provider Promo Start",
+        0x3D => "Provider Promo End",
+        0x3E => "Distributor Promo Start",
+        0x3F => "Distributor Promo End",
+        0x40 => "Unscheduled Event Start",
+        0x41 => "Unscheduled Event End",
+        0x42 => "Alternate Content Opportunity Start",
+        0x43 => "Alternate Content Opportunity End",
+        0x44 => "Provider Ad Block Start",
+        0x45 => "Provider Ad Block End",
+        0x46 => "Distributor Ad Block Start",
+        0x47 => "Distributor Ad Block End",
+        0x50 => "Network Start",
+        0x51 => "Network End",
+        _ => return None,
+    })
+}
+
+/// Start types closed by the next type id (0x10 Program Start / 0x11 Program
+/// End, ..., 0x50 Network Start / 0x51 Network End).
+fn end_of(t: u8) -> Option<u8> {
+    match t {
+        0x10 | 0x20 | 0x22 | 0x24 | 0x26 | 0x40 | 0x42 | 0x44 | 0x46 | 0x50 => Some(t + 1),
+        0x30..=0x3E if t.is_multiple_of(2) => Some(t + 1),
+        _ => None,
+    }
+}
+
+fn start_of(t: u8) -> Option<u8> {
+    let s = t.checked_sub(1)?;
+    (end_of(s) == Some(t)).then_some(s)
+}
+
+/// Starts whose end is implied by their declared duration (advertising and
+/// placement opportunities, ad blocks, breaks).
+fn needs_duration(t: u8) -> bool {
+    matches!(t, 0x22 | 0x30 | 0x32 | 0x34 | 0x36 | 0x38 | 0x3A | 0x44 | 0x46)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splice_info::{BreakDuration, SpliceInsert};
+
+    fn codes(findings: &[Finding]) -> Vec<&'static str> {
+        findings.iter().map(|f| f.code).collect()
+    }
+
+    fn seg(type_id: u8, upid_type: u8, upid: &[u8]) -> SpliceDescriptor {
+        SpliceDescriptor::Segmentation(SegmentationDescriptor {
+            reserved: 0x3F,
+            restriction_reserved: 0x1F,
+            segmentation_event_id: 7,
+            segmentation_type_id: type_id,
+            segmentation_upid_type: upid_type,
+            segmentation_upid: upid.to_vec(),
+            segmentation_duration: Some(90_000),
+            sub_segment_num: has_sub_segments(type_id).then_some(0),
+            sub_segments_expected: has_sub_segments(type_id).then_some(0),
+            ..Default::default()
+        })
+    }
+
+    fn time_signal(descriptors: Vec<SpliceDescriptor>) -> Vec<u8> {
+        let mut s = SpliceInfoSection::new(SpliceCommand::TimeSignal(SpliceTime { pts_time: None, reserved: 0x7F }));
+        s.descriptor_loop_reserved = 0x3F;
+        s.descriptors = descriptors;
+        s.encode().unwrap()
+    }
+
+    #[test]
+    fn clean_section_has_no_findings() {
+        let b = time_signal(vec![seg(0x34, 0x03, b"ABCD01234567")]);
+        assert_eq!(lint(&b), vec![]);
+    }
+
+    #[test]
+    fn structural_findings() {
+        let mut b = time_signal(vec![seg(0x34, 0x03, b"ABCD01234567")]);
+        let n = b.len();
+        b[n - 1] ^= 0xFF;
+        b[12] += 1; // splice_command_length 1 -> 2
+        let f = lint(&b);
+        assert!(codes(&f).contains(&"crc_32"));
+        assert!(codes(&f).contains(&"splice_command_length"));
+        assert!(!is_conformant(&f));
+
+        // descriptor_loop_length pointing past the CRC.
+        let mut b = time_signal(vec![]);
+        b[16] = 0x10;
+        assert_eq!(codes(&lint(&b)), vec!["descriptor_loop_length"]);
+    }
+
+    #[test]
+    fn upid_identifier_and_reserved_bits() {
+        let f = lint(&time_signal(vec![
+            seg(0x34, 0x0A, b"too-short"),
+            seg(0x36, 0x0D, &[0x03, 0x02, b'A', b'B']),
+            SpliceDescriptor::Other { tag: 0x02, data: b"ACME".to_vec() },
+        ]));
+        assert_eq!(codes(&f), vec!["upid_length", "upid_length", "private_descriptor"]);
+        assert_eq!(f[0].location, "descriptor[0]");
+        assert!(f[1].message.contains("Ad-ID"), "MID child checked: {}", f[1].message);
+
+        // Our builders write reserved bits as 1s, so a built cue lints clean.
+        let mut s = SpliceInfoSection::new(SpliceCommand::SpliceInsert(SpliceInsert {
+            out_of_network_indicator: true,
+            program_splice_flag: true,
+            splice_immediate_flag: true,
+            break_duration: Some(BreakDuration::new(true, 1)),
+            ..Default::default()
+        }));
+        assert_eq!(lint(&s.encode().unwrap()), vec![]);
+
+        s.descriptor_loop_reserved = 0;
+        let f = lint(&s.encode().unwrap());
+        assert_eq!(codes(&f), vec!["reserved_bits"]);
+        assert_eq!(f[0].severity, Severity::Warning);
+        assert!(is_conformant(&f));
+    }
+
+    #[test]
+    fn semantic_findings() {
+        let f = lint(&time_signal(vec![SpliceDescriptor::Segmentation(SegmentationDescriptor {
+            reserved: 0x3F,
+            restriction_reserved: 0x1F,
+            segmentation_type_id: 0x30,
+            segment_num: 3,
+            segments_expected: 2,
+            ..Default::default()
+        })]));
+        assert_eq!(codes(&f), vec!["start_without_duration", "segment_num"]);
+
+        let mut s = SpliceInfoSection::new(SpliceCommand::SpliceInsert(SpliceInsert {
+            reserved: 0x7F,
+            flags_reserved: 0x07,
+            out_of_network_indicator: true,
+            program_splice_flag: true,
+            splice_immediate_flag: true,
+            break_duration: Some(BreakDuration { auto_return: true, reserved: 0x3F, duration: 1 }),
+            ..Default::default()
+        }));
+        s.descriptor_loop_reserved = 0x3F;
+        s.descriptors = vec![seg(0x35, 0x00, b"")];
+        assert_eq!(codes(&lint(&s.encode().unwrap())), vec!["segmentation_type_for_command"]);
+
+        // A cancelled splice_insert is not an IN, whatever type rides with it.
+        let mut s = SpliceInfoSection::new(SpliceCommand::SpliceInsert(SpliceInsert {
+            splice_event_cancel_indicator: true,
+            ..Default::default()
+        }));
+        s.descriptors = vec![seg(0x34, 0x00, b"")];
+        assert_eq!(codes(&lint(&s.encode().unwrap())), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn end_without_start_needs_context() {
+        let start = time_signal(vec![seg(0x34, 0x00, b"")]);
+        let end = time_signal(vec![seg(0x35, 0x00, b"")]);
+        assert_eq!(lint(&end), vec![], "no context, no finding");
+
+        let mut state = LintState::default();
+        assert_eq!(codes(&state.lint(&end)), vec!["end_without_start"]);
+        assert_eq!(state.lint(&start), vec![]);
+        assert_eq!(state.lint(&end), vec![]);
+        assert_eq!(codes(&state.lint(&end)), vec!["end_without_start"], "already closed");
+    }
+}
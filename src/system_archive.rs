@@ -0,0 +1,737 @@
+// src/system_archive.rs
+//! Full-fidelity, versioned system archive.
+//!
+//! Unlike `backup::BackupFile` (channels + rules only), an archive captures the
+//! whole instance: users, groups and memberships, channels with their security
+//! policy (`sesame_min_tier`, `is_global`), event retention overrides and
+//! watchdog policies, rules, projects, templates, template instances, group
+//! links, API token metadata and the password-change audit.
+//! Every column of every archived table is carried (read via `PRAGMA
+//! table_info`), so new columns ride along without format changes. Events and
+//! watchdog alerts are operational data and are not archived.
+//!
+//! **Sensitive fields** (`users.password_hash`, `api_tokens.token_hash`) are
+//! sealed with AES-256-GCM under an Argon2id key derived from a passphrase
+//! (`enc:v1:<b64(iv|ciphertext)>`, field name as AAD). Without a passphrase they
+//! are left out (listed in `redacted`); such users restore with an unusable
+//! password and must be reset, such tokens restore revoked.
+//!
+//! **Restore** runs in one transaction, parents before children. Ids are kept
+//! when free and otherwise remapped, and every foreign key is rewritten through
+//! the remap. `mode=merge` maps users/groups/channels onto existing rows with the
+//! same name and leaves those untouched (their rules/tokens are not re-added);
+//! `mode=replace` first wipes the archived tables (disaster recovery
+//! onto a fresh DB). Both are super-admin only; the CLI flags
+//! `--export-archive FILE` / `--restore-archive FILE [--replace]` (passphrase
+//! from `POIS_ARCHIVE_PASSPHRASE`) cover an instance with no users yet.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use pois_esam_server::sesame::tier3_aead::{self, IV_LEN, KEY_LEN};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use sqlx::{Pool, QueryBuilder, Sqlite, SqliteConnection};
+
+use crate::jwt_auth::Claims;
+use crate::rbac;
+use crate::AppState;
+
+pub const ARCHIVE_FORMAT: &str = "pois-system-archive";
+pub const ARCHIVE_VERSION: u32 = 1;
+const ENC_PREFIX: &str = "enc:v1:";
+/// Known plaintext sealed under the key so a wrong passphrase fails up front.
+const KEY_CHECK: &[u8] = b"pois-system-archive";
+
+/// A foreign key: column, referenced table, and whether it may be NULLed when
+/// the parent is missing (else the row is dropped).
+type Fk = (&'static str, &'static str, bool);
+
+struct TableSpec {
+    name: &'static str,
+    /// Has an INTEGER `id` primary key that other tables reference.
+    has_id: bool,
+    /// UNIQUE column used to match existing rows in merge mode.
+    natural_key: Option<&'static str>,
+    fks: &'static [Fk],
+    sensitive: &'static [&'static str],
+}
+
+/// Archived tables, parents before children (restore order).
+const TABLES: &[TableSpec] = &[
+    TableSpec { name: "users", has_id: true, natural_key: Some("username"), fks: &[], sensitive: &["password_hash"] },
+    TableSpec { name: "groups", has_id: true, natural_key: Some("name"), fks: &[], sensitive: &[] },
+    TableSpec {
+        name: "group_members",
+        has_id: false,
+        natural_key: None,
+        fks: &[("group_id", "groups", false), ("user_id", "users", false)],
+        sensitive: &[],
+    },
+    TableSpec {
+        name: "channels",
+        has_id: true,
+        natural_key: Some("name"),
+        fks: &[("owner_user_id", "users", true)],
+        sensitive: &[],
+    },
+    // Keyed by channel name (like esam_events), so no foreign key.
+    TableSpec { name: "event_retention", has_id: false, natural_key: None, fks: &[], sensitive: &[] },
+    TableSpec { name: "watchdog_policies", has_id: false, natural_key: None, fks: &[], sensitive: &[] },
+    TableSpec {
+        name: "channel_groups",
+        has_id: false,
+        natural_key: None,
+        fks: &[("channel_id", "channels", false), ("group_id", "groups", false)],
+        sensitive: &[],
+    },
+    TableSpec {
+        name: "rules",
+        has_id: true,
+        natural_key: None,
+        fks: &[("channel_id", "channels", false), ("owner_user_id", "users", true)],
+        sensitive: &[],
+    },
+    TableSpec { name: "projects", has_id: true, natural_key: None, fks: &[("owner_user_id", "users", true)], sensitive: &[] },
+    TableSpec {
+        name: "project_groups",
+        has_id: false,
+        natural_key: None,
+        fks: &[("project_id", "projects", false), ("group_id", "groups", false)],
+        sensitive: &[],
+    },
+    TableSpec {
+        name: "templates",
+        has_id: true,
+        natural_key: None,
+        fks: &[("project_id", "projects", true), ("owner_user_id", "users", true)],
+        sensitive: &[],
+    },
+    TableSpec {
+        name: "template_groups",
+        has_id: false,
+        natural_key: None,
+        fks: &[("template_id", "templates", false), ("group_id", "groups", false)],
+        sensitive: &[],
+    },
+    TableSpec {
+        name: "template_instances",
+        has_id: true,
+        natural_key: None,
+        fks: &[
+            ("template_id", "templates", false),
+            ("channel_id", "channels", false),
+            ("rule_id", "rules", true),
+            ("owner_user_id", "users", true),
+        ],
+        sensitive: &[],
+    },
+    TableSpec {
+        name: "api_tokens",
+        has_id: true,
+        natural_key: None,
+        fks: &[("user_id", "users", false)],
+        sensitive: &["token_hash"],
+    },
+    TableSpec {
+        name: "password_changes",
+        has_id: true,
+        natural_key: None,
+        fks: &[("user_id", "users", false)],
+        sensitive: &[],
+    },
+];
+
+// --------------------------------- format ---------------------------------
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchiveEncryption {
+    /// "A256GCM"
+    pub alg: String,
+    /// "argon2id"
+    pub kdf: String,
+    pub salt: String,
+    /// `KEY_CHECK` sealed under the key.
+    pub check: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SystemArchive {
+    pub format: String,
+    pub format_version: u32,
+    pub created_at: String,
+    pub app_version: String,
+    /// Highest applied migration when the archive was taken.
+    #[serde(default)]
+    pub schema_version: Option<i64>,
+    #[serde(default)]
+    pub encryption: Option<ArchiveEncryption>,
+    /// `table.column` fields left out (no passphrase at export).
+    #[serde(default)]
+    pub redacted: Vec<String>,
+    pub tables: BTreeMap<String, Vec<Map<String, JsonValue>>>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    #[default]
+    Merge,
+    Replace,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct TableReport {
+    pub inserted: u32,
+    /// Rows matched onto an existing row by name (merge mode).
+    pub matched_existing: u32,
+    /// Rows inserted under a new id because theirs was taken.
+    pub remapped: u32,
+    pub skipped: u32,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct ArchiveRestoreReport {
+    pub tables: BTreeMap<String, TableReport>,
+    pub warnings: Vec<String>,
+}
+
+// ------------------------------- encryption -------------------------------
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN], String> {
+    let mut key = [0u8; KEY_LEN];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("key derivation: {e}"))?;
+    Ok(key)
+}
+
+fn seal(key: &[u8; KEY_LEN], aad: &str, plain: &[u8]) -> Result<String, String> {
+    let mut iv = [0u8; IV_LEN];
+    rand::thread_rng().fill_bytes(&mut iv);
+    let ct = tier3_aead::seal(key, &iv, aad.as_bytes(), plain).map_err(|_| "encryption failed".to_string())?;
+    let mut out = iv.to_vec();
+    out.extend_from_slice(&ct);
+    Ok(format!("{ENC_PREFIX}{}", B64.encode(out)))
+}
+
+fn open(key: &[u8; KEY_LEN], aad: &str, sealed: &str) -> Result<Vec<u8>, String> {
+    let raw = sealed
+        .strip_prefix(ENC_PREFIX)
+        .and_then(|b| B64.decode(b).ok())
+        .filter(|r| r.len() > IV_LEN)
+        .ok_or_else(|| format!("{aad}: not an encrypted value"))?;
+    let (iv, ct) = raw.split_at(IV_LEN);
+    let iv: [u8; IV_LEN] = iv.try_into().map_err(|_| "bad iv".to_string())?;
+    tier3_aead::open(key, &iv, aad.as_bytes(), ct).map_err(|_| format!("{aad}: decryption failed"))
+}
+
+/// New encryption header + key for an export.
+fn new_encryption(passphrase: &str) -> Result<(ArchiveEncryption, [u8; KEY_LEN]), String> {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+    let enc = ArchiveEncryption {
+        alg: "A256GCM".into(),
+        kdf: "argon2id".into(),
+        salt: B64.encode(salt),
+        check: seal(&key, "check", KEY_CHECK)?,
+    };
+    Ok((enc, key))
+}
+
+/// Key for an encrypted archive; rejects a wrong passphrase.
+fn unlock(enc: &ArchiveEncryption, passphrase: Option<&str>) -> Result<[u8; KEY_LEN], String> {
+    if enc.alg != "A256GCM" || enc.kdf != "argon2id" {
+        return Err(format!("unsupported encryption {}/{}", enc.alg, enc.kdf));
+    }
+    let passphrase = passphrase.ok_or("archive is encrypted: a passphrase is required")?;
+    let salt = B64.decode(&enc.salt).map_err(|e| format!("salt: {e}"))?;
+    let key = derive_key(passphrase, &salt)?;
+    match open(&key, "check", &enc.check) {
+        Ok(v) if v == KEY_CHECK => Ok(key),
+        _ => Err("wrong passphrase".into()),
+    }
+}
+
+// --------------------------------- export ---------------------------------
+
+pub(crate) async fn table_columns(conn: &mut SqliteConnection, table: &str) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(i64, String, String, i64, Option<String>, i64)> =
+        sqlx::query_as(&format!("PRAGMA table_info(\"{table}\")")).fetch_all(&mut *conn).await?;
+    Ok(rows.into_iter().map(|r| r.1).collect())
+}
+
+/// Build an archive of the whole instance. With a passphrase, sensitive fields
+/// are encrypted; without one they are left out.
+pub async fn export_archive(db: &Pool<Sqlite>, passphrase: Option<&str>) -> Result<SystemArchive, String> {
+    let crypt = match passphrase.filter(|p| !p.is_empty()) {
+        Some(p) => Some(new_encryption(p)?),
+        None => None,
+    };
+    let mut conn = db.acquire().await.map_err(|e| e.to_string())?;
+    let mut tables = BTreeMap::new();
+    let mut redacted = Vec::new();
+
+    for spec in TABLES {
+        let cols = table_columns(&mut conn, spec.name).await.map_err(|e| e.to_string())?;
+        let pairs: Vec<String> = cols.iter().map(|c| format!("'{c}', \"{c}\"")).collect();
+        let sql = format!("SELECT json_object({}) FROM \"{}\" ORDER BY rowid", pairs.join(", "), spec.name);
+        let rows: Vec<(String,)> = sqlx::query_as(&sql).fetch_all(&mut *conn).await.map_err(|e| e.to_string())?;
+
+        let mut out = Vec::with_capacity(rows.len());
+        for (json,) in rows {
+            let mut row: Map<String, JsonValue> = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            for field in spec.sensitive {
+                let aad = format!("{}.{}", spec.name, field);
+                match (&crypt, row.remove(*field)) {
+                    (Some((_, key)), Some(JsonValue::String(v))) => {
+                        row.insert(field.to_string(), JsonValue::String(seal(key, &aad, v.as_bytes())?));
+                    }
+                    (Some(_), Some(other)) => {
+                        row.insert(field.to_string(), other);
+                    }
+                    (None, _) => {
+                        if !redacted.contains(&aad) {
+                            redacted.push(aad);
+                        }
+                    }
+                    (Some(_), None) => {}
+                }
+            }
+            out.push(row);
+        }
+        tables.insert(spec.name.to_string(), out);
+    }
+
+    let schema_version: Option<(i64,)> = sqlx::query_as("SELECT MAX(version) FROM _sqlx_migrations")
+        .fetch_optional(&mut *conn)
+        .await
+        .ok()
+        .flatten();
+
+    Ok(SystemArchive {
+        format: ARCHIVE_FORMAT.into(),
+        format_version: ARCHIVE_VERSION,
+        created_at: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        app_version: env!("CARGO_PKG_VERSION").into(),
+        schema_version: schema_version.map(|v| v.0),
+        encryption: crypt.map(|(e, _)| e),
+        redacted,
+        tables,
+    })
+}
+
+// --------------------------------- restore --------------------------------
+
+type IdMaps = HashMap<&'static str, HashMap<i64, i64>>;
+
+/// Rewrite a row's foreign keys through `maps`. `Err` = drop the row (a
+/// required parent is missing).
+fn remap_row(spec: &TableSpec, row: &mut Map<String, JsonValue>, maps: &IdMaps) -> Result<(), String> {
+    for (col, parent, nullable) in spec.fks {
+        let Some(old) = row.get(*col).and_then(JsonValue::as_i64) else { continue };
+        match maps.get(parent).and_then(|m| m.get(&old)) {
+            Some(new) => {
+                row.insert(col.to_string(), JsonValue::from(*new));
+            }
+            None if *nullable => {
+                row.insert(col.to_string(), JsonValue::Null);
+            }
+            None => return Err(format!("{}.{} -> {} {} not in archive", spec.name, col, parent, old)),
+        }
+    }
+    Ok(())
+}
+
+/// Whether a row hangs off (via a required FK) a parent that merge matched
+/// onto an existing row.
+fn owned_by_kept(spec: &TableSpec, row: &Map<String, JsonValue>, kept: &HashMap<&'static str, HashSet<i64>>) -> bool {
+    spec.fks.iter().any(|(col, parent, nullable)| {
+        !nullable
+            && row
+                .get(*col)
+                .and_then(JsonValue::as_i64)
+                .is_some_and(|v| kept.get(parent).is_some_and(|k| k.contains(&v)))
+    })
+}
+
+fn push_json_bind(sep: &mut sqlx::query_builder::Separated<'_, '_, Sqlite, &str>, v: &JsonValue) {
+    match v {
+        JsonValue::Null => sep.push_bind(None::<String>),
+        JsonValue::Bool(b) => sep.push_bind(*b as i64),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => sep.push_bind(i),
+            None => sep.push_bind(n.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(s) => sep.push_bind(s.clone()),
+        other => sep.push_bind(other.to_string()),
+    };
+}
+
+/// Fill in / decrypt sensitive fields of one row.
+fn restore_secrets(
+    spec: &TableSpec,
+    row: &mut Map<String, JsonValue>,
+    key: Option<&[u8; KEY_LEN]>,
+    redacted: &[String],
+) -> Result<(), String> {
+    for field in spec.sensitive {
+        let aad = format!("{}.{}", spec.name, field);
+        match (row.get(*field).and_then(JsonValue::as_str), key) {
+            (Some(v), Some(key)) if v.starts_with(ENC_PREFIX) => {
+                let plain = String::from_utf8(open(key, &aad, v)?).map_err(|e| e.to_string())?;
+                row.insert(field.to_string(), JsonValue::String(plain));
+            }
+            (Some(v), None) if v.starts_with(ENC_PREFIX) => return Err(format!("{aad} is encrypted")),
+            (Some(_), _) => {}
+            (None, _) if redacted.contains(&aad) => {
+                // Unusable placeholders: a random password (admin must reset),
+                // a revoked token.
+                let mut r = [0u8; 24];
+                rand::thread_rng().fill_bytes(&mut r);
+                let placeholder = match (spec.name, *field) {
+                    ("users", "password_hash") => {
+                        row.insert("must_change_password".into(), JsonValue::from(1));
+                        crate::jwt_auth::PasswordService::hash_password(&B64.encode(r)).map_err(|e| e.to_string())?
+                    }
+                    _ => {
+                        row.insert("revoked".into(), JsonValue::from(1));
+                        format!("redacted:{}", B64.encode(r))
+                    }
+                };
+                row.insert(field.to_string(), JsonValue::String(placeholder));
+            }
+            (None, _) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Restore an archive in one transaction. See the module docs for semantics.
+pub async fn restore_archive(
+    db: &Pool<Sqlite>,
+    archive: &SystemArchive,
+    mode: RestoreMode,
+    passphrase: Option<&str>,
+) -> Result<ArchiveRestoreReport, String> {
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(format!("not a system archive (format '{}')", archive.format));
+    }
+    if archive.format_version > ARCHIVE_VERSION {
+        return Err(format!(
+            "archive format v{} is newer than this server supports (v{ARCHIVE_VERSION})",
+            archive.format_version
+        ));
+    }
+    let key = match &archive.encryption {
+        Some(enc) => Some(unlock(enc, passphrase)?),
+        None => None,
+    };
+
+    let mut report = ArchiveRestoreReport::default();
+    if !archive.redacted.is_empty() {
+        report.warnings.push(format!(
+            "archive has no {}: users get a random password (reset required), API tokens are revoked",
+            archive.redacted.join(", ")
+        ));
+    }
+
+    let err = |e: sqlx::Error| e.to_string();
+    let mut tx = db.begin().await.map_err(err)?;
+    // Ids are re-used where free; defer FK checks to commit so a wipe-and-refill
+    // doesn't trip over rows (events) that point at the old ids.
+    sqlx::query("PRAGMA defer_foreign_keys = ON").execute(&mut *tx).await.map_err(err)?;
+
+    if mode == RestoreMode::Replace {
+        for spec in TABLES.iter().rev() {
+            sqlx::query(&format!("DELETE FROM \"{}\"", spec.name)).execute(&mut *tx).await.map_err(err)?;
+        }
+    }
+
+    let mut maps: IdMaps = HashMap::new();
+    // Archive ids (per table) that merge mapped onto an already-present row.
+    let mut kept: HashMap<&'static str, HashSet<i64>> = HashMap::new();
+    for spec in TABLES {
+        let rows = archive.tables.get(spec.name).map(Vec::as_slice).unwrap_or_default();
+        let cols = table_columns(&mut tx, spec.name).await.map_err(err)?;
+        let mut tr = TableReport::default();
+        let mut map = HashMap::new();
+        let mut unknown: Vec<String> = Vec::new();
+
+        for src in rows {
+            let mut row = src.clone();
+            let old_id = row.get("id").and_then(JsonValue::as_i64);
+            // Merge leaves existing entities alone: rows owned by a parent that
+            // was already present (its rules, tokens, ...) are not re-added.
+            if spec.has_id && spec.natural_key.is_none() && owned_by_kept(spec, &row, &kept) {
+                tr.matched_existing += 1;
+                continue;
+            }
+            if let Err(e) = remap_row(spec, &mut row, &maps) {
+                tr.skipped += 1;
+                report.warnings.push(e);
+                continue;
+            }
+
+            // Merge: same-named row already there -> point at it.
+            if let (RestoreMode::Merge, Some(nk), Some(old)) = (mode, spec.natural_key, old_id) {
+                if let Some(v) = row.get(nk).and_then(JsonValue::as_str) {
+                    let existing: Option<(i64,)> =
+                        sqlx::query_as(&format!("SELECT id FROM \"{}\" WHERE \"{nk}\" = ?", spec.name))
+                            .bind(v)
+                            .fetch_optional(&mut *tx)
+                            .await
+                            .map_err(err)?;
+                    if let Some((id,)) = existing {
+                        map.insert(old, id);
+                        kept.entry(spec.name).or_default().insert(old);
+                        tr.matched_existing += 1;
+                        continue;
+                    }
+                }
+            }
+
+            restore_secrets(spec, &mut row, key.as_ref(), &archive.redacted)?;
+
+            // Keep the id when free, else let SQLite assign one.
+            if let Some(old) = old_id.filter(|_| spec.has_id) {
+                let taken: Option<(i64,)> = sqlx::query_as(&format!("SELECT 1 FROM \"{}\" WHERE id = ?", spec.name))
+                    .bind(old)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(err)?;
+                if taken.is_some() {
+                    row.remove("id");
+                    tr.remapped += 1;
+                }
+            }
+
+            for k in row.keys() {
+                if !cols.contains(k) && !unknown.contains(k) {
+                    unknown.push(k.clone());
+                }
+            }
+            let present: Vec<(&String, &JsonValue)> = row.iter().filter(|(k, _)| cols.contains(k)).collect();
+            let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new(format!(
+                "INSERT {}INTO \"{}\" (",
+                if spec.has_id { "" } else { "OR IGNORE " },
+                spec.name
+            ));
+            qb.push(present.iter().map(|(k, _)| format!("\"{k}\"")).collect::<Vec<_>>().join(", "));
+            qb.push(") VALUES (");
+            {
+                let mut sep = qb.separated(", ");
+                for (_, v) in &present {
+                    push_json_bind(&mut sep, v);
+                }
+            }
+            qb.push(")");
+            if spec.has_id {
+                qb.push(" RETURNING id");
+                let (new_id,): (i64,) = qb.build_query_as().fetch_one(&mut *tx).await.map_err(|e| {
+                    format!("{} row {:?}: {e}", spec.name, old_id)
+                })?;
+                if let Some(old) = old_id {
+                    map.insert(old, new_id);
+                }
+            } else {
+                qb.build().execute(&mut *tx).await.map_err(|e| format!("{}: {e}", spec.name))?;
+            }
+            tr.inserted += 1;
+        }
+
+        if !unknown.is_empty() {
+            report
+                .warnings
+                .push(format!("{}: columns not in this schema were dropped: {}", spec.name, unknown.join(", ")));
+        }
+        maps.insert(spec.name, map);
+        report.tables.insert(spec.name.to_string(), tr);
+    }
+
+    // Events outlive configuration: unlink any that now point at nothing.
+    sqlx::query(
+        "UPDATE esam_events SET matched_rule_id = NULL \
+         WHERE matched_rule_id IS NOT NULL AND matched_rule_id NOT IN (SELECT id FROM rules)",
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(err)?;
+
+    tx.commit().await.map_err(err)?;
+    Ok(report)
+}
+
+// ----------------------------------- CLI ----------------------------------
+
+/// Handle `--export-archive FILE` / `--restore-archive FILE [--replace]`.
+/// Returns the exit code when one of them ran, `None` to start normally.
+pub async fn run_cli(db: &Pool<Sqlite>) -> anyhow::Result<Option<i32>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let value_of = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned();
+    let passphrase = std::env::var("POIS_ARCHIVE_PASSPHRASE").ok().filter(|p| !p.is_empty());
+
+    if let Some(path) = value_of("--export-archive") {
+        if passphrase.is_none() {
+            eprintln!("POIS_ARCHIVE_PASSPHRASE not set: password and token hashes are left out of the archive");
+        }
+        let archive = export_archive(db, passphrase.as_deref()).await.map_err(anyhow::Error::msg)?;
+        std::fs::write(&path, serde_json::to_vec_pretty(&archive)?)?;
+        let rows: usize = archive.tables.values().map(Vec::len).sum();
+        println!("archive written to {path} ({rows} rows)");
+        return Ok(Some(0));
+    }
+
+    if let Some(path) = value_of("--restore-archive") {
+        let archive: SystemArchive = serde_json::from_slice(&std::fs::read(&path)?)?;
+        let mode = if args.iter().any(|a| a == "--replace") { RestoreMode::Replace } else { RestoreMode::Merge };
+        return match restore_archive(db, &archive, mode, passphrase.as_deref()).await {
+            Ok(report) => {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                Ok(Some(0))
+            }
+            Err(e) => {
+                eprintln!("restore failed: {e}");
+                Ok(Some(1))
+            }
+        };
+    }
+    Ok(None)
+}
+
+// -------------------------------- handlers --------------------------------
+
+#[derive(Deserialize, Default)]
+pub struct ExportArchiveRequest {
+    #[serde(default)]
+    pub passphrase: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreArchiveRequest {
+    pub archive: SystemArchive,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    #[serde(default)]
+    pub mode: RestoreMode,
+}
+
+async fn reject_unless_super(st: &AppState, claims: &Claims) -> Option<Response> {
+    let eff = rbac::effective(&st.db, claims).await;
+    (!eff.super_admin).then(|| (StatusCode::FORBIDDEN, "Admin only").into_response())
+}
+
+/// POST /api/system/archive — download a full system archive.
+pub async fn export_archive_handler(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    body: Option<Json<ExportArchiveRequest>>,
+) -> impl IntoResponse {
+    if let Some(rej) = reject_unless_super(&st, &claims).await {
+        return rej;
+    }
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    match export_archive(&st.db, req.passphrase.as_deref()).await {
+        Ok(a) => Json(a).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// POST /api/system/archive/restore — restore an archive (merge or replace).
+pub async fn restore_archive_handler(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<RestoreArchiveRequest>,
+) -> impl IntoResponse {
+    if let Some(rej) = reject_unless_super(&st, &claims).await {
+        return rej;
+    }
+    match restore_archive(&st.db, &req.archive, req.mode, req.passphrase.as_deref()).await {
+        Ok(r) => Json(r).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(name: &str) -> &'static TableSpec {
+        TABLES.iter().find(|t| t.name == name).unwrap()
+    }
+
+    #[test]
+    fn parents_precede_children() {
+        for (i, t) in TABLES.iter().enumerate() {
+            for (_, parent, _) in t.fks {
+                let p = TABLES.iter().position(|x| x.name == *parent).unwrap();
+                assert!(p < i, "{} must come after {}", t.name, parent);
+            }
+        }
+    }
+
+    #[test]
+    fn every_table_is_archived_or_operational() {
+        const OPERATIONAL: &[&str] = &["esam_events", "watchdog_alerts"];
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let sql = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            for rest in sql.split("CREATE TABLE IF NOT EXISTS ").skip(1) {
+                let name = rest.split_whitespace().next().unwrap().trim_matches('"');
+                assert!(
+                    OPERATIONAL.contains(&name) || TABLES.iter().any(|t| t.name == name),
+                    "table {name} is neither archived nor listed as operational"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn remap_rewrites_and_nulls_or_drops() {
+        let mut maps: IdMaps = HashMap::new();
+        maps.insert("channels", HashMap::from([(5, 50)]));
+        maps.insert("users", HashMap::new());
+        let mut row = json!({"id": 1, "channel_id": 5, "owner_user_id": 9}).as_object().unwrap().clone();
+        remap_row(spec("rules"), &mut row, &maps).unwrap();
+        assert_eq!(row["channel_id"], json!(50));
+        assert_eq!(row["owner_user_id"], JsonValue::Null);
+
+        let mut orphan = json!({"id": 2, "channel_id": 6}).as_object().unwrap().clone();
+        assert!(remap_row(spec("rules"), &mut orphan, &maps).is_err());
+    }
+
+    #[test]
+    fn sealed_fields_round_trip_and_bind_to_field() {
+        let (enc, key) = new_encryption("correct horse").unwrap();
+        let sealed = seal(&key, "users.password_hash", b"$argon2id$x").unwrap();
+        assert!(sealed.starts_with(ENC_PREFIX));
+        assert_eq!(open(&key, "users.password_hash", &sealed).unwrap(), b"$argon2id$x");
+        assert!(open(&key, "api_tokens.token_hash", &sealed).is_err());
+
+        assert!(unlock(&enc, Some("correct horse")).is_ok());
+        assert_eq!(unlock(&enc, Some("wrong")).unwrap_err(), "wrong passphrase");
+        assert!(unlock(&enc, None).is_err());
+
+        let mut row = json!({"password_hash": sealed}).as_object().unwrap().clone();
+        restore_secrets(spec("users"), &mut row, Some(&key), &[]).unwrap();
+        assert_eq!(row["password_hash"], json!("$argon2id$x"));
+    }
+
+    #[test]
+    fn redacted_tokens_restore_revoked() {
+        let mut row = json!({"id": 1, "user_id": 1}).as_object().unwrap().clone();
+        restore_secrets(spec("api_tokens"), &mut row, None, &["api_tokens.token_hash".into()]).unwrap();
+        assert_eq!(row["revoked"], json!(1));
+        assert!(row["token_hash"].as_str().unwrap().starts_with("redacted:"));
+    }
+}
@@ -11,6 +11,13 @@
 //! project is shared. Only the owner or an admin may edit/delete/share; any user
 //! may **apply** what they can see. Apply always creates NEW rows owned by the
 //! applying user and never mutates the source.
+//!
+//! Templates may declare typed variables (`template_vars`); their `${name}`
+//! placeholders are resolved from the apply request before anything is created.
+//! A project can be stamped out once per row of a JSON/CSV variable matrix.
+//!
+//! Every apply links its result back to the template (`template_sync`), so a
+//! later template fix can be re-synced into the copies.
 
 use std::sync::Arc;
 
@@ -21,16 +28,18 @@ use axum::{
     Extension, Json,
 };
 use serde::Deserialize;
-use serde_json::{json, Value as JsonValue};
+use serde_json::{json, Map, Value as JsonValue};
 use sqlx::{Pool, QueryBuilder, Sqlite};
 
 use crate::backup::{ChannelBackup, ChannelFullBackup, RuleBackup};
 use crate::jwt_auth::Claims;
 use crate::models::{
-    ApplyTemplate, Channel, Project, Rule, SaveTemplate, Template, UpdateProjectMeta,
-    UpdateTemplateMeta, UpsertProject,
+    ApplyProject, ApplyTemplate, Channel, Project, Rule, SaveTemplate, Template,
+    UpdateProjectMeta, UpdateTemplateMeta, UpsertProject,
 };
 use crate::rbac;
+use crate::template_sync;
+use crate::template_vars::{self, TemplateVariable};
 use crate::AppState;
 
 // ----------------------------- small helpers -----------------------------
@@ -120,6 +129,41 @@ async fn unique_channel_name(db: &Pool<Sqlite>, base: &str) -> String {
     format!("{base} (copy 10000)")
 }
 
+/// Resolve `supplied` against the template's declared variables and substitute
+/// them into its body. Returns the rendered body plus the resolved variable set
+/// (echoed in apply responses). A template without variables renders verbatim.
+pub(crate) fn render_body(
+    t: &Template,
+    supplied: &Map<String, JsonValue>,
+    strict: bool,
+) -> Result<(JsonValue, Map<String, JsonValue>), String> {
+    let decls = template_vars::parse_decls(&t.variables_json)?;
+    let vars = template_vars::resolve(&decls, supplied, strict).map_err(|e| e.join("; "))?;
+    let body: JsonValue = serde_json::from_str(&t.body_json).map_err(|e| e.to_string())?;
+    let rendered = template_vars::substitute(&body, &vars)?;
+    Ok((rendered, vars))
+}
+
+/// Light structural check on a replacement body. Placeholders may stand in for
+/// typed fields, so the full shape is only enforced once rendered at apply.
+fn check_body_shape(kind: &str, body: &JsonValue) -> Result<(), String> {
+    let ok = match kind {
+        "rule" => body.get("action").is_some() && body.get("match_json").is_some(),
+        "channel" => body.get("channel").is_some_and(JsonValue::is_object),
+        _ => false,
+    };
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("body is not a valid {kind} template"))
+    }
+}
+
+/// Validate + serialize declared variables for storage (`None` => no variables).
+fn encode_variables(vars: Option<Vec<TemplateVariable>>) -> Result<String, String> {
+    template_vars::encode_decls(&vars.unwrap_or_default())
+}
+
 /// Ensure `eff` may write to project `pid` (group-aware). `Some(rejection)` if not.
 async fn reject_if_project_unwritable(
     db: &Pool<Sqlite>,
@@ -290,7 +334,9 @@ pub async fn apply_project(
     State(st): State<Arc<AppState>>,
     Extension(claims): Extension<Claims>,
     Path(id): Path<i64>,
+    body: Option<Json<ApplyProject>>,
 ) -> impl IntoResponse {
+    let p = body.map(|Json(p)| p).unwrap_or_default();
     // Visibility check on the project (read).
     let eff = rbac::effective(&st.db, &claims).await;
     let exists: Option<(i64,)> =
@@ -307,6 +353,35 @@ pub async fn apply_project(
         return (StatusCode::FORBIDDEN, "Not allowed to apply this project").into_response();
     }
 
+    // One variable set per matrix row (each merged over the shared `variables`),
+    // or just the shared set when no matrix is given.
+    let rows: Vec<Map<String, JsonValue>> = match (p.matrix, p.matrix_csv) {
+        (Some(_), Some(_)) => {
+            return (StatusCode::BAD_REQUEST, "Supply either matrix or matrix_csv, not both").into_response()
+        }
+        (Some(m), None) => m,
+        (None, Some(csv)) => match template_vars::parse_csv_matrix(&csv) {
+            Ok(m) => m,
+            Err(e) => return (StatusCode::BAD_REQUEST, format!("matrix_csv: {e}")).into_response(),
+        },
+        (None, None) => vec![Map::new()],
+    };
+    if rows.is_empty() || rows.len() > template_vars::MAX_MATRIX_ROWS {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Variable matrix must have 1..={} rows", template_vars::MAX_MATRIX_ROWS),
+        )
+            .into_response();
+    }
+    let rows: Vec<Map<String, JsonValue>> = rows
+        .into_iter()
+        .map(|row| {
+            let mut merged = p.variables.clone();
+            merged.extend(row);
+            merged
+        })
+        .collect();
+
     let members: Vec<Template> = match sqlx::query_as(
         "SELECT * FROM templates \
          WHERE project_id = ? AND kind = 'channel' AND deleted_at IS NULL ORDER BY name",
@@ -326,26 +401,42 @@ pub async fn apply_project(
     let mut created = Vec::new();
     let mut errors = Vec::new();
 
-    for m in members {
-        match serde_json::from_str::<ChannelFullBackup>(&m.body_json) {
-            Ok(cfb) => match instantiate_channel_full(&st.db, &cfb, owner_id, None).await {
+    for (row, supplied) in rows.iter().enumerate() {
+        for m in &members {
+            // Project rows are shared across templates: extra names are not errors.
+            let cfb = render_body(m, supplied, false).and_then(|(body, vars)| {
+                serde_json::from_value::<ChannelFullBackup>(body.clone())
+                    .map(|cfb| (cfb, body, vars))
+                    .map_err(|e| format!("invalid body: {e}"))
+            });
+            let (cfb, body, vars) = match cfb {
+                Ok(v) => v,
+                Err(e) => {
+                    errors.push(format!("row {row}, template {}: {}", m.id, e));
+                    continue;
+                }
+            };
+            match instantiate_channel_full(&st.db, &cfb, owner_id, None).await {
                 Ok((cid, cname, n_rules)) => {
                     rbac::link_groups(&st.db, "channel_groups", "channel_id", cid, &target_groups).await;
+                    template_sync::record_instance(&st.db, m, cid, None, &vars, &body, owner_id).await;
                     created.push(json!({
+                        "row": row,
                         "template_id": m.id,
                         "channel_id": cid,
                         "channel_name": cname,
                         "rules_created": n_rules,
+                        "variables": vars,
                     }));
                 }
-                Err(e) => errors.push(format!("template {}: {}", m.id, e)),
-            },
-            Err(e) => errors.push(format!("template {} has invalid body: {}", m.id, e)),
+                Err(e) => errors.push(format!("row {row}, template {}: {}", m.id, e)),
+            }
         }
     }
 
     Json(json!({
         "project_id": id,
+        "rows": rows.len(),
         "channels_created": created.len(),
         "created": created,
         "errors": errors,
@@ -418,7 +509,7 @@ pub async fn get_template(
 }
 
 /// Load a template if the caller may read it (group-aware).
-async fn load_visible_template(
+pub(crate) async fn load_visible_template(
     db: &Pool<Sqlite>,
     eff: &rbac::Eff,
     id: i64,
@@ -495,10 +586,14 @@ pub async fn save_rule_template(
         Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
     };
     let name = p.name.unwrap_or(rule.name);
+    let variables_json = match encode_variables(p.variables) {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
 
     insert_template(
-        &st.db, &eff, &name, "rule", p.description, p.project_id, &body_json, p.is_shared,
-        p.is_default, p.group_ids,
+        &st.db, &eff, &name, "rule", p.description, p.project_id, &body_json, &variables_json,
+        p.is_shared, p.is_default, p.group_ids,
     )
     .await
 }
@@ -568,10 +663,14 @@ pub async fn save_channel_template(
         Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
     };
     let name = p.name.unwrap_or(channel.name);
+    let variables_json = match encode_variables(p.variables) {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
 
     insert_template(
-        &st.db, &eff, &name, "channel", p.description, p.project_id, &body_json, p.is_shared,
-        p.is_default, p.group_ids,
+        &st.db, &eff, &name, "channel", p.description, p.project_id, &body_json, &variables_json,
+        p.is_shared, p.is_default, p.group_ids,
     )
     .await
 }
@@ -585,6 +684,7 @@ async fn insert_template(
     description: Option<String>,
     project_id: Option<i64>,
     body_json: &str,
+    variables_json: &str,
     is_shared: Option<bool>,
     is_default: Option<bool>,
     group_ids: Option<Vec<i64>>,
@@ -594,14 +694,15 @@ async fn insert_template(
     // publish to their groups instead (links below).
     let want_global = eff.super_admin && (is_shared.unwrap_or(false) || want_default);
     let r = sqlx::query_as::<_, Template>(
-        "INSERT INTO templates(name,kind,description,project_id,body_json,is_shared,is_default,is_global,owner_user_id) \
-         VALUES(?,?,?,?,?,?,?,?,?) RETURNING *",
+        "INSERT INTO templates(name,kind,description,project_id,body_json,variables_json,is_shared,is_default,is_global,owner_user_id) \
+         VALUES(?,?,?,?,?,?,?,?,?,?) RETURNING *",
     )
     .bind(name)
     .bind(kind)
     .bind(description)
     .bind(project_id)
     .bind(body_json)
+    .bind(variables_json)
     .bind(want_global as i64) // keep legacy is_shared in sync with is_global
     .bind(want_default as i64)
     .bind(want_global as i64)
@@ -671,10 +772,32 @@ pub async fn update_template(
     if p.is_default == Some(true) && eff.super_admin {
         is_global = 1;
     }
+    let variables_json = match p.variables {
+        Some(v) => match encode_variables(Some(v)) {
+            Ok(v) => v,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        },
+        None => cur.variables_json.clone(),
+    };
+    let body_json = match p.body {
+        Some(b) => {
+            if let Err(e) = check_body_shape(&cur.kind, &b) {
+                return (StatusCode::BAD_REQUEST, e).into_response();
+            }
+            b.to_string()
+        }
+        None => cur.body_json.clone(),
+    };
+    // A content change moves linked instances out of date.
+    let revision = if body_json != cur.body_json || variables_json != cur.variables_json {
+        cur.revision + 1
+    } else {
+        cur.revision
+    };
 
     let r = sqlx::query_as::<_, Template>(
         "UPDATE templates SET name=?, description=?, project_id=?, is_global=?, is_default=?, \
-           updated_at=strftime('%Y-%m-%dT%H:%M:%fZ','now') \
+           variables_json=?, body_json=?, revision=?, updated_at=strftime('%Y-%m-%dT%H:%M:%fZ','now') \
          WHERE id=? AND deleted_at IS NULL RETURNING *",
     )
     .bind(name)
@@ -682,6 +805,9 @@ pub async fn update_template(
     .bind(project_id)
     .bind(is_global)
     .bind(is_default)
+    .bind(variables_json)
+    .bind(body_json)
+    .bind(revision)
     .bind(id)
     .fetch_one(&st.db)
     .await;
@@ -727,6 +853,12 @@ pub async fn apply_template(
         Err(rej) => return rej,
     };
     let owner_id = eff.uid;
+    // Resolve declared variables and substitute them into the body up front, so a
+    // bad/missing value fails the apply before any row is created.
+    let (body, vars) = match render_body(&t, &p.variables, true) {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
 
     match t.kind.as_str() {
         "rule" => {
@@ -741,8 +873,11 @@ pub async fn apply_template(
             if !rbac::can_write(&st.db, &eff, "channels", "channel_groups", "channel_id", channel_id).await {
                 return (StatusCode::FORBIDDEN, "Not allowed to add a rule to this channel").into_response();
             }
+            if let Some(src) = crate::config_as_code::channel_manager(&st.db, channel_id).await {
+                return crate::config_as_code::read_only(&src);
+            }
 
-            let rb: RuleBackup = match serde_json::from_str(&t.body_json) {
+            let rb: RuleBackup = match serde_json::from_value(body.clone()) {
                 Ok(v) => v,
                 Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
             };
@@ -772,10 +907,13 @@ pub async fn apply_template(
             .bind(owner_id)
             .fetch_one(&st.db)
             .await;
+            if let Ok(rule) = &r {
+                template_sync::record_instance(&st.db, &t, channel_id, Some(rule.id), &vars, &body, owner_id).await;
+            }
             resp(r)
         }
         "channel" => {
-            let cfb: ChannelFullBackup = match serde_json::from_str(&t.body_json) {
+            let cfb: ChannelFullBackup = match serde_json::from_value(body.clone()) {
                 Ok(v) => v,
                 Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
             };
@@ -784,10 +922,12 @@ pub async fn apply_template(
                     // New channel lands in the applier's own group(s); super: unlinked.
                     let groups: Vec<i64> = if eff.super_admin { Vec::new() } else { eff.member_of.clone() };
                     rbac::link_groups(&st.db, "channel_groups", "channel_id", cid, &groups).await;
+                    template_sync::record_instance(&st.db, &t, cid, None, &vars, &body, owner_id).await;
                     Json(json!({
                         "channel_id": cid,
                         "channel_name": cname,
                         "rules_created": n_rules,
+                        "variables": vars,
                     }))
                     .into_response()
                 }
@@ -0,0 +1,797 @@
+// src/template_sync.rs
+//! Linked template instances: drift detection + re-sync.
+//!
+//! Every template apply records a `template_instances` row (the created channel,
+//! or rule for a rule template) with the template revision it came from, the
+//! resolved variables, and a snapshot of the rendered body. Editing a template's
+//! body/variables bumps `templates.revision`, so an instance is:
+//!   - **outdated** when its `template_revision` is behind the template, and
+//!   - **locally modified** when its live rows differ from the snapshot.
+//!
+//! Re-sync is a three-way merge per field: base = snapshot, local = live rows,
+//! template = the current template re-rendered with the instance's variables.
+//! Fields only the template changed are updated; fields changed locally are
+//! kept (reported as `keep_local`, or `conflict` when both sides changed).
+//! Channel instances match rules by name: template-added rules are created,
+//! template-removed rules are deleted unless edited locally, and rules that only
+//! exist locally are left alone. `GET .../resync` previews, `POST` applies.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+
+use crate::backup::{ChannelFullBackup, RuleBackup};
+use crate::jwt_auth::Claims;
+use crate::models::{Channel, ResyncInstance, Rule, Template, TemplateInstance};
+use crate::rbac;
+use crate::template_library;
+use crate::AppState;
+
+/// Channel fields that follow the template (the name is always local).
+const CHANNEL_FIELDS: &[&str] = &["enabled", "timezone"];
+/// Rule fields that follow a channel template.
+const RULE_FIELDS: &[&str] = &["match_json", "action", "params_json", "priority", "enabled"];
+/// A rule template's priority is assigned at apply time, so it never syncs.
+const RULE_TEMPLATE_FIELDS: &[&str] = &["match_json", "action", "params_json", "enabled"];
+
+// ------------------------------ merge model ------------------------------
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    /// Only the template changed: take the template value.
+    Update,
+    /// Only the instance changed: the local override is preserved.
+    KeepLocal,
+    /// Both changed (differently): the local value is preserved.
+    Conflict,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub base: JsonValue,
+    pub local: JsonValue,
+    pub template: JsonValue,
+    pub resolution: Resolution,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOp {
+    Update,
+    Add,
+    Delete,
+    Keep,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleSync {
+    pub name: String,
+    pub op: RuleOp,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<i64>,
+    pub changes: Vec<FieldChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Final field values to write for `update`/`add`.
+    #[serde(skip)]
+    pub merged: Map<String, JsonValue>,
+    /// A kept rule whose template side also changed.
+    #[serde(skip)]
+    pub conflict: bool,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct SyncPlan {
+    pub channel: Vec<FieldChange>,
+    pub rules: Vec<RuleSync>,
+    pub updates: usize,
+    pub conflicts: usize,
+    /// Live rules with no template origin (not in the snapshot); never touched.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub local_only: Vec<String>,
+    #[serde(skip)]
+    pub channel_merged: Map<String, JsonValue>,
+}
+
+impl SyncPlan {
+    fn tally(mut self) -> Self {
+        let all = self.channel.iter().chain(self.rules.iter().flat_map(|r| r.changes.iter()));
+        let (mut u, mut c) = (0, 0);
+        for f in all {
+            match f.resolution {
+                Resolution::Update => u += 1,
+                Resolution::Conflict => c += 1,
+                Resolution::KeepLocal => {}
+            }
+        }
+        u += self.rules.iter().filter(|r| matches!(r.op, RuleOp::Add | RuleOp::Delete)).count();
+        self.updates = u;
+        self.conflicts = c + self.rules.iter().filter(|r| r.conflict).count();
+        self
+    }
+
+    /// True when applying the plan would change nothing.
+    pub fn is_noop(&self) -> bool {
+        self.updates == 0
+    }
+
+    /// True when the instance differs from its base anywhere.
+    pub fn has_local_changes(&self) -> bool {
+        self.channel.iter().any(|f| f.resolution != Resolution::Update)
+            || self.rules.iter().any(|r| r.op != RuleOp::Add && r.op != RuleOp::Delete)
+            || !self.local_only.is_empty()
+    }
+}
+
+/// Three-way merge of `fields`. Returns the per-field report (only fields where
+/// something differs) and the merged values.
+pub fn merge_fields(
+    base: &Map<String, JsonValue>,
+    local: &Map<String, JsonValue>,
+    theirs: &Map<String, JsonValue>,
+    fields: &[&str],
+) -> (Vec<FieldChange>, Map<String, JsonValue>) {
+    let mut changes = Vec::new();
+    let mut merged = Map::new();
+    for &f in fields {
+        let b = base.get(f).cloned().unwrap_or(JsonValue::Null);
+        let l = local.get(f).cloned().unwrap_or(JsonValue::Null);
+        let t = theirs.get(f).cloned().unwrap_or(JsonValue::Null);
+        let resolution = if l == t {
+            None
+        } else if t == b {
+            Some(Resolution::KeepLocal)
+        } else if l == b {
+            Some(Resolution::Update)
+        } else {
+            Some(Resolution::Conflict)
+        };
+        let value = if resolution == Some(Resolution::Update) { t.clone() } else { l.clone() };
+        merged.insert(f.to_string(), value);
+        if let Some(resolution) = resolution {
+            changes.push(FieldChange { field: f.to_string(), base: b, local: l, template: t, resolution });
+        }
+    }
+    (changes, merged)
+}
+
+pub fn rule_fields(rb: &RuleBackup) -> Map<String, JsonValue> {
+    let mut m = Map::new();
+    m.insert("match_json".into(), rb.match_json.clone());
+    m.insert("action".into(), json!(rb.action));
+    m.insert("params_json".into(), rb.params_json.clone());
+    m.insert("priority".into(), json!(rb.priority));
+    m.insert("enabled".into(), json!(rb.enabled));
+    m
+}
+
+fn channel_fields(cfb: &ChannelFullBackup) -> Map<String, JsonValue> {
+    let mut m = Map::new();
+    m.insert("enabled".into(), json!(cfb.channel.enabled));
+    m.insert("timezone".into(), json!(cfb.channel.timezone));
+    m
+}
+
+fn same_fields(a: &Map<String, JsonValue>, b: &Map<String, JsonValue>, fields: &[&str]) -> bool {
+    fields.iter().all(|f| a.get(*f) == b.get(*f))
+}
+
+/// Plan a single rule re-sync (rule templates). `local` is the live rule.
+pub fn plan_rule(base: &RuleBackup, local: &RuleBackup, theirs: &RuleBackup, rule_id: i64) -> SyncPlan {
+    let (changes, merged) =
+        merge_fields(&rule_fields(base), &rule_fields(local), &rule_fields(theirs), RULE_TEMPLATE_FIELDS);
+    let mut plan = SyncPlan::default();
+    if !changes.is_empty() {
+        let op = if changes.iter().any(|c| c.resolution == Resolution::Update) {
+            RuleOp::Update
+        } else {
+            RuleOp::Keep
+        };
+        plan.rules.push(RuleSync { name: local.name.clone(), op, rule_id: Some(rule_id), changes, note: None, merged, conflict: false });
+    }
+    plan.tally()
+}
+
+/// Plan a channel re-sync. `local` rules carry their live ids (same order).
+pub fn plan_channel(
+    base: &ChannelFullBackup,
+    local: &ChannelFullBackup,
+    local_ids: &[i64],
+    theirs: &ChannelFullBackup,
+) -> SyncPlan {
+    let (channel, channel_merged) =
+        merge_fields(&channel_fields(base), &channel_fields(local), &channel_fields(theirs), CHANNEL_FIELDS);
+
+    // First rule per name wins (names are not unique in the schema).
+    fn by_name(rules: &[RuleBackup]) -> HashMap<&str, (usize, &RuleBackup)> {
+        let mut m = HashMap::new();
+        for (i, r) in rules.iter().enumerate() {
+            m.entry(r.name.as_str()).or_insert((i, r));
+        }
+        m
+    }
+    let b = by_name(&base.rules);
+    let l = by_name(&local.rules);
+    let t = by_name(&theirs.rules);
+
+    let mut rules = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for tr in &theirs.rules {
+        if !seen.insert(tr.name.as_str()) {
+            continue;
+        }
+        let tf = rule_fields(tr);
+        match (b.get(tr.name.as_str()), l.get(tr.name.as_str())) {
+            (Some((_, br)), Some((li, lr))) => {
+                let (changes, merged) = merge_fields(&rule_fields(br), &rule_fields(lr), &tf, RULE_FIELDS);
+                if changes.is_empty() {
+                    continue;
+                }
+                let op = if changes.iter().any(|c| c.resolution == Resolution::Update) {
+                    RuleOp::Update
+                } else {
+                    RuleOp::Keep
+                };
+                rules.push(RuleSync {
+                    name: tr.name.clone(),
+                    op,
+                    rule_id: local_ids.get(*li).copied(),
+                    changes,
+                    note: None,
+                    merged,
+                    conflict: false,
+                });
+            }
+            (Some((_, br)), None) => {
+                // Deleted locally: that is an override too; never re-create.
+                let conflict = rule_fields(br) != tf;
+                let note = if conflict {
+                    "deleted locally but changed in template; not re-created"
+                } else {
+                    "deleted locally"
+                };
+                rules.push(RuleSync {
+                    name: tr.name.clone(),
+                    op: RuleOp::Keep,
+                    rule_id: None,
+                    changes: Vec::new(),
+                    note: Some(note.into()),
+                    merged: Map::new(),
+                    conflict,
+                });
+            }
+            (None, None) => rules.push(RuleSync {
+                name: tr.name.clone(),
+                op: RuleOp::Add,
+                rule_id: None,
+                changes: Vec::new(),
+                note: None,
+                merged: tf,
+                conflict: false,
+            }),
+            (None, Some((li, lr))) => {
+                if same_fields(&rule_fields(lr), &tf, RULE_FIELDS) {
+                    continue;
+                }
+                rules.push(RuleSync {
+                    name: tr.name.clone(),
+                    op: RuleOp::Keep,
+                    rule_id: local_ids.get(*li).copied(),
+                    changes: Vec::new(),
+                    note: Some("added by template but a local rule with this name exists".into()),
+                    merged: Map::new(),
+                    conflict: true,
+                });
+            }
+        }
+    }
+    // Rules the template dropped.
+    for br in &base.rules {
+        if t.contains_key(br.name.as_str()) || !seen.insert(br.name.as_str()) {
+            continue;
+        }
+        let Some((li, lr)) = l.get(br.name.as_str()) else { continue };
+        let unchanged = same_fields(&rule_fields(br), &rule_fields(lr), RULE_FIELDS);
+        rules.push(RuleSync {
+            name: br.name.clone(),
+            op: if unchanged { RuleOp::Delete } else { RuleOp::Keep },
+            rule_id: local_ids.get(*li).copied(),
+            changes: Vec::new(),
+            note: (!unchanged).then(|| "removed from template but modified locally".into()),
+            merged: Map::new(),
+            conflict: !unchanged,
+        });
+    }
+
+    // Rules added locally: left alone, but they are local changes all the same.
+    let mut local_seen = std::collections::HashSet::new();
+    let local_only = local
+        .rules
+        .iter()
+        .filter(|lr| !b.contains_key(lr.name.as_str()) && local_seen.insert(lr.name.as_str()))
+        .map(|lr| lr.name.clone())
+        .collect();
+
+    SyncPlan { channel, rules, updates: 0, conflicts: 0, local_only, channel_merged }.tally()
+}
+
+// ------------------------------ persistence ------------------------------
+
+/// Link a freshly applied channel/rule to its template. Best-effort: a failure
+/// is logged and leaves an unlinked (but otherwise fine) copy.
+pub async fn record_instance(
+    db: &Pool<Sqlite>,
+    t: &Template,
+    channel_id: i64,
+    rule_id: Option<i64>,
+    vars: &Map<String, JsonValue>,
+    snapshot: &JsonValue,
+    owner_id: i64,
+) {
+    let r = sqlx::query(
+        "INSERT INTO template_instances(template_id,template_revision,kind,channel_id,rule_id,variables_json,snapshot_json,owner_user_id) \
+         VALUES(?,?,?,?,?,?,?,?)",
+    )
+    .bind(t.id)
+    .bind(t.revision)
+    .bind(&t.kind)
+    .bind(channel_id)
+    .bind(rule_id)
+    .bind(JsonValue::Object(vars.clone()).to_string())
+    .bind(snapshot.to_string())
+    .bind(owner_id)
+    .execute(db)
+    .await;
+    if let Err(e) = r {
+        tracing::warn!("failed to record template instance for template {}: {}", t.id, e);
+    }
+}
+
+fn rule_to_backup(r: &Rule) -> RuleBackup {
+    RuleBackup {
+        name: r.name.clone(),
+        match_json: serde_json::from_str(&r.match_json).unwrap_or(JsonValue::Null),
+        action: r.action.clone(),
+        params_json: serde_json::from_str(&r.params_json).unwrap_or(JsonValue::Null),
+        priority: r.priority,
+        enabled: r.enabled != 0,
+    }
+}
+
+/// Live state of an instance, shaped like its snapshot.
+enum Local {
+    Rule(RuleBackup, i64),
+    Channel(ChannelFullBackup, Vec<i64>),
+}
+
+async fn load_local(db: &Pool<Sqlite>, inst: &TemplateInstance) -> Result<Option<Local>, String> {
+    if inst.kind == "rule" {
+        let Some(rid) = inst.rule_id else { return Ok(None) };
+        let r: Option<Rule> = sqlx::query_as("SELECT * FROM rules WHERE id = ? AND deleted_at IS NULL")
+            .bind(rid)
+            .fetch_optional(db)
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(r.map(|r| Local::Rule(rule_to_backup(&r), r.id)));
+    }
+    let ch: Option<Channel> = sqlx::query_as("SELECT * FROM channels WHERE id = ? AND deleted_at IS NULL")
+        .bind(inst.channel_id)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(ch) = ch else { return Ok(None) };
+    let rules: Vec<Rule> =
+        sqlx::query_as("SELECT * FROM rules WHERE channel_id = ? AND deleted_at IS NULL ORDER BY priority, id")
+            .bind(ch.id)
+            .fetch_all(db)
+            .await
+            .map_err(|e| e.to_string())?;
+    let cfb = ChannelFullBackup {
+        channel: crate::backup::ChannelBackup { name: ch.name, enabled: ch.enabled != 0, timezone: ch.timezone },
+        rules: rules.iter().map(rule_to_backup).collect(),
+        backup_metadata: Default::default(),
+    };
+    Ok(Some(Local::Channel(cfb, rules.iter().map(|r| r.id).collect())))
+}
+
+/// Plan `theirs` against the instance's snapshot and live state.
+fn plan_for(inst: &TemplateInstance, local: &Local, theirs: &JsonValue) -> Result<SyncPlan, String> {
+    let base: JsonValue = serde_json::from_str(&inst.snapshot_json).map_err(|e| e.to_string())?;
+    match local {
+        Local::Rule(lr, rid) => {
+            let b: RuleBackup = serde_json::from_value(base).map_err(|e| format!("snapshot: {e}"))?;
+            let t: RuleBackup = serde_json::from_value(theirs.clone()).map_err(|e| format!("template: {e}"))?;
+            Ok(plan_rule(&b, lr, &t, *rid))
+        }
+        Local::Channel(lc, ids) => {
+            let b: ChannelFullBackup = serde_json::from_value(base).map_err(|e| format!("snapshot: {e}"))?;
+            let t: ChannelFullBackup =
+                serde_json::from_value(theirs.clone()).map_err(|e| format!("template: {e}"))?;
+            Ok(plan_channel(&b, lc, ids, &t))
+        }
+    }
+}
+
+// -------------------------------- handlers --------------------------------
+
+#[derive(Deserialize)]
+pub struct InstanceQuery {
+    #[serde(default)]
+    pub template_id: Option<i64>,
+    #[serde(default)]
+    pub channel_id: Option<i64>,
+    /// Only instances that are outdated or locally modified.
+    #[serde(default)]
+    pub drifted: Option<bool>,
+}
+
+/// GET /api/template-instances — linked instances the caller can see, each with
+/// its drift status.
+pub async fn list_instances(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(q): Query<InstanceQuery>,
+) -> impl IntoResponse {
+    let eff = rbac::effective(&st.db, &claims).await;
+    let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT ti.* FROM template_instances ti \
+         JOIN channels ON channels.id = ti.channel_id AND channels.deleted_at IS NULL WHERE 1=1",
+    );
+    rbac::push_read_predicate(&mut qb, &eff, "channels", "channel_groups", "channel_id");
+    if let Some(tid) = q.template_id {
+        qb.push(" AND ti.template_id = ").push_bind(tid);
+    }
+    if let Some(cid) = q.channel_id {
+        qb.push(" AND ti.channel_id = ").push_bind(cid);
+    }
+    qb.push(" ORDER BY ti.template_id, ti.id");
+    let instances = match qb.build_query_as::<TemplateInstance>().fetch_all(&st.db).await {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let mut revisions: HashMap<i64, Option<(i64, String)>> = HashMap::new();
+    let mut out = Vec::new();
+    for inst in instances {
+        if let std::collections::hash_map::Entry::Vacant(slot) = revisions.entry(inst.template_id) {
+            let t: Option<(i64, String)> =
+                sqlx::query_as("SELECT revision, name FROM templates WHERE id = ? AND deleted_at IS NULL")
+                    .bind(inst.template_id)
+                    .fetch_optional(&st.db)
+                    .await
+                    .ok()
+                    .flatten();
+            slot.insert(t);
+        }
+        let tmpl = revisions.get(&inst.template_id).cloned().flatten();
+        let outdated = tmpl.as_ref().is_some_and(|(rev, _)| *rev > inst.template_revision);
+
+        // Local drift = live rows vs the snapshot (merge against itself).
+        let (detached, modified) = match load_local(&st.db, &inst).await {
+            Ok(Some(local)) => {
+                let snap: JsonValue = serde_json::from_str(&inst.snapshot_json).unwrap_or(JsonValue::Null);
+                let modified = plan_for(&inst, &local, &snap).map(|p| p.has_local_changes()).unwrap_or(false);
+                (false, modified)
+            }
+            _ => (true, false),
+        };
+        if q.drifted == Some(true) && !(outdated || modified) {
+            continue;
+        }
+        let mut v = serde_json::to_value(&inst).unwrap_or_else(|_| json!({}));
+        if let Some(obj) = v.as_object_mut() {
+            obj.remove("snapshot_json");
+            obj.insert("template_name".into(), json!(tmpl.as_ref().map(|t| t.1.clone())));
+            obj.insert("current_revision".into(), json!(tmpl.as_ref().map(|t| t.0)));
+            obj.insert("outdated".into(), json!(outdated));
+            obj.insert("locally_modified".into(), json!(modified));
+            obj.insert("detached".into(), json!(detached || tmpl.is_none()));
+        }
+        out.push(v);
+    }
+    Json(out).into_response()
+}
+
+/// Everything a preview/apply needs, or a ready-made rejection.
+struct Prepared {
+    inst: TemplateInstance,
+    template: Template,
+    plan: SyncPlan,
+    rendered: JsonValue,
+    vars: Map<String, JsonValue>,
+}
+
+async fn prepare(
+    st: &AppState,
+    claims: &Claims,
+    id: i64,
+    extra: &Map<String, JsonValue>,
+) -> Result<Prepared, Response> {
+    let eff = rbac::effective(&st.db, claims).await;
+    let inst: Option<TemplateInstance> = sqlx::query_as("SELECT * FROM template_instances WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&st.db)
+        .await
+        .ok()
+        .flatten();
+    let Some(inst) = inst else {
+        return Err((StatusCode::NOT_FOUND, "Template instance not found").into_response());
+    };
+    if !rbac::can_write(&st.db, &eff, "channels", "channel_groups", "channel_id", inst.channel_id).await {
+        return Err((StatusCode::FORBIDDEN, "Not allowed to modify this channel").into_response());
+    }
+    if let Some(src) = crate::config_as_code::channel_manager(&st.db, inst.channel_id).await {
+        return Err(crate::config_as_code::read_only(&src));
+    }
+    let template = template_library::load_visible_template(&st.db, &eff, inst.template_id).await?;
+
+    let local = match load_local(&st.db, &inst).await {
+        Ok(Some(l)) => l,
+        Ok(None) => {
+            return Err((StatusCode::CONFLICT, "Instance was deleted; nothing to re-sync").into_response())
+        }
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e).into_response()),
+    };
+
+    // Re-render with the recorded variables, topped up by the request (the
+    // template may have gained variables since this instance was applied).
+    let mut supplied: Map<String, JsonValue> = serde_json::from_str(&inst.variables_json).unwrap_or_default();
+    supplied.extend(extra.clone());
+    let (rendered, vars) = template_library::render_body(&template, &supplied, false)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    let plan = plan_for(&inst, &local, &rendered).map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    Ok(Prepared { inst, template, plan, rendered, vars })
+}
+
+fn plan_json(p: &Prepared, applied: bool) -> JsonValue {
+    json!({
+        "instance_id": p.inst.id,
+        "template_id": p.template.id,
+        "from_revision": p.inst.template_revision,
+        "to_revision": p.template.revision,
+        "applied": applied,
+        "up_to_date": p.plan.is_noop() && p.inst.template_revision == p.template.revision,
+        "plan": p.plan,
+    })
+}
+
+/// GET /api/template-instances/{id}/resync — preview the merge, change nothing.
+pub async fn preview_resync(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match prepare(&st, &claims, id, &Map::new()).await {
+        Ok(p) => Json(plan_json(&p, false)).into_response(),
+        Err(rej) => rej,
+    }
+}
+
+/// POST /api/template-instances/{id}/resync — apply the merge in one transaction
+/// and advance the instance to the template's current revision.
+pub async fn resync(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+    body: Option<Json<ResyncInstance>>,
+) -> impl IntoResponse {
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let p = match prepare(&st, &claims, id, &req.variables).await {
+        Ok(p) => p,
+        Err(rej) => return rej,
+    };
+    if let Some(rev) = req.expected_revision {
+        if rev != p.template.revision {
+            return (
+                StatusCode::CONFLICT,
+                format!("Template is at revision {}, not {rev}; preview again", p.template.revision),
+            )
+                .into_response();
+        }
+    }
+    let owner_id = rbac::effective(&st.db, &claims).await.uid;
+    match apply_plan(&st.db, &p, owner_id).await {
+        Ok(()) => Json(plan_json(&p, true)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+async fn apply_plan(db: &Pool<Sqlite>, p: &Prepared, owner_id: i64) -> Result<(), String> {
+    let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+    let err = |e: sqlx::Error| e.to_string();
+
+    if p.plan.channel.iter().any(|c| c.resolution == Resolution::Update) {
+        let m = &p.plan.channel_merged;
+        sqlx::query(
+            "UPDATE channels SET enabled=?, timezone=?, updated_at=strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id=?",
+        )
+        .bind(m.get("enabled").and_then(JsonValue::as_bool).unwrap_or(true) as i64)
+        .bind(m.get("timezone").and_then(JsonValue::as_str).unwrap_or("UTC"))
+        .bind(p.inst.channel_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(err)?;
+    }
+
+    let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    for r in &p.plan.rules {
+        let m = &r.merged;
+        let text = |k: &str| m.get(k).map(|v| v.to_string()).unwrap_or_else(|| "{}".into());
+        let action = m.get("action").and_then(JsonValue::as_str).unwrap_or("noop");
+        let enabled = m.get("enabled").and_then(JsonValue::as_bool).unwrap_or(true) as i64;
+        match (r.op, r.rule_id) {
+            (RuleOp::Update, Some(rid)) => {
+                // Rule-template merges carry no priority: keep the live one.
+                sqlx::query(
+                    "UPDATE rules SET match_json=?, action=?, params_json=?, enabled=?, \
+                       priority=COALESCE(?, priority), updated_at=strftime('%Y-%m-%dT%H:%M:%fZ','now') \
+                     WHERE id=?",
+                )
+                .bind(text("match_json"))
+                .bind(action)
+                .bind(text("params_json"))
+                .bind(enabled)
+                .bind(m.get("priority").and_then(JsonValue::as_i64))
+                .bind(rid)
+                .execute(&mut *tx)
+                .await
+                .map_err(err)?;
+            }
+            (RuleOp::Add, _) => {
+                sqlx::query(
+                    "INSERT INTO rules(channel_id,name,priority,enabled,match_json,action,params_json,owner_user_id) \
+                     VALUES(?,?,?,?,?,?,?,?)",
+                )
+                .bind(p.inst.channel_id)
+                .bind(&r.name)
+                .bind(m.get("priority").and_then(JsonValue::as_i64).unwrap_or(0))
+                .bind(enabled)
+                .bind(text("match_json"))
+                .bind(action)
+                .bind(text("params_json"))
+                .bind(owner_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(err)?;
+            }
+            (RuleOp::Delete, Some(rid)) => {
+                sqlx::query("UPDATE rules SET deleted_at=? WHERE id=? AND deleted_at IS NULL")
+                    .bind(&now)
+                    .bind(rid)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(err)?;
+            }
+            _ => {}
+        }
+    }
+
+    // The new rendering becomes the base: kept local overrides stay overrides.
+    sqlx::query(
+        "UPDATE template_instances SET template_revision=?, variables_json=?, snapshot_json=?, \
+           synced_at=strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE id=?",
+    )
+    .bind(p.template.revision)
+    .bind(JsonValue::Object(p.vars.clone()).to_string())
+    .bind(p.rendered.to_string())
+    .bind(p.inst.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(err)?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::ChannelBackup;
+
+    fn rule(name: &str, action: &str, priority: i64) -> RuleBackup {
+        RuleBackup {
+            name: name.into(),
+            match_json: json!({"anyOf": []}),
+            action: action.into(),
+            params_json: json!({}),
+            priority,
+            enabled: true,
+        }
+    }
+
+    fn chan(tz: &str, rules: Vec<RuleBackup>) -> ChannelFullBackup {
+        ChannelFullBackup {
+            channel: ChannelBackup { name: "c".into(), enabled: true, timezone: tz.into() },
+            rules,
+            backup_metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn merge_takes_template_only_changes_and_keeps_local_overrides() {
+        let base = json!({"a": 1, "b": 1, "c": 1, "d": 1});
+        let local = json!({"a": 1, "b": 2, "c": 2, "d": 1});
+        let theirs = json!({"a": 3, "b": 1, "c": 3, "d": 1});
+        let (changes, merged) = merge_fields(
+            base.as_object().unwrap(),
+            local.as_object().unwrap(),
+            theirs.as_object().unwrap(),
+            &["a", "b", "c", "d"],
+        );
+        assert_eq!(merged, *json!({"a": 3, "b": 2, "c": 2, "d": 1}).as_object().unwrap());
+        let res: Vec<_> = changes.iter().map(|c| (c.field.as_str(), c.resolution)).collect();
+        assert_eq!(
+            res,
+            vec![("a", Resolution::Update), ("b", Resolution::KeepLocal), ("c", Resolution::Conflict)]
+        );
+    }
+
+    #[test]
+    fn channel_plan_adds_deletes_and_preserves_local_rules() {
+        let base = chan("UTC", vec![rule("keep", "noop", 0), rule("drop", "noop", 10), rule("edited", "noop", 20)]);
+        let local = chan(
+            "America/Chicago",
+            vec![rule("keep", "noop", 0), rule("drop", "noop", 10), rule("edited", "delete", 20), rule("mine", "noop", 30)],
+        );
+        let theirs = chan("UTC", vec![rule("keep", "replace", 0), rule("new", "noop", 40)]);
+        let plan = plan_channel(&base, &local, &[1, 2, 3, 4], &theirs);
+
+        assert_eq!(plan.channel.len(), 1);
+        assert_eq!(plan.channel[0].resolution, Resolution::KeepLocal);
+        let ops: Vec<_> = plan.rules.iter().map(|r| (r.name.as_str(), r.op, r.rule_id)).collect();
+        assert_eq!(
+            ops,
+            vec![
+                ("keep", RuleOp::Update, Some(1)),
+                ("new", RuleOp::Add, None),
+                ("drop", RuleOp::Delete, Some(2)),
+                ("edited", RuleOp::Keep, Some(3)),
+            ]
+        );
+        assert_eq!(plan.rules[0].merged["action"], json!("replace"));
+        assert_eq!(plan.updates, 3);
+        assert_eq!(plan.conflicts, 1);
+        assert_eq!(plan.local_only, vec!["mine".to_string()]);
+    }
+
+    #[test]
+    fn locally_added_rule_is_a_local_change() {
+        let base = chan("UTC", vec![rule("a", "noop", 0)]);
+        let local = chan("UTC", vec![rule("a", "noop", 0), rule("mine", "delete", 10)]);
+        let plan = plan_channel(&base, &local, &[1, 2], &base);
+        assert!(plan.is_noop());
+        assert!(plan.rules.is_empty());
+        assert!(plan.has_local_changes());
+    }
+
+    #[test]
+    fn unchanged_instance_is_noop_and_not_modified() {
+        let c = chan("UTC", vec![rule("a", "noop", 0)]);
+        let plan = plan_channel(&c, &c, &[1], &c);
+        assert!(plan.is_noop());
+        assert!(!plan.has_local_changes());
+        assert!(plan.rules.is_empty());
+    }
+
+    #[test]
+    fn rule_plan_ignores_priority() {
+        let base = rule("r", "noop", 0);
+        let local = rule("renamed", "noop", 50);
+        let theirs = rule("r", "delete", 0);
+        let plan = plan_rule(&base, &local, &theirs, 9);
+        assert_eq!(plan.rules.len(), 1);
+        assert_eq!(plan.rules[0].op, RuleOp::Update);
+        assert!(!plan.rules[0].merged.contains_key("priority"));
+        assert_eq!(plan.rules[0].merged["action"], json!("delete"));
+    }
+}
@@ -0,0 +1,437 @@
+// src/template_vars.rs
+//! Parameterized templates: typed variables resolved at apply time.
+//!
+//! A template declares its variables (`templates.variables_json`, a JSON array of
+//! `TemplateVariable`). Its body may then carry `${name}` placeholders anywhere a
+//! JSON string appears (channel/rule names, match_json, params_json). At apply
+//! time the caller's values are coerced to the declared type, validated, merged
+//! over the defaults, and substituted into the body before any row is created.
+//!
+//! - A string that is exactly `${name}` becomes the typed value (so
+//!   `"duration_s": "${break_s}"` renders as the number `30`, not `"30"`).
+//! - Anywhere else the value is interpolated as text (`"${region_code}-AFE1"`).
+//! - `$${` is an escape for a literal `${`.
+//! - An unresolved placeholder is an error; nothing is created.
+//!
+//! Pure functions only (no DB/axum), so the apply handlers in
+//! `template_library.rs` stay thin and this module is unit-testable.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Upper bound on rows in one `apply_project` variable matrix.
+pub const MAX_MATRIX_ROWS: usize = 500;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VarType {
+    #[default]
+    String,
+    Integer,
+    Number,
+    Boolean,
+}
+
+/// One declared template variable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub var_type: VarType,
+    /// Used when the caller supplies no value. No default => required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Enumerated allowed values (compared after type coercion).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<Value>,
+    /// Inclusive numeric bounds (integer/number).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Inclusive length bounds (string).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    /// Glob the string value must match (same `*` syntax as rule matching).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+/// A variable name is an identifier: `[A-Za-z_][A-Za-z0-9_]*`.
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse a template's stored `variables_json` (empty/NULL-ish => no variables).
+pub fn parse_decls(variables_json: &str) -> Result<Vec<TemplateVariable>, String> {
+    if variables_json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(variables_json).map_err(|e| format!("invalid variables_json: {e}"))
+}
+
+/// Check a set of declarations (unique identifier names, defaults that satisfy
+/// their own constraints) and serialize them for storage.
+pub fn encode_decls(decls: &[TemplateVariable]) -> Result<String, String> {
+    let mut seen = HashSet::new();
+    for d in decls {
+        if !valid_name(&d.name) {
+            return Err(format!("invalid variable name '{}'", d.name));
+        }
+        if !seen.insert(d.name.as_str()) {
+            return Err(format!("duplicate variable '{}'", d.name));
+        }
+        if let Some(def) = &d.default {
+            check_value(d, def).map_err(|e| format!("default for {e}"))?;
+        }
+    }
+    serde_json::to_string(decls).map_err(|e| e.to_string())
+}
+
+/// Coerce a supplied value to the declared type. Strings are accepted for every
+/// type so CSV cells and query-ish input work (`"30"` -> `30`, `"yes"` -> `true`).
+fn coerce(d: &TemplateVariable, v: &Value) -> Result<Value, String> {
+    let bad = || format!("'{}': expected {:?}, got {}", d.name, d.var_type, v);
+    match d.var_type {
+        VarType::String => match v {
+            Value::String(_) => Ok(v.clone()),
+            Value::Number(_) | Value::Bool(_) => Ok(Value::String(v.to_string())),
+            _ => Err(bad()),
+        },
+        VarType::Integer => match v {
+            Value::Number(n) if n.is_i64() || n.is_u64() => Ok(v.clone()),
+            Value::Number(n) => match n.as_f64() {
+                Some(f) if f.fract() == 0.0 => Ok(Value::from(f as i64)),
+                _ => Err(bad()),
+            },
+            Value::String(s) => s.trim().parse::<i64>().map(Value::from).map_err(|_| bad()),
+            _ => Err(bad()),
+        },
+        VarType::Number => match v {
+            Value::Number(_) => Ok(v.clone()),
+            Value::String(s) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(bad),
+            _ => Err(bad()),
+        },
+        VarType::Boolean => match v {
+            Value::Bool(_) => Ok(v.clone()),
+            Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" | "on" => Ok(Value::Bool(true)),
+                "false" | "0" | "no" | "off" => Ok(Value::Bool(false)),
+                _ => Err(bad()),
+            },
+            Value::Number(n) => match n.as_i64() {
+                Some(0) => Ok(Value::Bool(false)),
+                Some(1) => Ok(Value::Bool(true)),
+                _ => Err(bad()),
+            },
+            _ => Err(bad()),
+        },
+    }
+}
+
+/// Coerce then validate against the declaration's constraints.
+fn check_value(d: &TemplateVariable, v: &Value) -> Result<Value, String> {
+    let v = coerce(d, v)?;
+    if !d.allowed.is_empty() {
+        let ok = d
+            .allowed
+            .iter()
+            .any(|a| coerce(d, a).map(|a| a == v).unwrap_or(false));
+        if !ok {
+            return Err(format!("'{}': {} is not one of the allowed values", d.name, v));
+        }
+    }
+    if let Some(n) = v.as_f64() {
+        if d.min.is_some_and(|m| n < m) || d.max.is_some_and(|m| n > m) {
+            return Err(format!("'{}': {} is out of range", d.name, n));
+        }
+    }
+    if let Some(s) = v.as_str() {
+        let len = s.chars().count();
+        if d.min_length.is_some_and(|m| len < m) || d.max_length.is_some_and(|m| len > m) {
+            return Err(format!("'{}': length {} is out of range", d.name, len));
+        }
+        if let Some(p) = &d.pattern {
+            if !crate::rules::glob_match(p, s) {
+                return Err(format!("'{}': '{}' does not match pattern '{}'", d.name, s, p));
+            }
+        }
+    }
+    Ok(v)
+}
+
+/// Resolve the final variable set for one apply: supplied values (coerced and
+/// validated) over declared defaults. `strict` rejects supplied names the
+/// template does not declare (single-template apply); a project matrix row is
+/// shared by several templates, so it is applied non-strict. All problems are
+/// reported together.
+pub fn resolve(
+    decls: &[TemplateVariable],
+    supplied: &Map<String, Value>,
+    strict: bool,
+) -> Result<Map<String, Value>, Vec<String>> {
+    let mut out = Map::new();
+    let mut errors = Vec::new();
+    for d in decls {
+        match supplied.get(&d.name).or(d.default.as_ref()) {
+            Some(v) => match check_value(d, v) {
+                Ok(v) => {
+                    out.insert(d.name.clone(), v);
+                }
+                Err(e) => errors.push(e),
+            },
+            None => errors.push(format!("missing required variable '{}'", d.name)),
+        }
+    }
+    if strict {
+        for k in supplied.keys() {
+            if !decls.iter().any(|d| &d.name == k) {
+                errors.push(format!("unknown variable '{k}'"));
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(out)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Substitute `${name}` placeholders throughout a JSON tree (string values only;
+/// object keys are left alone).
+pub fn substitute(body: &Value, vars: &Map<String, Value>) -> Result<Value, String> {
+    Ok(match body {
+        Value::String(s) => substitute_str(s, vars)?,
+        Value::Array(a) => Value::Array(
+            a.iter()
+                .map(|v| substitute(v, vars))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(o) => {
+            let mut m = Map::new();
+            for (k, v) in o {
+                m.insert(k.clone(), substitute(v, vars)?);
+            }
+            Value::Object(m)
+        }
+        other => other.clone(),
+    })
+}
+
+fn substitute_str(s: &str, vars: &Map<String, Value>) -> Result<Value, String> {
+    // Whole-string placeholder keeps the variable's JSON type.
+    if let Some(name) = s.strip_prefix("${").and_then(|r| r.strip_suffix('}')) {
+        if valid_name(name) {
+            return vars
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("unresolved variable '${{{name}}}'"));
+        }
+    }
+    if !s.contains("${") {
+        return Ok(Value::String(s.to_string()));
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find("${") {
+        // `$${` escapes a literal `${`.
+        if i > 0 && rest.as_bytes()[i - 1] == b'$' {
+            out.push_str(&rest[..i - 1]);
+            out.push_str("${");
+            rest = &rest[i + 2..];
+            continue;
+        }
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 2..];
+        let Some(end) = after.find('}') else {
+            return Err(format!("unterminated placeholder in '{s}'"));
+        };
+        let name = &after[..end];
+        match vars.get(name) {
+            Some(Value::String(v)) => out.push_str(v),
+            Some(v) => out.push_str(&v.to_string()),
+            None => return Err(format!("unresolved variable '${{{name}}}'")),
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(Value::String(out))
+}
+
+/// Parse a CSV variable matrix: the first non-blank line is the header (variable
+/// names), each following line is one variable set. Cells are strings (coerced to
+/// the declared type on resolve); an empty cell is omitted so the default applies.
+/// Supports RFC 4180 quoting (`"a,b"`, `""` for a literal quote).
+pub fn parse_csv_matrix(text: &str) -> Result<Vec<Map<String, Value>>, String> {
+    let records = parse_csv(text)?;
+    let mut it = records.into_iter().filter(|r| r.iter().any(|c| !c.trim().is_empty()));
+    let Some(header) = it.next() else {
+        return Ok(Vec::new());
+    };
+    let header: Vec<String> = header.into_iter().map(|h| h.trim().to_string()).collect();
+    let mut rows = Vec::new();
+    for (n, rec) in it.enumerate() {
+        if rec.len() > header.len() {
+            return Err(format!("row {}: {} cells but {} columns", n + 1, rec.len(), header.len()));
+        }
+        let mut row = Map::new();
+        for (h, cell) in header.iter().zip(rec) {
+            if !h.is_empty() && !cell.is_empty() {
+                row.insert(h.clone(), Value::String(cell));
+            }
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut rec = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => rec.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                rec.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut rec));
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quoted field".into());
+    }
+    if !field.is_empty() || !rec.is_empty() {
+        rec.push(field);
+        records.push(rec);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decls() -> Vec<TemplateVariable> {
+        serde_json::from_value(json!([
+            { "name": "region_code", "pattern": "R*", "max_length": 8 },
+            { "name": "break_s", "type": "integer", "default": 30, "min": 1, "max": 600 },
+            { "name": "blackout", "type": "boolean", "default": false },
+            { "name": "tier", "allowed": ["gold", "silver"], "default": "silver" }
+        ]))
+        .unwrap()
+    }
+
+    fn vars(v: Value) -> Map<String, Value> {
+        v.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn resolve_applies_defaults_and_coerces_strings() {
+        let r = resolve(&decls(), &vars(json!({"region_code": "R12", "break_s": "45"})), true).unwrap();
+        assert_eq!(r["break_s"], json!(45));
+        assert_eq!(r["blackout"], json!(false));
+        assert_eq!(r["tier"], json!("silver"));
+    }
+
+    #[test]
+    fn resolve_reports_every_problem() {
+        let errs = resolve(
+            &decls(),
+            &vars(json!({"break_s": 9000, "tier": "bronze", "typo": 1})),
+            true,
+        )
+        .unwrap_err();
+        assert_eq!(errs.len(), 4, "{errs:?}"); // missing region_code, range, allowed, unknown
+        // Non-strict (project matrix) ignores names other templates declare.
+        let errs = resolve(&decls(), &vars(json!({"region_code": "R1", "typo": 1})), false);
+        assert!(errs.is_ok());
+    }
+
+    #[test]
+    fn pattern_uses_rule_glob() {
+        assert!(resolve(&decls(), &vars(json!({"region_code": "X1"})), true).is_err());
+    }
+
+    #[test]
+    fn substitute_keeps_type_for_whole_placeholders() {
+        let body = json!({
+            "name": "${region_code}-break",
+            "params_json": { "duration_s": "${break_s}", "literal": "$${not_a_var}" },
+            "match_json": { "anyOf": [{ "scte35.segmentation_upid": "*${region_code}*" }] }
+        });
+        let v = vars(json!({"region_code": "R12", "break_s": 45}));
+        let out = substitute(&body, &v).unwrap();
+        assert_eq!(out["name"], json!("R12-break"));
+        assert_eq!(out["params_json"]["duration_s"], json!(45));
+        assert_eq!(out["params_json"]["literal"], json!("${not_a_var}"));
+        assert_eq!(out["match_json"]["anyOf"][0]["scte35.segmentation_upid"], json!("*R12*"));
+    }
+
+    #[test]
+    fn substitute_rejects_unresolved() {
+        let err = substitute(&json!({"a": "x-${nope}"}), &Map::new()).unwrap_err();
+        assert!(err.contains("nope"), "{err}");
+    }
+
+    #[test]
+    fn encode_rejects_bad_declarations() {
+        let dup: Vec<TemplateVariable> =
+            serde_json::from_value(json!([{ "name": "a" }, { "name": "a" }])).unwrap();
+        assert!(encode_decls(&dup).is_err());
+        let bad_default: Vec<TemplateVariable> =
+            serde_json::from_value(json!([{ "name": "n", "type": "integer", "default": "x" }])).unwrap();
+        assert!(encode_decls(&bad_default).is_err());
+        let bad_name: Vec<TemplateVariable> =
+            serde_json::from_value(json!([{ "name": "9lives" }])).unwrap();
+        assert!(encode_decls(&bad_name).is_err());
+    }
+
+    #[test]
+    fn csv_matrix_with_quotes_and_blank_cells() {
+        let rows = parse_csv_matrix(
+            "station_call_sign,region_code,break_s\r\nWABC,R1,\n\"K,XYZ\",\"R\"\"2\",60\n\n",
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["station_call_sign"], json!("WABC"));
+        assert!(rows[0].get("break_s").is_none(), "blank cell falls back to default");
+        assert_eq!(rows[1]["station_call_sign"], json!("K,XYZ"));
+        assert_eq!(rows[1]["region_code"], json!("R\"2"));
+        assert!(parse_csv_matrix("a\n\"open").is_err());
+    }
+}
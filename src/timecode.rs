@@ -0,0 +1,258 @@
+// src/timecode.rs
+//
+// Frame rates and SMPTE timecode (SMPTE ST 12-1) for the frame-oriented SCTE-35
+// tools: SCTE-104 pre-roll and VITC timestamps, duration_extension_frames,
+// PTS-relative timecode in the decoder and frame-snapped break durations.
+//
+// A frame rate is kept as an exact rational (30000/1001, not 29.97) so that
+// 90 kHz tick <-> frame conversions do not drift. Drop-frame counting (29.97 and
+// 59.94 only) skips frame numbers 0 and 1 (0-3 at 59.94) at the start of every
+// minute except each tenth minute; no frames are dropped, only labels.
+
+use std::fmt;
+
+/// The MPEG-2 system clock the SCTE-35 pts_time and durations are counted in.
+pub const TICKS_PER_SECOND: u64 = 90_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRate {
+    pub num: u32,
+    pub den: u32,
+    pub drop_frame: bool,
+}
+
+impl FrameRate {
+    pub const NTSC: Self = Self { num: 30000, den: 1001, drop_frame: false };
+
+    /// Accepts "25", "29.97", "29.97df", "59.94 NDF", "30000/1001", ... A
+    /// fractional NTSC rate is non-drop unless marked "df". Only broadcast
+    /// rates, 23.976 to 60 fps, are accepted: timecode counts whole frames per
+    /// second, so a rate under 1 fps would have none.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let t = s.trim().to_ascii_lowercase();
+        let (rate, drop_frame) = if let Some(r) = t.strip_suffix("ndf") {
+            (r, false)
+        } else if let Some(r) = t.strip_suffix("df") {
+            (r, true)
+        } else {
+            (t.as_str(), false)
+        };
+        let rate = rate.trim().trim_end_matches(['-', '_']).trim();
+        let (num, den) = match rate {
+            "23.976" | "23.98" => (24000, 1001),
+            "29.97" => (30000, 1001),
+            "47.952" => (48000, 1001),
+            "59.94" => (60000, 1001),
+            _ => match rate.split_once('/') {
+                Some((n, d)) => (
+                    n.trim().parse().map_err(|_| format!("invalid frame rate '{s}'"))?,
+                    d.trim().parse().map_err(|_| format!("invalid frame rate '{s}'"))?,
+                ),
+                None => (rate.parse().map_err(|_| format!("invalid frame rate '{s}'"))?, 1),
+            },
+        };
+        if den == 0 {
+            return Err(format!("invalid frame rate '{s}'"));
+        }
+        let (n, d) = (num as u64, den as u64);
+        if n * 1001 < 24000 * d || n > 60 * d {
+            return Err(format!("frame rate '{s}' is outside 23.976 to 60 fps"));
+        }
+        let fr = Self { num, den, drop_frame };
+        if drop_frame && fr.drop_per_minute() == 0 {
+            return Err(format!("drop-frame applies only to 29.97 and 59.94, not '{s}'"));
+        }
+        Ok(fr)
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Frames per second as counted by timecode (30 for 29.97).
+    pub fn nominal(&self) -> u64 {
+        (self.num as u64 + self.den as u64 / 2) / self.den as u64
+    }
+
+    /// Frame numbers skipped per minute in drop-frame counting.
+    fn drop_per_minute(&self) -> u64 {
+        match (self.num, self.den) {
+            (30000, 1001) => 2,
+            (60000, 1001) => 4,
+            _ => 0,
+        }
+    }
+
+    /// 90 kHz ticks to whole frames, rounded to the nearest frame.
+    pub fn ticks_to_frames(&self, ticks: u64) -> u64 {
+        let per = TICKS_PER_SECOND as u128 * self.den as u128;
+        ((ticks as u128 * self.num as u128 + per / 2) / per) as u64
+    }
+
+    /// Start of frame `frames` in 90 kHz ticks, rounded to the nearest tick.
+    pub fn frames_to_ticks(&self, frames: u64) -> u64 {
+        let n = frames as u128 * TICKS_PER_SECOND as u128 * self.den as u128;
+        ((n + self.num as u128 / 2) / self.num as u128) as u64
+    }
+
+    /// `ticks` moved to the nearest frame boundary.
+    pub fn snap_ticks(&self, ticks: u64) -> u64 {
+        self.frames_to_ticks(self.ticks_to_frames(ticks))
+    }
+
+    /// How far `ticks` is past (+) or before (-) the nearest frame boundary.
+    pub fn frame_error_ticks(&self, ticks: u64) -> i64 {
+        ticks as i64 - self.snap_ticks(ticks) as i64
+    }
+
+    /// Milliseconds to whole frames, rounded to the nearest frame.
+    pub fn ms_to_frames(&self, ms: u64) -> u64 {
+        self.ticks_to_frames(ms * 90)
+    }
+
+    /// Frames in 24 hours of timecode (the wrap point).
+    pub fn frames_per_day(&self) -> u64 {
+        let d = if self.drop_frame { self.drop_per_minute() } else { 0 };
+        24 * 6 * (self.nominal() * 600 - 9 * d)
+    }
+
+    /// Timecode label of frame number `frames` (counted from 00:00:00:00),
+    /// wrapping at 24 hours.
+    pub fn timecode(&self, frames: u64) -> Timecode {
+        let fps = self.nominal();
+        let mut f = frames % self.frames_per_day();
+        if self.drop_frame {
+            let d = self.drop_per_minute();
+            let per_ten = fps * 600 - 9 * d;
+            let per_min = fps * 60 - d;
+            let (tens, rem) = (f / per_ten, f % per_ten);
+            f += 9 * d * tens + if rem > d { d * ((rem - d) / per_min) } else { 0 };
+        }
+        Timecode {
+            hours: (f / (fps * 3600)) as u8,
+            minutes: (f / (fps * 60) % 60) as u8,
+            seconds: (f / fps % 60) as u8,
+            frames: (f % fps) as u8,
+            drop_frame: self.drop_frame,
+        }
+    }
+
+    /// Frame number of a timecode label; the inverse of `timecode`.
+    pub fn frame_number(&self, tc: &Timecode) -> Result<u64, String> {
+        let fps = self.nominal();
+        if tc.hours > 23 || tc.minutes > 59 || tc.seconds > 59 || tc.frames as u64 >= fps {
+            return Err(format!("timecode {tc} is out of range at {fps} fps"));
+        }
+        let minutes = tc.hours as u64 * 60 + tc.minutes as u64;
+        let mut f = (minutes * 60 + tc.seconds as u64) * fps + tc.frames as u64;
+        if self.drop_frame {
+            let d = self.drop_per_minute();
+            if tc.seconds == 0 && (tc.frames as u64) < d && !tc.minutes.is_multiple_of(10) {
+                return Err(format!("timecode {tc} is dropped in drop-frame counting"));
+            }
+            f -= d * (minutes - minutes / 10);
+        }
+        Ok(f)
+    }
+}
+
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)?;
+        } else {
+            write!(f, "{}", format!("{:.3}", self.fps()).trim_end_matches('0'))?;
+        }
+        if self.drop_per_minute() > 0 {
+            write!(f, " {}", if self.drop_frame { "DF" } else { "NDF" })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub drop_frame: bool,
+}
+
+impl Timecode {
+    /// "HH:MM:SS:FF"; a ';' (or '.') before the frames marks drop-frame.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let bad = || format!("invalid timecode '{s}' (expected HH:MM:SS:FF)");
+        let s = s.trim();
+        let sep = s.rfind([':', ';', '.']).ok_or_else(bad)?;
+        let hms: Vec<&str> = s[..sep].split(':').collect();
+        if hms.len() != 3 {
+            return Err(bad());
+        }
+        let num = |v: &str| v.parse::<u8>().map_err(|_| bad());
+        Ok(Self {
+            hours: num(hms[0])?,
+            minutes: num(hms[1])?,
+            seconds: num(hms[2])?,
+            frames: num(&s[sep + 1..])?,
+            drop_frame: s[sep..].starts_with([';', '.']),
+        })
+    }
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sep = if self.drop_frame { ';' } else { ':' };
+        write!(f, "{:02}:{:02}:{:02}{sep}{:02}", self.hours, self.minutes, self.seconds, self.frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_frame_rates() {
+        assert_eq!(FrameRate::parse("29.97").unwrap(), FrameRate::NTSC);
+        assert_eq!(FrameRate::parse("29.97 DF").unwrap(), FrameRate { drop_frame: true, ..FrameRate::NTSC });
+        assert_eq!(FrameRate::parse("60000/1001ndf").unwrap(), FrameRate { num: 60000, den: 1001, drop_frame: false });
+        assert_eq!(FrameRate::parse("25").unwrap(), FrameRate { num: 25, den: 1, drop_frame: false });
+        assert!(FrameRate::parse("25df").is_err());
+        assert!(FrameRate::parse("fast").is_err());
+        assert_eq!(FrameRate::parse("24000/1001").unwrap().nominal(), 24);
+        assert_eq!(FrameRate::parse("60").unwrap().nominal(), 60);
+        // Sub-1 fps rates have no frames per second to count in a timecode.
+        for bad in ["0.5", "1/2", "1/0", "0", "23", "61", "119.88"] {
+            assert!(FrameRate::parse(bad).is_err(), "{bad}");
+        }
+        assert_eq!(FrameRate::parse("59.94df").unwrap().to_string(), "59.94 DF");
+    }
+
+    #[test]
+    fn ticks_and_frames() {
+        let ntsc = FrameRate::NTSC;
+        assert_eq!(ntsc.frames_to_ticks(1), 3003);
+        assert_eq!(ntsc.ticks_to_frames(90_000 * 60), 1798);
+        assert_eq!(FrameRate::parse("25").unwrap().ms_to_frames(2000), 50);
+        let film = FrameRate::parse("23.976").unwrap();
+        assert_eq!(film.snap_ticks(30 * 90_000), 2_698_946); // 719 frames
+        assert_eq!(film.frame_error_ticks(30 * 90_000), 1_054);
+        assert_eq!(ntsc.frame_error_ticks(3003 * 7), 0);
+    }
+
+    #[test]
+    fn drop_frame_labels_round_trip() {
+        let df = FrameRate::parse("29.97df").unwrap();
+        assert_eq!(df.timecode(1800).to_string(), "00:01:00;02");
+        assert_eq!(df.timecode(17982).to_string(), "00:10:00;00");
+        assert_eq!(df.frames_per_day(), 2_589_408);
+        for f in [0, 1799, 1800, 17981, 17982, 2_589_407] {
+            assert_eq!(df.frame_number(&df.timecode(f)).unwrap(), f);
+        }
+        assert!(df.frame_number(&Timecode::parse("00:01:00;00").unwrap()).is_err());
+
+        let ndf = FrameRate::NTSC;
+        assert_eq!(ndf.timecode(1800).to_string(), "00:01:00:00");
+        assert_eq!(ndf.frame_number(&Timecode::parse("01:00:00:00").unwrap()).unwrap(), 108_000);
+    }
+}
@@ -1,11 +1,47 @@
 // src/tools_api.rs
-// Version: 4.0.7
+// Version: 4.8.0
 // Created: 2024-11-17
-// Updated: 2026-03-12
+// Updated: 2026-10-18
 // 
 // Enhanced SCTE-35 Tools API - Decoder, Validator, Test Sender, Advanced Builder
 //
 // Changelog:
+// v4.8.0 (2026-10-18): /decode takes an optional `frame_rate` and adds `frames`:
+//   every pts_time (pts_adjustment applied), break_duration and
+//   segmentation_duration in frames, with a PTS-relative HH:MM:SS:FF label (not
+//   a broadcast timecode) and its distance from the nearest frame boundary
+//   - snap_break_durations_b64 rounds durations to whole frames (shorten/extend
+//     `frame_rate` param)
+// v4.7.0 (2026-10-18): POST /api/tools/scte35/extract-ts - SCTE-35 cues from an
+//   uploaded MPEG-TS capture (PAT/PMT stream_type 0x86, sections reassembled
+//   across packets) with packet offset and last PCR/PTS; `?channel_id=` sends
+//   them through the channel's rules as Quick Test signals
+//   - Quick Test split into test_channel / send_test_signal for reuse
+// v4.6.0 (2026-10-18): POST /api/tools/scte104/convert - SCTE-35 <-> SCTE-104
+//   multiple_operation_message (splice_request, splice_null, time_signal_request,
+//   insert segmentation / DTMF / avail descriptor) with frame-rate aware pre-roll
+//   (ms or frames) and a VITC timestamp derived from the splice point timecode
+// v4.5.0 (2026-10-18): /validate runs the splice_lint conformance checks and returns
+//   structured `findings` (severity, code, location, message); `valid` means no
+//   error-severity finding. Optional `previous` cues give end-without-start context
+// v4.4.0 (2026-10-18): Full splice_insert / time_signal builder via /build and rule
+//   `build` params: every splice_insert field (event id, out/in, program or
+//   component splice, splice_time, break_duration + auto_return, unique_program_id,
+//   avail_num/avails_expected) and any number of segmentation descriptors (event id,
+//   delivery restrictions, segment/sub-segment numbering, components, MID UPIDs)
+//   - AdvancedBuildRequest.event_id / pts_time are now applied
+// v4.3.0 (2026-10-18): Decoder reports every splice descriptor: time_descriptor
+//   (TAI seconds/ns, UTC offset), audio_descriptor (per-component ISO code,
+//   Bit_Stream_Mode, channels) and private descriptors by identifier; a MID
+//   segmentation_upid is also broken out into its child UPIDs
+// v4.2.0 (2026-10-18): splice_schedule (program/component mode, utc_splice_time),
+//   private_command (identifier + payload), bandwidth_reservation and splice_null
+//   are decoded in full and can be built via /build and rule `build` params
+// v4.1.0 (2026-10-18): Decoder, validator and rewrite_* helpers are built on the
+//   shared typed splice_info::SpliceInfoSection (decode -> edit -> encode) instead
+//   of a private BitReader and byte-offset patching
+//   - avail/DTMF descriptors now read past the CUEI identifier
+//   - validate also rejects sections whose structure does not parse
 // v4.0.7 (2026-03-12): Fixed /api/tools/scte35/build endpoint
 //   - BuildRequest now accepts segmentation_type_id, segmentation_upid_type, segmentation_upid
 //   - build_scte35 handler routes to advanced builder when segmentation params present
@@ -32,7 +68,8 @@
 // v3.1.0 (2024-11-17): Initial release
 
 use axum::{
-    extract::State,
+    body::Bytes,
+    extract::{Query, State},
     Extension,
     http::StatusCode,
     response::{IntoResponse, Response},
@@ -43,6 +80,14 @@ use base64::Engine;
 use serde::{Deserialize, Serialize};
 use crate::scte35;
 use crate::AppState;
+use pois_esam_server::splice_info::{
+    self, BreakDuration, DeliveryRestrictions, ScheduledEvent, SegmentationComponent, SegmentationDescriptor,
+    SpliceCommand, SpliceDescriptor, SpliceInfoSection, SpliceInsert, SpliceInsertComponent, SpliceTime,
+};
+use pois_esam_server::mpegts;
+use pois_esam_server::scte104::{self, MultipleOperationMessage, Operation, Timestamp};
+use pois_esam_server::splice_lint::{self, Finding, LintState, Severity};
+use pois_esam_server::timecode::{FrameRate, Timecode, TICKS_PER_SECOND};
 use crate::jwt_auth;
 
 // ============================================================================
@@ -56,6 +101,153 @@ pub struct BuildRequest {
     pub segmentation_type_id: Option<String>,
     pub segmentation_upid_type: Option<String>,
     pub segmentation_upid: Option<String>,
+    #[serde(flatten)]
+    pub command_spec: CommandSpec,
+}
+
+/// Fields for splice_schedule / private_command, shared by
+/// /api/tools/scte35/build and rule `build` params.
+#[derive(Deserialize, Default)]
+pub struct CommandSpec {
+    /// splice_schedule events.
+    #[serde(default)]
+    pub events: Vec<ScheduleEventSpec>,
+    /// private_command identifier: four ASCII characters ("ABCD") or hex ("0x41424344").
+    pub identifier: Option<String>,
+    /// private_command payload as hex.
+    pub private_bytes: Option<String>,
+    /// splice_insert: splice_event_id (default 1). Also applied to the
+    /// time_signal / splice_insert_out builders.
+    pub splice_event_id: Option<u32>,
+    /// splice_insert: splice_event_cancel_indicator.
+    #[serde(default)]
+    pub cancel: bool,
+    /// splice_insert: out_of_network_indicator (OUT when true).
+    #[serde(default = "default_true")]
+    pub out_of_network: bool,
+    /// splice_insert / time_signal splice time (33-bit, 90 kHz); immediate when
+    /// absent.
+    pub pts_time: Option<u64>,
+    /// splice_insert component mode: one entry per elementary stream.
+    #[serde(default)]
+    pub components: Vec<InsertComponentSpec>,
+    /// splice_insert break_duration; no break_duration when absent.
+    pub break_duration_seconds: Option<f64>,
+    #[serde(default = "default_true")]
+    pub auto_return: bool,
+    #[serde(default)]
+    pub unique_program_id: u16,
+    #[serde(default)]
+    pub avail_num: u8,
+    #[serde(default)]
+    pub avails_expected: u8,
+    /// splice_insert / time_signal segmentation descriptors, in order.
+    #[serde(default)]
+    pub segmentations: Vec<SegmentationSpec>,
+}
+
+#[derive(Deserialize)]
+pub struct InsertComponentSpec {
+    pub component_tag: u8,
+    /// Ignored for an immediate splice.
+    pub pts_time: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct SegmentationSpec {
+    #[serde(default)]
+    pub segmentation_event_id: u32,
+    #[serde(default)]
+    pub cancel: bool,
+    /// Hex, e.g. "0x34". Required unless `cancel`.
+    pub segmentation_type_id: Option<String>,
+    /// Hex; defaults to 0x01 (user defined) with `upid`, 0x00 (not used) without.
+    pub upid_type: Option<String>,
+    pub upid: Option<String>,
+    /// MID (0x0D) children; overrides `upid_type` / `upid`.
+    #[serde(default)]
+    pub mid: Vec<UpidSpec>,
+    pub duration_seconds: Option<f64>,
+    /// Absent means delivery_not_restricted.
+    pub delivery_restrictions: Option<DeliveryRestrictionsSpec>,
+    #[serde(default)]
+    pub segment_num: u8,
+    #[serde(default)]
+    pub segments_expected: u8,
+    /// Only for the placement-opportunity / ad-block start types (default 0 there).
+    pub sub_segment_num: Option<u8>,
+    pub sub_segments_expected: Option<u8>,
+    /// Component mode: one PTS offset per elementary stream.
+    #[serde(default)]
+    pub components: Vec<SegmentationComponentSpec>,
+}
+
+#[derive(Deserialize)]
+pub struct UpidSpec {
+    pub upid_type: String,
+    pub upid: String,
+}
+
+#[derive(Deserialize, Default)]
+pub struct DeliveryRestrictionsSpec {
+    #[serde(default)]
+    pub web_delivery_allowed: bool,
+    #[serde(default)]
+    pub no_regional_blackout: bool,
+    #[serde(default)]
+    pub archive_allowed: bool,
+    #[serde(default)]
+    pub device_restrictions: u8,
+}
+
+#[derive(Deserialize)]
+pub struct SegmentationComponentSpec {
+    pub component_tag: u8,
+    /// 33-bit, 90 kHz.
+    #[serde(default)]
+    pub pts_offset: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleEventSpec {
+    pub splice_event_id: u32,
+    #[serde(default)]
+    pub cancel: bool,
+    #[serde(default = "default_true")]
+    pub out_of_network: bool,
+    /// Program mode splice time; required unless `components` is given.
+    pub utc_splice_time: Option<UtcSpliceTimeSpec>,
+    /// Component mode: one splice time per elementary stream.
+    #[serde(default)]
+    pub components: Vec<ScheduleComponentSpec>,
+    pub duration_seconds: Option<f64>,
+    #[serde(default = "default_true")]
+    pub auto_return: bool,
+    #[serde(default)]
+    pub unique_program_id: u16,
+    #[serde(default)]
+    pub avail_num: u8,
+    #[serde(default)]
+    pub avails_expected: u8,
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleComponentSpec {
+    pub component_tag: u8,
+    pub utc_splice_time: UtcSpliceTimeSpec,
+}
+
+/// utc_splice_time as the raw field (seconds since 1980-01-06, GPS epoch) or
+/// an RFC 3339 timestamp.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum UtcSpliceTimeSpec {
+    Raw(u32),
+    Rfc3339(String),
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Serialize)]
@@ -66,6 +258,9 @@ pub struct BuildResponse {
 #[derive(Deserialize)]
 pub struct DecodeRequest {
     pub base64: String,
+    /// Also count the cue's times in frames at this rate ("29.97df", "25", ...).
+    #[serde(default)]
+    pub frame_rate: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -73,6 +268,37 @@ pub struct DecodeResponse {
     pub valid: bool,
     pub error: Option<String>,
     pub decoded: Option<DecodedScte35>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frames: Option<FrameTiming>,
+}
+
+/// The cue's times counted in frames. A PTS is program-relative, so its
+/// timecode is the label of that many frames after PTS 0, not the broadcast
+/// timecode of the splice point.
+#[derive(Serialize)]
+pub struct FrameTiming {
+    pub frame_rate: String,
+    pub times: Vec<FrameTime>,
+}
+
+#[derive(Serialize)]
+pub struct FrameTime {
+    /// Where the time sits in the cue, e.g. `splice_insert.break_duration`.
+    pub field: String,
+    /// `pts` (pts_adjustment applied) or `duration`.
+    pub kind: &'static str,
+    pub ticks: u64,
+    pub seconds: f64,
+    /// Whole frames, rounded to the nearest.
+    pub frames: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pts_relative_timecode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_timecode: Option<String>,
+    /// `ticks` on the nearest frame boundary, and how far past (+) or before
+    /// (-) it `ticks` is.
+    pub frame_aligned_ticks: u64,
+    pub frame_error_ticks: i64,
 }
 
 #[derive(Serialize)]
@@ -99,6 +325,10 @@ pub struct DescriptorInfo {
 #[derive(Deserialize)]
 pub struct ValidateRequest {
     pub base64: String,
+    /// Earlier cues of the same stream, oldest first, so that an end type can
+    /// be checked against the starts before it.
+    #[serde(default)]
+    pub previous: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -106,10 +336,10 @@ pub struct ValidateResponse {
     pub valid: bool,
     pub error: Option<String>,
     pub info: Option<String>,
+    pub findings: Vec<Finding>,
 }
 
 #[derive(Deserialize)]
-#[allow(dead_code)]
 pub struct AdvancedBuildRequest {
     pub command: String,
     pub duration_seconds: Option<u32>,
@@ -133,6 +363,113 @@ pub struct TestSendResponse {
     pub event_id: Option<i64>,
 }
 
+/// One cue run through a channel's rules as a Quick Test signal.
+#[derive(Serialize)]
+pub struct TestSent {
+    pub event_id: i64,
+    pub action: String,
+    pub rule: Option<String>,
+}
+
+/// Uploads larger than this are refused (413) by /extract-ts.
+pub const TS_UPLOAD_LIMIT: usize = 256 * 1024 * 1024;
+/// Cues sent to a channel per /extract-ts upload at most.
+const MAX_TS_SEND: usize = 500;
+
+#[derive(Deserialize, Default)]
+pub struct ExtractTsQuery {
+    /// Also send every decodable cue to this channel as a Quick Test signal.
+    pub channel_id: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ExtractTsResponse {
+    pub packet_size: usize,
+    pub packets: u64,
+    pub programs: Vec<TsProgramInfo>,
+    pub cues: Vec<TsCueInfo>,
+    pub warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TsProgramInfo {
+    pub program_number: u16,
+    pub pmt_pid: u16,
+    pub pcr_pid: Option<u16>,
+    pub scte35_pids: Vec<u16>,
+}
+
+/// A cue found in the capture. `pcr`/`pts` are the last ones seen before it
+/// (90 kHz), also given in seconds.
+#[derive(Serialize)]
+pub struct TsCueInfo {
+    pub pid: u16,
+    pub program_number: u16,
+    pub packet: u64,
+    pub offset: u64,
+    pub pcr: Option<u64>,
+    pub pcr_seconds: Option<f64>,
+    pub pts: Option<u64>,
+    pub pts_seconds: Option<f64>,
+    pub base64: String,
+    pub decoded: Option<DecodedScte35>,
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sent: Option<TestSent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub send_error: Option<String>,
+}
+
+/// Exactly one of `scte35` (base64, hex or binary) or `scte104` (hex or base64
+/// multiple_operation_message) is converted to the other.
+#[derive(Deserialize)]
+pub struct Scte104ConvertRequest {
+    #[serde(default)]
+    pub scte35: Option<String>,
+    #[serde(default)]
+    pub scte104: Option<String>,
+    /// "29.97", "29.97df", "25", "60000/1001", ... (default 29.97 NDF).
+    #[serde(default)]
+    pub frame_rate: Option<String>,
+    #[serde(default)]
+    pub pre_roll_ms: Option<u16>,
+    /// Alternative to `pre_roll_ms`, counted at `frame_rate`.
+    #[serde(default)]
+    pub pre_roll_frames: Option<u32>,
+    /// SCTE-35 -> 104: splice point timecode ("HH:MM:SS:FF"); the message gets
+    /// a VITC timestamp one pre-roll earlier.
+    #[serde(default)]
+    pub timecode: Option<String>,
+    #[serde(default)]
+    pub as_index: u8,
+    #[serde(default)]
+    pub message_number: u8,
+    #[serde(default)]
+    pub dpi_pid_index: u16,
+    /// SCTE-104 -> 35: PTS at the message timestamp; the splice_time is this
+    /// plus the pre-roll.
+    #[serde(default)]
+    pub pts_time: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct Scte104ConvertResponse {
+    pub direction: &'static str,
+    pub frame_rate: String,
+    pub scte104_hex: String,
+    pub scte104_base64: String,
+    pub scte35_base64: String,
+    pub scte35_hex: String,
+    pub timestamp: serde_json::Value,
+    /// Splice point timecode when the message carries a VITC timestamp.
+    pub splice_timecode: Option<String>,
+    pub operations: Vec<serde_json::Value>,
+    /// What one side could not carry (component splices, exact PTS, ...).
+    pub warnings: Vec<String>,
+}
+
 // ============================================================================
 // API HANDLERS
 // ============================================================================
@@ -143,16 +480,34 @@ pub async fn build_scte35(
     Extension(_claims): Extension<jwt_auth::Claims>,
     Json(req): Json<BuildRequest>,
 ) -> Response {
-    // Route to advanced builder if segmentation params present
-    if req.segmentation_type_id.is_some() || req.segmentation_upid_type.is_some() {
+    // Commands without a splice time / segmentation descriptor.
+    if let Some(built) = build_other_command(&req.command, &req.command_spec) {
+        return match built {
+            Ok(b64) => Json(BuildResponse { base64: b64 }).into_response(),
+            Err(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e})),
+            )
+                .into_response(),
+        };
+    }
+
+    // Route to advanced builder if segmentation params, an event id or a splice
+    // time are present
+    let spec = &req.command_spec;
+    if req.segmentation_type_id.is_some()
+        || req.segmentation_upid_type.is_some()
+        || spec.splice_event_id.is_some()
+        || spec.pts_time.is_some()
+    {
         let adv = AdvancedBuildRequest {
             command: req.command,
             duration_seconds: req.duration_seconds,
             segmentation_type_id: req.segmentation_type_id,
             segmentation_upid_type: req.segmentation_upid_type,
             segmentation_upid: req.segmentation_upid,
-            event_id: None,
-            pts_time: None,
+            event_id: spec.splice_event_id,
+            pts_time: spec.pts_time,
         };
         return match build_advanced_internal(&adv) {
             Ok(b64) => Json(BuildResponse { base64: b64 }).into_response(),
@@ -182,23 +537,36 @@ pub async fn build_scte35(
     Json(BuildResponse { base64: b64 }).into_response()
 }
 
+/// The optional `frame_rate` of a decode request; blank means none.
+fn requested_frame_rate(frame_rate: Option<&str>) -> Result<Option<FrameRate>, String> {
+    frame_rate.filter(|s| !s.trim().is_empty()).map(FrameRate::parse).transpose()
+}
+
 /// POST /api/tools/scte35/decode - Decode SCTE-35 Base64 to human-readable
 pub async fn decode_scte35(
     State(_st): State<std::sync::Arc<AppState>>,
     Extension(_claims): Extension<jwt_auth::Claims>,
     Json(req): Json<DecodeRequest>,
 ) -> Response {
+    let frame_rate = match requested_frame_rate(req.frame_rate.as_deref()) {
+        Ok(fr) => fr,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response()
+        }
+    };
     match decode_scte35_internal(&req.base64) {
         Ok(decoded) => Json(DecodeResponse {
             valid: true,
             error: None,
             decoded: Some(decoded),
+            frames: frame_rate.and_then(|fr| frame_timing(&req.base64, fr).ok()),
         })
         .into_response(),
         Err(e) => Json(DecodeResponse {
             valid: false,
             error: Some(e),
             decoded: None,
+            frames: None,
         })
         .into_response(),
     }
@@ -210,22 +578,87 @@ pub async fn validate_scte35(
     Extension(_claims): Extension<jwt_auth::Claims>,
     Json(req): Json<ValidateRequest>,
 ) -> Response {
-    match validate_scte35_internal(&req.base64) {
-        Ok(info) => Json(ValidateResponse {
-            valid: true,
-            error: None,
+    match validate_scte35_internal(&req.base64, &req.previous) {
+        Ok((info, findings)) => Json(ValidateResponse {
+            valid: splice_lint::is_conformant(&findings),
+            error: findings.iter().find(|f| f.severity == Severity::Error).map(|f| f.message.clone()),
             info: Some(info),
+            findings,
         })
         .into_response(),
         Err(e) => Json(ValidateResponse {
             valid: false,
             error: Some(e),
             info: None,
+            findings: Vec::new(),
         })
         .into_response(),
     }
 }
 
+/// POST /api/tools/scte104/convert - SCTE-35 <-> SCTE-104 multiple_operation_message
+pub async fn convert_scte104(
+    State(_st): State<std::sync::Arc<AppState>>,
+    Extension(_claims): Extension<jwt_auth::Claims>,
+    Json(req): Json<Scte104ConvertRequest>,
+) -> Response {
+    match convert_scte104_internal(&req) {
+        Ok(resp) => Json(resp).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e})),
+        )
+            .into_response(),
+    }
+}
+
+/// POST /api/tools/scte35/extract-ts - SCTE-35 cues from an MPEG-TS capture
+/// (raw request body); `?channel_id=` also sends them to a channel as Quick Tests
+pub async fn extract_ts(
+    State(st): State<std::sync::Arc<AppState>>,
+    Extension(claims): Extension<jwt_auth::Claims>,
+    Query(q): Query<ExtractTsQuery>,
+    body: Bytes,
+) -> Response {
+    // Refuse a channel the caller cannot send to before scanning the upload.
+    let channel = match q.channel_id {
+        Some(channel_id) => match test_channel(&st, &claims, channel_id).await {
+            Ok(name) => Some((channel_id, name)),
+            Err(resp) => return resp,
+        },
+        None => None,
+    };
+    // A large capture takes a while to walk; keep it off the async workers.
+    let scan = match tokio::task::spawn_blocking(move || mpegts::extract(&body)).await {
+        Ok(Ok(scan)) => scan,
+        Ok(Err(e)) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": e}))).into_response();
+        }
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response();
+        }
+    };
+    let mut resp = extract_ts_response(scan);
+
+    if let Some((channel_id, channel_name)) = channel {
+        let batch = chrono::Utc::now().timestamp_millis();
+        let decoded = resp.cues.iter_mut().enumerate().filter(|(_, c)| c.decoded.is_some());
+        for (sent, (i, cue)) in decoded.enumerate() {
+            if sent == MAX_TS_SEND {
+                resp.warnings.push(format!("only the first {MAX_TS_SEND} cues were sent to the channel"));
+                break;
+            }
+            let signal_id = format!("TSTEST-{batch}-{i}");
+            match send_test_signal(&st, &claims, channel_id, &channel_name, &signal_id, &cue.base64).await {
+                Ok(r) => cue.sent = Some(r),
+                Err((_, e)) => cue.send_error = Some(e),
+            }
+        }
+        resp.channel = Some(channel_name);
+    }
+    Json(resp).into_response()
+}
+
 /// POST /api/tools/scte35/build-advanced - Advanced builder with segmentation
 #[allow(dead_code)]
 pub async fn build_advanced_scte35(
@@ -249,35 +682,52 @@ pub async fn test_send(
     Extension(claims): Extension<jwt_auth::Claims>,
     Json(req): Json<TestSendRequest>,
 ) -> Response {
-    use crate::esam::{extract_facts, build_notification};
-    use crate::rules::rule_matches;
-    use crate::models::Rule;
-    use crate::event_logging::{ClientInfo, ProcessingMetrics};
-    use std::time::Instant;
-    
+    let channel_name = match test_channel(&st, &claims, req.channel_id).await {
+        Ok(name) => name,
+        Err(resp) => return resp,
+    };
+
+    let test_signal_id = format!("QUICKTEST-{}", chrono::Utc::now().timestamp_millis());
+    match send_test_signal(&st, &claims, req.channel_id, &channel_name, &test_signal_id, &req.base64).await {
+        Ok(sent) => Json(TestSendResponse {
+            success: true,
+            message: format!(
+                "Test signal processed: {} → {} | Check Event Monitor",
+                channel_name,
+                sent.rule.as_deref().unwrap_or("no match")
+            ),
+            event_id: Some(sent.event_id),
+        })
+        .into_response(),
+        Err((status, error)) => (status, Json(serde_json::json!({"error": error}))).into_response(),
+    }
+}
+
+/// The name of a channel the caller may send test signals to.
+async fn test_channel(st: &AppState, claims: &jwt_auth::Claims, channel_id: i64) -> Result<String, Response> {
     // Verify channel exists and user has access
     let channel_check: Result<Option<(i64, String)>, _> = sqlx::query_as(
         "SELECT id, name FROM channels WHERE id = ? AND deleted_at IS NULL"
     )
-    .bind(req.channel_id)
+    .bind(channel_id)
     .fetch_optional(&st.db)
     .await;
 
-    let (channel_id, channel_name) = match channel_check {
-        Ok(Some((id, name))) => (id, name),
+    let channel_name = match channel_check {
+        Ok(Some((_, name))) => name,
         Ok(None) => {
-            return (
+            return Err((
                 StatusCode::NOT_FOUND,
                 Json(serde_json::json!({"error": "channel not found"})),
             )
-                .into_response();
+                .into_response());
         }
         Err(e) => {
-            return (
+            return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({"error": format!("database error: {}", e)})),
             )
-                .into_response();
+                .into_response());
         }
     };
 
@@ -293,25 +743,42 @@ pub async fn test_send(
         
         match owner_check {
             Ok(Some((Some(owner_id),))) if owner_id != user_id => {
-                return (
+                return Err((
                     StatusCode::FORBIDDEN,
                     Json(serde_json::json!({"error": "not your channel"})),
                 )
-                    .into_response();
+                    .into_response());
             }
             Ok(Some((None,))) => {
-                return (
+                return Err((
                     StatusCode::FORBIDDEN,
                     Json(serde_json::json!({"error": "cannot test on system channel"})),
                 )
-                    .into_response();
+                    .into_response());
             }
             _ => {}
         }
     }
-    
+    Ok(channel_name)
+}
+
+/// Run one SCTE-35 cue through a channel's rules as a Quick Test signal and
+/// log it (marked `QuickTest:<user>`); waits for the event id.
+async fn send_test_signal(
+    st: &AppState,
+    claims: &jwt_auth::Claims,
+    channel_id: i64,
+    channel_name: &str,
+    test_signal_id: &str,
+    base64: &str,
+) -> Result<TestSent, (StatusCode, String)> {
+    use crate::esam::{extract_facts, build_notification};
+    use crate::rules::rule_matches;
+    use crate::models::Rule;
+    use crate::event_logging::{ClientInfo, ProcessingMetrics};
+    use std::time::Instant;
+
     // Build a proper ESAM XML request with the SCTE-35 signal
-    let test_signal_id = format!("QUICKTEST-{}", chrono::Utc::now().timestamp_millis());
     let utc_point = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
     
     let esam_xml = format!(
@@ -322,42 +789,25 @@ pub async fn test_send(
   </AcquiredSignal>
   <sig:BinaryData signalType="SCTE35">{}</sig:BinaryData>
 </SignalProcessingEvent>"#,
-        test_signal_id, utc_point, req.base64
+        test_signal_id, utc_point, base64
     );
     
     let start = Instant::now();
     
     // Extract facts from the ESAM request
-    let facts = match extract_facts(&esam_xml) {
-        Ok(v) => v,
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({"error": format!("invalid SCTE-35: {}", e)})),
-            )
-                .into_response();
-        }
-    };
+    let facts = extract_facts(&esam_xml)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid SCTE-35: {}", e)))?;
     
     let obj = facts.as_object().cloned().unwrap_or_default();
     
     // Get rules for this channel
-    let rules = match sqlx::query_as::<_, Rule>(
+    let rules = sqlx::query_as::<_, Rule>(
         "SELECT * FROM rules WHERE channel_id=? AND enabled=1 AND deleted_at IS NULL ORDER BY priority",
     )
     .bind(channel_id)
     .fetch_all(&st.db)
     .await
-    {
-        Ok(v) => v,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"error": format!("failed to load rules: {}", e)})),
-            )
-                .into_response();
-        }
-    };
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to load rules: {}", e)))?;
     
     // Find matching rule
     let matched_rule: Option<Rule> = rules.into_iter().find(|r| {
@@ -369,10 +819,10 @@ pub async fn test_send(
     // Build response based on matched rule or noop
     let (action, resp_xml) = if let Some(ref r) = matched_rule {
         let params: serde_json::Value = serde_json::from_str(&r.params_json).unwrap_or_default();
-        let resp = build_notification(&test_signal_id, &utc_point, "", &r.action, &params, Some(&params));
+        let resp = build_notification(test_signal_id, &utc_point, "", &r.action, &params, Some(&params));
         (r.action.clone(), resp)
     } else {
-        let resp = build_notification(&test_signal_id, &utc_point, "", "noop", &serde_json::json!({}), None);
+        let resp = build_notification(test_signal_id, &utc_point, "", "noop", &serde_json::json!({}), None);
         ("noop".to_string(), resp)
     };
     
@@ -386,63 +836,31 @@ pub async fn test_send(
     };
     
     // Log the event - pass the actual matched rule if available
-    let log_result = if let Some(ref rule) = matched_rule {
-        st.event_logger.log_esam_event(
-            &channel_name,
-            &facts,
-            Some((rule, action.as_str())),
-            client_info,
-            ProcessingMetrics {
-                request_size: Some(esam_xml.len() as i32),
-                processing_time_ms: Some(duration.as_millis() as i32),
-                response_status: 200,
-                error_message: None,
-            },
-            Some(&esam_xml),
-            Some(&resp_xml),
-        ).await
-    } else {
-        st.event_logger.log_esam_event(
-            &channel_name,
-            &facts,
-            None,
-            client_info,
-            ProcessingMetrics {
-                request_size: Some(esam_xml.len() as i32),
-                processing_time_ms: Some(duration.as_millis() as i32),
-                response_status: 200,
-                error_message: None,
-            },
-            Some(&esam_xml),
-            Some(&resp_xml),
-        ).await
-    };
+    let log_result = st.event_logger.log_esam_event(
+        channel_name,
+        &facts,
+        matched_rule.as_ref().map(|rule| (rule, action.as_str())),
+        client_info,
+        ProcessingMetrics {
+            request_size: Some(esam_xml.len() as i32),
+            processing_time_ms: Some(duration.as_millis() as i32),
+            response_status: 200,
+            error_message: None,
+        },
+        Some(&esam_xml),
+        Some(&resp_xml),
+    ).await;
     
-    match log_result {
-        Ok(event_id) => {
-            let rule_info = matched_rule
-                .as_ref()
-                .map(|r| r.name.as_str())
-                .unwrap_or("no match");
-            
-            Json(TestSendResponse {
-                success: true,
-                message: format!(
-                    "Test signal processed: {} → {} | Check Event Monitor", 
-                    channel_name,
-                    rule_info
-                ),
-                event_id: Some(event_id),
-            })
-            .into_response()
-        }
+    // Quick Test reports the event id, so it waits for the write.
+    match log_result.event_id().await {
+        Ok(event_id) => Ok(TestSent {
+            event_id,
+            action,
+            rule: matched_rule.map(|r| r.name),
+        }),
         Err(e) => {
             tracing::error!("Failed to log test event: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"error": format!("logging failed: {}", e)})),
-            )
-                .into_response()
+            Err((StatusCode::INTERNAL_SERVER_ERROR, format!("logging failed: {}", e)))
         }
     }
 }
@@ -528,7 +946,7 @@ fn scte35_input_to_bytes(raw: &str) -> Result<Vec<u8>, String> {
     }
 }
 
-fn decode_scte35_internal(input: &str) -> Result<DecodedScte35, String> {
+pub(crate) fn decode_scte35_internal(input: &str) -> Result<DecodedScte35, String> {
     let bytes = scte35_input_to_bytes(input)?;
 
     if bytes.is_empty() {
@@ -540,339 +958,346 @@ fn decode_scte35_internal(input: &str) -> Result<DecodedScte35, String> {
         return Err(format!("Invalid table_id: 0x{:02X} (expected 0xFC)", table_id));
     }
 
-    let mut br = BitReader::new(&bytes);
-    
-    // Parse header
-    let _table_id = br.read_u8(8)?;
-    let _section_syntax = br.read_u8(1)?;
-    let _private_indicator = br.read_u8(1)?;
-    let _reserved = br.read_u8(2)?;
-    let _section_length = br.read_u16(12)?;
-    
-    let protocol_version = br.read_u8(8)?;
-    let encrypted_packet = br.read_u8(1)? == 1;
-    let _encryption_algorithm = br.read_u8(6)?;
-    let pts_adjustment = br.read_u64(33)?;
-    let _cw_index = br.read_u8(8)?;
-    let _tier = br.read_u16(12)?;
-    
-    let splice_command_length = br.read_u16(12)? as usize;
-    let command_type = br.read_u8(8)?;
-    
-    tracing::info!("Command type: 0x{:02X}, splice_command_length: {} bytes, bitpos: {}", 
-                   command_type, splice_command_length, br.bitpos);
-    
-    let command_name = match command_type {
-        0x00 => "splice_null",
-        0x04 => "splice_schedule",
-        0x05 => "splice_insert",
-        0x06 => "time_signal",
-        0x07 => "bandwidth_reservation",
-        0xFF => "private_command",
-        _ => "unknown",
+    let section = SpliceInfoSection::decode(&bytes)?;
+    let (command_type, command_type_id, command_info) = match section.encrypted_payload.as_deref() {
+        Some(payload) => (
+            "encrypted".to_string(),
+            payload.first().copied().unwrap_or(0),
+            serde_json::json!({
+                "info": "Splice command is encrypted",
+                "encryption_algorithm": section.encryption_algorithm,
+                "cw_index": section.cw_index
+            }),
+        ),
+        None => (
+            section.splice_command.name().to_string(),
+            section.splice_command.command_type(),
+            command_json(&section.splice_command),
+        ),
     };
-    
-    let _command_start_pos = br.bitpos;
-    let command_info = parse_command_info(&mut br, command_type)?;
-    
-    tracing::info!("After command parse, bitpos: {}", br.bitpos);
-    tracing::info!("Before reading descriptor_loop_length, bitpos: {}, total message bits: {}", 
-                   br.bitpos, br.data.len() * 8);
-    
-    // v4.0.5 FIX: descriptor_loop_length is 10 bits with 6 reserved bits
-    let descriptor_word = br.read_u16(16)?;
-    let descriptor_loop_length = (descriptor_word & 0x03FF) as usize;
-    
-    tracing::info!("Descriptor loop word: 0x{:04X}, masked length: {} bytes, bitpos now: {}", 
-                   descriptor_word, descriptor_loop_length, br.bitpos);
 
-    let mut descriptors = Vec::new();
-    
-    if descriptor_loop_length > 0 {
-        let desc_start = br.bitpos;
-        let desc_end = desc_start + (descriptor_loop_length * 8);
-        
-        while br.bitpos < desc_end {
-            match parse_descriptor(&mut br) {
-                Ok(desc) => {
-                    descriptors.push(desc);
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to parse descriptor at bit {}: {}", br.bitpos, e);
-                    if br.bitpos < desc_end {
-                        let remaining_bits = desc_end - br.bitpos;
-                        if let Err(skip_err) = br.skip_bits(remaining_bits as u32) {
-                            tracing::error!("Failed to skip remaining descriptor bits: {}", skip_err);
-                        }
-                    }
-                    break;
-                }
-            }
-        }
-    }
-    
     Ok(DecodedScte35 {
         table_id: format!("0x{:02X}", table_id),
-        protocol_version,
-        encrypted_packet,
-        pts_adjustment,
-        command_type: command_name.to_string(),
-        command_type_id: command_type,
+        protocol_version: section.protocol_version,
+        encrypted_packet: section.encrypted_packet,
+        pts_adjustment: section.pts_adjustment,
+        command_type,
+        command_type_id,
         command_info,
-        descriptors,
+        descriptors: section.descriptors.iter().map(descriptor_info).collect(),
         raw_hex: bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" "),
     })
 }
 
-fn parse_command_info(
-    br: &mut BitReader,
-    command_type: u8,
-) -> Result<serde_json::Value, String> {
-    match command_type {
-        0x00 => {
-            Ok(serde_json::json!({ "command": "splice_null" }))
+fn splice_time_pts(t: Option<SpliceTime>) -> Option<u64> {
+    t.and_then(|t| t.pts_time)
+}
+
+/// Every pts_time and duration of a cue, counted at `fr`.
+fn frame_timing(input: &str, fr: FrameRate) -> Result<FrameTiming, String> {
+    let section = SpliceInfoSection::decode(&scte35_input_to_bytes(input)?)?;
+    let adjusted = |pts: u64| (pts + section.pts_adjustment) & 0x1_FFFF_FFFF;
+    let mut times = Vec::new();
+    match &section.splice_command {
+        SpliceCommand::SpliceInsert(si) => {
+            if let Some(pts) = splice_time_pts(si.splice_time) {
+                times.push(frame_time(fr, "splice_insert.splice_time".into(), "pts", adjusted(pts)));
+            }
+            for (i, c) in si.components.iter().enumerate() {
+                if let Some(pts) = splice_time_pts(c.splice_time) {
+                    times.push(frame_time(fr, format!("splice_insert.components[{i}].splice_time"), "pts", adjusted(pts)));
+                }
+            }
+            if let Some(bd) = &si.break_duration {
+                times.push(frame_time(fr, "splice_insert.break_duration".into(), "duration", bd.duration));
+            }
         }
-        0x05 => {
-            let event_id = br.read_u32(32)?;
-            let event_cancel = br.read_u8(1)? == 1;
-            br.skip_bits(7)?;
-            
-            if event_cancel {
-                return Ok(serde_json::json!({
+        SpliceCommand::TimeSignal(t) => {
+            if let Some(pts) = t.pts_time {
+                times.push(frame_time(fr, "time_signal.splice_time".into(), "pts", adjusted(pts)));
+            }
+        }
+        _ => {}
+    }
+    for (i, d) in section.descriptors.iter().enumerate() {
+        if let SpliceDescriptor::Segmentation(sd) = d {
+            if let Some(ticks) = sd.segmentation_duration {
+                times.push(frame_time(fr, format!("descriptors[{i}].segmentation_duration"), "duration", ticks));
+            }
+        }
+    }
+    Ok(FrameTiming { frame_rate: fr.to_string(), times })
+}
+
+fn frame_time(fr: FrameRate, field: String, kind: &'static str, ticks: u64) -> FrameTime {
+    let frames = fr.ticks_to_frames(ticks);
+    let timecode = Some(fr.timecode(frames).to_string());
+    let (pts_relative_timecode, duration_timecode) = if kind == "pts" { (timecode, None) } else { (None, timecode) };
+    FrameTime {
+        field,
+        kind,
+        ticks,
+        seconds: ticks as f64 / TICKS_PER_SECOND as f64,
+        frames,
+        pts_relative_timecode,
+        duration_timecode,
+        frame_aligned_ticks: fr.snap_ticks(ticks),
+        frame_error_ticks: fr.frame_error_ticks(ticks),
+    }
+}
+
+fn command_json(command: &SpliceCommand) -> serde_json::Value {
+    match command {
+        SpliceCommand::SpliceNull => serde_json::json!({ "command": "splice_null" }),
+        SpliceCommand::SpliceInsert(si) => {
+            if si.splice_event_cancel_indicator {
+                return serde_json::json!({
                     "command": "splice_insert",
-                    "splice_event_id": event_id,
+                    "splice_event_id": si.splice_event_id,
                     "splice_event_cancel_indicator": true
-                }));
+                });
             }
-            
-            let out_of_network = br.read_u8(1)? == 1;
-            let program_splice_flag = br.read_u8(1)? == 1;
-            let duration_flag = br.read_u8(1)? == 1;
-            let splice_immediate_flag = br.read_u8(1)? == 1;
-            br.skip_bits(4)?;
-            
+
             let mut result = serde_json::json!({
                 "command": "splice_insert",
-                "splice_event_id": event_id,
-                "out_of_network_indicator": out_of_network,
-                "program_splice_flag": program_splice_flag,
-                "duration_flag": duration_flag,
-                "splice_immediate_flag": splice_immediate_flag
+                "splice_event_id": si.splice_event_id,
+                "out_of_network_indicator": si.out_of_network_indicator,
+                "program_splice_flag": si.program_splice_flag,
+                "duration_flag": si.break_duration.is_some(),
+                "splice_immediate_flag": si.splice_immediate_flag
             });
-            
-            if program_splice_flag && !splice_immediate_flag {
-                let time_specified = br.read_u8(1)? == 1;
-                if time_specified {
-                    br.skip_bits(6)?;
-                    let pts_time = br.read_u64(33)?;
-                    result["pts_time"] = serde_json::json!(pts_time);
-                } else {
-                    br.skip_bits(7)?;
-                }
+
+            if let Some(pts_time) = splice_time_pts(si.splice_time) {
+                result["pts_time"] = serde_json::json!(pts_time);
             }
-            
-            if !program_splice_flag {
-                let component_count = br.read_u8(8)?;
-                let mut components = Vec::new();
-                for _ in 0..component_count {
-                    let tag = br.read_u8(8)?;
-                    if !splice_immediate_flag {
-                        let time_specified = br.read_u8(1)? == 1;
-                        if time_specified {
-                            br.skip_bits(6)?;
-                            let pts = br.read_u64(33)?;
-                            components.push(serde_json::json!({"tag": tag, "pts_time": pts}));
-                        } else {
-                            br.skip_bits(7)?;
-                            components.push(serde_json::json!({"tag": tag}));
-                        }
-                    } else {
-                        components.push(serde_json::json!({"tag": tag}));
-                    }
-                }
+
+            if !si.program_splice_flag {
+                let components: Vec<_> = si
+                    .components
+                    .iter()
+                    .map(|c| match splice_time_pts(c.splice_time) {
+                        Some(pts) => serde_json::json!({"tag": c.component_tag, "pts_time": pts}),
+                        None => serde_json::json!({"tag": c.component_tag}),
+                    })
+                    .collect();
                 result["components"] = serde_json::json!(components);
             }
-            
-            if duration_flag {
-                let auto_return = br.read_u8(1)? == 1;
-                br.skip_bits(6)?;
-                let duration = br.read_u64(33)?;
+
+            if let Some(bd) = si.break_duration {
                 result["break_duration"] = serde_json::json!({
-                    "auto_return": auto_return,
-                    "duration_ticks": duration,
-                    "duration_seconds": duration as f64 / 90000.0
+                    "auto_return": bd.auto_return,
+                    "duration_ticks": bd.duration,
+                    "duration_seconds": bd.duration as f64 / 90000.0
                 });
             }
-            
-            let unique_program_id = br.read_u16(16)?;
-            let avail_num = br.read_u8(8)?;
-            let avails_expected = br.read_u8(8)?;
-            
-            result["unique_program_id"] = serde_json::json!(unique_program_id);
-            result["avail_num"] = serde_json::json!(avail_num);
-            result["avails_expected"] = serde_json::json!(avails_expected);
-            
-            Ok(result)
-        }
-        0x06 => {
-            let time_specified = br.read_u8(1)? == 1;
-            if time_specified {
-                br.skip_bits(6)?;
-                let pts_time = br.read_u64(33)?;
-                Ok(serde_json::json!({
-                    "command": "time_signal",
-                    "time_specified": true,
-                    "pts_time": pts_time
-                }))
-            } else {
-                br.skip_bits(7)?;
-                Ok(serde_json::json!({
-                    "command": "time_signal",
-                    "time_specified": false,
-                    "immediate": true
-                }))
-            }
-        }
-        0x07 => {
-            Ok(serde_json::json!({ "command": "bandwidth_reservation" }))
+
+            result["unique_program_id"] = serde_json::json!(si.unique_program_id);
+            result["avail_num"] = serde_json::json!(si.avail_num);
+            result["avails_expected"] = serde_json::json!(si.avails_expected);
+            result
         }
-        _ => Ok(serde_json::json!({"info": "Command parsing not implemented"})),
+        SpliceCommand::TimeSignal(t) => match t.pts_time {
+            Some(pts_time) => serde_json::json!({
+                "command": "time_signal",
+                "time_specified": true,
+                "pts_time": pts_time
+            }),
+            None => serde_json::json!({
+                "command": "time_signal",
+                "time_specified": false,
+                "immediate": true
+            }),
+        },
+        SpliceCommand::BandwidthReservation => serde_json::json!({ "command": "bandwidth_reservation" }),
+        SpliceCommand::SpliceSchedule(sched) => serde_json::json!({
+            "command": "splice_schedule",
+            "splice_count": sched.events.len(),
+            "events": sched.events.iter().map(scheduled_event_json).collect::<Vec<_>>()
+        }),
+        SpliceCommand::PrivateCommand { identifier, private_bytes } => serde_json::json!({
+            "command": "private_command",
+            "identifier": format!("0x{:08X}", identifier),
+            "identifier_ascii": identifier_ascii(*identifier),
+            "private_length": private_bytes.len(),
+            "private_bytes_hex": private_bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>()
+        }),
+        SpliceCommand::Unknown { command_type, body } => serde_json::json!({
+            "info": format!("Reserved splice_command_type 0x{:02X}", command_type),
+            "body_hex": body.iter().map(|b| format!("{:02X}", b)).collect::<String>()
+        }),
     }
 }
 
-fn parse_descriptor(br: &mut BitReader) -> Result<DescriptorInfo, String> {
-    let tag = br.read_u8(8)?;
-    let length = br.read_u8(8)? as usize;
-    
-    let tag_name = match tag {
-        0x00 => "avail_descriptor",
-        0x01 => "DTMF_descriptor",
-        0x02 => "segmentation_descriptor",
-        _ => "unknown",
-    };
-    
-    let data = if tag == 0x02 && length >= 6 {
-        let start_pos = br.bitpos;
-        
-        let identifier = br.read_u32(32)?;
-        tracing::info!("Segmentation descriptor identifier: 0x{:08X}", identifier);
-        
-        let seg_event_id = br.read_u32(32)?;
-        let seg_cancel = br.read_u8(1)?;
-        br.skip_bits(7)?;
-        
-        if seg_cancel == 0 && length > 5 {
-            let program_seg_flag = br.read_u8(1)?;
-            let seg_duration_flag = br.read_u8(1)?;
-            let delivery_not_restricted = br.read_u8(1)?;
-
-            // Delivery restriction flags (surfaced below for blackout/regionalize).
-            let (mut web_delivery, mut no_regional_blackout, mut archive_allowed, mut device_restrictions) =
-                (true, true, true, 3u8);
-            if delivery_not_restricted == 0 {
-                web_delivery = br.read_u8(1)? == 1;
-                no_regional_blackout = br.read_u8(1)? == 1;
-                archive_allowed = br.read_u8(1)? == 1;
-                device_restrictions = br.read_u8(2)?;
-            } else {
-                br.skip_bits(5)?;
-            }
-            
-            if program_seg_flag == 0 {
-                let component_count = br.read_u8(8)?;
-                for _ in 0..component_count {
-                    let _component_tag = br.read_u8(8)?;
-                    br.skip_bits(7)?;
-                    let _pts_offset = br.read_u64(33)?;
-                }
-            }
-            
-            let seg_duration = if seg_duration_flag == 1 {
-                Some(br.read_u64(40)?)
-            } else {
-                None
-            };
-            
-            let upid_type = br.read_u8(8)?;
-            let upid_length = br.read_u8(8)? as usize;
-            let mut upid_bytes = Vec::new();
-            for _ in 0..upid_length {
-                upid_bytes.push(br.read_u8(8)?);
-            }
-            
-            let seg_type_id = br.read_u8(8)?;
-            let segment_num = br.read_u8(8)?;
-            let segments_expected = br.read_u8(8)?;
-            
-            let upid_display = format_upid(upid_type, &upid_bytes);
-            let seg_type_name = format_segmentation_type(seg_type_id);
-            
-            let bytes_read = (br.bitpos - start_pos) / 8;
-            if bytes_read < length {
-                br.skip_bits(((length - bytes_read) * 8) as u32)?;
-            }
-            
-            serde_json::json!({
-                "identifier": format!("0x{:08X}", identifier),
-                "segmentation_event_id": seg_event_id,
-                "segmentation_type_id": format!("0x{:02X}", seg_type_id),
-                "segmentation_type_name": seg_type_name,
-                "segmentation_duration_ticks": seg_duration,
-                "segmentation_duration_seconds": seg_duration.map(|d| d as f64 / 90000.0),
-                "delivery_not_restricted": delivery_not_restricted == 1,
-                "web_delivery_allowed": web_delivery,
-                "no_regional_blackout": no_regional_blackout,
-                "archive_allowed": archive_allowed,
-                "device_restrictions": device_restrictions,
-                "upid_type": format!("0x{:02X}", upid_type),
-                "upid_type_name": format_upid_type(upid_type),
-                "upid_value": upid_display,
-                "segment_num": segment_num,
-                "segments_expected": segments_expected
+/// The identifier as text when all four bytes are printable ASCII.
+fn identifier_ascii(identifier: u32) -> Option<String> {
+    let b = identifier.to_be_bytes();
+    b.iter().all(|c| (0x20..=0x7E).contains(c)).then(|| String::from_utf8_lossy(&b).into_owned())
+}
+
+fn utc_splice_time_json(t: u32) -> serde_json::Value {
+    let iso = chrono::DateTime::from_timestamp(splice_info::utc_splice_time_to_unix(t), 0)
+        .map(|d| d.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+    serde_json::json!({ "utc_splice_time": t, "utc_splice_time_iso": iso })
+}
+
+fn scheduled_event_json(ev: &ScheduledEvent) -> serde_json::Value {
+    if ev.splice_event_cancel_indicator {
+        return serde_json::json!({
+            "splice_event_id": ev.splice_event_id,
+            "splice_event_cancel_indicator": true
+        });
+    }
+    let mut out = serde_json::json!({
+        "splice_event_id": ev.splice_event_id,
+        "out_of_network_indicator": ev.out_of_network_indicator,
+        "program_splice_flag": ev.program_splice_flag,
+        "duration_flag": ev.break_duration.is_some(),
+        "unique_program_id": ev.unique_program_id,
+        "avail_num": ev.avail_num,
+        "avails_expected": ev.avails_expected
+    });
+    if let Some(t) = ev.utc_splice_time {
+        let tj = utc_splice_time_json(t);
+        out["utc_splice_time"] = tj["utc_splice_time"].clone();
+        out["utc_splice_time_iso"] = tj["utc_splice_time_iso"].clone();
+    }
+    if !ev.program_splice_flag {
+        let comps: Vec<_> = ev
+            .components
+            .iter()
+            .map(|(tag, t)| {
+                let mut c = utc_splice_time_json(*t);
+                c["tag"] = serde_json::json!(tag);
+                c
             })
-        } else {
-            let bytes_read = (br.bitpos - start_pos) / 8;
-            if bytes_read < length {
-                br.skip_bits(((length - bytes_read) * 8) as u32)?;
-            }
+            .collect();
+        out["components"] = serde_json::json!(comps);
+    }
+    if let Some(bd) = ev.break_duration {
+        out["break_duration"] = serde_json::json!({
+            "auto_return": bd.auto_return,
+            "duration_ticks": bd.duration,
+            "duration_seconds": bd.duration as f64 / 90000.0
+        });
+    }
+    out
+}
+
+fn descriptor_info(d: &SpliceDescriptor) -> DescriptorInfo {
+    let data = match d {
+        SpliceDescriptor::Segmentation(sd) if sd.segmentation_event_cancel_indicator => serde_json::json!({
+            "identifier": format!("0x{:08X}", sd.identifier),
+            "segmentation_event_id": sd.segmentation_event_id,
+            "cancelled": true
+        }),
+        SpliceDescriptor::Segmentation(sd) => {
+            let r = &sd.delivery_restrictions;
             serde_json::json!({
-                "identifier": format!("0x{:08X}", identifier),
-                "segmentation_event_id": seg_event_id,
-                "cancelled": true
+                "identifier": format!("0x{:08X}", sd.identifier),
+                "segmentation_event_id": sd.segmentation_event_id,
+                "segmentation_type_id": format!("0x{:02X}", sd.segmentation_type_id),
+                "segmentation_type_name": format_segmentation_type(sd.segmentation_type_id),
+                "segmentation_duration_ticks": sd.segmentation_duration,
+                "segmentation_duration_seconds": sd.segmentation_duration.map(|d| d as f64 / 90000.0),
+                "delivery_not_restricted": sd.delivery_not_restricted_flag,
+                "web_delivery_allowed": r.web_delivery_allowed_flag,
+                "no_regional_blackout": r.no_regional_blackout_flag,
+                "archive_allowed": r.archive_allowed_flag,
+                "device_restrictions": r.device_restrictions,
+                "upid_type": format!("0x{:02X}", sd.segmentation_upid_type),
+                "upid_type_name": format_upid_type(sd.segmentation_upid_type),
+                "upid_value": format_upid(sd.segmentation_upid_type, &sd.segmentation_upid),
+                "upid_children": upid_children_json(sd.segmentation_upid_type, &sd.segmentation_upid),
+                "segment_num": sd.segment_num,
+                "segments_expected": sd.segments_expected,
+                "sub_segment_num": sd.sub_segment_num,
+                "sub_segments_expected": sd.sub_segments_expected,
+                "program_segmentation": sd.program_segmentation_flag,
+                "components": sd.components.iter().map(|c| serde_json::json!({
+                    "component_tag": c.component_tag,
+                    "pts_offset": c.pts_offset
+                })).collect::<Vec<_>>()
             })
         }
-    } else if tag == 0x00 && length >= 4 {
-        let provider_avail_id = br.read_u32(32)?;
-        let bytes_read = 4;
-        if bytes_read < length {
-            br.skip_bits(((length - bytes_read) * 8) as u32)?;
-        }
-        serde_json::json!({ "provider_avail_id": provider_avail_id })
-    } else if tag == 0x01 && length >= 1 {
-        let preroll = br.read_u8(8)?;
-        let dtmf_count = br.read_u8(3)?;
-        br.skip_bits(5)?;
-        let mut dtmf_chars = String::new();
-        for _ in 0..dtmf_count {
-            let ch = br.read_u8(8)?;
-            dtmf_chars.push(ch as char);
+        SpliceDescriptor::Avail(a) => serde_json::json!({ "provider_avail_id": a.provider_avail_id }),
+        SpliceDescriptor::Dtmf(dt) => serde_json::json!({
+            "preroll": dt.preroll,
+            "dtmf_chars": dt.dtmf_chars.iter().map(|&c| c as char).collect::<String>()
+        }),
+        SpliceDescriptor::Time(t) => {
+            // TAI counts from 1970 (PTP epoch); UTC is TAI less the leap-second offset.
+            let utc = chrono::DateTime::from_timestamp(t.tai_seconds as i64 - t.utc_offset as i64, t.tai_ns)
+                .map(|d| d.format("%Y-%m-%dT%H:%M:%S%.9fZ").to_string());
+            serde_json::json!({
+                "tai_seconds": t.tai_seconds,
+                "tai_ns": t.tai_ns,
+                "utc_offset": t.utc_offset,
+                "utc_iso": utc
+            })
         }
-        let bytes_read = 2 + dtmf_count as usize;
-        if bytes_read < length {
-            br.skip_bits(((length - bytes_read) * 8) as u32)?;
+        SpliceDescriptor::Audio(a) => serde_json::json!({
+            "audio_count": a.components.len(),
+            "components": a.components.iter().map(|c| serde_json::json!({
+                "component_tag": c.component_tag,
+                "iso_code": iso_639_code(c.iso_code),
+                "bit_stream_mode": c.bit_stream_mode,
+                "bit_stream_mode_name": format_bit_stream_mode(c.bit_stream_mode),
+                "num_channels": c.num_channels,
+                "full_srvc_audio": c.full_srvc_audio
+            })).collect::<Vec<_>>()
+        }),
+        SpliceDescriptor::Other { data, .. } => {
+            let identifier = d.identifier();
+            serde_json::json!({
+                "identifier": identifier.map(|id| format!("0x{:08X}", id)),
+                "identifier_ascii": identifier.and_then(identifier_ascii),
+                "private_bytes_hex": data.iter().skip(4).map(|b| format!("{:02X}", b)).collect::<String>()
+            })
         }
-        serde_json::json!({ "preroll": preroll, "dtmf_chars": dtmf_chars })
-    } else {
-        br.skip_bits((length * 8) as u32)?;
-        serde_json::json!({})
     };
-    
-    Ok(DescriptorInfo {
-        tag,
-        tag_name: tag_name.to_string(),
-        length,
+
+    DescriptorInfo {
+        tag: d.tag(),
+        tag_name: d.name().to_string(),
+        length: d.encode_body().map(|b| b.len()).unwrap_or_default(),
         data,
-    })
+    }
+}
+
+/// Child UPIDs of a MID (0x0D) segmentation_upid; null for every other type or
+/// a MID body that does not tile into children.
+fn upid_children_json(upid_type: u8, bytes: &[u8]) -> serde_json::Value {
+    let children = (upid_type == 0x0D).then(|| splice_info::mid_children(bytes)).flatten();
+    match children {
+        Some(children) => serde_json::json!(children
+            .iter()
+            .map(|(t, b)| serde_json::json!({
+                "upid_type": format!("0x{:02X}", t),
+                "upid_type_name": format_upid_type(*t),
+                "upid_value": format_upid(*t, b)
+            }))
+            .collect::<Vec<_>>()),
+        None => serde_json::Value::Null,
+    }
+}
+
+/// ISO 639-2 language code (three 8-bit characters) as text.
+fn iso_639_code(code: u32) -> String {
+    code.to_be_bytes()[1..].iter().map(|&c| c as char).collect()
+}
+
+/// ATSC A/52 bsmod.
+fn format_bit_stream_mode(bsmod: u8) -> &'static str {
+    match bsmod {
+        0 => "Main Audio (Complete Main)",
+        1 => "Main Audio (Music and Effects)",
+        2 => "Associated (Visually Impaired)",
+        3 => "Associated (Hearing Impaired)",
+        4 => "Associated (Dialogue)",
+        5 => "Associated (Commentary)",
+        6 => "Associated (Emergency)",
+        _ => "Associated (Voice Over) / Karaoke",
+    }
 }
 
 fn format_upid_type(upid_type: u8) -> &'static str {
@@ -989,7 +1414,9 @@ fn format_segmentation_type(type_id: u8) -> &'static str {
     }
 }
 
-fn validate_scte35_internal(input: &str) -> Result<String, String> {
+/// Input-level problems (not SCTE-35 at all) are an `Err`; everything about the
+/// section itself comes back as lint findings.
+fn validate_scte35_internal(input: &str, previous: &[String]) -> Result<(String, Vec<Finding>), String> {
     let bytes = scte35_input_to_bytes(input)?;
 
     if bytes.is_empty() {
@@ -1004,33 +1431,217 @@ fn validate_scte35_internal(input: &str) -> Result<String, String> {
         return Err(format!("Invalid table_id: 0x{:02X} (expected 0xFC)", table_id));
     }
 
-    if bytes.len() < 4 {
-        return Err("Message too short for CRC".to_string());
+    let mut state = LintState::default();
+    for cue in previous {
+        if let Ok(b) = scte35_input_to_bytes(cue) {
+            state.lint(&b);
+        }
     }
+    let findings = state.lint(&bytes);
+    let count = |sev| findings.iter().filter(|f| f.severity == sev).count();
+    let info = format!(
+        "SCTE-35 message ({} bytes, CRC: 0x{:08X}): {} errors, {} warnings",
+        bytes.len(),
+        u32::from_be_bytes([bytes[bytes.len() - 4], bytes[bytes.len() - 3], bytes[bytes.len() - 2], bytes[bytes.len() - 1]]),
+        count(Severity::Error),
+        count(Severity::Warning)
+    );
+    Ok((info, findings))
+}
+
+// ============================================================================
+// SCTE-104 CONVERSION
+// ============================================================================
 
-    let calculated_crc = calculate_crc32(&bytes[..bytes.len() - 4]);
-    let stored_crc = u32::from_be_bytes([
-        bytes[bytes.len() - 4],
-        bytes[bytes.len() - 3],
-        bytes[bytes.len() - 2],
-        bytes[bytes.len() - 1],
-    ]);
+fn convert_scte104_internal(req: &Scte104ConvertRequest) -> Result<Scte104ConvertResponse, String> {
+    let frame_rate = match req.frame_rate.as_deref() {
+        Some(f) => FrameRate::parse(f)?,
+        None => FrameRate::NTSC,
+    };
+    let pre_roll_ms = match (req.pre_roll_ms, req.pre_roll_frames) {
+        (Some(_), Some(_)) => return Err("give pre_roll_ms or pre_roll_frames, not both".into()),
+        (_, Some(frames)) => u16::try_from(frame_rate.frames_to_ticks(frames as u64) / 90)
+            .map_err(|_| format!("pre-roll of {frames} frames exceeds 65535 ms"))?,
+        (ms, None) => ms.unwrap_or(0),
+    };
+    let opts = scte104::ConvertOptions {
+        frame_rate,
+        pre_roll_ms,
+        splice_timecode: req.timecode.as_deref().map(Timecode::parse).transpose()?,
+        as_index: req.as_index,
+        message_number: req.message_number,
+        dpi_pid_index: req.dpi_pid_index,
+        timestamp_pts: req.pts_time.map(check_pts).transpose()?,
+    };
 
-    if calculated_crc != stored_crc {
-        return Err(format!(
-            "CRC mismatch: calculated 0x{:08X}, stored 0x{:08X}",
-            calculated_crc, stored_crc
-        ));
+    let (direction, msg, section, warnings) = match (&req.scte35, &req.scte104) {
+        (Some(s35), None) => {
+            let bytes = scte35_input_to_bytes(s35)?;
+            let section = SpliceInfoSection::decode(&bytes)?;
+            let (msg, warnings) = scte104::from_scte35(&section, &opts)?;
+            ("scte35_to_scte104", msg, section, warnings)
+        }
+        (None, Some(s104)) => {
+            let msg = MultipleOperationMessage::decode(&scte104_input_to_bytes(s104)?)?;
+            let (section, warnings) = scte104::to_scte35(&msg, &opts)?;
+            ("scte104_to_scte35", msg, section, warnings)
+        }
+        _ => return Err("give exactly one of scte35 or scte104".into()),
+    };
+    let bytes104 = msg.encode()?;
+    let bytes35 = section.encode()?;
+    Ok(Scte104ConvertResponse {
+        direction,
+        frame_rate: frame_rate.to_string(),
+        scte104_hex: bytes104.iter().map(|b| format!("{:02X}", b)).collect(),
+        scte104_base64: B64.encode(&bytes104),
+        scte35_base64: B64.encode(&bytes35),
+        scte35_hex: bytes35.iter().map(|b| format!("{:02X}", b)).collect(),
+        timestamp: scte104_timestamp_json(&msg.timestamp),
+        splice_timecode: msg.splice_timecode(&frame_rate)?.map(|tc| tc.to_string()),
+        operations: msg.operations.iter().map(scte104_operation_json).collect(),
+        warnings,
+    })
+}
+
+/// SCTE-104 messages come as hex ("FFFF0025...", "0xFF FF ...") or base64; a
+/// form that starts with the multiple_operation_message 0xFFFF wins.
+fn scte104_input_to_bytes(raw: &str) -> Result<Vec<u8>, String> {
+    let cleaned: String = raw.chars().filter(|c| !c.is_whitespace() && *c != ':').collect();
+    if let Some(h) = cleaned.strip_prefix("0x").or_else(|| cleaned.strip_prefix("0X")) {
+        return hex_str_to_bytes(h);
     }
+    let as_hex = hex_str_to_bytes(&cleaned).ok().filter(|b| !b.is_empty());
+    let as_b64 = B64.decode(cleaned.as_bytes()).ok().filter(|b| !b.is_empty());
+    match (as_hex, as_b64) {
+        (Some(h), Some(b)) if !h.starts_with(&[0xFF, 0xFF]) && b.starts_with(&[0xFF, 0xFF]) => Ok(b),
+        (Some(h), _) => Ok(h),
+        (None, Some(b)) => Ok(b),
+        (None, None) => Err("Input is not valid hex or base64".to_string()),
+    }
+}
 
-    Ok(format!(
-        "Valid SCTE-35 message ({} bytes, CRC: 0x{:08X})",
-        bytes.len(),
-        stored_crc
-    ))
+fn scte104_timestamp_json(t: &Timestamp) -> serde_json::Value {
+    match *t {
+        Timestamp::Immediate => serde_json::json!({ "time_type": 0, "name": "immediate" }),
+        Timestamp::Utc { seconds, microseconds } => serde_json::json!({
+            "time_type": 1, "name": "UTC", "UTC_seconds": seconds, "UTC_microseconds": microseconds,
+            // UTC_seconds counts from the GPS epoch, like utc_splice_time.
+            "utc_iso": chrono::DateTime::from_timestamp(splice_info::utc_splice_time_to_unix(seconds), microseconds as u32 * 1000)
+                .map(|t| t.to_rfc3339())
+        }),
+        Timestamp::Vitc { hours, minutes, seconds, frames } => serde_json::json!({
+            "time_type": 2, "name": "VITC",
+            "timecode": format!("{hours:02}:{minutes:02}:{seconds:02}:{frames:02}")
+        }),
+        Timestamp::Gpi { number, edge } => serde_json::json!({
+            "time_type": 3, "name": "GPI", "GPI_number": number, "GPI_edge": edge
+        }),
+    }
+}
+
+fn scte104_operation_json(op: &Operation) -> serde_json::Value {
+    let data = match op {
+        Operation::SpliceRequest(r) => serde_json::json!({
+            "splice_insert_type": r.splice_insert_type,
+            "splice_insert_type_name": scte104::splice_insert_type_name(r.splice_insert_type),
+            "splice_event_id": r.splice_event_id,
+            "unique_program_id": r.unique_program_id,
+            "pre_roll_time": r.pre_roll_time,
+            "break_duration": r.break_duration,
+            "avail_num": r.avail_num,
+            "avails_expected": r.avails_expected,
+            "auto_return_flag": r.auto_return_flag
+        }),
+        Operation::SpliceNull => serde_json::json!({}),
+        Operation::TimeSignal { pre_roll_time } => serde_json::json!({ "pre_roll_time": pre_roll_time }),
+        Operation::InsertDtmf { pre_roll, dtmf } => serde_json::json!({
+            "pre_roll": pre_roll,
+            "dtmf_chars": String::from_utf8_lossy(dtmf)
+        }),
+        Operation::InsertAvail { provider_avail_ids } => serde_json::json!({ "provider_avail_ids": provider_avail_ids }),
+        Operation::InsertSegmentation(sd) => serde_json::json!({
+            "segmentation_event_id": sd.segmentation_event_id,
+            "segmentation_event_cancel_indicator": sd.segmentation_event_cancel_indicator,
+            "duration": sd.duration,
+            "duration_extension_frames": sd.duration_extension_frames,
+            "segmentation_upid_type": format!("0x{:02X}", sd.segmentation_upid_type),
+            "segmentation_upid": format_upid(sd.segmentation_upid_type, &sd.segmentation_upid),
+            "segmentation_type_id": format!("0x{:02X}", sd.segmentation_type_id),
+            "segmentation_type_name": format_segmentation_type(sd.segmentation_type_id),
+            "segment_num": sd.segment_num,
+            "segments_expected": sd.segments_expected,
+            "delivery_not_restricted_flag": sd.delivery_not_restricted_flag,
+            "web_delivery_allowed_flag": sd.web_delivery_allowed_flag,
+            "no_regional_blackout_flag": sd.no_regional_blackout_flag,
+            "archive_allowed_flag": sd.archive_allowed_flag,
+            "device_restrictions": sd.device_restrictions,
+            "insert_sub_segment_info": sd.insert_sub_segment_info,
+            "sub_segment_num": sd.sub_segment_num,
+            "sub_segments_expected": sd.sub_segments_expected
+        }),
+        Operation::Other { data, .. } => serde_json::json!({
+            "data_hex": data.iter().map(|b| format!("{:02X}", b)).collect::<String>()
+        }),
+    };
+    serde_json::json!({
+        "opID": format!("0x{:04X}", op.op_id()),
+        "name": op.name(),
+        "data": data
+    })
+}
+
+// ============================================================================
+// MPEG-TS EXTRACTION
+// ============================================================================
+
+fn extract_ts_response(scan: mpegts::Scan) -> ExtractTsResponse {
+    let seconds = |t: Option<u64>| t.map(|t| t as f64 / 90_000.0);
+    let cues = scan
+        .cues
+        .into_iter()
+        .map(|cue| {
+            let base64 = B64.encode(&cue.section);
+            let (decoded, error) = match decode_scte35_internal(&base64) {
+                Ok(d) => (Some(d), None),
+                Err(e) => (None, Some(e)),
+            };
+            TsCueInfo {
+                pid: cue.pid,
+                program_number: cue.program_number,
+                packet: cue.packet,
+                offset: cue.offset,
+                pcr: cue.pcr,
+                pcr_seconds: seconds(cue.pcr),
+                pts: cue.pts,
+                pts_seconds: seconds(cue.pts),
+                base64,
+                decoded,
+                error,
+                sent: None,
+                send_error: None,
+            }
+        })
+        .collect();
+    ExtractTsResponse {
+        packet_size: scan.packet_size,
+        packets: scan.packets,
+        programs: scan
+            .programs
+            .into_iter()
+            .map(|p| TsProgramInfo {
+                program_number: p.program_number,
+                pmt_pid: p.pmt_pid,
+                pcr_pid: p.pcr_pid,
+                scte35_pids: p.scte35_pids,
+            })
+            .collect(),
+        cues,
+        warnings: scan.warnings,
+        channel: None,
+    }
 }
 
-#[allow(dead_code)]
 fn parse_hex_u8(s: &str) -> Option<u8> {
     let s = s.trim().trim_start_matches("0x").trim_start_matches("0X");
     u8::from_str_radix(s, 16).ok()
@@ -1040,7 +1651,7 @@ fn build_advanced_internal(req: &AdvancedBuildRequest) -> Result<String, String>
     let seg_type = req.segmentation_type_id.as_deref().and_then(parse_hex_u8);
     let upid_type = req.segmentation_upid_type.as_deref().and_then(parse_hex_u8);
     
-    match req.command.as_str() {
+    let b64 = match req.command.as_str() {
         "time_signal" | "time_signal_immediate" => {
             Ok(scte35::build_time_signal_advanced_b64(
                 seg_type,
@@ -1058,123 +1669,331 @@ fn build_advanced_internal(req: &AdvancedBuildRequest) -> Result<String, String>
             ))
         }
         _ => Err(format!("Unknown command: {}", req.command)),
+    }?;
+    set_event_id_and_pts_b64(&b64, req.event_id, req.pts_time)
+}
+
+/// Apply an explicit splice_event_id (to the splice_insert and every
+/// segmentation descriptor) and splice time (to the splice_insert or
+/// time_signal) on top of a basic/advanced build.
+pub(crate) fn set_event_id_and_pts_b64(input: &str, event_id: Option<u32>, pts_time: Option<u64>) -> Result<String, String> {
+    if event_id.is_none() && pts_time.is_none() {
+        return Ok(input.to_string());
+    }
+    let pts_time = pts_time.map(check_pts).transpose()?;
+    let mut section = decode_for_rewrite(input).ok_or("built section did not re-decode")?;
+    match &mut section.splice_command {
+        SpliceCommand::SpliceInsert(si) => {
+            if let Some(id) = event_id {
+                si.splice_event_id = id;
+            }
+            if let Some(pts) = pts_time {
+                si.splice_immediate_flag = false;
+                si.splice_time = Some(SpliceTime::at(pts));
+            }
+        }
+        SpliceCommand::TimeSignal(t) => {
+            if let Some(pts) = pts_time {
+                *t = SpliceTime::at(pts);
+            }
+        }
+        _ => {}
     }
+    if let Some(id) = event_id {
+        for sd in section.segmentation_descriptors_mut() {
+            sd.segmentation_event_id = id;
+        }
+    }
+    reencode_b64(&section).ok_or_else(|| "section could not be re-encoded".to_string())
 }
 
-/// Parse the fixed header + splice_command and return byte offsets describing the
-/// descriptor loop: (descriptor_loop_length word offset, loop_start, loop_end).
-/// Returns None for non-SCTE-35, encrypted, or unparseable/misaligned input.
-/// Shared by every in-place rewrite below.
-fn locate_descriptor_loop(bytes: &[u8]) -> Option<(usize, usize, usize)> {
-    if bytes.len() < 15 || bytes[0] != 0xFC || bytes[4] & 0x80 != 0 {
-        return None;
+fn check_pts(pts: u64) -> Result<u64, String> {
+    if pts >= 1 << 33 {
+        return Err(format!("pts_time {pts} exceeds 33 bits"));
     }
-    let mut br = BitReader::new(bytes);
-    br.read_u8(8).ok()?; // table_id
-    br.read_u8(1).ok()?; // section_syntax_indicator
-    br.read_u8(1).ok()?; // private_indicator
-    br.read_u8(2).ok()?; // sap_type / reserved
-    br.read_u16(12).ok()?; // section_length
-    br.read_u8(8).ok()?; // protocol_version
-    br.read_u8(1).ok()?; // encrypted_packet
-    br.read_u8(6).ok()?; // encryption_algorithm
-    br.read_u64(33).ok()?; // pts_adjustment
-    br.read_u8(8).ok()?; // cw_index
-    br.read_u16(12).ok()?; // tier
-    br.read_u16(12).ok()?; // splice_command_length
-    let command_type = br.read_u8(8).ok()?;
-    parse_command_info(&mut br, command_type).ok()?;
-    if !br.bitpos.is_multiple_of(8) {
-        return None; // unexpected misalignment after the command
-    }
-    let dlw_off = br.bitpos / 8;
-    if dlw_off + 2 > bytes.len() {
-        return None;
+    Ok(pts)
+}
+
+fn seconds_to_ticks(what: &str, secs: f64, bits: u32) -> Result<u64, String> {
+    let ticks = (secs * 90000.0).round();
+    if !(0.0..(1u64 << bits) as f64).contains(&ticks) {
+        return Err(format!("{what} {secs}s out of range"));
     }
-    let word = u16::from_be_bytes([bytes[dlw_off], bytes[dlw_off + 1]]);
-    let loop_start = dlw_off + 2;
-    let loop_end = loop_start + (word & 0x03FF) as usize;
-    if loop_end + 4 > bytes.len() {
-        return None;
+    Ok(ticks as u64)
+}
+
+/// splice_insert from the `CommandSpec` splice fields.
+fn splice_insert(spec: &CommandSpec) -> Result<SpliceInsert, String> {
+    let mut si = SpliceInsert {
+        splice_event_id: spec.splice_event_id.unwrap_or(1),
+        splice_event_cancel_indicator: spec.cancel,
+        ..Default::default()
+    };
+    if spec.cancel {
+        return Ok(si);
+    }
+    si.out_of_network_indicator = spec.out_of_network;
+    si.unique_program_id = spec.unique_program_id;
+    si.avail_num = spec.avail_num;
+    si.avails_expected = spec.avails_expected;
+    let pts_time = spec.pts_time.map(check_pts).transpose()?;
+    si.splice_immediate_flag = pts_time.is_none() && spec.components.iter().all(|c| c.pts_time.is_none());
+    if spec.components.is_empty() {
+        si.program_splice_flag = true;
+        si.splice_time = pts_time.map(SpliceTime::at);
+    } else {
+        si.components = spec
+            .components
+            .iter()
+            .map(|c| {
+                let pts = c.pts_time.or(pts_time).map(check_pts).transpose()?;
+                if !si.splice_immediate_flag && pts.is_none() {
+                    return Err(format!("component {}: pts_time is required for a timed splice", c.component_tag));
+                }
+                Ok(SpliceInsertComponent {
+                    component_tag: c.component_tag,
+                    splice_time: (!si.splice_immediate_flag).then_some(SpliceTime::new(pts)),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+    }
+    if let Some(secs) = spec.break_duration_seconds {
+        si.break_duration =
+            Some(BreakDuration::new(spec.auto_return, seconds_to_ticks("break_duration_seconds", secs, 33)?));
     }
-    Some((dlw_off, loop_start, loop_end))
+    Ok(si)
 }
 
-/// Recompute the trailing CRC-32 in place for a message whose total length is
-/// unchanged (delivery-flag / duration edits don't move any bytes).
-fn refresh_crc_in_place(bytes: &mut [u8]) {
-    let n = bytes.len();
-    let crc = crate::scte35::compute_crc32(&bytes[..n - 4]);
-    bytes[n - 4..].copy_from_slice(&crc.to_be_bytes());
+fn segmentation(spec: &SegmentationSpec) -> Result<SpliceDescriptor, String> {
+    let hex = |what: &str, v: &str| parse_hex_u8(v).ok_or_else(|| format!("{what} {v:?} is not a hex byte"));
+    let mut d = SegmentationDescriptor {
+        segmentation_event_id: spec.segmentation_event_id,
+        segmentation_event_cancel_indicator: spec.cancel,
+        ..Default::default()
+    };
+    if spec.cancel {
+        return Ok(SpliceDescriptor::Segmentation(d));
+    }
+    let type_id = spec
+        .segmentation_type_id
+        .as_deref()
+        .ok_or_else(|| format!("segmentation {}: segmentation_type_id is required", spec.segmentation_event_id))?;
+    d.segmentation_type_id = hex("segmentation_type_id", type_id)?;
+
+    if !spec.mid.is_empty() {
+        let children = spec
+            .mid
+            .iter()
+            .map(|c| Ok((hex("mid upid_type", &c.upid_type)?, c.upid.as_str())))
+            .collect::<Result<Vec<_>, String>>()?;
+        d.segmentation_upid_type = 0x0D;
+        d.segmentation_upid = scte35::encode_mid(&children)?;
+    } else if let Some(upid) = spec.upid.as_deref() {
+        let upid_type = spec.upid_type.as_deref().map(|t| hex("upid_type", t)).transpose()?.unwrap_or(0x01);
+        d.segmentation_upid_type = upid_type;
+        d.segmentation_upid = scte35::encode_upid(upid_type, upid);
+    } else if let Some(t) = spec.upid_type.as_deref() {
+        d.segmentation_upid_type = hex("upid_type", t)?;
+    }
+
+    if let Some(r) = &spec.delivery_restrictions {
+        if r.device_restrictions > 3 {
+            return Err(format!("device_restrictions {} exceeds 2 bits", r.device_restrictions));
+        }
+        d.delivery_not_restricted_flag = false;
+        d.delivery_restrictions = DeliveryRestrictions {
+            web_delivery_allowed_flag: r.web_delivery_allowed,
+            no_regional_blackout_flag: r.no_regional_blackout,
+            archive_allowed_flag: r.archive_allowed,
+            device_restrictions: r.device_restrictions,
+        };
+    }
+    if !spec.components.is_empty() {
+        d.program_segmentation_flag = false;
+        d.components = spec
+            .components
+            .iter()
+            .map(|c| {
+                Ok(SegmentationComponent {
+                    component_tag: c.component_tag,
+                    pts_offset: check_pts(c.pts_offset)?,
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<_, String>>()?;
+    }
+    d.segmentation_duration =
+        spec.duration_seconds.map(|secs| seconds_to_ticks("duration_seconds", secs, 40)).transpose()?;
+    d.segment_num = spec.segment_num;
+    d.segments_expected = spec.segments_expected;
+    let sub_given = spec.sub_segment_num.is_some() || spec.sub_segments_expected.is_some();
+    if splice_info::has_sub_segments(d.segmentation_type_id) {
+        d.sub_segment_num = Some(spec.sub_segment_num.unwrap_or(0));
+        d.sub_segments_expected = Some(spec.sub_segments_expected.unwrap_or(0));
+    } else if sub_given {
+        return Err(format!(
+            "segmentation_type_id 0x{:02X} does not carry sub_segment_num / sub_segments_expected",
+            d.segmentation_type_id
+        ));
+    }
+    Ok(SpliceDescriptor::Segmentation(d))
+}
+
+fn segmentations(spec: &CommandSpec) -> Result<Vec<SpliceDescriptor>, String> {
+    spec.segmentations.iter().map(segmentation).collect()
+}
+
+/// Build splice_null, bandwidth_reservation, splice_schedule, private_command,
+/// a fully specified splice_insert, or a time_signal carrying `segmentations`
+/// from `spec`. None when `command` is none of these (the basic time_signal /
+/// splice_insert_out builders handle it).
+pub(crate) fn build_other_command(command: &str, spec: &CommandSpec) -> Option<Result<String, String>> {
+    Some(match command {
+        "splice_insert" => splice_insert(spec).and_then(|si| {
+            scte35::build_section_b64(SpliceCommand::SpliceInsert(si), segmentations(spec)?)
+        }),
+        "time_signal" | "time_signal_immediate" if !spec.segmentations.is_empty() => spec
+            .pts_time
+            .map(check_pts)
+            .transpose()
+            .and_then(|pts| {
+                let t = pts.map_or_else(SpliceTime::immediate, SpliceTime::at);
+                scte35::build_section_b64(SpliceCommand::TimeSignal(t), segmentations(spec)?)
+            }),
+        "splice_null" => Ok(scte35::build_splice_null_b64()),
+        "bandwidth_reservation" => Ok(scte35::build_bandwidth_reservation_b64()),
+        "splice_schedule" => spec
+            .events
+            .iter()
+            .map(scheduled_event)
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|events| {
+                if events.is_empty() {
+                    return Err("splice_schedule needs at least one event".to_string());
+                }
+                scte35::build_splice_schedule_b64(events)
+            }),
+        "private_command" => (|| {
+            let identifier = parse_identifier(spec.identifier.as_deref().ok_or("private_command needs an identifier")?)?;
+            let payload = match spec.private_bytes.as_deref().map(str::trim) {
+                Some(h) if !h.is_empty() => {
+                    let cleaned: String = h
+                        .trim_start_matches("0x")
+                        .chars()
+                        .filter(|c| !c.is_whitespace() && *c != ':')
+                        .collect();
+                    hex_str_to_bytes(&cleaned).map_err(|e| format!("private_bytes: {e}"))?
+                }
+                _ => Vec::new(),
+            };
+            scte35::build_private_command_b64(identifier, payload)
+        })(),
+        _ => return None,
+    })
+}
+
+/// private_command / descriptor identifier: "0x" + up to 8 hex digits, or
+/// exactly four ASCII characters.
+fn parse_identifier(s: &str) -> Result<u32, String> {
+    let s = s.trim();
+    if let Some(h) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return u32::from_str_radix(h, 16).map_err(|_| format!("identifier {s:?} is not valid hex"));
+    }
+    match s.as_bytes() {
+        b @ [_, _, _, _] if b.is_ascii() => Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]])),
+        _ => Err(format!("identifier {s:?} must be 4 ASCII characters or 0x-prefixed hex")),
+    }
+}
+
+fn utc_splice_time(t: &UtcSpliceTimeSpec) -> Result<u32, String> {
+    match t {
+        UtcSpliceTimeSpec::Raw(v) => Ok(*v),
+        UtcSpliceTimeSpec::Rfc3339(s) => {
+            let dt = chrono::DateTime::parse_from_rfc3339(s)
+                .map_err(|e| format!("utc_splice_time {s:?}: {e}"))?;
+            splice_info::unix_to_utc_splice_time(dt.timestamp())
+                .ok_or_else(|| format!("utc_splice_time {s:?} is outside 1980-01-06 .. 2116"))
+        }
+    }
+}
+
+fn scheduled_event(spec: &ScheduleEventSpec) -> Result<ScheduledEvent, String> {
+    let mut ev = ScheduledEvent {
+        splice_event_id: spec.splice_event_id,
+        splice_event_cancel_indicator: spec.cancel,
+        ..Default::default()
+    };
+    if spec.cancel {
+        return Ok(ev);
+    }
+    ev.out_of_network_indicator = spec.out_of_network;
+    ev.unique_program_id = spec.unique_program_id;
+    ev.avail_num = spec.avail_num;
+    ev.avails_expected = spec.avails_expected;
+    if spec.components.is_empty() {
+        let t = spec.utc_splice_time.as_ref().ok_or_else(|| {
+            format!("event {}: utc_splice_time or components is required", spec.splice_event_id)
+        })?;
+        ev.program_splice_flag = true;
+        ev.utc_splice_time = Some(utc_splice_time(t)?);
+    } else {
+        ev.components = spec
+            .components
+            .iter()
+            .map(|c| Ok((c.component_tag, utc_splice_time(&c.utc_splice_time)?)))
+            .collect::<Result<_, String>>()?;
+    }
+    if let Some(secs) = spec.duration_seconds {
+        if !(0.0..=95443.0).contains(&secs) {
+            return Err(format!("event {}: duration_seconds out of range", spec.splice_event_id));
+        }
+        ev.break_duration = Some(BreakDuration::new(spec.auto_return, (secs * 90000.0).round() as u64));
+    }
+    Ok(ev)
+}
+
+/// Decode `input` for an in-place rewrite. None for non-SCTE-35, malformed or
+/// encrypted input. Shared by every rewrite below.
+fn decode_for_rewrite(input: &str) -> Option<SpliceInfoSection> {
+    let bytes = scte35_input_to_bytes(input).ok()?;
+    SpliceInfoSection::decode(&bytes).ok().filter(|s| !s.encrypted_packet)
+}
+
+/// Re-encode a rewritten section (lengths + CRC-32 recomputed) as base64; None
+/// when the edit made it unrepresentable (e.g. a descriptor over 255 bytes).
+fn reencode_b64(section: &SpliceInfoSection) -> Option<String> {
+    section.encode().ok().map(|b| B64.encode(b))
 }
 
 /// Rewrite the segmentation_upid of every segmentation_descriptor in a SCTE-35
-/// message, preserving every other byte. `new_type` optionally changes the
+/// message, preserving every other field. `new_type` optionally changes the
 /// segmentation_upid_type (None keeps each descriptor's existing type).
 ///
 /// Returns the re-encoded base64 (descriptor/section lengths + CRC-32 fixed), or
 /// None when the input can't be parsed, is encrypted, or carries no segmentation
 /// UPID — callers should then pass the original signal through unchanged.
 pub fn rewrite_upid_b64(input: &str, new_type: Option<u8>, new_value: &str) -> Option<String> {
-    let bytes = scte35_input_to_bytes(input).ok()?;
-    let (dlw_off, loop_start, loop_end) = locate_descriptor_loop(&bytes)?;
-    let orig_word = u16::from_be_bytes([bytes[dlw_off], bytes[dlw_off + 1]]);
-
-    // Walk the descriptor loop, rewriting the UPID of each segmentation_descriptor.
-    let mut new_loop: Vec<u8> = Vec::with_capacity((loop_end - loop_start) + 8);
-    let mut cursor = loop_start;
+    let mut section = decode_for_rewrite(input)?;
     let mut changed = false;
-    while cursor + 2 <= loop_end {
-        let tag = bytes[cursor];
-        let len = bytes[cursor + 1] as usize;
-        let body_end = cursor + 2 + len;
-        if body_end > loop_end {
-            return None; // malformed descriptor loop
+    for sd in section.segmentation_descriptors_mut() {
+        if sd.segmentation_event_cancel_indicator {
+            continue; // no UPID present
         }
-        if tag == 0x02 {
-            match rewrite_seg_descriptor_body(&bytes[cursor + 2..body_end], new_type, new_value) {
-                Some(nb) if nb.len() <= 255 => {
-                    new_loop.push(0x02);
-                    new_loop.push(nb.len() as u8);
-                    new_loop.extend_from_slice(&nb);
-                    changed = true;
-                }
-                Some(_) => return None, // descriptor would exceed the u8 length field
-                None => new_loop.extend_from_slice(&bytes[cursor..body_end]), // cancel / no UPID
-            }
-        } else {
-            new_loop.extend_from_slice(&bytes[cursor..body_end]);
-        }
-        cursor = body_end;
+        let upid_type = new_type.unwrap_or(sd.segmentation_upid_type);
+        sd.segmentation_upid = crate::scte35::encode_upid(upid_type, new_value);
+        sd.segmentation_upid_type = upid_type;
+        changed = true;
     }
-    if !changed || new_loop.len() > 0x03FF {
+    if !changed {
         return None;
     }
-
-    // Reassemble: header+command unchanged, new loop, preserved trailing stuffing,
-    // fixed descriptor_loop_length / section_length, recomputed CRC-32.
-    let mut out: Vec<u8> = Vec::with_capacity(bytes.len() + new_loop.len());
-    out.extend_from_slice(&bytes[..dlw_off]);
-    let new_word = (orig_word & 0xFC00) | (new_loop.len() as u16 & 0x03FF);
-    out.extend_from_slice(&new_word.to_be_bytes());
-    out.extend_from_slice(&new_loop);
-    out.extend_from_slice(&bytes[loop_end..bytes.len() - 4]); // alignment stuffing, if any
-
-    // section_length covers everything after the 3-byte prefix, including the CRC.
-    let section_length = (out.len() + 4 - 3) as u16;
-    out[1] = (out[1] & 0xF0) | (((section_length >> 8) & 0x0F) as u8);
-    out[2] = (section_length & 0xFF) as u8;
-
-    let crc = crate::scte35::compute_crc32(&out);
-    out.extend_from_slice(&crc.to_be_bytes());
-
-    Some(B64.encode(out))
+    reencode_b64(&section)
 }
 
 /// Set the delivery-restriction flags on every (non-cancelled) segmentation
 /// descriptor: clears `delivery_not_restricted_flag` and writes the 5 restriction
-/// bits. Same byte width → CRC-only refresh. Used by `blackout`/`regionalize`.
-/// Returns None when there is no segmentation descriptor to mark.
+/// bits. Used by `blackout`/`regionalize`. Returns None when there is no
+/// segmentation descriptor to mark.
 pub fn rewrite_delivery_flags_b64(
     input: &str,
     web_delivery_allowed: bool,
@@ -1182,88 +2001,33 @@ pub fn rewrite_delivery_flags_b64(
     archive_allowed: bool,
     device_restrictions: u8,
 ) -> Option<String> {
-    let mut bytes = scte35_input_to_bytes(input).ok()?;
-    let (_dlw, loop_start, loop_end) = locate_descriptor_loop(&bytes)?;
-    let mut cursor = loop_start;
+    let mut section = decode_for_rewrite(input)?;
     let mut changed = false;
-    while cursor + 2 <= loop_end {
-        let tag = bytes[cursor];
-        let len = bytes[cursor + 1] as usize;
-        let body_start = cursor + 2;
-        let body_end = body_start + len;
-        if body_end > loop_end {
-            return None;
+    for sd in section.segmentation_descriptors_mut() {
+        if sd.segmentation_event_cancel_indicator {
+            continue;
         }
-        // tag 0x02, body has the flags byte (>=10), and not a cancel descriptor.
-        if tag == 0x02 && len >= 10 && bytes[body_start + 8] & 0x80 == 0 {
-            // flags byte: program_seg(1) | seg_dur(1) | delivery_not_restricted(1) | 5 bits.
-            let top = bytes[body_start + 9] & 0xC0; // preserve program_seg + seg_dur
-            let restr = ((web_delivery_allowed as u8) << 4)
-                | ((no_regional_blackout as u8) << 3)
-                | ((archive_allowed as u8) << 2)
-                | (device_restrictions & 0x03);
-            bytes[body_start + 9] = top | restr; // delivery_not_restricted (bit5) cleared
-            changed = true;
-        }
-        cursor = body_end;
+        sd.delivery_not_restricted_flag = false;
+        sd.delivery_restrictions = DeliveryRestrictions {
+            web_delivery_allowed_flag: web_delivery_allowed,
+            no_regional_blackout_flag: no_regional_blackout,
+            archive_allowed_flag: archive_allowed,
+            device_restrictions: device_restrictions & 0x03,
+        };
+        changed = true;
     }
     if !changed {
         return None;
     }
-    refresh_crc_in_place(&mut bytes);
-    Some(B64.encode(bytes))
+    reencode_b64(&section)
 }
 
-/// Set the break duration (90 kHz ticks) of the avail in place: patches the
-/// splice_insert `break_duration` and/or any `segmentation_duration`. Same byte
-/// width → CRC-only refresh. Used by `shorten`/`extend`/`fill`. Returns None when
-/// the signal carries no duration field to modify.
+/// Set the break duration (90 kHz ticks) of the avail: the splice_insert
+/// `break_duration` and/or any `segmentation_duration`. Used by
+/// `shorten`/`extend`/`fill`. Returns None when the signal carries no duration
+/// field to modify.
 pub fn rewrite_break_duration_b64(input: &str, new_ticks: u64) -> Option<String> {
-    let mut bytes = scte35_input_to_bytes(input).ok()?;
-    let mut changed = false;
-
-    // splice_insert break_duration (auto_return 1b + reserved 6b + duration 33b).
-    if let Some(bd) = locate_break_duration(&bytes) {
-        let t = new_ticks & 0x1_FFFF_FFFF; // 33-bit
-        bytes[bd] = (bytes[bd] & 0xFE) | (((t >> 32) & 0x1) as u8); // keep auto_return + reserved
-        bytes[bd + 1] = (t >> 24) as u8;
-        bytes[bd + 2] = (t >> 16) as u8;
-        bytes[bd + 3] = (t >> 8) as u8;
-        bytes[bd + 4] = t as u8;
-        changed = true;
-    }
-
-    // segmentation_duration (40b) in any segmentation descriptor (time_signal avails).
-    if let Some((_dlw, loop_start, loop_end)) = locate_descriptor_loop(&bytes) {
-        let mut cursor = loop_start;
-        while cursor + 2 <= loop_end {
-            let len = bytes[cursor + 1] as usize;
-            let body_start = cursor + 2;
-            let body_end = body_start + len;
-            if body_end > loop_end {
-                break;
-            }
-            if bytes[cursor] == 0x02 {
-                if let Some(off) = seg_duration_offset(&bytes[body_start..body_end]) {
-                    let abs = body_start + off;
-                    let t = new_ticks & 0xFF_FFFF_FFFF; // 40-bit
-                    bytes[abs] = (t >> 32) as u8;
-                    bytes[abs + 1] = (t >> 24) as u8;
-                    bytes[abs + 2] = (t >> 16) as u8;
-                    bytes[abs + 3] = (t >> 8) as u8;
-                    bytes[abs + 4] = t as u8;
-                    changed = true;
-                }
-            }
-            cursor = body_end;
-        }
-    }
-
-    if !changed {
-        return None;
-    }
-    refresh_crc_in_place(&mut bytes);
-    Some(B64.encode(bytes))
+    update_durations(input, |_| new_ticks)
 }
 
 /// Additive variant of `rewrite_break_duration_b64`: ADD `delta_ticks` (90 kHz,
@@ -1272,275 +2036,41 @@ pub fn rewrite_break_duration_b64(input: &str, new_ticks: u64) -> Option<String>
 /// label "extend by Ns" lengthens the incoming break rather than overwriting it.
 /// Returns None when the signal carries no duration field to modify.
 pub fn adjust_break_duration_b64(input: &str, delta_ticks: i64) -> Option<String> {
-    let mut bytes = scte35_input_to_bytes(input).ok()?;
-    let mut changed = false;
-
-    // splice_insert break_duration (33-bit), preserving auto_return + reserved.
-    if let Some(bd) = locate_break_duration(&bytes) {
-        let cur = (((bytes[bd] & 0x01) as u64) << 32)
-            | ((bytes[bd + 1] as u64) << 24)
-            | ((bytes[bd + 2] as u64) << 16)
-            | ((bytes[bd + 3] as u64) << 8)
-            | (bytes[bd + 4] as u64);
-        let t = (cur as i64 + delta_ticks).max(0) as u64 & 0x1_FFFF_FFFF;
-        bytes[bd] = (bytes[bd] & 0xFE) | (((t >> 32) & 0x1) as u8);
-        bytes[bd + 1] = (t >> 24) as u8;
-        bytes[bd + 2] = (t >> 16) as u8;
-        bytes[bd + 3] = (t >> 8) as u8;
-        bytes[bd + 4] = t as u8;
-        changed = true;
-    }
-
-    // segmentation_duration (40-bit) in any segmentation descriptor.
-    if let Some((_dlw, loop_start, loop_end)) = locate_descriptor_loop(&bytes) {
-        let mut cursor = loop_start;
-        while cursor + 2 <= loop_end {
-            let len = bytes[cursor + 1] as usize;
-            let body_start = cursor + 2;
-            let body_end = body_start + len;
-            if body_end > loop_end {
-                break;
-            }
-            if bytes[cursor] == 0x02 {
-                if let Some(off) = seg_duration_offset(&bytes[body_start..body_end]) {
-                    let abs = body_start + off;
-                    let cur = ((bytes[abs] as u64) << 32)
-                        | ((bytes[abs + 1] as u64) << 24)
-                        | ((bytes[abs + 2] as u64) << 16)
-                        | ((bytes[abs + 3] as u64) << 8)
-                        | (bytes[abs + 4] as u64);
-                    let t = (cur as i64 + delta_ticks).max(0) as u64 & 0xFF_FFFF_FFFF;
-                    bytes[abs] = (t >> 32) as u8;
-                    bytes[abs + 1] = (t >> 24) as u8;
-                    bytes[abs + 2] = (t >> 16) as u8;
-                    bytes[abs + 3] = (t >> 8) as u8;
-                    bytes[abs + 4] = t as u8;
-                    changed = true;
-                }
-            }
-            cursor = body_end;
-        }
-    }
-
-    if !changed {
-        return None;
-    }
-    refresh_crc_in_place(&mut bytes);
-    Some(B64.encode(bytes))
+    update_durations(input, |cur| (cur as i64 + delta_ticks).max(0) as u64)
 }
 
-/// Byte offset (from the start of `bytes`) of the splice_insert break_duration
-/// field, navigating the flag-dependent layout. None unless this is a
-/// splice_insert with `duration_flag` set. All preceding fields are whole bytes,
-/// so the result is byte-aligned.
-fn locate_break_duration(bytes: &[u8]) -> Option<usize> {
-    if bytes.len() < 15 || bytes[0] != 0xFC || bytes[4] & 0x80 != 0 {
-        return None;
-    }
-    let mut br = BitReader::new(bytes);
-    br.read_u8(8).ok()?;
-    br.read_u8(1).ok()?;
-    br.read_u8(1).ok()?;
-    br.read_u8(2).ok()?;
-    br.read_u16(12).ok()?;
-    br.read_u8(8).ok()?;
-    br.read_u8(1).ok()?;
-    br.read_u8(6).ok()?;
-    br.read_u64(33).ok()?;
-    br.read_u8(8).ok()?;
-    br.read_u16(12).ok()?;
-    br.read_u16(12).ok()?;
-    if br.read_u8(8).ok()? != 0x05 {
-        return None; // not splice_insert
-    }
-    br.read_u32(32).ok()?; // splice_event_id
-    let cancel = br.read_u8(1).ok()? == 1;
-    br.skip_bits(7).ok()?;
-    if cancel {
-        return None;
-    }
-    let _oon = br.read_u8(1).ok()?;
-    let program_splice = br.read_u8(1).ok()? == 1;
-    let duration_flag = br.read_u8(1).ok()? == 1;
-    let splice_immediate = br.read_u8(1).ok()? == 1;
-    br.skip_bits(4).ok()?;
-    if !duration_flag {
-        return None;
-    }
-    if program_splice && !splice_immediate {
-        skip_splice_time(&mut br)?;
-    } else if !program_splice {
-        let cc = br.read_u8(8).ok()?;
-        for _ in 0..cc {
-            br.read_u8(8).ok()?; // component_tag
-            if !splice_immediate {
-                skip_splice_time(&mut br)?;
-            }
-        }
-    }
-    if !br.bitpos.is_multiple_of(8) {
-        return None;
-    }
-    Some(br.bitpos / 8)
+/// Round the splice_insert `break_duration` and every `segmentation_duration`
+/// to whole frames at `fr` (`shorten`/`extend` with a `frame_rate` param).
+/// Returns None when the signal carries no duration field.
+pub fn snap_break_durations_b64(input: &str, fr: FrameRate) -> Option<String> {
+    update_durations(input, |cur| fr.snap_ticks(cur))
 }
 
-fn skip_splice_time(br: &mut BitReader) -> Option<()> {
-    if br.read_u8(1).ok()? == 1 {
-        br.skip_bits(6).ok()?; // reserved
-        br.read_u64(33).ok()?; // pts_time
-    } else {
-        br.skip_bits(7).ok()?; // reserved
-    }
-    Some(())
-}
-
-/// Offset within a segmentation_descriptor body of the 40-bit segmentation_duration,
-/// if `segmentation_duration_flag` is set. None otherwise.
-fn seg_duration_offset(body: &[u8]) -> Option<usize> {
-    if body.len() < 10 || body[8] & 0x80 != 0 {
-        return None;
-    }
-    let flags = body[9];
-    let program_segmentation_flag = flags & 0x80 != 0;
-    let segmentation_duration_flag = flags & 0x40 != 0;
-    if !segmentation_duration_flag {
-        return None;
-    }
-    let mut idx = 10usize;
-    if !program_segmentation_flag {
-        let cc = *body.get(idx)? as usize;
-        idx += 1;
-        idx += cc.checked_mul(6)?;
-    }
-    if idx + 5 > body.len() {
-        return None;
-    }
-    Some(idx)
-}
-
-/// Rewrite the segmentation_upid (type + value) inside one segmentation_descriptor
-/// body (the bytes after tag+length). Returns None for a cancelled descriptor or
-/// when the layout can't be walked.
-fn rewrite_seg_descriptor_body(body: &[u8], new_type: Option<u8>, new_value: &str) -> Option<Vec<u8>> {
-    // identifier(4) + segmentation_event_id(4) + cancel(1)/reserved(7)
-    if body.len() < 9 {
-        return None;
-    }
-    if body[8] & 0x80 != 0 {
-        return None; // segmentation_event_cancel_indicator set: no UPID present
-    }
-    let flags = *body.get(9)?;
-    let program_segmentation_flag = flags & 0x80 != 0;
-    let segmentation_duration_flag = flags & 0x40 != 0;
-    let mut idx = 10usize;
-    if !program_segmentation_flag {
-        // component_count(8), then component_count * (tag(8)+reserved(7)+pts_offset(33)) = 6 bytes each
-        let cc = *body.get(idx)? as usize;
-        idx += 1;
-        idx += cc.checked_mul(6)?;
-    }
-    if segmentation_duration_flag {
-        idx += 5; // segmentation_duration (40 bits)
-    }
-    // segmentation_upid_type(8), segmentation_upid_length(8), segmentation_upid[len]
-    let upid_type = *body.get(idx)?;
-    let upid_len = *body.get(idx + 1)? as usize;
-    let upid_end = (idx + 2).checked_add(upid_len)?;
-    if upid_end > body.len() {
-        return None;
-    }
-    let tail = &body[upid_end..]; // segmentation_type_id, segment_num, segments_expected, [sub_*]
-
-    let new_type_val = new_type.unwrap_or(upid_type);
-    let new_upid = crate::scte35::encode_upid(new_type_val, new_value);
-    if new_upid.len() > 255 {
-        return None;
-    }
-
-    let mut nb: Vec<u8> = Vec::with_capacity(idx + 2 + new_upid.len() + tail.len());
-    nb.extend_from_slice(&body[..idx]);
-    nb.push(new_type_val);
-    nb.push(new_upid.len() as u8);
-    nb.extend_from_slice(&new_upid);
-    nb.extend_from_slice(tail);
-    Some(nb)
-}
-
-// ============================================================================
-// BIT READER HELPER
-// ============================================================================
-
-struct BitReader<'a> {
-    data: &'a [u8],
-    bitpos: usize,
-}
-
-impl<'a> BitReader<'a> {
-    fn new(data: &'a [u8]) -> Self {
-        Self { data, bitpos: 0 }
-    }
-
-    fn read_u8(&mut self, nbits: u32) -> Result<u8, String> {
-        Ok(self.read_bits(nbits)? as u8)
-    }
-
-    fn read_u16(&mut self, nbits: u32) -> Result<u16, String> {
-        Ok(self.read_bits(nbits)? as u16)
-    }
-
-    fn read_u32(&mut self, nbits: u32) -> Result<u32, String> {
-        Ok(self.read_bits(nbits)? as u32)
-    }
-
-    fn read_u64(&mut self, nbits: u32) -> Result<u64, String> {
-        self.read_bits(nbits)
-    }
+/// Apply `f` to the splice_insert break_duration (33-bit, auto_return kept) and
+/// every segmentation_duration (40-bit) present.
+fn update_durations(input: &str, f: impl Fn(u64) -> u64) -> Option<String> {
+    let mut section = decode_for_rewrite(input)?;
+    let mut changed = false;
 
-    fn read_bits(&mut self, nbits: u32) -> Result<u64, String> {
-        let mut v = 0u64;
-        for _ in 0..nbits {
-            let idx = self.bitpos / 8;
-            if idx >= self.data.len() {
-                return Err("out of bounds".into());
-            }
-            let byte = self.data[idx];
-            let bit = 7 - (self.bitpos % 8);
-            v = (v << 1) | (((byte >> bit) & 1) as u64);
-            self.bitpos += 1;
+    if let SpliceCommand::SpliceInsert(si) = &mut section.splice_command {
+        if let Some(bd) = si.break_duration.as_mut() {
+            bd.duration = f(bd.duration) & 0x1_FFFF_FFFF;
+            changed = true;
         }
-        Ok(v)
     }
-
-    fn skip_bits(&mut self, nbits: u32) -> Result<(), String> {
-        self.bitpos = self
-            .bitpos
-            .checked_add(nbits as usize)
-            .ok_or_else(|| "overflow".to_string())?;
-        if self.bitpos / 8 > self.data.len() {
-            return Err("out of bounds".into());
+    for sd in section.segmentation_descriptors_mut() {
+        if let Some(d) = sd.segmentation_duration.as_mut() {
+            *d = f(*d) & 0xFF_FFFF_FFFF;
+            changed = true;
         }
-        Ok(())
     }
-}
 
-// ============================================================================
-// CRC-32 CALCULATION
-// ============================================================================
-
-fn calculate_crc32(data: &[u8]) -> u32 {
-    let mut crc = 0xFFFFFFFFu32;
-    for &byte in data {
-        crc ^= (byte as u32) << 24;
-        for _ in 0..8 {
-            if crc & 0x80000000 != 0 {
-                crc = (crc << 1) ^ 0x04C11DB7;
-            } else {
-                crc <<= 1;
-            }
-        }
+    if !changed {
+        return None;
     }
-    crc
+    reencode_b64(&section)
 }
+
 #[cfg(test)]
 mod input_format_tests {
     use super::*;
@@ -1675,6 +2205,34 @@ mod input_format_tests {
         assert!(adjust_break_duration_b64(&plain, 90000).is_none());
     }
 
+    #[test]
+    fn decode_rejects_unusable_frame_rates() {
+        assert_eq!(requested_frame_rate(Some(" ")), Ok(None));
+        assert_eq!(requested_frame_rate(Some("25")), Ok(Some(FrameRate { num: 25, den: 1, drop_frame: false })));
+        // These used to reach frame_time() and divide by zero there.
+        for bad in ["1/2", "1/1000", "0"] {
+            assert!(requested_frame_rate(Some(bad)).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn frame_timing_counts_pts_relative_frames() {
+        let df = frame_timing(SAMPLE, FrameRate::parse("29.97df").unwrap()).unwrap();
+        assert_eq!(df.frame_rate, "29.97 DF");
+        let pts = &df.times[0];
+        assert_eq!((pts.field.as_str(), pts.kind, pts.ticks, pts.frames), ("splice_insert.splice_time", "pts", 13_937_536, 4641));
+        assert_eq!(pts.pts_relative_timecode.as_deref(), Some("00:02:34;25"));
+        assert_eq!((pts.frame_aligned_ticks, pts.frame_error_ticks), (4641 * 3003, 613));
+        let dur = &df.times[1];
+        assert_eq!((dur.kind, dur.frames, dur.duration_timecode.as_deref()), ("duration", 899, Some("00:00:29;29")));
+        assert!(dur.pts_relative_timecode.is_none());
+
+        // Snapping the 30 s break moves it onto frame 899.
+        let snapped = snap_break_durations_b64(SAMPLE, FrameRate::NTSC).expect("snap");
+        let d = decode_scte35_internal(&snapped).unwrap();
+        assert_eq!(d.command_info["break_duration"]["duration_ticks"], 899 * 3003);
+    }
+
     // ---- delivery-flag rewrite (blackout/regionalize) ----
 
     #[test]
@@ -1689,4 +2247,86 @@ mod input_format_tests {
         assert_eq!(sd["no_regional_blackout"], false);
         assert_eq!(sd["archive_allowed"], true);
     }
+
+    // ---- descriptor coverage ----
+
+    #[test]
+    fn decodes_time_audio_private_and_mid_children() {
+        use splice_info::{AudioComponent, AudioDescriptor, SegmentationDescriptor, TimeDescriptor, CUEI};
+        let mut section = SpliceInfoSection::new(SpliceCommand::TimeSignal(SpliceTime::immediate()));
+        section.descriptors = vec![
+            SpliceDescriptor::Time(TimeDescriptor {
+                identifier: CUEI,
+                tai_seconds: 1_700_000_037,
+                tai_ns: 500,
+                utc_offset: 37,
+                extra: Vec::new(),
+            }),
+            SpliceDescriptor::Audio(AudioDescriptor {
+                identifier: CUEI,
+                reserved: 0x0F,
+                components: vec![AudioComponent {
+                    component_tag: 2,
+                    iso_code: u32::from_be_bytes([0, b'e', b'n', b'g']),
+                    bit_stream_mode: 0,
+                    num_channels: 6,
+                    full_srvc_audio: true,
+                }],
+                extra: Vec::new(),
+            }),
+            SpliceDescriptor::Other { tag: 0xF0, data: b"ACME\x01\x02".to_vec() },
+            SpliceDescriptor::Segmentation(SegmentationDescriptor {
+                segmentation_type_id: 0x34,
+                segmentation_upid_type: 0x0D,
+                segmentation_upid: b"\x0F\x05a://b\x03\x02AD".to_vec(),
+                ..Default::default()
+            }),
+        ];
+        let decoded = decode_scte35_internal(&B64.encode(section.encode().unwrap())).unwrap();
+        let d = &decoded.descriptors;
+
+        assert_eq!(d[0].tag_name, "time_descriptor");
+        assert_eq!(d[0].data["utc_iso"], "2023-11-14T22:13:20.000000500Z");
+        assert_eq!(d[1].data["components"][0]["iso_code"], "eng");
+        assert_eq!(d[1].data["components"][0]["num_channels"], 6);
+        assert_eq!(d[2].tag_name, "private_descriptor");
+        assert_eq!(d[2].data["identifier_ascii"], "ACME");
+        assert_eq!(d[2].data["private_bytes_hex"], "0102");
+        let children = d[3].data["upid_children"].as_array().expect("MID children");
+        assert_eq!(children[0]["upid_type_name"], "URI");
+        assert_eq!(children[0]["upid_value"], "a://b");
+        assert_eq!(children[1]["upid_value"], "AD");
+    }
+
+    // ---- SCTE-104 ----
+
+    #[test]
+    fn scte104_convert_both_directions() {
+        let req: Scte104ConvertRequest = serde_json::from_value(serde_json::json!({
+            "scte35": SAMPLE, "frame_rate": "29.97", "pre_roll_frames": 120, "timecode": "01:00:00:00"
+        }))
+        .unwrap();
+        let fwd = convert_scte104_internal(&req).unwrap();
+        assert_eq!(fwd.direction, "scte35_to_scte104");
+        assert_eq!(fwd.operations[0]["name"], "splice_request_data");
+        assert_eq!(fwd.operations[0]["data"]["splice_insert_type_name"], "spliceStart_normal");
+        assert_eq!(fwd.operations[0]["data"]["pre_roll_time"], 4004);
+        assert_eq!(fwd.operations[0]["data"]["break_duration"], 300);
+        assert_eq!(fwd.timestamp["timecode"], "00:59:56:00");
+        assert_eq!(fwd.splice_timecode.as_deref(), Some("01:00:00:00"));
+        assert!(fwd.scte104_hex.starts_with("FFFF"));
+
+        let req: Scte104ConvertRequest =
+            serde_json::from_value(serde_json::json!({ "scte104": fwd.scte104_hex, "pts_time": 0 })).unwrap();
+        let back = convert_scte104_internal(&req).unwrap();
+        assert_eq!(back.direction, "scte104_to_scte35");
+        assert_eq!(back.scte104_base64, fwd.scte104_base64);
+        let d = decode_scte35_internal(&back.scte35_base64).unwrap();
+        assert_eq!(d.command_info["pts_time"], 360_360, "timestamp PTS + 4004 ms pre-roll");
+        assert_eq!(d.command_info["break_duration"]["duration_ticks"], 2_700_000);
+
+        let both: Scte104ConvertRequest =
+            serde_json::from_value(serde_json::json!({ "scte35": SAMPLE, "scte104": "FFFF" })).unwrap();
+        assert!(convert_scte104_internal(&both).is_err());
+    }
 }
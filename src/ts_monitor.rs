@@ -0,0 +1,730 @@
+// src/ts_monitor.rs
+//! Passive MPEG-TS monitor: the cues the encoder actually emitted.
+//!
+//! `POIS_TS_MONITOR` watches one UDP transport stream per channel (the encoder
+//! output), as comma separated `ADDR[@IFACE]=CHANNEL` entries, e.g.
+//! `239.1.1.1:5000@10.0.0.5=east,0.0.0.0:5001=west`. A multicast ADDR is
+//! joined (on IFACE, or the default interface); anything else is bound as a
+//! unicast listener. Each entry needs its own port; one that cannot be bound
+//! yet is retried, backing off to once a minute. Datagrams carry whole
+//! 188-byte packets, bare or in RTP (RFC 2250); SCTE-35 sections are pulled
+//! out with `mpegts::Demux`.
+//!
+//! Every observed cue is correlated with the decisions POIS returned for the
+//! channel (its `esam_events`, from ESAM and SCTE-104 ingest; Quick Tests are
+//! left out) by splice / segmentation event id and splice PTS
+//! (`pts_adjustment` applied). Results go to the Event Monitor as events with
+//! `source_ip` `TSMON:<sender ip>`:
+//!   - `on_air`: a cue seen on air, with the decision it matched (its signal id,
+//!     rule and, in the message, its event id) or none.
+//!   - `not_honored`: a `delete` decision whose cue was emitted anyway, a
+//!     replace whose original cue was emitted instead of the replacement, or a
+//!     pass-through / replace decision not seen on air within
+//!     `POIS_TS_MONITOR_WINDOW_SECONDS` (default 10).
+//!
+//! Replacement cues are only known when raw payloads are stored
+//! (`POIS_STORE_RAW_PAYLOADS`); otherwise a replace is matched on the inbound
+//! cue. Since a replacement usually keeps the event ids and splice time, the
+//! aired section is compared with the original and the replacement (CRC and
+//! `pts_adjustment` aside) to tell which one the encoder emitted. Repeated emissions of a cue are reported once, and `splice_null`
+//! heartbeats and other commands without an event id or splice time are not
+//! correlated. As-run, break audit, replay and the watchdog read decisions from
+//! the `decision_events` view, which leaves `on_air` and `not_honored` out.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use pois_esam_server::mpegts::{self, Demux, PACKET_SIZE, SYNC_BYTE};
+use pois_esam_server::splice_info::{SpliceCommand, SpliceDescriptor, SpliceInfoSection};
+use sqlx::{Pool, Sqlite};
+use tokio::net::UdpSocket;
+use tracing::{debug, error, warn};
+
+use crate::esam::esam_verb;
+use crate::event_queue::EventRow;
+use crate::AppState;
+
+pub const ON_AIR: &str = "on_air";
+pub const NOT_HONORED: &str = "not_honored";
+
+/// How often new decisions are read and expired.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long an observed cue waits for its decision to reach the database.
+const OBSERVATION_GRACE: Duration = Duration::from_secs(3);
+/// Longest wait between attempts to start an input (bind, first query).
+const MAX_RETRY: Duration = Duration::from_secs(60);
+/// Largest splice PTS difference still taken as the same cue (100 ms).
+const PTS_TOLERANCE: u64 = 9_000;
+const PTS_MASK: u64 = (1 << 33) - 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Input {
+    pub addr: SocketAddr,
+    /// Interface to join an IPv4 multicast group on.
+    pub interface: Option<Ipv4Addr>,
+    pub channel: String,
+}
+
+pub struct TsMonitor {
+    pub inputs: Vec<Input>,
+    pub window: Duration,
+}
+
+impl TsMonitor {
+    /// `None` unless `POIS_TS_MONITOR` is set; an invalid setting is an error.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(spec) = std::env::var("POIS_TS_MONITOR").ok().filter(|s| !s.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let window = match std::env::var("POIS_TS_MONITOR_WINDOW_SECONDS") {
+            Ok(v) if !v.trim().is_empty() => match v.trim().parse::<u64>() {
+                Ok(s) if s > 0 => Duration::from_secs(s),
+                _ => return Err(format!("POIS_TS_MONITOR_WINDOW_SECONDS: invalid value '{v}'")),
+            },
+            _ => Duration::from_secs(10),
+        };
+        Ok(Some(Self { inputs: parse_inputs(&spec)?, window }))
+    }
+
+    pub fn spawn(self: Arc<Self>, st: Arc<AppState>) {
+        for input in self.inputs.iter().cloned() {
+            let this = self.clone();
+            let st = st.clone();
+            tokio::spawn(async move { this.run(&st, input).await });
+        }
+    }
+
+    async fn run(&self, st: &AppState, input: Input) {
+        let input = &input;
+        let mut retry = POLL_INTERVAL;
+        let (socket, mut last_id) = loop {
+            match start(st, input).await {
+                Ok(v) => break v,
+                Err(e) => {
+                    error!(
+                        "ts monitor: {} for channel '{}' cannot start: {}; retrying in {}s",
+                        input.addr,
+                        input.channel,
+                        e,
+                        retry.as_secs()
+                    );
+                    tokio::time::sleep(retry).await;
+                    retry = (retry * 2).min(MAX_RETRY);
+                }
+            }
+        };
+        let mut demux = Demux::new();
+        let mut correlator = Correlator::new(self.window);
+        let mut tick = tokio::time::interval(POLL_INTERVAL);
+        let mut buf = vec![0u8; 65_536];
+        let mut offset = 0u64;
+        loop {
+            let records = tokio::select! {
+                r = socket.recv_from(&mut buf) => {
+                    let (n, from) = match r {
+                        Ok(v) => v,
+                        Err(e) => {
+                            warn!("ts monitor: receive on {} failed: {}", input.addr, e);
+                            continue;
+                        }
+                    };
+                    let mut records = Vec::new();
+                    for cue in receive(&mut demux, &buf[..n], &mut offset) {
+                        if let Some(obs) = Observation::from_cue(&cue, from.ip(), Instant::now()) {
+                            records.extend(correlator.observe(obs));
+                        }
+                    }
+                    for w in demux.take_warnings() {
+                        debug!("ts monitor: {}: {}", input.addr, w);
+                    }
+                    records
+                }
+                _ = tick.tick() => {
+                    let mut records = Vec::new();
+                    match new_decisions(&st.db, &input.channel, &mut last_id).await {
+                        Ok(decisions) => {
+                            for d in decisions {
+                                records.extend(correlator.add_decision(d));
+                            }
+                        }
+                        Err(e) => warn!("ts monitor: reading decisions for '{}': {}", input.channel, e),
+                    }
+                    records.extend(correlator.expire(Instant::now()));
+                    records
+                }
+            };
+            for r in records {
+                log_record(st, input, r).await;
+            }
+        }
+    }
+}
+
+/// `ADDR[@IFACE]=CHANNEL`, comma separated.
+fn parse_inputs(spec: &str) -> Result<Vec<Input>, String> {
+    let mut out: Vec<Input> = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let bad = || format!("POIS_TS_MONITOR: invalid entry '{entry}' (expected ADDR[@IFACE]=CHANNEL)");
+        let (addr, channel) = entry.split_once('=').ok_or_else(bad)?;
+        let (addr, interface) = match addr.trim().split_once('@') {
+            Some((a, i)) => (a, Some(i.trim().parse::<Ipv4Addr>().map_err(|_| bad())?)),
+            None => (addr.trim(), None),
+        };
+        let addr: SocketAddr = addr.trim().parse().map_err(|_| bad())?;
+        let channel = channel.trim();
+        if channel.is_empty() || (interface.is_some() && !(addr.is_ipv4() && addr.ip().is_multicast())) {
+            return Err(bad());
+        }
+        if out.iter().any(|i| i.addr.port() == addr.port()) {
+            return Err(format!("POIS_TS_MONITOR: port {} is listed twice", addr.port()));
+        }
+        out.push(Input { addr, interface, channel: channel.to_string() });
+    }
+    if out.is_empty() {
+        return Err("POIS_TS_MONITOR has no entries".into());
+    }
+    Ok(out)
+}
+
+/// The socket for `input` and the id of its channel's newest event, after which
+/// decisions are read.
+async fn start(st: &AppState, input: &Input) -> Result<(UdpSocket, i64), String> {
+    let socket = bind(input).await?;
+    let last_id: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM esam_events WHERE channel_name = ?")
+        .bind(&input.channel)
+        .fetch_one(&st.db)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((socket, last_id))
+}
+
+/// A UDP socket receiving `input`, joined to its multicast group if it is one.
+async fn bind(input: &Input) -> Result<UdpSocket, String> {
+    let ip = input.addr.ip();
+    let local = match ip {
+        IpAddr::V4(g) if g.is_multicast() => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), input.addr.port()),
+        IpAddr::V6(g) if g.is_multicast() => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), input.addr.port()),
+        _ => input.addr,
+    };
+    let socket = UdpSocket::bind(local).await.map_err(|e| format!("cannot bind {local}: {e}"))?;
+    let joined = match ip {
+        IpAddr::V4(g) if g.is_multicast() => socket.join_multicast_v4(g, input.interface.unwrap_or(Ipv4Addr::UNSPECIFIED)),
+        IpAddr::V6(g) if g.is_multicast() => socket.join_multicast_v6(&g, 0),
+        _ => Ok(()),
+    };
+    joined.map_err(|e| format!("cannot join {ip}: {e}"))?;
+    Ok(socket)
+}
+
+/// Feed one datagram's transport packets to `demux`.
+fn receive(demux: &mut Demux, datagram: &[u8], offset: &mut u64) -> Vec<mpegts::Cue> {
+    let mut cues = Vec::new();
+    for pkt in ts_payload(datagram).chunks_exact(PACKET_SIZE) {
+        cues.extend(demux.push(pkt, *offset));
+        *offset += PACKET_SIZE as u64;
+    }
+    cues
+}
+
+/// The transport packets of a datagram: the whole datagram, or an RTP
+/// payload (RFC 3550 header, CSRCs and header extension skipped).
+fn ts_payload(datagram: &[u8]) -> &[u8] {
+    if datagram.first() == Some(&SYNC_BYTE) {
+        return datagram;
+    }
+    if datagram.len() < 12 || datagram[0] >> 6 != 2 {
+        return &[];
+    }
+    let mut len = 12 + 4 * (datagram[0] & 0x0F) as usize;
+    if datagram[0] & 0x10 != 0 {
+        let Some(ext) = datagram.get(len + 2..len + 4) else { return &[] };
+        len += 4 + 4 * u16::from_be_bytes([ext[0], ext[1]]) as usize;
+    }
+    datagram.get(len..).unwrap_or(&[])
+}
+
+// -------------------------------- correlation --------------------------------
+
+/// What identifies a cue on air: its splice / segmentation event ids and its
+/// splice PTS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CueKey {
+    pub event_ids: Vec<u32>,
+    pub pts: Option<u64>,
+}
+
+impl CueKey {
+    /// `None` for commands that are not correlated (splice_null, ...).
+    pub fn of(section: &[u8]) -> Option<Self> {
+        let s = SpliceInfoSection::decode(section).ok()?;
+        let (mut event_ids, pts) = match &s.splice_command {
+            SpliceCommand::SpliceInsert(i) => (vec![i.splice_event_id], i.splice_time.and_then(|t| t.pts_time)),
+            SpliceCommand::TimeSignal(t) => (Vec::new(), t.pts_time),
+            _ => return None,
+        };
+        event_ids.extend(s.descriptors.iter().filter_map(|d| match d {
+            SpliceDescriptor::Segmentation(seg) => Some(seg.segmentation_event_id),
+            _ => None,
+        }));
+        let pts = pts.map(|p| (p + s.pts_adjustment) & PTS_MASK);
+        (!event_ids.is_empty() || pts.is_some()).then_some(Self { event_ids, pts })
+    }
+
+    /// Same cue: a shared event id (when both have one) and splice PTS within
+    /// `PTS_TOLERANCE` (when both have one); at least one of the two must apply.
+    pub fn matches(&self, other: &CueKey) -> bool {
+        let ids = !self.event_ids.is_empty() && !other.event_ids.is_empty();
+        if ids && !self.event_ids.iter().any(|id| other.event_ids.contains(id)) {
+            return false;
+        }
+        match (self.pts, other.pts) {
+            (Some(a), Some(b)) => {
+                let d = a.wrapping_sub(b) & PTS_MASK;
+                d.min(PTS_MASK + 1 - d) <= PTS_TOLERANCE
+            }
+            _ => ids,
+        }
+    }
+}
+
+/// A section with `pts_adjustment` cleared and its CRC recomputed, so the same
+/// cue compares equal however it was re-stamped on the way to air.
+fn canonical(section: &[u8]) -> Option<Vec<u8>> {
+    let mut s = SpliceInfoSection::decode(section).ok()?;
+    s.pts_adjustment = 0;
+    s.encode().ok()
+}
+
+/// The cue a replace decision sent back in place of the inbound one.
+#[derive(Debug, Clone)]
+pub struct Replacement {
+    pub key: CueKey,
+    /// [`canonical`] form.
+    pub section: Vec<u8>,
+}
+
+impl Replacement {
+    /// `None` unless `section` decodes and differs from the inbound cue.
+    pub fn of(section: &[u8], inbound: Option<&[u8]>) -> Option<Self> {
+        let canon = canonical(section)?;
+        if inbound == Some(canon.as_slice()) {
+            return None;
+        }
+        Some(Self { key: CueKey::of(section)?, section: canon })
+    }
+}
+
+/// A decision POIS returned for the channel (one `esam_events` row).
+#[derive(Debug, Clone)]
+pub struct Decision {
+    pub event_id: i64,
+    pub signal_id: String,
+    pub action: String,
+    pub rule: Option<(i64, String)>,
+    pub scte35_b64: String,
+    pub key: CueKey,
+    /// The inbound cue in [`canonical`] form.
+    pub original: Option<Vec<u8>>,
+    /// The replacement cue, when it is known and differs from the inbound one.
+    pub replacement: Option<Replacement>,
+    pub at: Instant,
+    seen: bool,
+}
+
+impl Decision {
+    /// A replace whose original cue aired instead of the replacement. Replace
+    /// usually keeps the event ids and splice time, so the sections decide;
+    /// the keys only when the aired one is neither section.
+    fn original_aired(&self, obs: &Observation) -> bool {
+        let Some(r) = &self.replacement else { return false };
+        match canonical(&obs.section) {
+            Some(aired) if aired == r.section => false,
+            Some(aired) if Some(&aired) == self.original.as_ref() => true,
+            _ => !r.key.matches(&obs.key),
+        }
+    }
+}
+
+/// A cue seen on air.
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub section: Vec<u8>,
+    pub pid: u16,
+    pub source: IpAddr,
+    pub key: CueKey,
+    pub at: Instant,
+}
+
+impl Observation {
+    pub fn from_cue(cue: &mpegts::Cue, source: IpAddr, at: Instant) -> Option<Self> {
+        let key = CueKey::of(&cue.section)?;
+        Some(Self { section: cue.section.clone(), pid: cue.pid, source, key, at })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// `ON_AIR` or `NOT_HONORED`.
+    pub action: &'static str,
+    pub decision: Option<Decision>,
+    pub observation: Option<Observation>,
+    pub reason: Option<String>,
+}
+
+/// Pairs observed cues with decisions. Observations wait `OBSERVATION_GRACE`
+/// for a decision (the event writer batches), decisions wait `window` for
+/// their cue.
+pub struct Correlator {
+    window: Duration,
+    decisions: Vec<Decision>,
+    pending: Vec<Observation>,
+    /// Sections seen within the window, to drop repeated emissions.
+    recent: HashMap<Vec<u8>, Instant>,
+}
+
+impl Correlator {
+    pub fn new(window: Duration) -> Self {
+        Self { window, decisions: Vec::new(), pending: Vec::new(), recent: HashMap::new() }
+    }
+
+    pub fn add_decision(&mut self, d: Decision) -> Vec<Record> {
+        self.decisions.push(d);
+        let mut out = Vec::new();
+        for obs in std::mem::take(&mut self.pending) {
+            match self.correlate(obs) {
+                Ok(r) => out.extend(r),
+                Err(obs) => self.pending.push(obs),
+            }
+        }
+        out
+    }
+
+    pub fn observe(&mut self, obs: Observation) -> Option<Record> {
+        if let Some(&t) = self.recent.get(&obs.section) {
+            if obs.at.saturating_duration_since(t) < self.window {
+                return None;
+            }
+        }
+        self.recent.insert(obs.section.clone(), obs.at);
+        self.correlate(obs).unwrap_or_else(|obs| {
+            self.pending.push(obs);
+            None
+        })
+    }
+
+    /// Ok(None) for another emission of a cue already reported; Err when no
+    /// decision matches (yet).
+    fn correlate(&mut self, obs: Observation) -> Result<Option<Record>, Observation> {
+        let hit = |d: &Decision| d.key.matches(&obs.key) || d.replacement.as_ref().is_some_and(|r| r.key.matches(&obs.key));
+        let found = match self.decisions.iter().position(|d| !d.seen && hit(d)) {
+            Some(i) => i,
+            None if self.decisions.iter().any(hit) => return Ok(None),
+            None => return Err(obs),
+        };
+        let d = &mut self.decisions[found];
+        d.seen = true;
+        let reason = match esam_verb(&d.action) {
+            "delete" => Some(format!("decision {} deleted this cue, but the encoder emitted it", d.event_id)),
+            "replace" if d.original_aired(&obs) => Some(format!(
+                "decision {} replaced this cue, but the encoder emitted the original",
+                d.event_id
+            )),
+            _ => None,
+        };
+        Ok(Some(Record {
+            action: if reason.is_some() { NOT_HONORED } else { ON_AIR },
+            decision: Some(d.clone()),
+            observation: Some(obs),
+            reason,
+        }))
+    }
+
+    /// Observations nobody decided on, and decisions whose cue never aired.
+    pub fn expire(&mut self, now: Instant) -> Vec<Record> {
+        let mut out = Vec::new();
+        let (late, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|o| now.saturating_duration_since(o.at) >= OBSERVATION_GRACE);
+        self.pending = pending;
+        out.extend(late.into_iter().map(|obs| Record { action: ON_AIR, decision: None, observation: Some(obs), reason: None }));
+
+        let window = self.window;
+        self.decisions.retain(|d| {
+            if now.saturating_duration_since(d.at) < window {
+                return true;
+            }
+            if !d.seen && esam_verb(&d.action) != "delete" {
+                out.push(Record {
+                    action: NOT_HONORED,
+                    decision: Some(d.clone()),
+                    observation: None,
+                    reason: Some(format!(
+                        "decision {} ({}) not seen on air within {} s",
+                        d.event_id,
+                        d.action,
+                        window.as_secs()
+                    )),
+                });
+            }
+            false
+        });
+        self.recent.retain(|_, t| now.saturating_duration_since(*t) < window);
+        out
+    }
+}
+
+// ------------------------------ events in and out -----------------------------
+
+#[derive(sqlx::FromRow)]
+struct DecisionRow {
+    id: i64,
+    acquisition_signal_id: String,
+    scte35_b64: String,
+    action: String,
+    matched_rule_id: Option<i64>,
+    matched_rule_name: Option<String>,
+    raw_esam_response: Option<String>,
+}
+
+/// Decisions logged for `channel` after `last_id` (advanced past them).
+async fn new_decisions(db: &Pool<Sqlite>, channel: &str, last_id: &mut i64) -> Result<Vec<Decision>, String> {
+    let rows: Vec<DecisionRow> = sqlx::query_as(
+        "SELECT id, acquisition_signal_id, scte35_b64, action, matched_rule_id, matched_rule_name, raw_esam_response \
+         FROM decision_events WHERE channel_name = ? AND id > ? AND scte35_b64 IS NOT NULL AND scte35_b64 != '' \
+         AND response_status < 400 AND (source_ip IS NULL OR source_ip NOT LIKE 'QuickTest:%') ORDER BY id",
+    )
+    .bind(channel)
+    .bind(*last_id)
+    .fetch_all(db)
+    .await
+    .map_err(|e| e.to_string())?;
+    let now = Instant::now();
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            *last_id = (*last_id).max(r.id);
+            let inbound = B64.decode(&r.scte35_b64).ok()?;
+            let key = CueKey::of(&inbound)?;
+            let original = canonical(&inbound);
+            let replacement = match esam_verb(&r.action) {
+                "replace" => r
+                    .raw_esam_response
+                    .as_deref()
+                    .and_then(response_cue)
+                    .and_then(|b64| Replacement::of(&B64.decode(b64).ok()?, original.as_deref())),
+                _ => None,
+            };
+            Some(Decision {
+                event_id: r.id,
+                signal_id: r.acquisition_signal_id,
+                action: r.action,
+                rule: r.matched_rule_id.zip(r.matched_rule_name),
+                scte35_b64: r.scte35_b64,
+                key,
+                original,
+                replacement,
+                at: now,
+                seen: false,
+            })
+        })
+        .collect())
+}
+
+/// The SCTE-35 payload of a SignalProcessingNotification.
+fn response_cue(xml: &str) -> Option<&str> {
+    let start = xml.find("BinaryData")?;
+    let body = &xml[start + xml[start..].find('>')? + 1..];
+    Some(body[..body.find('<')?].trim()).filter(|b| !b.is_empty())
+}
+
+async fn log_record(st: &AppState, input: &Input, r: Record) {
+    let (source, user_agent, cue) = match &r.observation {
+        Some(o) => (
+            o.source.to_string(),
+            format!("MPEG-TS monitor (PID 0x{:04X})", o.pid),
+            Some(B64.encode(&o.section)),
+        ),
+        None => (input.addr.to_string(), "MPEG-TS monitor".to_string(), None),
+    };
+    if let Some(reason) = &r.reason {
+        warn!("ts monitor: channel '{}': {}", input.channel, reason);
+    }
+    let d = r.decision.as_ref();
+    let _ = st
+        .event_logger
+        .queue
+        .push(EventRow {
+            timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            channel_name: input.channel.clone(),
+            acquisition_signal_id: d.map(|d| d.signal_id.clone()).unwrap_or_default(),
+            utc_point: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            source_ip: Some(format!("TSMON:{source}")),
+            user_agent: Some(user_agent),
+            request_size: r.observation.as_ref().map(|o| o.section.len() as i32),
+            scte35_b64: cue.or_else(|| d.map(|d| d.scte35_b64.clone())),
+            matched_rule_id: d.and_then(|d| d.rule.as_ref().map(|(id, _)| *id)),
+            matched_rule_name: d.and_then(|d| d.rule.as_ref().map(|(_, name)| name.clone())),
+            action: r.action.to_string(),
+            processing_time_ms: None,
+            response_status: 200,
+            error_message: r.reason,
+            raw_esam_request: None,
+            raw_esam_response: None,
+            sesame_tier: None,
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pois_esam_server::splice_info::{SpliceInsert, SpliceTime};
+
+    fn insert(event_id: u32, pts: u64) -> Vec<u8> {
+        SpliceInfoSection::new(SpliceCommand::SpliceInsert(SpliceInsert {
+            splice_event_id: event_id,
+            out_of_network_indicator: true,
+            program_splice_flag: true,
+            splice_time: Some(SpliceTime::at(pts)),
+            ..Default::default()
+        }))
+        .encode()
+        .unwrap()
+    }
+
+    fn decision(event_id: i64, action: &str, section: &[u8], at: Instant) -> Decision {
+        Decision {
+            event_id,
+            signal_id: format!("sig-{event_id}"),
+            action: action.into(),
+            rule: None,
+            scte35_b64: B64.encode(section),
+            key: CueKey::of(section).unwrap(),
+            original: canonical(section),
+            replacement: None,
+            at,
+            seen: false,
+        }
+    }
+
+    fn observed(section: &[u8], at: Instant) -> Observation {
+        let key = CueKey::of(section).unwrap();
+        Observation { section: section.to_vec(), pid: 0x102, source: Ipv4Addr::LOCALHOST.into(), key, at }
+    }
+
+    #[test]
+    fn correlates_by_event_id_and_pts() {
+        let t0 = Instant::now();
+        let window = Duration::from_secs(10);
+        let mut c = Correlator::new(window);
+        let (aired, deleted, missing) = (insert(1, 900_000), insert(2, 1_800_000), insert(3, 2_700_000));
+
+        // The cue can air before its decision is read back from the database.
+        assert!(c.observe(observed(&aired, t0)).is_none());
+        let r = c.add_decision(decision(10, "noop", &aired, t0));
+        assert_eq!((r.len(), r[0].action), (1, ON_AIR));
+        assert_eq!(r[0].decision.as_ref().unwrap().event_id, 10);
+        assert!(c.observe(observed(&aired, t0 + Duration::from_secs(1))).is_none(), "repeat");
+
+        assert!(c.add_decision(decision(11, "delete", &deleted, t0)).is_empty());
+        let r = c.observe(observed(&deleted, t0)).unwrap();
+        assert_eq!(r.action, NOT_HONORED);
+        assert!(r.reason.unwrap().contains("decision 11 deleted"));
+
+        assert!(c.add_decision(decision(12, "replace", &missing, t0)).is_empty());
+        // Same event id, PTS a second off: a different cue, nobody decided on it.
+        assert!(c.observe(observed(&insert(3, 2_790_000), t0)).is_none());
+        let r = c.expire(t0 + window);
+        assert_eq!(r.len(), 2);
+        assert!(r[0].decision.is_none() && r[0].action == ON_AIR);
+        assert_eq!(r[1].action, NOT_HONORED);
+        assert!(r[1].reason.as_deref().unwrap().contains("not seen on air"));
+    }
+
+    #[test]
+    fn replace_with_the_same_ids_is_told_apart_by_section() {
+        let t0 = Instant::now();
+        let mut c = Correlator::new(Duration::from_secs(10));
+        let original = insert(5, 900_000);
+        let mut sec = SpliceInfoSection::decode(&original).unwrap();
+        if let SpliceCommand::SpliceInsert(i) = &mut sec.splice_command {
+            i.unique_program_id = 42;
+        }
+        let replacement = sec.encode().unwrap();
+        let replace = |event_id| Decision {
+            replacement: Replacement::of(&replacement, canonical(&original).as_deref()),
+            ..decision(event_id, "replace", &original, t0)
+        };
+
+        assert!(c.add_decision(replace(20)).is_empty());
+        let r = c.observe(observed(&original, t0)).unwrap();
+        assert_eq!(r.action, NOT_HONORED);
+        assert!(r.reason.unwrap().contains("decision 20 replaced this cue"));
+
+        let mut c = Correlator::new(Duration::from_secs(10));
+        assert!(c.add_decision(replace(21)).is_empty());
+        // Re-stamped by the multiplexer: still the replacement.
+        let mut restamped = SpliceInfoSection::decode(&replacement).unwrap();
+        restamped.pts_adjustment = 9_000;
+        let r = c.observe(observed(&restamped.encode().unwrap(), t0)).unwrap();
+        assert_eq!((r.action, r.reason), (ON_AIR, None));
+    }
+
+    #[test]
+    fn parses_inputs() {
+        let i = parse_inputs("239.1.1.1:5000@10.0.0.5=east, 0.0.0.0:5001=west").unwrap();
+        assert_eq!(
+            i[0],
+            Input { addr: "239.1.1.1:5000".parse().unwrap(), interface: Some(Ipv4Addr::new(10, 0, 0, 5)), channel: "east".into() }
+        );
+        assert_eq!((i[1].interface, i[1].channel.as_str()), (None, "west"));
+        assert!(parse_inputs("0.0.0.0:5001@10.0.0.5=west").is_err());
+        assert!(parse_inputs("239.1.1.1:5000=a,239.1.1.2:5000=b").is_err());
+    }
+
+    #[tokio::test]
+    async fn receives_cues_over_loopback_udp() {
+        let input = Input { addr: "127.0.0.1:0".parse().unwrap(), interface: None, channel: "east".into() };
+        let rx = bind(&input).await.unwrap();
+        let tx = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let section = |table_id: u8, id: u16, body: &[u8]| {
+            let len = 5 + body.len() + 4;
+            let mut s = vec![table_id, 0xB0 | (len >> 8) as u8, len as u8];
+            s.extend(id.to_be_bytes());
+            s.extend([0xC1, 0x00, 0x00]);
+            s.extend(body);
+            let crc = pois_esam_server::splice_info::crc32(&s);
+            s.extend(crc.to_be_bytes());
+            s
+        };
+        let packet = |pid: u16, section: &[u8]| {
+            let mut p = vec![SYNC_BYTE, 0x40 | (pid >> 8) as u8, pid as u8, 0x10, 0x00];
+            p.extend(section);
+            p.resize(PACKET_SIZE, 0xFF);
+            p
+        };
+        let cue = insert(7, 900_000);
+        let mut datagram = packet(0x0000, &section(0x00, 1, &[0x00, 0x01, 0xE1, 0x00]));
+        datagram.extend(packet(0x0100, &section(0x02, 1, &[0xFF, 0xFF, 0xF0, 0x00, 0x86, 0xE1, 0x02, 0xF0, 0x00])));
+        datagram.extend(packet(0x0102, &cue));
+        // RTP-wrapped datagrams carry the same packets after the RTP header.
+        let mut rtp = vec![0x80, 33, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1];
+        rtp.extend(&datagram[2 * PACKET_SIZE..]);
+        tx.send_to(&datagram, rx.local_addr().unwrap()).await.unwrap();
+
+        let mut demux = Demux::new();
+        let mut offset = 0;
+        let mut buf = vec![0u8; 65_536];
+        let (n, from) = rx.recv_from(&mut buf).await.unwrap();
+        let cues = receive(&mut demux, &buf[..n], &mut offset);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].section, cue);
+        assert_eq!(Observation::from_cue(&cues[0], from.ip(), Instant::now()).unwrap().key.event_ids, vec![7]);
+        assert_eq!(ts_payload(&rtp).len(), PACKET_SIZE);
+    }
+}
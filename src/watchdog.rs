@@ -0,0 +1,1032 @@
+// src/watchdog.rs
+//! Missing-signal and anomaly watchdog.
+//!
+//! Per-channel policies (`watchdog_policies`, `'*'` = default for every live
+//! channel without its own row) enable any of four checks, all computed from
+//! `esam_events`:
+//!   - `silence`: no ESAM request for `silence_minutes` while one of the
+//!     policy's dayparts is active (in the channel's timezone). The quiet
+//!     period counts from the last event, the start of the daypart or the
+//!     policy change, whichever is latest.
+//!   - `open_break`: an out cue with no in cue and no declared duration for
+//!     `open_break_minutes` (paired as in the as-run log).
+//!   - `error_rate`: responses >= 400 make up `error_rate_pct` or more of the
+//!     last `error_window_minutes`, once `error_min_requests` were seen.
+//!   - `sesame_rejections`: `sesame_rejections` or more SESAME rejections in
+//!     the last `sesame_window_minutes`.
+//!
+//! At most one alert per channel and check is firing; it is updated while the
+//! condition holds and resolved when it clears (or the check is disabled).
+//! Notifiers (see `notifiers`) are called once when an alert fires and once
+//! when it resolves. Every channel is evaluated every
+//! `POIS_WATCHDOG_INTERVAL_SECONDS` (default 60), and a channel is also
+//! re-evaluated about a second after each event logged for it.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::{Pool, Sqlite};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, info, warn};
+
+use crate::as_run::{self, BreakStatus};
+use crate::event_logging::EventFilters;
+use crate::jwt_auth::Claims;
+use crate::monitor_ws::MonitorHub;
+use crate::notifiers::{self, Notification, Notifier};
+use crate::rbac;
+use crate::AppState;
+
+const TS_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+/// How far back out cues are paired for the open-break check.
+const BREAK_LOOKBACK_HOURS: i64 = 12;
+/// Events for a channel within this window trigger one re-evaluation.
+const DEBOUNCE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Silence,
+    OpenBreak,
+    ErrorRate,
+    SesameRejections,
+}
+
+impl Kind {
+    const ALL: [Kind; 4] = [Kind::Silence, Kind::OpenBreak, Kind::ErrorRate, Kind::SesameRejections];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Silence => "silence",
+            Kind::OpenBreak => "open_break",
+            Kind::ErrorRate => "error_rate",
+            Kind::SesameRejections => "sesame_rejections",
+        }
+    }
+}
+
+// -------------------------------- dayparts --------------------------------
+
+const DAYS: [(&str, &str); 7] = [
+    ("mon", "monday"),
+    ("tue", "tuesday"),
+    ("wed", "wednesday"),
+    ("thu", "thursday"),
+    ("fri", "friday"),
+    ("sat", "saturday"),
+    ("sun", "sunday"),
+];
+
+/// `[days ]HH:MM-HH:MM`, e.g. `Mon-Fri 06:00-23:00`, `Sat,Sun 22:00-02:00`
+/// or `06:00-24:00` (every day). A window whose end is not after its start
+/// runs past midnight and belongs to the day it starts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Daypart {
+    /// Monday first.
+    days: [bool; 7],
+    /// Minutes after midnight; `end` may be 1440.
+    start: u32,
+    end: u32,
+}
+
+fn parse_day(s: &str) -> Option<usize> {
+    let s = s.trim().to_ascii_lowercase();
+    DAYS.iter().position(|(short, long)| s == *short || s == *long)
+}
+
+fn parse_hhmm(s: &str) -> Option<u32> {
+    let (h, m) = s.trim().split_once(':')?;
+    if !(1..=2).contains(&h.len()) || m.len() != 2 {
+        return None;
+    }
+    let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    match (h, m) {
+        (24, 0) => Some(1440),
+        (0..=23, 0..=59) => Some(h * 60 + m),
+        _ => None,
+    }
+}
+
+impl Daypart {
+    fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let (day_spec, times) = s.rsplit_once(' ').map(|(d, t)| (d.trim(), t)).unwrap_or(("*", s));
+        let mut days = [false; 7];
+        if day_spec == "*" || day_spec.eq_ignore_ascii_case("daily") {
+            days = [true; 7];
+        } else {
+            for item in day_spec.split(',') {
+                let bad = || format!("invalid day '{}' in daypart '{s}'", item.trim());
+                match item.split_once('-') {
+                    Some((a, b)) => {
+                        let (mut d, last) = (parse_day(a).ok_or_else(bad)?, parse_day(b).ok_or_else(bad)?);
+                        loop {
+                            days[d] = true;
+                            if d == last {
+                                break;
+                            }
+                            d = (d + 1) % 7;
+                        }
+                    }
+                    None => days[parse_day(item).ok_or_else(bad)?] = true,
+                }
+            }
+        }
+        let bad_time = || format!("invalid time range in daypart '{s}' (HH:MM-HH:MM)");
+        let (a, b) = times.split_once('-').ok_or_else(bad_time)?;
+        let (start, end) = (parse_hhmm(a).ok_or_else(bad_time)?, parse_hhmm(b).ok_or_else(bad_time)?);
+        if start == 1440 || start == end {
+            return Err(bad_time());
+        }
+        Ok(Self { days, start, end })
+    }
+
+    /// The window of this daypart that starts on `date` (local time), if any.
+    fn window_on(&self, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        if !self.days[date.weekday().num_days_from_monday() as usize] {
+            return None;
+        }
+        let midnight = date.and_time(NaiveTime::MIN);
+        let end_day = if self.end > self.start { midnight } else { midnight + chrono::Duration::days(1) };
+        Some((
+            midnight + chrono::Duration::minutes(self.start.into()),
+            end_day + chrono::Duration::minutes(self.end.into()),
+        ))
+    }
+}
+
+/// Start of the active stretch containing local time `now`, or None when no
+/// daypart is active. Back-to-back or overlapping windows count as one
+/// stretch (followed back at most two weeks).
+fn active_since(parts: &[Daypart], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let earliest_containing = |t: NaiveDateTime, end_inclusive: bool| {
+        let day = t.date();
+        parts
+            .iter()
+            .flat_map(|p| [day.pred_opt(), Some(day)].into_iter().flatten().filter_map(|d| p.window_on(d)))
+            .filter(|(s, e)| *s <= t && (t < *e || (end_inclusive && t == *e)))
+            .map(|(s, _)| s)
+            .min()
+    };
+    let mut since = earliest_containing(now, false)?;
+    for _ in 0..14 {
+        match earliest_containing(since, true) {
+            Some(s) if s < since => since = s,
+            _ => break,
+        }
+    }
+    Some(since)
+}
+
+fn local_to_utc(tz: Tz, t: NaiveDateTime) -> DateTime<Utc> {
+    // A time skipped by a DST change is taken an hour later.
+    tz.from_local_datetime(&t)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(t + chrono::Duration::hours(1))).earliest())
+        .map(|d| d.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&t))
+}
+
+fn parse_ts(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).ok().map(|d| d.with_timezone(&Utc))
+}
+
+/// Minutes the channel has been quiet, if that reaches `minutes`. Quiet time
+/// counts from the latest of the last event, the start of the active daypart
+/// and `baseline` (when the policy last changed).
+fn silent_minutes(
+    minutes: i64,
+    now: DateTime<Utc>,
+    active_since: DateTime<Utc>,
+    last_event: Option<DateTime<Utc>>,
+    baseline: DateTime<Utc>,
+) -> Option<i64> {
+    let quiet_from = [Some(active_since), last_event, Some(baseline)].into_iter().flatten().max()?;
+    let quiet = (now - quiet_from).num_minutes();
+    (quiet >= minutes).then(|| last_event.map_or(quiet, |t| (now - t).num_minutes()))
+}
+
+/// Error percentage if it breaches `pct` with enough requests to judge.
+fn error_rate(total: i64, errors: i64, min_requests: i64, pct: f64) -> Option<f64> {
+    if total == 0 || total < min_requests {
+        return None;
+    }
+    let rate = errors as f64 * 100.0 / total as f64;
+    (rate >= pct).then_some(rate)
+}
+
+// --------------------------------- policies -------------------------------
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct PolicyRow {
+    channel_name: String,
+    enabled: bool,
+    silence_minutes: Option<i64>,
+    /// JSON array of daypart strings.
+    dayparts: Option<String>,
+    open_break_minutes: Option<i64>,
+    error_rate_pct: Option<f64>,
+    error_window_minutes: i64,
+    error_min_requests: i64,
+    sesame_rejections: Option<i64>,
+    sesame_window_minutes: i64,
+    /// Comma-separated notifier names; None = all.
+    notify: Option<String>,
+    updated_at: String,
+}
+
+impl PolicyRow {
+    fn daypart_strings(&self) -> Option<Vec<String>> {
+        self.dayparts.as_deref().and_then(|d| serde_json::from_str(d).ok())
+    }
+
+    fn notify_list(&self) -> Option<Vec<String>> {
+        self.notify.as_deref().map(split_names)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "channel_name": self.channel_name,
+            "enabled": self.enabled,
+            "silence_minutes": self.silence_minutes,
+            "dayparts": self.daypart_strings(),
+            "open_break_minutes": self.open_break_minutes,
+            "error_rate_pct": self.error_rate_pct,
+            "error_window_minutes": self.error_window_minutes,
+            "error_min_requests": self.error_min_requests,
+            "sesame_rejections": self.sesame_rejections,
+            "sesame_window_minutes": self.sesame_window_minutes,
+            "notify": self.notify_list(),
+            "updated_at": self.updated_at,
+        })
+    }
+}
+
+fn split_names(s: &str) -> Vec<String> {
+    s.split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect()
+}
+
+/// The channel's own policy, else the `'*'` default.
+async fn load_policy(db: &Pool<Sqlite>, channel: &str) -> Result<Option<PolicyRow>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT * FROM watchdog_policies WHERE channel_name IN (?, '*') \
+         ORDER BY channel_name = '*' LIMIT 1",
+    )
+    .bind(channel)
+    .fetch_optional(db)
+    .await
+}
+
+/// Turn off the channel's own policy when the channel is soft-deleted, so a
+/// later channel with the same name does not silently inherit it.
+pub async fn disable_policy<'c>(db: impl sqlx::Executor<'c, Database = Sqlite>, channel_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE watchdog_policies SET enabled = 0, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') \
+         WHERE channel_name = (SELECT name FROM channels WHERE id = ?)",
+    )
+    .bind(channel_id)
+    .execute(db)
+    .await
+    .map(|_| ())
+}
+
+/// Body of `PUT /api/watchdog/policies/{channel}` (replaces the policy).
+#[derive(Debug, Deserialize)]
+pub struct PolicyBody {
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+    pub silence_minutes: Option<i64>,
+    pub dayparts: Option<Vec<String>>,
+    pub open_break_minutes: Option<i64>,
+    pub error_rate_pct: Option<f64>,
+    pub error_window_minutes: Option<i64>,
+    pub error_min_requests: Option<i64>,
+    pub sesame_rejections: Option<i64>,
+    pub sesame_window_minutes: Option<i64>,
+    pub notify: Option<Vec<String>>,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+impl PolicyBody {
+    fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("silence_minutes", self.silence_minutes),
+            ("open_break_minutes", self.open_break_minutes),
+            ("error_window_minutes", self.error_window_minutes),
+            ("error_min_requests", self.error_min_requests),
+            ("sesame_rejections", self.sesame_rejections),
+            ("sesame_window_minutes", self.sesame_window_minutes),
+        ];
+        for (name, v) in positive {
+            if v.is_some_and(|v| v < 1) {
+                return Err(format!("{name} must be >= 1 (null disables the check)"));
+            }
+        }
+        if self.error_rate_pct.is_some_and(|p| !(p > 0.0 && p <= 100.0)) {
+            return Err("error_rate_pct must be in (0, 100]".into());
+        }
+        match &self.dayparts {
+            Some(d) if d.is_empty() => return Err("dayparts must not be empty (omit it to watch around the clock)".into()),
+            Some(d) => {
+                for p in d {
+                    Daypart::parse(p)?;
+                }
+            }
+            None => {}
+        }
+        for n in self.notify.iter().flatten() {
+            if !notifiers::KNOWN.contains(&n.as_str()) {
+                return Err(format!("unknown notifier '{n}' ({})", notifiers::KNOWN.join(", ")));
+            }
+        }
+        Ok(())
+    }
+}
+
+// --------------------------------- watchdog -------------------------------
+
+struct Finding {
+    message: String,
+    details: Value,
+}
+
+pub struct Watchdog {
+    pub interval: Duration,
+    notifiers: Vec<Arc<dyn Notifier>>,
+    /// Evaluations run one at a time so alerts fire and resolve exactly once.
+    eval: tokio::sync::Mutex<()>,
+    last_run: Mutex<Option<String>>,
+}
+
+impl Watchdog {
+    pub fn from_env() -> Result<Self, String> {
+        let secs = std::env::var("POIS_WATCHDOG_INTERVAL_SECONDS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(60)
+            .max(5);
+        Ok(Self {
+            interval: Duration::from_secs(secs),
+            notifiers: notifiers::from_env()?,
+            eval: tokio::sync::Mutex::new(()),
+            last_run: Mutex::new(None),
+        })
+    }
+
+    pub fn notifier_names(&self) -> Vec<&'static str> {
+        self.notifiers.iter().map(|n| n.name()).collect()
+    }
+
+    /// Periodic sweep plus re-evaluation of channels as their events are logged.
+    pub fn spawn(self: Arc<Self>, db: Pool<Sqlite>, monitor: &MonitorHub) {
+        let mut rx = monitor.subscribe();
+        let (me, pool) = (self.clone(), db.clone());
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(me.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = me.evaluate_all(&pool).await {
+                    error!("watchdog sweep failed: {e}");
+                }
+            }
+        });
+        tokio::spawn(async move {
+            let mut dirty: HashSet<String> = HashSet::new();
+            loop {
+                match rx.recv().await {
+                    Ok(frame) => {
+                        dirty.insert(frame.channel.clone());
+                    }
+                    // Missed frames are covered by the periodic sweep.
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                }
+                let deadline = tokio::time::Instant::now() + DEBOUNCE;
+                while let Ok(r) = tokio::time::timeout_at(deadline, rx.recv()).await {
+                    match r {
+                        Ok(frame) => {
+                            dirty.insert(frame.channel.clone());
+                        }
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => return,
+                    }
+                }
+                for channel in dirty.drain() {
+                    if let Err(e) = self.evaluate_channel(&db, &channel).await {
+                        warn!("watchdog: evaluating {channel} failed: {e}");
+                    }
+                }
+            }
+        });
+    }
+
+    /// Evaluate every channel covered by a policy or with a firing alert.
+    pub async fn evaluate_all(&self, db: &Pool<Sqlite>) -> Result<usize, String> {
+        let channels: Vec<String> = sqlx::query_scalar(
+            "SELECT channel_name FROM watchdog_policies WHERE channel_name <> '*' \
+             UNION SELECT name FROM channels WHERE deleted_at IS NULL \
+               AND EXISTS (SELECT 1 FROM watchdog_policies WHERE channel_name = '*') \
+             UNION SELECT channel_name FROM watchdog_alerts WHERE state = 'firing'",
+        )
+        .fetch_all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+        for channel in &channels {
+            // One bad channel must not stop the sweep for the rest.
+            if let Err(e) = self.evaluate_channel(db, channel).await {
+                warn!("watchdog: evaluating {channel} failed: {e}");
+            }
+        }
+        *self.last_run.lock().unwrap() = Some(Utc::now().format(TS_FORMAT).to_string());
+        Ok(channels.len())
+    }
+
+    pub async fn evaluate_channel(&self, db: &Pool<Sqlite>, channel: &str) -> Result<(), String> {
+        let _one_at_a_time = self.eval.lock().await;
+        let policy = load_policy(db, channel).await.map_err(|e| e.to_string())?;
+        let live: Option<(bool, String)> =
+            sqlx::query_as("SELECT enabled, timezone FROM channels WHERE name = ? AND deleted_at IS NULL")
+                .bind(channel)
+                .fetch_optional(db)
+                .await
+                .map_err(|e| e.to_string())?;
+        let notify = policy.as_ref().and_then(|p| p.notify.clone());
+        // Only live, enabled channels are watched; a deleted channel's alerts resolve.
+        let active = policy.filter(|p| p.enabled && live.as_ref().is_some_and(|(on, _)| *on));
+        let tz = as_run::parse_tz(live.as_ref().map_or("UTC", |(_, tz)| tz.as_str()));
+        let now = Utc::now();
+        for kind in Kind::ALL {
+            let finding = match &active {
+                Some(p) => match self.check(db, channel, p, tz, kind, now).await {
+                    Ok(f) => f,
+                    Err(e) => {
+                        warn!("watchdog: {} check on {channel} failed: {e}", kind.as_str());
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            self.reconcile(db, channel, kind, finding, notify.as_deref()).await?;
+        }
+        Ok(())
+    }
+
+    async fn check(
+        &self,
+        db: &Pool<Sqlite>,
+        channel: &str,
+        p: &PolicyRow,
+        tz: Tz,
+        kind: Kind,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Finding>, String> {
+        let since = |minutes: i64| (now - chrono::Duration::minutes(minutes)).format(TS_FORMAT).to_string();
+        match kind {
+            Kind::Silence => {
+                let Some(minutes) = p.silence_minutes else { return Ok(None) };
+                let parts: Vec<Daypart> =
+                    p.daypart_strings().unwrap_or_default().iter().filter_map(|d| Daypart::parse(d).ok()).collect();
+                let active = if parts.is_empty() {
+                    DateTime::<Utc>::MIN_UTC
+                } else {
+                    match active_since(&parts, now.with_timezone(&tz).naive_local()) {
+                        Some(t) => local_to_utc(tz, t),
+                        None => return Ok(None),
+                    }
+                };
+                let last: Option<String> = sqlx::query_scalar(
+                    "SELECT MAX(timestamp) FROM decision_events WHERE channel_name = ?",
+                )
+                .bind(channel)
+                .fetch_one(db)
+                .await
+                .map_err(|e| e.to_string())?;
+                let last_at = last.as_deref().and_then(parse_ts);
+                let baseline = parse_ts(&p.updated_at).unwrap_or(now);
+                Ok(silent_minutes(minutes, now, active, last_at, baseline).map(|quiet| Finding {
+                    message: match &last {
+                        Some(t) => format!("No ESAM request for {quiet} min (last at {t})"),
+                        None => format!("No ESAM request received (watched for {quiet} min)"),
+                    },
+                    details: json!({ "last_event_at": last, "silence_minutes": minutes, "dayparts": p.daypart_strings() }),
+                }))
+            }
+            Kind::OpenBreak => {
+                let Some(minutes) = p.open_break_minutes else { return Ok(None) };
+                let breaks =
+                    as_run::recent_breaks(db, channel, now - chrono::Duration::hours(BREAK_LOOKBACK_HOURS)).await?;
+                let cutoff = now - chrono::Duration::minutes(minutes);
+                let stuck: Vec<Value> = breaks
+                    .iter()
+                    .filter(|b| {
+                        b.status == BreakStatus::Open
+                            && b.planned_duration_s.is_none()
+                            && b.started_at().is_some_and(|t| t <= cutoff)
+                    })
+                    .map(|b| json!({ "break_ref": b.break_ref, "cue": b.cue, "start": b.start, "out_event_id": b.out_event_id }))
+                    .collect();
+                let Some(first) = stuck.first() else { return Ok(None) };
+                Ok(Some(Finding {
+                    message: format!(
+                        "{} break(s) without an in cue for over {minutes} min (oldest {} since {})",
+                        stuck.len(),
+                        first["break_ref"].as_str().unwrap_or("?"),
+                        first["start"].as_str().unwrap_or("?"),
+                    ),
+                    details: json!({ "open_break_minutes": minutes, "breaks": stuck }),
+                }))
+            }
+            Kind::ErrorRate => {
+                let Some(pct) = p.error_rate_pct else { return Ok(None) };
+                let (total, errors): (i64, i64) = sqlx::query_as(
+                    "SELECT COUNT(*), COUNT(CASE WHEN response_status >= 400 THEN 1 END) \
+                     FROM decision_events WHERE channel_name = ? AND timestamp >= ?",
+                )
+                .bind(channel)
+                .bind(since(p.error_window_minutes))
+                .fetch_one(db)
+                .await
+                .map_err(|e| e.to_string())?;
+                Ok(error_rate(total, errors, p.error_min_requests, pct).map(|rate| Finding {
+                    message: format!(
+                        "{rate:.1}% of ESAM requests failed in the last {} min ({errors}/{total})",
+                        p.error_window_minutes
+                    ),
+                    details: json!({
+                        "errors": errors,
+                        "total": total,
+                        "rate_pct": rate,
+                        "threshold_pct": pct,
+                        "window_minutes": p.error_window_minutes,
+                    }),
+                }))
+            }
+            Kind::SesameRejections => {
+                let Some(threshold) = p.sesame_rejections else { return Ok(None) };
+                let codes: Vec<(String, i64)> = sqlx::query_as(
+                    "SELECT error_message, COUNT(*) FROM esam_events \
+                     WHERE channel_name = ? AND timestamp >= ? AND error_message LIKE 'SESAME:%' \
+                     GROUP BY error_message ORDER BY 2 DESC",
+                )
+                .bind(channel)
+                .bind(since(p.sesame_window_minutes))
+                .fetch_all(db)
+                .await
+                .map_err(|e| e.to_string())?;
+                let count: i64 = codes.iter().map(|(_, n)| n).sum();
+                if count < threshold {
+                    return Ok(None);
+                }
+                let by_code: serde_json::Map<String, Value> = codes
+                    .into_iter()
+                    .map(|(m, n)| (m.trim_start_matches("SESAME:").trim().to_string(), json!(n)))
+                    .collect();
+                Ok(Some(Finding {
+                    message: format!("{count} SESAME rejections in the last {} min", p.sesame_window_minutes),
+                    details: json!({
+                        "rejections": count,
+                        "threshold": threshold,
+                        "window_minutes": p.sesame_window_minutes,
+                        "by_code": by_code,
+                    }),
+                }))
+            }
+        }
+    }
+
+    /// Fire, refresh or resolve the (channel, kind) alert to match `finding`.
+    async fn reconcile(
+        &self,
+        db: &Pool<Sqlite>,
+        channel: &str,
+        kind: Kind,
+        finding: Option<Finding>,
+        notify: Option<&str>,
+    ) -> Result<(), String> {
+        let firing: Option<(i64, String, String, Option<String>)> = sqlx::query_as(
+            "SELECT id, message, fired_at, details FROM watchdog_alerts \
+             WHERE channel_name = ? AND kind = ? AND state = 'firing'",
+        )
+        .bind(channel)
+        .bind(kind.as_str())
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+        let now = Utc::now().format(TS_FORMAT).to_string();
+        match (firing, finding) {
+            (None, None) => {}
+            (Some((id, ..)), Some(f)) => {
+                sqlx::query("UPDATE watchdog_alerts SET message = ?, details = ?, last_seen_at = ? WHERE id = ?")
+                    .bind(&f.message)
+                    .bind(f.details.to_string())
+                    .bind(&now)
+                    .bind(id)
+                    .execute(db)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            (None, Some(f)) => {
+                let id: i64 = sqlx::query_scalar(
+                    "INSERT INTO watchdog_alerts (channel_name, kind, state, message, details, fired_at, last_seen_at) \
+                     VALUES (?, ?, 'firing', ?, ?, ?, ?) RETURNING id",
+                )
+                .bind(channel)
+                .bind(kind.as_str())
+                .bind(&f.message)
+                .bind(f.details.to_string())
+                .bind(&now)
+                .bind(&now)
+                .fetch_one(db)
+                .await
+                .map_err(|e| e.to_string())?;
+                warn!("watchdog alert #{id} firing on {channel}: {}", f.message);
+                self.notify(
+                    Notification {
+                        alert_id: id,
+                        channel: channel.to_string(),
+                        kind: kind.as_str().to_string(),
+                        state: "firing".into(),
+                        message: f.message,
+                        details: f.details,
+                        fired_at: now,
+                        resolved_at: None,
+                    },
+                    notify,
+                );
+            }
+            (Some((id, message, fired_at, details)), None) => {
+                sqlx::query("UPDATE watchdog_alerts SET state = 'resolved', resolved_at = ? WHERE id = ?")
+                    .bind(&now)
+                    .bind(id)
+                    .execute(db)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                info!("watchdog alert #{id} on {channel} resolved");
+                self.notify(
+                    Notification {
+                        alert_id: id,
+                        channel: channel.to_string(),
+                        kind: kind.as_str().to_string(),
+                        state: "resolved".into(),
+                        message,
+                        details: details.and_then(|d| serde_json::from_str(&d).ok()).unwrap_or(Value::Null),
+                        fired_at,
+                        resolved_at: Some(now),
+                    },
+                    notify,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Send through the policy's notifiers (all when `only` is None) in the
+    /// background; failures are logged.
+    fn notify(&self, n: Notification, only: Option<&str>) {
+        let wanted = only.map(split_names);
+        let n = Arc::new(n);
+        for notifier in &self.notifiers {
+            if wanted.as_ref().is_some_and(|w| !w.iter().any(|w| w == notifier.name())) {
+                continue;
+            }
+            let (notifier, n) = (notifier.clone(), n.clone());
+            tokio::spawn(async move {
+                if let Err(e) = notifier.send(&n).await {
+                    warn!("watchdog: {} notification for alert #{} failed: {e}", notifier.name(), n.alert_id);
+                }
+            });
+        }
+    }
+}
+
+// -------------------------------- handlers --------------------------------
+
+async fn require_super(st: &AppState, claims: &Claims) -> Result<(), Response> {
+    if !rbac::effective(&st.db, claims).await.super_admin {
+        return Err((StatusCode::FORBIDDEN, "Super-admin only").into_response());
+    }
+    Ok(())
+}
+
+/// Policies can be changed by whoever may edit the channel; `'*'` by super-admins.
+async fn authorize_policy_write(st: &AppState, claims: &Claims, channel: &str) -> Result<(), Response> {
+    let eff = rbac::effective(&st.db, claims).await;
+    if eff.super_admin {
+        return Ok(());
+    }
+    if channel == "*" {
+        return Err((StatusCode::FORBIDDEN, "Super-admin only").into_response());
+    }
+    let id: Option<i64> = match sqlx::query_scalar("SELECT id FROM channels WHERE name = ? AND deleted_at IS NULL")
+        .bind(channel)
+        .fetch_optional(&st.db)
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()),
+    };
+    let Some(id) = id else {
+        return Err((StatusCode::NOT_FOUND, "Channel not found").into_response());
+    };
+    if !rbac::can_read(&st.db, &eff, "channels", "channel_groups", "channel_id", id).await {
+        return Err((StatusCode::NOT_FOUND, "Channel not found").into_response());
+    }
+    if !rbac::can_write(&st.db, &eff, "channels", "channel_groups", "channel_id", id).await {
+        return Err((StatusCode::FORBIDDEN, "Not allowed to change this channel").into_response());
+    }
+    Ok(())
+}
+
+/// Re-evaluate after a policy change so disabled checks resolve promptly.
+fn reevaluate(st: &AppState, channel: String) {
+    let (wd, db) = (st.watchdog.clone(), st.db.clone());
+    tokio::spawn(async move {
+        let res = if channel == "*" { wd.evaluate_all(&db).await.map(|_| ()) } else { wd.evaluate_channel(&db, &channel).await };
+        if let Err(e) = res {
+            warn!("watchdog: re-evaluation after policy change failed: {e}");
+        }
+    });
+}
+
+/// GET /api/watchdog — notifiers, sweep interval and firing alerts (super-admin).
+pub async fn get_status(State(st): State<Arc<AppState>>, Extension(claims): Extension<Claims>) -> Response {
+    if let Err(rej) = require_super(&st, &claims).await {
+        return rej;
+    }
+    let firing: Vec<(String, i64)> = match sqlx::query_as(
+        "SELECT kind, COUNT(*) FROM watchdog_alerts WHERE state = 'firing' GROUP BY kind ORDER BY kind",
+    )
+    .fetch_all(&st.db)
+    .await
+    {
+        Ok(r) => r,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let wd = &st.watchdog;
+    Json(json!({
+        "notifiers": wd.notifier_names(),
+        "interval_seconds": wd.interval.as_secs(),
+        "firing": firing.iter().map(|(_, n)| n).sum::<i64>(),
+        "firing_by_kind": firing.into_iter().map(|(k, n)| (k, json!(n))).collect::<serde_json::Map<String, Value>>(),
+        "last_run": *wd.last_run.lock().unwrap(),
+    }))
+    .into_response()
+}
+
+/// GET /api/watchdog/policies — policies of the channels the caller can read.
+pub async fn list_policies(State(st): State<Arc<AppState>>, Extension(claims): Extension<Claims>) -> Response {
+    let eff = rbac::effective(&st.db, &claims).await;
+    let mut qb = sqlx::QueryBuilder::new("SELECT * FROM watchdog_policies WHERE 1=1");
+    EventFilters { event_scope: rbac::event_scope(&eff), ..Default::default() }.push_where(&mut qb);
+    qb.push(" ORDER BY channel_name");
+    match qb.build_query_as::<PolicyRow>().fetch_all(&st.db).await {
+        Ok(rows) => Json(rows.iter().map(PolicyRow::to_json).collect::<Vec<_>>()).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// PUT /api/watchdog/policies/{channel} — create or replace a policy.
+pub async fn put_policy(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(channel): Path<String>,
+    Json(body): Json<PolicyBody>,
+) -> Response {
+    if let Err(rej) = authorize_policy_write(&st, &claims, &channel).await {
+        return rej;
+    }
+    if let Err(e) = body.validate() {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+    let res = sqlx::query_as::<_, PolicyRow>(
+        "INSERT INTO watchdog_policies (channel_name, enabled, silence_minutes, dayparts, open_break_minutes, \
+           error_rate_pct, error_window_minutes, error_min_requests, sesame_rejections, sesame_window_minutes, notify) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+         ON CONFLICT(channel_name) DO UPDATE SET enabled = excluded.enabled, \
+           silence_minutes = excluded.silence_minutes, dayparts = excluded.dayparts, \
+           open_break_minutes = excluded.open_break_minutes, error_rate_pct = excluded.error_rate_pct, \
+           error_window_minutes = excluded.error_window_minutes, error_min_requests = excluded.error_min_requests, \
+           sesame_rejections = excluded.sesame_rejections, sesame_window_minutes = excluded.sesame_window_minutes, \
+           notify = excluded.notify, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') \
+         RETURNING *",
+    )
+    .bind(&channel)
+    .bind(body.enabled)
+    .bind(body.silence_minutes)
+    .bind(body.dayparts.as_ref().map(|d| json!(d).to_string()))
+    .bind(body.open_break_minutes)
+    .bind(body.error_rate_pct)
+    .bind(body.error_window_minutes.unwrap_or(10))
+    .bind(body.error_min_requests.unwrap_or(20))
+    .bind(body.sesame_rejections)
+    .bind(body.sesame_window_minutes.unwrap_or(5))
+    .bind(body.notify.as_ref().map(|n| n.join(",")))
+    .fetch_one(&st.db)
+    .await;
+    match res {
+        Ok(row) => {
+            reevaluate(&st, channel);
+            Json(row.to_json()).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// DELETE /api/watchdog/policies/{channel} — fall back to the `'*'` default.
+pub async fn delete_policy(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(channel): Path<String>,
+) -> Response {
+    if let Err(rej) = authorize_policy_write(&st, &claims, &channel).await {
+        return rej;
+    }
+    match sqlx::query("DELETE FROM watchdog_policies WHERE channel_name = ?").bind(&channel).execute(&st.db).await {
+        Ok(r) if r.rows_affected() == 0 => (StatusCode::NOT_FOUND, "No watchdog policy for this channel").into_response(),
+        Ok(_) => {
+            reevaluate(&st, channel);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct AlertQuery {
+    pub state: Option<String>,
+    pub channel: Option<String>,
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct AlertRow {
+    id: i64,
+    channel_name: String,
+    kind: String,
+    state: String,
+    message: String,
+    details: Option<String>,
+    fired_at: String,
+    last_seen_at: String,
+    resolved_at: Option<String>,
+}
+
+/// GET /api/watchdog/alerts — newest first, for channels the caller can read.
+pub async fn list_alerts(
+    State(st): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(q): Query<AlertQuery>,
+) -> Response {
+    if let Some(s) = q.state.as_deref().filter(|s| !["firing", "resolved"].contains(s)) {
+        return (StatusCode::BAD_REQUEST, format!("invalid state: {s} (firing or resolved)")).into_response();
+    }
+    let eff = rbac::effective(&st.db, &claims).await;
+    let mut qb = sqlx::QueryBuilder::new(
+        "SELECT id, channel_name, kind, state, message, details, fired_at, last_seen_at, resolved_at \
+         FROM watchdog_alerts WHERE 1=1",
+    );
+    if let Some(s) = q.state {
+        qb.push(" AND state = ").push_bind(s);
+    }
+    if let Some(k) = q.kind {
+        qb.push(" AND kind = ").push_bind(k);
+    }
+    EventFilters { channel_name: q.channel, event_scope: rbac::event_scope(&eff), ..Default::default() }
+        .push_where(&mut qb);
+    qb.push(" ORDER BY id DESC LIMIT ").push_bind(q.limit.unwrap_or(100).clamp(1, 1000));
+    match qb.build_query_as::<AlertRow>().fetch_all(&st.db).await {
+        Ok(rows) => Json(
+            rows.into_iter()
+                .map(|a| {
+                    json!({
+                        "id": a.id,
+                        "channel_name": a.channel_name,
+                        "kind": a.kind,
+                        "state": a.state,
+                        "message": a.message,
+                        "details": a.details.and_then(|d| serde_json::from_str::<Value>(&d).ok()),
+                        "fired_at": a.fired_at,
+                        "last_seen_at": a.last_seen_at,
+                        "resolved_at": a.resolved_at,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// POST /api/watchdog/test — send a test notification through every notifier.
+pub async fn test_notifiers(State(st): State<Arc<AppState>>, Extension(claims): Extension<Claims>) -> Response {
+    if let Err(rej) = require_super(&st, &claims).await {
+        return rej;
+    }
+    if st.watchdog.notifiers.is_empty() {
+        return (StatusCode::CONFLICT, "No notifiers configured (set POIS_WATCHDOG_WEBHOOK_URL, _SMTP_URL or _SYSLOG)")
+            .into_response();
+    }
+    let n = Notification {
+        alert_id: 0,
+        channel: "*".into(),
+        kind: "test".into(),
+        state: "test".into(),
+        message: "Test notification from the POIS watchdog".into(),
+        details: json!({}),
+        fired_at: Utc::now().format(TS_FORMAT).to_string(),
+        resolved_at: None,
+    };
+    let mut results = Vec::new();
+    for notifier in &st.watchdog.notifiers {
+        let res = notifier.send(&n).await;
+        results.push(json!({ "notifier": notifier.name(), "ok": res.is_ok(), "error": res.err() }));
+    }
+    Json(results).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn dayparts_parse() {
+        let p = Daypart::parse("Mon-Fri 06:00-23:00").unwrap();
+        assert_eq!(p.days, [true, true, true, true, true, false, false]);
+        assert_eq!((p.start, p.end), (360, 1380));
+        assert_eq!(Daypart::parse("Fri-Mon 22:00-02:00").unwrap().days, [true, false, false, false, true, true, true]);
+        assert_eq!(Daypart::parse("sat,Sunday 00:00-24:00").unwrap().end, 1440);
+        assert_eq!(Daypart::parse("06:00-24:00").unwrap().days, [true; 7]);
+        for bad in ["Mon-Fri", "Funday 06:00-07:00", "06:00-06:00", "24:00-06:00", "06:00-25:00", "6-7"] {
+            assert!(Daypart::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn active_since_follows_windows() {
+        // 2026-10-19 is a Monday.
+        let weekdays = [Daypart::parse("Mon-Fri 06:00-23:00").unwrap()];
+        assert_eq!(active_since(&weekdays, local("2026-10-19 10:00")), Some(local("2026-10-19 06:00")));
+        assert_eq!(active_since(&weekdays, local("2026-10-19 23:00")), None);
+        assert_eq!(active_since(&weekdays, local("2026-10-18 10:00")), None);
+        // Past midnight: Sunday's window is still active early Monday.
+        let late = [Daypart::parse("Sun 22:00-02:00").unwrap()];
+        assert_eq!(active_since(&late, local("2026-10-19 01:00")), Some(local("2026-10-18 22:00")));
+        // Back-to-back windows are one stretch.
+        let chained = [Daypart::parse("Sun 12:00-24:00").unwrap(), Daypart::parse("Mon 00:00-08:00").unwrap()];
+        assert_eq!(active_since(&chained, local("2026-10-19 03:00")), Some(local("2026-10-18 12:00")));
+    }
+
+    #[test]
+    fn silence_counts_from_latest_reference() {
+        let t = |s: &str| parse_ts(s).unwrap();
+        let now = t("2026-10-19T10:00:00Z");
+        let long_ago = t("2026-10-01T00:00:00Z");
+        // Last event 20 min ago, daypart active since 06:00: silent 20 min.
+        assert_eq!(silent_minutes(15, now, t("2026-10-19T06:00:00Z"), Some(t("2026-10-19T09:40:00Z")), long_ago), Some(20));
+        assert_eq!(silent_minutes(30, now, t("2026-10-19T06:00:00Z"), Some(t("2026-10-19T09:40:00Z")), long_ago), None);
+        // Daypart started 10 min ago: not yet, even though the last event is old.
+        assert_eq!(silent_minutes(15, now, t("2026-10-19T09:50:00Z"), Some(long_ago), long_ago), None);
+        // A policy changed 5 min ago gets its full grace period.
+        assert_eq!(silent_minutes(15, now, DateTime::<Utc>::MIN_UTC, None, t("2026-10-19T09:55:00Z")), None);
+        assert_eq!(silent_minutes(15, now, DateTime::<Utc>::MIN_UTC, None, t("2026-10-19T09:30:00Z")), Some(30));
+    }
+
+    #[test]
+    fn error_rate_needs_enough_requests() {
+        assert_eq!(error_rate(10, 10, 20, 50.0), None);
+        assert_eq!(error_rate(40, 10, 20, 50.0), None);
+        assert_eq!(error_rate(40, 20, 20, 50.0), Some(50.0));
+        assert_eq!(error_rate(0, 0, 0, 1.0), None);
+    }
+
+    #[test]
+    fn policy_validation() {
+        let body = |v: Value| serde_json::from_value::<PolicyBody>(v).unwrap();
+        assert!(body(json!({"silence_minutes": 10, "dayparts": ["Mon-Fri 06:00-23:00"], "notify": ["webhook"]}))
+            .validate()
+            .is_ok());
+        assert!(body(json!({"silence_minutes": 0})).validate().is_err());
+        assert!(body(json!({"error_rate_pct": 120.0})).validate().is_err());
+        assert!(body(json!({"dayparts": []})).validate().is_err());
+        assert!(body(json!({"notify": ["pager"]})).validate().is_err());
+    }
+}